@@ -0,0 +1,116 @@
+//! Placeholder radio and executor shared by the sensor examples.
+//!
+//! **This is not a radio driver.** The zigbee stack drives the transceiver
+//! through the [`Radio`] trait, which is not implemented for the
+//! IEEE 802.15.4 peripherals of the ESP32-C6 or the nRF52 yet. Until it is,
+//! the examples build against [`PlaceholderRadio`]: nothing is sent, nothing
+//! is received and the time advances by the timeouts the stack waits for.
+//! A device running it never finds a network.
+//!
+//! Both examples include this file with `#[path]`, so a real driver replaces
+//! it in one place per example.
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::RawWaker;
+use core::task::RawWakerVTable;
+use core::task::Waker;
+use core::time::Duration;
+
+use zigbee::radio::Radio;
+use zigbee::radio::RxFrame;
+use zigbee::radio::TxStatus;
+use zigbee::types::IeeeAddress;
+use zigbee::types::ShortAddress;
+
+/// Radio without a transceiver, standing in for the platform driver.
+pub struct PlaceholderRadio {
+    now: Duration,
+    seed: u32,
+}
+
+impl PlaceholderRadio {
+    pub const fn new() -> Self {
+        Self {
+            now: Duration::ZERO,
+            seed: 0x2545_f491,
+        }
+    }
+}
+
+impl Radio for PlaceholderRadio {
+    type Error = Infallible;
+
+    async fn set_channel(&mut self, _channel: u8) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn set_pan_id(&mut self, _pan_id: u16) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn set_short_address(&mut self, _address: ShortAddress) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn set_extended_address(&mut self, _address: IeeeAddress) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn transmit(&mut self, _frame: &[u8], _cca: bool) -> Result<TxStatus, Self::Error> {
+        Ok(TxStatus::NoAck)
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<RxFrame>, Self::Error> {
+        self.now += timeout;
+        Ok(None)
+    }
+
+    async fn energy_detect(&mut self, duration: Duration) -> Result<u8, Self::Error> {
+        self.now += duration;
+        Ok(0)
+    }
+
+    async fn sleep(&mut self, duration: Duration) -> Result<(), Self::Error> {
+        self.now += duration;
+        Ok(())
+    }
+
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn random(&mut self) -> u32 {
+        // xorshift, there is no receiver noise to sample
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+}
+
+const NOOP_WAKER: RawWakerVTable = RawWakerVTable::new(
+    |data| RawWaker::new(data, &NOOP_WAKER),
+    |_| {},
+    |_| {},
+    |_| {},
+);
+
+/// Runs `future` to completion by busy polling it.
+///
+/// Good enough for the [`PlaceholderRadio`], which never waits. A real
+/// driver waking on radio interrupts wants an executor sleeping in between,
+/// like `embassy-executor`.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    // SAFETY: the vtable ignores the data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &NOOP_WAKER)) };
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
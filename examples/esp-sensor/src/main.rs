@@ -1,12 +1,22 @@
 #![no_std]
 #![no_main]
 
+use core::time::Duration;
+
 use esp_backtrace as _;
 
 use esp_hal::gpio::{Input, Pull};
 use esp_hal::prelude::*;
 use esp_hal::delay::Delay;
 use esp_println::println;
+use zigbee::apl::descriptors::node_descriptor::LogicalType;
+use zigbee::nwk::nlme::PollRates;
+
+#[path = "../../common/placeholder.rs"]
+mod placeholder;
+
+use placeholder::block_on;
+use placeholder::PlaceholderRadio;
 
 #[entry]
 fn main() -> ! {
@@ -14,7 +24,18 @@ fn main() -> ! {
     let delay = Delay::new();
 
     log::info!("Init zigbee!");
-    let zigbee_device = zigbee::init(zigbee::Config { radio_channel: 11, ..Default::default() });
+    let config = zigbee::Config {
+        radio_channel: 11,
+        device_type: LogicalType::EndDevice,
+        // the sensor sleeps between polls of its parent
+        poll_rates: Some(PollRates {
+            fast: Duration::from_millis(250),
+            slow: Duration::from_secs(30),
+            fast_poll_timeout: Duration::from_secs(5),
+        }),
+        ..Default::default()
+    };
+    let mut zigbee_device = zigbee::init(config, PlaceholderRadio::new());
 
     // init GPIO for device pairing
     let peripherals = esp_hal::init(esp_hal::Config::default());
@@ -22,15 +43,16 @@ fn main() -> ! {
     loop {
         if button.is_low() {
             println!("Connect to nearby zigbee network.");
-            zigbee_device.try_to_connect();
+            let _ = block_on(zigbee_device.try_to_connect());
         } else if zigbee_device.is_connected() {
-            println!("Send keep alive to stay in network.");
+            println!("Poll the parent to stay in network.");
 
-            // send keep alive to stay in network
-            zigbee_device.send_keep_alive();
+            // polls the parent, which keeps the device alive, and handles
+            // the frames kept for it
+            let _ = block_on(zigbee_device.process(Duration::from_secs(1)));
 
             // periodic update of sensor data
-            zigbee_device.send_data(&[0x7au8, 0x69u8, 0x67u8, 0x62u8, 0x65u8, 0x65u8]);
+            let _ = block_on(zigbee_device.send_data(&[0x7au8, 0x69u8, 0x67u8, 0x62u8, 0x65u8, 0x65u8]));
 
         } else {
             println!("Idle.");
@@ -40,4 +62,3 @@ fn main() -> ! {
         delay.delay(1_000.millis());
     }
 }
-
@@ -8,7 +8,9 @@ use esp_backtrace as _;
 use esp_hal::prelude::*;
 use esp_ieee802154::*;
 use esp_println::println;
-use ieee802154::mac::Address;
+use zigbee_rs::mac::frame::Address;
+use zigbee_rs::mac::frame::MacFrame;
+use zigbee_rs::mac::frame::FCS_SIZE;
 use zigbee_rs::nwk::frame::NwkFrame;
 use zigbee_rs::PackBytes;
 
@@ -30,26 +32,44 @@ fn main() -> ! {
     ieee802154.start_receive();
 
     loop {
-        if let Some(Ok(frame)) = ieee802154.received() {
+        if let Some(raw) = ieee802154.raw_received() {
+            // the first byte holds the PSDU length, the radio replaces the
+            // FCS with RSSI and LQI
+            let len = usize::from(raw.data[0]);
+            if len < FCS_SIZE || len >= raw.data.len() {
+                continue;
+            }
+            let mpdu = &raw.data[1..][..len - FCS_SIZE];
+            let frame = match MacFrame::unpack_from_slice(mpdu) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    println!("[IEEE 802.15.4] [Invalid frame: {err}]");
+                    continue;
+                }
+            };
             print_ieee802154_mac_frame(&frame).and_then(print_zigbee_nwk_frame);
         }
     }
 }
 
-fn print_ieee802154_mac_frame(frame: &ReceivedFrame) -> Option<&[u8]> {
-    let frame = &frame.frame;
-    let header = frame.header;
+fn print_ieee802154_mac_frame(frame: &MacFrame) -> Option<&[u8]> {
+    let header = frame.header();
+    let (frame_type, payload) = match frame {
+        MacFrame::Beacon(beacon_frame) => ("Beacon", &beacon_frame.payload[..]),
+        MacFrame::Data(data_frame) => ("Data", &data_frame.payload[..]),
+        MacFrame::Acknowledgement(_) => ("Acknowledgement", &[][..]),
+        MacFrame::Command(_) => ("Command", &[][..]),
+    };
 
     println!(
-        "[IEEE 802.15.4] [{frame_type:?}] [Dest: {}] [Src: {}] [Payload size: {size}B]",
-        PrintableAddress(header.destination),
-        PrintableAddress(header.source),
-        frame_type = frame.content,
-        size = frame.payload.len(),
+        "[IEEE 802.15.4] [{frame_type}] [Dest: {}] [Src: {}] [Payload size: {size}B]",
+        PrintableAddress(header.destination, header.destination_pan_id),
+        PrintableAddress(header.source, header.source_pan_id()),
+        size = payload.len(),
     );
 
-    match frame.content {
-        ieee802154::mac::FrameContent::Data => Some(&frame.payload),
+    match frame {
+        MacFrame::Data(_) => Some(payload),
         _ => None,
     }
 }
@@ -60,7 +80,13 @@ fn print_zigbee_nwk_frame(payload: &[u8]) -> Option<&[u8]> {
     let s = unsafe { str::from_utf8_unchecked(&buf) };
     println!("  [ZIGBEE] payload: {s}");
 
-    let nwk_frame = NwkFrame::unpack_from_slice(payload)?;
+    let nwk_frame = match NwkFrame::unpack_from_slice(payload) {
+        Ok(nwk_frame) => nwk_frame,
+        Err(err) => {
+            println!("  [ZIGBEE] [NWK] [Invalid frame: {err}]");
+            return None;
+        }
+    };
     let frame_type = nwk_frame.frame_type_identifier();
     match nwk_frame {
         NwkFrame::Data(nwk_data_frame) => {
@@ -81,7 +107,7 @@ fn print_zigbee_nwk_frame(payload: &[u8]) -> Option<&[u8]> {
 
             None
         }
-        NwkFrame::Reserved(_) | NwkFrame::InterPan(_) => {
+        NwkFrame::InterPan(_) => {
             println!("  [ZIGBEE] [NWK] [{frame_type:?}]");
             None
         }
@@ -89,22 +115,21 @@ fn print_zigbee_nwk_frame(payload: &[u8]) -> Option<&[u8]> {
 }
 
 #[derive(Debug)]
-struct PrintableAddress(Option<Address>);
+struct PrintableAddress(Option<Address>, Option<u16>);
 
 impl Display for PrintableAddress {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        if let Some(addr) = self.0 {
-            match addr {
-                Address::Short(pid, short_address) => {
-                    write!(f, "0x{:04X} (PAN: 0x{:04X})", short_address.0, pid.0)
-                }
-                Address::Extended(pid, extended_address) => {
-                    write!(f, "0x{:016X} (PAN: 0x{:04X})", extended_address.0, pid.0)
-                }
+        match self.0 {
+            Some(Address::Short(short_address)) => write!(f, "0x{:04X}", short_address.0)?,
+            Some(Address::Extended(extended_address)) => {
+                write!(f, "0x{:016X}", extended_address.0)?
             }
-        } else {
-            write!(f, "None")
+            None => return write!(f, "None"),
+        }
+        if let Some(pan_id) = self.1 {
+            write!(f, " (PAN: 0x{pan_id:04X})")?;
         }
+        Ok(())
     }
 }
 
@@ -4,6 +4,8 @@
 /* define a default panic handler */
 use panic_halt as _;
 
+use core::time::Duration;
+
 use cortex_m_rt::entry;
 use embedded_hal::digital::InputPin;
 use rtt_target::{rprintln, rtt_init_print};
@@ -15,13 +17,31 @@ use nrf52833_hal::gpio::Level;
 use nrf52840_hal as hal;
 #[cfg(feature = "nrf52840")]
 use nrf52840_hal::gpio::Level;
+use zigbee::apl::descriptors::node_descriptor::LogicalType;
+use zigbee::nwk::nlme::PollRates;
+
+#[path = "../../common/placeholder.rs"]
+mod placeholder;
 
+use placeholder::block_on;
+use placeholder::PlaceholderRadio;
 
 #[entry]
 fn main() -> ! {
 
     rprintln!("Init zigbee!");
-    let zigbee_device = zigbee::init(zigbee::Config { radio_channel: 11, ..Default::default() });
+    let config = zigbee::Config {
+        radio_channel: 11,
+        device_type: LogicalType::EndDevice,
+        // the sensor sleeps between polls of its parent
+        poll_rates: Some(PollRates {
+            fast: Duration::from_millis(250),
+            slow: Duration::from_secs(30),
+            fast_poll_timeout: Duration::from_secs(5),
+        }),
+        ..Default::default()
+    };
+    let mut zigbee_device = zigbee::init(config, PlaceholderRadio::new());
 
     // init GPIO for device pairing
     let p = hal::pac::Peripherals::take().unwrap();
@@ -31,15 +51,16 @@ fn main() -> ! {
     loop {
         if button.is_high().unwrap() {
             rprintln!("Connect to nearby zigbee network.");
-            zigbee_device.try_to_connect();
+            let _ = block_on(zigbee_device.try_to_connect());
         } else if zigbee_device.is_connected() {
-            rprintln!("Send keep alive to stay in network.");
+            rprintln!("Poll the parent to stay in network.");
 
-            // send keep alive to stay in network
-            zigbee_device.send_keep_alive();
+            // polls the parent, which keeps the device alive, and handles
+            // the frames kept for it
+            let _ = block_on(zigbee_device.process(Duration::from_secs(1)));
 
             // periodic update of sensor data
-            zigbee_device.send_data(&[0x7au8, 0x69u8, 0x67u8, 0x62u8, 0x65u8, 0x65u8]);
+            let _ = block_on(zigbee_device.send_data(&[0x7au8, 0x69u8, 0x67u8, 0x62u8, 0x65u8, 0x65u8]));
 
         } else {
             rprintln!("Idle.");
@@ -49,4 +70,3 @@ fn main() -> ! {
         cortex_m::asm::delay(1_000);
     }
 }
-
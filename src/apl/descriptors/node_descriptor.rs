@@ -7,6 +7,7 @@ use heapless::Vec;
 use strum::EnumCount;
 
 use crate::common::types::macros::bitfield_bits;
+use crate::impl_pack_bytes;
 
 const NODE_DESCRIPTOR_SIZE: usize = 13;
 
@@ -118,7 +119,7 @@ impl NodeDescriptor {
 // 2.3.2.3.1 Logical Type Field
 // The logical type field of the node descriptor is three bits in length and specifies the device type of the ZigBee node.
 #[repr(u8)]
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum LogicalType {
     Coordinator = 0b000,
     Router = 0b001,
@@ -184,7 +185,11 @@ impl FrequencyBands {
 
 // 2.3.2.3.6 MAC Capability Flags Field
 // The MAC capability flags field is eight bits in length and specifies the node capabilities, as required by the IEEE  802.15.4-2015 MAC sub-layer [B1].
-pub struct MacCapabilities(u8);
+impl_pack_bytes! {
+    /// 2.3.2.3.6 MAC Capability Flags Field
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MacCapabilities(u8);
+}
 
 #[repr(u8)]
 #[derive(Clone, Copy, Eq, Hash, PartialEq, EnumCount)]
@@ -211,8 +216,9 @@ pub enum MacCapabilityFlag {
 }
 
 impl MacCapabilities {
+    /// Creates the capability flags field from the set flags.
     // Note: Capacity of IndexSet must be a power of 2.
-    fn new(
+    pub fn new(
         capability_flags: FnvIndexSet<
             MacCapabilityFlag,
             { MacCapabilityFlag::COUNT.next_power_of_two() },
@@ -226,7 +232,8 @@ impl MacCapabilities {
         Self(value)
     }
 
-    fn is_set(&self, mac_capability_flag: MacCapabilityFlag) -> bool {
+    /// Returns if the given flag is set.
+    pub fn is_set(&self, mac_capability_flag: MacCapabilityFlag) -> bool {
         return (self.0 & (1 << mac_capability_flag as u8)) != 0;
     }
 }
@@ -19,12 +19,13 @@ impl ApsInformationBase {
 
     pub fn write_attribute_value(
         &mut self,
-        _id: u8,
-        _value: AIBAttribute,
+        id: u8,
+        value: AIBAttribute,
     ) -> Result<(), &'static str> {
-        // self.attributes.insert(id, value).map_err(|_| "could not insert");
-
-        Ok(())
+        self.attributes
+            .insert(id, value)
+            .map(|_| ())
+            .map_err(|_| "could not insert")
     }
 }
 
@@ -17,7 +17,7 @@ pub struct ApsmeBindRequest {
     pub src_endpoint: types::SrcEndpoint,
     pub cluster_id: u16,
     pub dst_addr_mode: DstAddrMode,
-    pub dst_address: u8,
+    pub dst_address: Address,
     pub dst_endpoint: u8,
 }
 
@@ -38,7 +38,7 @@ pub struct ApsmeBindConfirm {
     pub src_endpoint: types::SrcEndpoint,
     pub cluster_id: u16,
     pub dst_addr_mode: DstAddrMode,
-    pub dst_address: u8,
+    pub dst_address: Address,
     pub dst_endpoint: u8,
 }
 
@@ -49,7 +49,7 @@ pub struct ApsmeUnbindRequest {
     pub(crate) src_endpoint: types::SrcEndpoint,
     pub(crate) cluster_id: u16,
     pub(crate) dst_addr_mode: DstAddrMode,
-    pub(crate) dst_address: u8,
+    pub(crate) dst_address: Address,
     pub(crate) dst_endpoint: u8,
 }
 
@@ -68,7 +68,7 @@ pub struct ApsmeUnbindConfirm {
     pub(crate) src_endpoint: types::SrcEndpoint,
     pub(crate) cluster_id: u16,
     pub(crate) dst_addr_mode: DstAddrMode,
-    pub(crate) dst_address: u8,
+    pub(crate) dst_address: Address,
     pub(crate) dst_endpoint: u8,
 }
 
@@ -119,22 +119,66 @@ pub struct ApsmeSetConfirm {
 
 /// 2.2.4.5.1 - APSME-ADD-GROUP.request
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeAddGroupRequest {}
+pub struct ApsmeAddGroupRequest {
+    pub group_address: u16,
+    pub endpoint: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ApsmeAddGroupStatus {
+    #[default]
+    Success,
+    InvalidParameter,
+    TableFull,
+}
 
 /// 2.2.4.5.2 - APSME-ADD-GROUP.confirm
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeAddGroupConfirm {}
+pub struct ApsmeAddGroupConfirm {
+    pub(crate) status: ApsmeAddGroupStatus,
+    pub group_address: u16,
+    pub endpoint: u8,
+}
 
 /// 2.2.4.5.3 - APSME-REMOVE-GROUP.request
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeRemoveGroupRequest {}
-/// 2.2.4.5.3 - APSME-REMOVE-GROUP.request
+pub struct ApsmeRemoveGroupRequest {
+    pub group_address: u16,
+    pub endpoint: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ApsmeRemoveGroupStatus {
+    #[default]
+    Success,
+    InvalidGroup,
+    InvalidParameter,
+}
+
+/// 2.2.4.5.4 - APSME-REMOVE-GROUP.confirm
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeRemoveGroupConfirm {}
+pub struct ApsmeRemoveGroupConfirm {
+    pub(crate) status: ApsmeRemoveGroupStatus,
+    pub group_address: u16,
+    pub endpoint: u8,
+}
 
 /// 2.2.4.5.5 - APSME-REMOVE-ALL-GROUPS.request
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeRemoveAllGroupsRequest {}
-/// 2.2.4.5.6 - APSME-REMOVE-ALL-GROUPs.request
+pub struct ApsmeRemoveAllGroupsRequest {
+    pub endpoint: u8,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ApsmeRemoveAllGroupsStatus {
+    #[default]
+    Success,
+    InvalidParameter,
+}
+
+/// 2.2.4.5.6 - APSME-REMOVE-ALL-GROUPS.confirm
 #[derive(Debug, Clone, Default, PartialEq, Eq)]
-pub struct ApsmeRemoveAllGroupsConfirm {}
+pub struct ApsmeRemoveAllGroupsConfirm {
+    pub(crate) status: ApsmeRemoveAllGroupsStatus,
+    pub endpoint: u8,
+}
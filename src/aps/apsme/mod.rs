@@ -11,9 +11,11 @@
 #![allow(dead_code)]
 
 use core::ops::Not;
+use core::time::Duration;
 
 use basemgt::ApsmeAddGroupConfirm;
 use basemgt::ApsmeAddGroupRequest;
+use basemgt::ApsmeAddGroupStatus;
 use basemgt::ApsmeBindConfirm;
 use basemgt::ApsmeBindRequest;
 use basemgt::ApsmeBindRequestStatus;
@@ -21,8 +23,10 @@ use basemgt::ApsmeGetConfirm;
 use basemgt::ApsmeGetConfirmStatus;
 use basemgt::ApsmeRemoveAllGroupsConfirm;
 use basemgt::ApsmeRemoveAllGroupsRequest;
+use basemgt::ApsmeRemoveAllGroupsStatus;
 use basemgt::ApsmeRemoveGroupConfirm;
 use basemgt::ApsmeRemoveGroupRequest;
+use basemgt::ApsmeRemoveGroupStatus;
 use basemgt::ApsmeSetConfirm;
 use basemgt::ApsmeUnbindConfirm;
 use basemgt::ApsmeUnbindRequest;
@@ -31,16 +35,49 @@ use basemgt::ApsmeUnbindRequestStatus;
 use super::aib::AIBAttribute;
 use super::aib::ApsInformationBase;
 use super::binding::ApsBindingTable;
+use super::frame::ApsDataFrame;
+use super::frame::DeliveryMode;
+use super::group::ApsGroupTable;
 use super::types::Address;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::common::parse::PackBytes;
+use crate::common::types::ShortAddress;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::nwk::nlde::NldeAddressMode;
+use crate::nwk::nlde::NldeDataRequest;
+use crate::nwk::nlde::NldeDataStatus;
+use crate::nwk::nlme::management::NlmeEdScanConfirm;
+use crate::nwk::nlme::management::NlmeEdScanRequest;
+use crate::nwk::nlme::management::NlmeIndication;
+use crate::nwk::nlme::management::NlmeJoinConfirm;
 use crate::nwk::nlme::management::NlmeJoinRequest;
 use crate::nwk::nlme::management::NlmeJoinStatus;
+use crate::nwk::nlme::management::NlmeLeaveConfirm;
+use crate::nwk::nlme::management::NlmeLeaveIndication;
+use crate::nwk::nlme::management::NlmeLeaveRequest;
+use crate::nwk::nlme::management::NlmeLeaveStatus;
+use crate::nwk::nlme::management::NlmeNetworkDiscoveryConfirm;
 use crate::nwk::nlme::management::NlmeNetworkDiscoveryRequest;
+use crate::nwk::nlme::management::NlmeNetworkFormationConfirm;
+use crate::nwk::nlme::management::NlmeNetworkFormationRequest;
+use crate::nwk::nlme::management::NlmeNetworkFormationStatus;
+use crate::nwk::nlme::management::NlmePermitJoiningConfirm;
+use crate::nwk::nlme::management::NlmePermitJoiningRequest;
+use crate::nwk::nlme::management::NlmeStartRouterConfirm;
+use crate::nwk::nlme::management::NlmeStartRouterRequest;
+use crate::nwk::nlme::management::RejoinNetwork;
 use crate::nwk::nlme::Nlme;
 use crate::nwk::nlme::NlmeSap;
+use crate::radio::ChannelMask;
+use crate::radio::Radio;
 
 pub mod basemgt;
 pub mod groupmgt;
 
+/// Scan duration used for network discovery and energy scans, about 138 ms
+/// per channel.
+const SCAN_DURATION: u8 = 3;
+
 /// Application support sub-layer management service - service access point
 ///
 /// 2.2.4.2
@@ -58,64 +95,270 @@ pub trait ApsmeSap {
     /// 2.2.4.4.3 - APSME-SET.request
     fn set(&mut self, attribute: AIBAttribute) -> ApsmeSetConfirm;
     /// 2.2.4.5.1 - APSME-ADD-GROUP.request
-    fn add_group(&self, request: ApsmeAddGroupRequest) -> ApsmeAddGroupConfirm;
+    fn add_group(&mut self, request: ApsmeAddGroupRequest) -> ApsmeAddGroupConfirm;
     /// 2.2.4.5.3 - APSME-REMOVE-GROUP.request
-    fn remove_group(&self, request: ApsmeRemoveGroupRequest) -> ApsmeRemoveGroupConfirm;
+    fn remove_group(&mut self, request: ApsmeRemoveGroupRequest) -> ApsmeRemoveGroupConfirm;
     /// 2.2.4.5.5 - APSME-REMOVE-ALL-GROUPS.request
     fn remove_all_groups(
-        &self,
+        &mut self,
         request: ApsmeRemoveAllGroupsRequest,
     ) -> ApsmeRemoveAllGroupsConfirm;
 }
 
-pub(crate) struct Apsme {
+pub(crate) struct Apsme<R> {
     pub(crate) supports_binding_table: bool,
     pub(crate) binding_table: ApsBindingTable,
+    group_table: ApsGroupTable,
     pub(crate) joined_network: Option<Address>,
     pub(crate) aib: ApsInformationBase,
-    pub(crate) nwk: Nlme,
+    /// The APS counter of the next frame sent.
+    counter: u8,
+    pub(crate) nwk: Nlme<R>,
 }
 
-impl Apsme {
-    pub(crate) fn new() -> Self {
+impl<R: Radio> Apsme<R> {
+    pub(crate) fn new(radio: R) -> Self {
         Self {
             supports_binding_table: true,
             binding_table: ApsBindingTable::new(),
+            group_table: ApsGroupTable::new(),
             joined_network: None,
             aib: ApsInformationBase::new(),
-            nwk: Nlme::new(),
+            counter: 0,
+            nwk: Nlme::new(radio),
         }
     }
-    fn is_joined(&self) -> bool {
+    pub(crate) fn is_joined(&self) -> bool {
         self.joined_network.is_some()
     }
 
-    pub(crate) fn start_network_discovery(&self) {
+    /// Mirrors the groups of the group table into nwkGroupIDTable.
+    fn update_group_id_table(&mut self) {
+        self.nwk.nib.group_id_table = self.group_table.groups().collect();
+    }
+
+    pub(crate) async fn start_network_discovery(
+        &mut self,
+        channels: ChannelMask,
+    ) -> NlmeNetworkDiscoveryConfirm {
         let request = NlmeNetworkDiscoveryRequest {
-            scan_channels_list_structure: [0, 0, 0, 0, 0, 0, 0, 0],
-            scan_duration: 10u8,
+            scan_channels_list_structure: channels,
+            scan_duration: SCAN_DURATION,
         };
-        let confirm = self.nwk.network_discovery(request);
+        self.nwk.network_discovery(request).await
+    }
 
-        match confirm.status {
-            crate::nwk::nlme::management::NlmeNetworkDiscoveryStatus::Successful => {
-                // TODO: return list of available networks
-            }
+    /// Measures the energy of `channels` and records the energy of the current
+    /// channel as apsLastChannelEnergy.
+    pub(crate) async fn scan_channel_energy(&mut self, channels: ChannelMask) -> NlmeEdScanConfirm {
+        let request = NlmeEdScanRequest {
+            scan_channels_list_structure: channels,
+            scan_duration: SCAN_DURATION,
+        };
+        let confirm = self.nwk.ed_scan(request).await;
+        let current_channel = self.nwk.mac.channel;
+        if let Some(measured) = confirm
+            .energy_detect_list
+            .iter()
+            .find(|measured| measured.channel == current_channel)
+        {
+            let _ = self.set(AIBAttribute::ApsLastChannelEnergy(measured.energy));
         }
+        confirm
     }
 
-    pub(crate) fn join_network(&self) {
+    /// Forms a new network as coordinator on one of `channels`, using
+    /// apsUseExtendedPANID as extended PAN ID if set.
+    pub(crate) async fn form_network(
+        &mut self,
+        channels: ChannelMask,
+    ) -> NlmeNetworkFormationConfirm {
+        // apsUseExtendedPANID
+        let extended_pan_id = match self.aib.get_attribute(0xc4) {
+            Some(AIBAttribute::ApsUseExtendedPanId(extended_pan_id)) => *extended_pan_id,
+            _ => 0,
+        };
+        let request = NlmeNetworkFormationRequest {
+            scan_channels_list_structure: channels,
+            scan_duration: SCAN_DURATION,
+            beacon_order: 15,
+            superframe_order: 15,
+            battery_life_extension: false,
+            extended_pan_id,
+        };
+        let confirm = self.nwk.network_formation(request).await;
+        if confirm.status == NlmeNetworkFormationStatus::Success {
+            self.joined_network = Some(Address::Extended(confirm.extended_pan_id));
+        }
+        confirm
+    }
+
+    /// Joins the network `extended_pan_id` found by a previous network
+    /// discovery through association.
+    pub(crate) async fn join_network(
+        &mut self,
+        extended_pan_id: u64,
+        capability_information: MacCapabilities,
+    ) -> NlmeJoinConfirm {
         let request = NlmeJoinRequest {
-            extended_pan_id: 0x0015_8D00_01AB_CD12,
-            rejoin_network: 0u8,
-            scan_duration: 10u8,
+            extended_pan_id,
+            rejoin_network: RejoinNetwork::Association,
+            scan_channels_list_structure: ChannelMask(0),
+            scan_duration: SCAN_DURATION,
+            capability_information,
             security_enabled: false,
         };
-        let confirm = self.nwk.join(request);
-        if let NlmeJoinStatus::Success = confirm.status {
-            // confirm.extended_pan_id
-        } else {
-            // TODO: handle errors
+        let confirm = self.nwk.join(request).await;
+        if confirm.status == NlmeJoinStatus::Success {
+            self.joined_network = Some(Address::Extended(confirm.extended_pan_id));
+        }
+        confirm
+    }
+
+    /// Rejoins the network `extended_pan_id` through a router on one of
+    /// `channels`, secured with the network key if available.
+    pub(crate) async fn rejoin_network(
+        &mut self,
+        extended_pan_id: u64,
+        channels: ChannelMask,
+        capability_information: MacCapabilities,
+    ) -> NlmeJoinConfirm {
+        let request = NlmeJoinRequest {
+            extended_pan_id,
+            rejoin_network: RejoinNetwork::Rejoin,
+            scan_channels_list_structure: channels,
+            scan_duration: SCAN_DURATION,
+            capability_information,
+            security_enabled: true,
+        };
+        let confirm = self.nwk.join(request).await;
+        if confirm.status == NlmeJoinStatus::Success {
+            self.joined_network = Some(Address::Extended(confirm.extended_pan_id));
+        }
+        confirm
+    }
+
+    /// Leaves the network, after asking the children to leave as well if
+    /// `remove_children` is set.
+    pub(crate) async fn leave_network(
+        &mut self,
+        rejoin: bool,
+        remove_children: bool,
+    ) -> NlmeLeaveConfirm {
+        let request = NlmeLeaveRequest {
+            device_address: None,
+            remove_children,
+            rejoin,
+        };
+        let confirm = self.nwk.leave(request).await;
+        if confirm.status == NlmeLeaveStatus::Success {
+            self.forget_network(rejoin);
+        }
+        confirm
+    }
+
+    /// Asks the child `extended_address` to leave the network.
+    ///
+    /// The bindings to the child are removed along with it.
+    pub(crate) async fn remove_child(
+        &mut self,
+        extended_address: u64,
+        rejoin: bool,
+    ) -> NlmeLeaveConfirm {
+        let request = NlmeLeaveRequest {
+            device_address: Some(extended_address),
+            remove_children: false,
+            rejoin,
+        };
+        let confirm = self.nwk.leave(request).await;
+        self.forget_departed_devices();
+        confirm
+    }
+
+    /// Starts operating as router on the joined non-beacon network.
+    pub(crate) async fn start_router(&mut self) -> NlmeStartRouterConfirm {
+        let request = NlmeStartRouterRequest {
+            beacon_order: 15,
+            superframe_order: 15,
+            battery_life_extension: false,
+        };
+        self.nwk.start_router(request).await
+    }
+
+    /// Permits joining through this device for `permit_duration` seconds.
+    pub(crate) async fn permit_joining(&mut self, permit_duration: u8) -> NlmePermitJoiningConfirm {
+        self.nwk
+            .permit_joining(NlmePermitJoiningRequest { permit_duration })
+            .await
+    }
+
+    /// Sends `frame` to the device or broadcast address `destination`,
+    /// secured with the network key.
+    ///
+    /// The delivery mode and the APS counter of the frame are filled in.
+    pub(crate) async fn send_frame(
+        &mut self,
+        destination: ShortAddress,
+        mut frame: ApsDataFrame,
+    ) -> Result<NldeDataStatus, R::Error> {
+        frame
+            .frame_control
+            .set_delivery_mode(if destination.0 >= MIN_BROADCAST_ADDRESS {
+                DeliveryMode::Broadcast
+            } else {
+                DeliveryMode::Unicast
+            });
+        frame.counter = self.counter;
+        self.counter = self.counter.wrapping_add(1);
+        let Ok(nsdu) = frame.pack_to_vec() else {
+            return Ok(NldeDataStatus::FrameTooLong);
+        };
+        let request = NldeDataRequest {
+            destination_address_mode: NldeAddressMode::Network,
+            destination,
+            nsdu,
+            radius: 0,
+            non_member_radius: 0,
+            discover_route: true,
+            security_enable: true,
+        };
+        Ok(self.nwk.data_request(request).await?.status)
+    }
+
+    /// Handles the frames received within `timeout`.
+    ///
+    /// Returns early with the first indication of the network layer. The
+    /// network is forgotten once this device left it, the bindings to other
+    /// devices once they left.
+    pub(crate) async fn process(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let indication = self.nwk.process(timeout).await?;
+        self.forget_departed_devices();
+        if let Some(NlmeIndication::Leave(NlmeLeaveIndication {
+            device_address: None,
+            rejoin,
+        })) = indication
+        {
+            self.forget_network(rejoin);
+        }
+        Ok(indication)
+    }
+
+    /// Removes the bindings to the devices which left the network.
+    fn forget_departed_devices(&mut self) {
+        for device in self.nwk.take_departed_devices() {
+            self.binding_table.remove_device(device.0);
+        }
+    }
+
+    /// Forgets about the network this device left, bindings are kept for a
+    /// rejoin.
+    fn forget_network(&mut self, rejoin: bool) {
+        self.joined_network = None;
+        if !rejoin {
+            self.binding_table.clear();
         }
     }
 
@@ -131,7 +374,7 @@ impl Apsme {
     // }
 }
 
-impl ApsmeSap for Apsme {
+impl<R: Radio> ApsmeSap for Apsme<R> {
     /// 2.2.4.3.1 - APSME-BIND.request
     /// request to bind two devices together, or to bind a device to a group
     fn bind_request(&mut self, request: ApsmeBindRequest) -> ApsmeBindConfirm {
@@ -192,17 +435,20 @@ impl ApsmeSap for Apsme {
     // 2.2.4.4.1 APSME-GET.request
     fn get(&self, identifier: u8) -> ApsmeGetConfirm {
         let attr = self.aib.get_attribute(identifier);
-        attr.map_or(ApsmeGetConfirm {
+        attr.map_or(
+            ApsmeGetConfirm {
                 status: ApsmeGetConfirmStatus::UnsupportedAttribute,
                 attribute: identifier,
                 attribute_length: 0,
                 attribute_value: None,
-            }, |attr| ApsmeGetConfirm {
+            },
+            |attr| ApsmeGetConfirm {
                 status: ApsmeGetConfirmStatus::Success,
                 attribute: attr.id(),
                 attribute_length: attr.length(),
                 attribute_value: Some(attr.value()),
-            })
+            },
+        )
     }
 
     // 2.2.4.4.3 APSME-SET.request
@@ -218,43 +464,95 @@ impl ApsmeSap for Apsme {
     }
 
     /// 2.2.4.5.1 - APSME-ADD-GROUP.request
-    fn add_group(&self, _request: ApsmeAddGroupRequest) -> ApsmeAddGroupConfirm {
-        ApsmeAddGroupConfirm {}
+    fn add_group(&mut self, request: ApsmeAddGroupRequest) -> ApsmeAddGroupConfirm {
+        let status = if is_valid_endpoint(request.endpoint) {
+            match self
+                .group_table
+                .add(request.group_address, request.endpoint)
+            {
+                Ok(()) => ApsmeAddGroupStatus::Success,
+                Err(_) => ApsmeAddGroupStatus::TableFull,
+            }
+        } else {
+            ApsmeAddGroupStatus::InvalidParameter
+        };
+        self.update_group_id_table();
+
+        ApsmeAddGroupConfirm {
+            status,
+            group_address: request.group_address,
+            endpoint: request.endpoint,
+        }
     }
 
     /// 2.2.4.5.3 - APSME-REMOVE-GROUP.request
-    fn remove_group(&self, _request: ApsmeRemoveGroupRequest) -> ApsmeRemoveGroupConfirm {
-        todo!()
+    fn remove_group(&mut self, request: ApsmeRemoveGroupRequest) -> ApsmeRemoveGroupConfirm {
+        let status = if is_valid_endpoint(request.endpoint) {
+            match self
+                .group_table
+                .remove(request.group_address, request.endpoint)
+            {
+                Ok(()) => ApsmeRemoveGroupStatus::Success,
+                Err(_) => ApsmeRemoveGroupStatus::InvalidGroup,
+            }
+        } else {
+            ApsmeRemoveGroupStatus::InvalidParameter
+        };
+        self.update_group_id_table();
+
+        ApsmeRemoveGroupConfirm {
+            status,
+            group_address: request.group_address,
+            endpoint: request.endpoint,
+        }
     }
 
     /// 2.2.4.5.5 - APSME-REMOVE-ALL-GROUPS.request
     fn remove_all_groups(
-        &self,
-        _request: ApsmeRemoveAllGroupsRequest,
+        &mut self,
+        request: ApsmeRemoveAllGroupsRequest,
     ) -> ApsmeRemoveAllGroupsConfirm {
-        todo!()
+        let status = if is_valid_endpoint(request.endpoint) {
+            self.group_table.remove_all(request.endpoint);
+            ApsmeRemoveAllGroupsStatus::Success
+        } else {
+            ApsmeRemoveAllGroupsStatus::InvalidParameter
+        };
+        self.update_group_id_table();
+
+        ApsmeRemoveAllGroupsConfirm {
+            status,
+            endpoint: request.endpoint,
+        }
     }
 }
 
+/// Endpoints which may be members of a group.
+fn is_valid_endpoint(endpoint: u8) -> bool {
+    (0x01..=0xfe).contains(&endpoint)
+}
+
 #[cfg(test)]
 mod tests {
     use basemgt::ApsmeBindRequestStatus;
 
     use super::*;
     use crate::aps::types::SrcEndpoint;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
 
     // 2.2.4.3.1
     #[test]
     fn bind_request_device_does_not_support_binding_should_fail() {
         // given
-        let mut apsme = Apsme::new();
+        let mut apsme = Apsme::new(MockRadio::new());
         apsme.supports_binding_table = false;
         let request = ApsmeBindRequest {
             src_address: Address::Extended(0u64),
             src_endpoint: SrcEndpoint::new(10).unwrap_or(SrcEndpoint { value: 0 }),
             cluster_id: 1u16,
             dst_addr_mode: 0u8,
-            dst_address: 1u8,
+            dst_address: Address::Extended(1u64),
             dst_endpoint: 2u8,
         };
 
@@ -269,13 +567,13 @@ mod tests {
     #[test]
     fn bind_request_from_an_unjoined_device_should_fail() {
         // given
-        let mut apsme = Apsme::new();
+        let mut apsme = Apsme::new(MockRadio::new());
         let request = ApsmeBindRequest {
             src_address: Address::Extended(0u64),
             src_endpoint: SrcEndpoint::new(10).unwrap_or(SrcEndpoint { value: 0 }),
             cluster_id: 1u16,
             dst_addr_mode: 0u8,
-            dst_address: 1u8,
+            dst_address: Address::Extended(1u64),
             dst_endpoint: 2u8,
         };
 
@@ -290,7 +588,7 @@ mod tests {
     #[test]
     fn bind_request_with_full_table_should_fail() {
         // given
-        let mut apsme = Apsme::new();
+        let mut apsme = Apsme::new(MockRadio::new());
         apsme.joined_network = Some(Address::Extended(10u64));
         for n in 0..265u64 {
             let request = ApsmeBindRequest {
@@ -298,7 +596,7 @@ mod tests {
                 src_endpoint: SrcEndpoint::new(10).unwrap_or(SrcEndpoint { value: 0 }),
                 cluster_id: 1u16,
                 dst_addr_mode: 0u8,
-                dst_address: 1u8,
+                dst_address: Address::Extended(1u64),
                 dst_endpoint: 2u8,
             };
             let _ = apsme.bind_request(request);
@@ -310,7 +608,7 @@ mod tests {
             src_endpoint: SrcEndpoint::new(10).unwrap_or(SrcEndpoint { value: 0 }),
             cluster_id: 1u16,
             dst_addr_mode: 0u8,
-            dst_address: 1u8,
+            dst_address: Address::Extended(1u64),
             dst_endpoint: 2u8,
         };
         let result = apsme.bind_request(request);
@@ -322,7 +620,7 @@ mod tests {
     #[test]
     fn bind_request_with_valid_request_should_succeed() {
         // given
-        let mut apsme = Apsme::new();
+        let mut apsme = Apsme::new(MockRadio::new());
         apsme.joined_network = Some(Address::Extended(10u64));
 
         // when
@@ -331,7 +629,7 @@ mod tests {
             src_endpoint: SrcEndpoint::new(10).unwrap_or(SrcEndpoint { value: 0 }),
             cluster_id: 1u16,
             dst_addr_mode: 0u8,
-            dst_address: 1u8,
+            dst_address: Address::Extended(1u64),
             dst_endpoint: 2u8,
         };
         let result = apsme.bind_request(request);
@@ -339,4 +637,97 @@ mod tests {
         // then
         assert_eq!(result.status, ApsmeBindRequestStatus::Success);
     }
+
+    #[test]
+    fn channel_energy_scan_records_last_channel_energy() {
+        // given
+        let mut radio = MockRadio::new();
+        radio.energy[0] = 0x42;
+        let mut apsme = Apsme::new(radio);
+
+        // when
+        let confirm = block_on(apsme.scan_channel_energy(ChannelMask::ALL_2_4_GHZ));
+
+        // then
+        assert_eq!(confirm.energy_detect_list.len(), 16);
+        assert_eq!(
+            apsme.aib.get_attribute(0xca),
+            Some(&AIBAttribute::ApsLastChannelEnergy(0x42))
+        );
+    }
+
+    // 2.2.4.5.1
+    #[test]
+    fn add_group_updates_nwk_group_id_table() {
+        // given
+        let mut apsme = Apsme::new(MockRadio::new());
+
+        // when
+        let first = apsme.add_group(ApsmeAddGroupRequest {
+            group_address: 0x0123,
+            endpoint: 1,
+        });
+        let second = apsme.add_group(ApsmeAddGroupRequest {
+            group_address: 0x0123,
+            endpoint: 2,
+        });
+        let invalid = apsme.add_group(ApsmeAddGroupRequest {
+            group_address: 0x0456,
+            endpoint: 0xff,
+        });
+
+        // then
+        assert_eq!(first.status, ApsmeAddGroupStatus::Success);
+        assert_eq!(second.status, ApsmeAddGroupStatus::Success);
+        assert_eq!(invalid.status, ApsmeAddGroupStatus::InvalidParameter);
+        assert_eq!(apsme.nwk.nib.group_id_table, [0x0123]);
+    }
+
+    // 2.2.4.5.3
+    #[test]
+    fn remove_group_keeps_group_of_other_endpoints() {
+        // given
+        let mut apsme = Apsme::new(MockRadio::new());
+        for endpoint in [1, 2] {
+            let _ = apsme.add_group(ApsmeAddGroupRequest {
+                group_address: 0x0123,
+                endpoint,
+            });
+        }
+
+        // when
+        let removed = apsme.remove_group(ApsmeRemoveGroupRequest {
+            group_address: 0x0123,
+            endpoint: 1,
+        });
+        let unknown = apsme.remove_group(ApsmeRemoveGroupRequest {
+            group_address: 0x0123,
+            endpoint: 1,
+        });
+
+        // then
+        assert_eq!(removed.status, ApsmeRemoveGroupStatus::Success);
+        assert_eq!(unknown.status, ApsmeRemoveGroupStatus::InvalidGroup);
+        assert_eq!(apsme.nwk.nib.group_id_table, [0x0123]);
+    }
+
+    // 2.2.4.5.5
+    #[test]
+    fn remove_all_groups_of_endpoint_clears_nwk_group_id_table() {
+        // given
+        let mut apsme = Apsme::new(MockRadio::new());
+        for group_address in [0x0123, 0x0456] {
+            let _ = apsme.add_group(ApsmeAddGroupRequest {
+                group_address,
+                endpoint: 1,
+            });
+        }
+
+        // when
+        let confirm = apsme.remove_all_groups(ApsmeRemoveAllGroupsRequest { endpoint: 1 });
+
+        // then
+        assert_eq!(confirm.status, ApsmeRemoveAllGroupsStatus::Success);
+        assert!(apsme.nwk.nib.group_id_table.is_empty());
+    }
 }
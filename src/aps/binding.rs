@@ -16,6 +16,7 @@ pub(crate) struct Binding {
     source: Address,
     endpoint: u8,
     cluster_id: u16,
+    destination: Address,
 }
 
 /// 2.2.8.2
@@ -24,8 +25,6 @@ pub(crate) struct ApsBindingTable {
     entries: Vec<Binding, 265>,
 }
 
-pub(crate) struct ApsGroupTable {}
-
 impl ApsBindingTable {
     pub(crate) fn new() -> Self {
         Self {
@@ -49,10 +48,22 @@ impl ApsBindingTable {
                 source: request.src_address.clone(),
                 endpoint: request.dst_endpoint,
                 cluster_id: request.cluster_id,
+                destination: request.dst_address.clone(),
             })
             .map_err(|_| BindingError::TableFull)
     }
 
+    /// Removes all bindings.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Removes the bindings to the device `extended_address`.
+    pub(crate) fn remove_device(&mut self, extended_address: u64) {
+        self.entries
+            .retain(|binding| binding.destination != Address::Extended(extended_address));
+    }
+
     pub(crate) fn remove_binding_link(
         &self,
         _request: &ApsmeUnbindRequest,
@@ -68,3 +79,43 @@ pub(crate) enum BindingError {
     InvalidBinding,
     TableFull,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aps::types::SrcEndpoint;
+
+    fn bind_request(destination: Address) -> ApsmeBindRequest {
+        ApsmeBindRequest {
+            src_address: Address::Extended(0u64),
+            src_endpoint: SrcEndpoint { value: 1 },
+            cluster_id: 6u16,
+            dst_addr_mode: 3u8,
+            dst_address: destination,
+            dst_endpoint: 1u8,
+        }
+    }
+
+    #[test]
+    fn bindings_to_a_departed_device_are_removed() {
+        let mut table = ApsBindingTable::new();
+        for destination in [
+            Address::Extended(1u64),
+            Address::Extended(2u64),
+            Address::Group(1u16),
+            Address::Extended(1u64),
+        ] {
+            table
+                .create_binding_link(&bind_request(destination))
+                .unwrap();
+        }
+
+        table.remove_device(1u64);
+
+        assert_eq!(table.entries.len(), 2);
+        assert!(table
+            .entries
+            .iter()
+            .all(|binding| binding.destination != Address::Extended(1u64)));
+    }
+}
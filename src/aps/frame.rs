@@ -0,0 +1,246 @@
+//! APS Frame Formats
+//!
+//! Only data frames without extended header are supported, as exchanged
+//! between the ZigBee Device Objects of the devices.
+//!
+//! See Section 2.2.5.
+use core::fmt::Debug;
+
+use heapless::Vec;
+
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+use crate::impl_pack_bytes;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+
+/// 2.2.5.1.1.1 Frame Type Sub-Field
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum ApsFrameType {
+    Data = 0b00,
+    Command = 0b01,
+    Acknowledgement = 0b10,
+    InterPanAps = 0b11,
+}
+
+/// 2.2.5.1.1.2 Delivery Mode Sub-Field
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum DeliveryMode {
+    Unicast = 0b00,
+    Reserved = 0b01,
+    Broadcast = 0b10,
+    Group = 0b11,
+}
+
+impl_pack_bytes! {
+    /// 2.2.5.1.1 Frame Control Field
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct ApsFrameControl(pub u8);
+}
+
+impl Debug for ApsFrameControl {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ApsFrameControl")
+            .field("frame_type", &self.frame_type())
+            .field("delivery_mode", &self.delivery_mode())
+            .field("security", &self.security())
+            .field("ack_request", &self.ack_request())
+            .field("extended_header", &self.extended_header())
+            .finish()
+    }
+}
+
+impl ApsFrameControl {
+    /// Creates the frame control field of a unicast frame without security
+    /// and acknowledgement.
+    pub fn new(frame_type: ApsFrameType) -> Self {
+        Self(frame_type as u8)
+    }
+
+    /// See Section 2.2.5.1.1.1.
+    pub fn frame_type(&self) -> ApsFrameType {
+        match self.0 & 0b11 {
+            0b00 => ApsFrameType::Data,
+            0b01 => ApsFrameType::Command,
+            0b10 => ApsFrameType::Acknowledgement,
+            _ => ApsFrameType::InterPanAps,
+        }
+    }
+
+    /// See Section 2.2.5.1.1.2.
+    pub fn delivery_mode(&self) -> DeliveryMode {
+        match (self.0 >> 2) & 0b11 {
+            0b00 => DeliveryMode::Unicast,
+            0b01 => DeliveryMode::Reserved,
+            0b10 => DeliveryMode::Broadcast,
+            _ => DeliveryMode::Group,
+        }
+    }
+
+    /// See Section 2.2.5.1.1.2.
+    pub fn set_delivery_mode(&mut self, delivery_mode: DeliveryMode) {
+        self.0 = (self.0 & !(0b11 << 2)) | (delivery_mode as u8) << 2;
+    }
+
+    /// The frame is secured at the APS layer.
+    ///
+    /// See Section 2.2.5.1.1.4.
+    pub fn security(&self) -> bool {
+        (self.0 >> 5) & 0b1 != 0
+    }
+
+    /// The recipient is asked to acknowledge the frame.
+    ///
+    /// See Section 2.2.5.1.1.5.
+    pub fn ack_request(&self) -> bool {
+        (self.0 >> 6) & 0b1 != 0
+    }
+
+    /// See Section 2.2.5.1.1.6.
+    pub fn extended_header(&self) -> bool {
+        (self.0 >> 7) & 0b1 != 0
+    }
+}
+
+/// 2.2.5.2.1 Data Frame Format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApsDataFrame {
+    /// See Section 2.2.5.1.1.
+    pub frame_control: ApsFrameControl,
+    /// Set unless the frame is delivered to a group.
+    /// See Section 2.2.5.1.2.
+    pub destination_endpoint: Option<u8>,
+    /// Set only if the frame is delivered to a group.
+    /// See Section 2.2.5.1.3.
+    pub group_address: Option<u16>,
+    /// See Section 2.2.5.1.4.
+    pub cluster_id: u16,
+    /// See Section 2.2.5.1.5.
+    pub profile_id: u16,
+    /// See Section 2.2.5.1.6.
+    pub source_endpoint: u8,
+    /// See Section 2.2.5.1.7.
+    pub counter: u8,
+    /// The ASDU.
+    pub payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+}
+
+impl PackBytes for ApsDataFrame {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let frame_control: ApsFrameControl = src.unpack()?;
+        if frame_control.frame_type() != ApsFrameType::Data {
+            return Err(ParseError::InvalidValue {
+                field: "frame_type",
+                offset: 0,
+            });
+        }
+        if frame_control.extended_header() {
+            return Err(ParseError::InvalidValue {
+                field: "extended_header",
+                offset: 0,
+            });
+        }
+        let (destination_endpoint, group_address) = match frame_control.delivery_mode() {
+            DeliveryMode::Unicast | DeliveryMode::Broadcast => (Some(src.byte()?), None),
+            DeliveryMode::Group => (None, Some(src.unpack()?)),
+            DeliveryMode::Reserved => {
+                return Err(ParseError::InvalidValue {
+                    field: "delivery_mode",
+                    offset: 0,
+                })
+            }
+        };
+        Ok(Self {
+            frame_control,
+            destination_endpoint,
+            group_address,
+            cluster_id: src.unpack()?,
+            profile_id: src.unpack()?,
+            source_endpoint: src.byte()?,
+            counter: src.byte()?,
+            payload: src.unpack()?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.frame_control.pack_into(buf)?;
+        if let Some(destination_endpoint) = &self.destination_endpoint {
+            offset += destination_endpoint.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(group_address) = &self.group_address {
+            offset += group_address.pack_into(remaining(buf, offset)?)?;
+        }
+        offset += self.cluster_id.pack_into(remaining(buf, offset)?)?;
+        offset += self.profile_id.pack_into(remaining(buf, offset)?)?;
+        offset += self.source_endpoint.pack_into(remaining(buf, offset)?)?;
+        offset += self.counter.pack_into(remaining(buf, offset)?)?;
+        offset += self.payload.pack_into(remaining(buf, offset)?)?;
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_broadcast_data_frame() {
+        let frame = ApsDataFrame::unpack_from_slice(&[
+            0x08, 0x00, 0x36, 0x00, 0x00, 0x00, 0x00, 0x2a, 0x01, 0xb4, 0x01,
+        ])
+        .unwrap();
+
+        assert_eq!(frame.frame_control.delivery_mode(), DeliveryMode::Broadcast);
+        assert_eq!(frame.destination_endpoint, Some(0x00));
+        assert_eq!(frame.group_address, None);
+        assert_eq!(frame.cluster_id, 0x0036);
+        assert_eq!(frame.profile_id, 0x0000);
+        assert_eq!(frame.source_endpoint, 0x00);
+        assert_eq!(frame.counter, 0x2a);
+        assert_eq!(frame.payload, [0x01, 0xb4, 0x01]);
+    }
+
+    #[test]
+    fn pack_group_data_frame() {
+        let mut frame_control = ApsFrameControl::new(ApsFrameType::Data);
+        frame_control.set_delivery_mode(DeliveryMode::Group);
+        let frame = ApsDataFrame {
+            frame_control,
+            destination_endpoint: None,
+            group_address: Some(0x1234),
+            cluster_id: 0x0006,
+            profile_id: 0x0104,
+            source_endpoint: 0x01,
+            counter: 0x07,
+            payload: Vec::from_slice(&[0x01]).unwrap(),
+        };
+
+        let packed: Vec<u8, 16> = frame.pack_to_vec().unwrap();
+
+        assert_eq!(
+            packed,
+            [0x0c, 0x34, 0x12, 0x06, 0x00, 0x04, 0x01, 0x01, 0x07, 0x01]
+        );
+        assert_eq!(ApsDataFrame::unpack_from_slice(&packed), Ok(frame));
+    }
+
+    #[test]
+    fn unpack_command_frame_should_fail() {
+        let got = ApsDataFrame::unpack_from_slice(&[0x01, 0x00]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "frame_type",
+                offset: 0
+            })
+        );
+    }
+}
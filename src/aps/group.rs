@@ -0,0 +1,95 @@
+//! 2.2.8.3 Group Table
+//!
+//! The groups the endpoints of this device are members of. The NWK layer
+//! keeps the group identifiers in nwkGroupIDTable to filter multicast
+//! frames, which are delivered to the endpoints of their group.
+
+use heapless::Vec;
+use thiserror::Error;
+
+use crate::nwk::nib::MAX_GROUP_IDS;
+
+/// Maximum number of endpoints in a group.
+const MAX_GROUP_ENDPOINTS: usize = 8;
+
+/// Group table entry
+struct Group {
+    address: u16,
+    endpoints: Vec<u8, MAX_GROUP_ENDPOINTS>,
+}
+
+/// 2.2.8.3
+pub(super) struct ApsGroupTable {
+    entries: Vec<Group, MAX_GROUP_IDS>,
+}
+
+impl ApsGroupTable {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Iterates over the addresses of all groups.
+    pub(crate) fn groups(&self) -> impl Iterator<Item = u16> + '_ {
+        self.entries.iter().map(|group| group.address)
+    }
+
+    /// Adds `endpoint` to the group `address`, creating the group if needed.
+    pub(crate) fn add(&mut self, address: u16, endpoint: u8) -> Result<(), GroupError> {
+        if let Some(group) = self
+            .entries
+            .iter_mut()
+            .find(|group| group.address == address)
+        {
+            if group.endpoints.contains(&endpoint) {
+                return Ok(());
+            }
+            return group
+                .endpoints
+                .push(endpoint)
+                .map_err(|_| GroupError::TableFull);
+        }
+        let mut endpoints = Vec::new();
+        let _ = endpoints.push(endpoint);
+        self.entries
+            .push(Group { address, endpoints })
+            .map_err(|_| GroupError::TableFull)
+    }
+
+    /// Removes `endpoint` from the group `address`, and the group once it
+    /// has no endpoints left.
+    pub(crate) fn remove(&mut self, address: u16, endpoint: u8) -> Result<(), GroupError> {
+        let index = self
+            .entries
+            .iter()
+            .position(|group| group.address == address)
+            .ok_or(GroupError::InvalidGroup)?;
+        let endpoints = &mut self.entries[index].endpoints;
+        let position = endpoints
+            .iter()
+            .position(|&member| member == endpoint)
+            .ok_or(GroupError::InvalidGroup)?;
+        endpoints.remove(position);
+        if endpoints.is_empty() {
+            self.entries.remove(index);
+        }
+        Ok(())
+    }
+
+    /// Removes `endpoint` from all groups.
+    pub(crate) fn remove_all(&mut self, endpoint: u8) {
+        for group in &mut self.entries {
+            group.endpoints.retain(|&member| member != endpoint);
+        }
+        self.entries.retain(|group| !group.endpoints.is_empty());
+    }
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub(super) enum GroupError {
+    #[error("the group table is full")]
+    TableFull,
+    #[error("the endpoint is not a member of the group")]
+    InvalidGroup,
+}
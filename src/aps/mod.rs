@@ -12,3 +12,6 @@ mod aib;
 /// information base (AIB).
 pub mod apsme;
 mod binding;
+/// APS frame formats.
+pub mod frame;
+mod group;
@@ -1,2 +1,2 @@
-pub mod types;
 pub(crate) mod parse;
+pub mod types;
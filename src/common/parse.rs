@@ -1,35 +1,187 @@
-use core::iter::FromIterator;
-
 use heapless::Vec;
+use thiserror::Error;
+
+/// Error returned when a value can not be parsed.
+///
+/// Offsets are counted in bytes from the start of the parsed input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum ParseError {
+    /// The input ended before the value was complete.
+    #[error("truncated at offset {offset}")]
+    Truncated {
+        /// Offset of the first missing byte.
+        offset: usize,
+    },
+    /// A field holds a reserved or unsupported value.
+    #[error("invalid value in `{field}` at offset {offset}")]
+    InvalidValue {
+        /// Name of the field.
+        field: &'static str,
+        /// Offset of the field.
+        offset: usize,
+    },
+    /// The length announced by a field does not match the data.
+    #[error("length mismatch in `{field}` at offset {offset}")]
+    LengthMismatch {
+        /// Name of the field.
+        field: &'static str,
+        /// Offset at which the mismatch was detected.
+        offset: usize,
+    },
+}
+
+impl ParseError {
+    /// Returns the offset at which parsing failed.
+    pub fn offset(&self) -> usize {
+        match self {
+            Self::Truncated { offset }
+            | Self::InvalidValue { offset, .. }
+            | Self::LengthMismatch { offset, .. } => *offset,
+        }
+    }
+
+    /// Moves the reported offset `base` bytes further into the input.
+    fn offset_by(self, base: usize) -> Self {
+        match self {
+            Self::Truncated { offset } => Self::Truncated {
+                offset: base + offset,
+            },
+            Self::InvalidValue { field, offset } => Self::InvalidValue {
+                field,
+                offset: base + offset,
+            },
+            Self::LengthMismatch { field, offset } => Self::LengthMismatch {
+                field,
+                offset: base + offset,
+            },
+        }
+    }
+}
+
+/// Error returned when a value can not be packed into a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PackError {
+    /// The buffer is too small to hold the packed value.
+    #[error("buffer too small")]
+    BufferTooSmall,
+}
 
+/// Conversion between a type and its over-the-air representation.
 pub trait PackBytes
 where
     Self: Sized,
 {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self>;
-    fn unpack_from_slice(src: &[u8]) -> Option<Self> {
-        Self::unpack_from_iter(src.iter().cloned())
+    /// Parses a value from the given bytes.
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError>;
+
+    /// Parses a value from the given slice.
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ParseError> {
+        Self::unpack_from_iter(src.iter().copied())
     }
+
+    /// Writes the value to the start of `buf` and returns the number of
+    /// bytes written.
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError>;
+
+    /// Writes the value into a new [`Vec`] with a capacity of `N` bytes.
+    fn pack_to_vec<const N: usize>(&self) -> Result<Vec<u8, N>, PackError> {
+        let mut buf = [0u8; N];
+        let len = self.pack_into(&mut buf)?;
+        Vec::from_slice(&buf[..len]).map_err(|()| PackError::BufferTooSmall)
+    }
+}
+
+/// Byte iterator keeping track of the offset into the parsed input.
+pub struct Input<I> {
+    src: I,
+    offset: usize,
+}
+
+impl<I: Iterator<Item = u8>> Input<I> {
+    /// Starts reading `src` at offset 0.
+    pub fn new(src: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            src: src.into_iter(),
+            offset: 0,
+        }
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Reads the next byte.
+    pub fn byte(&mut self) -> Result<u8, ParseError> {
+        let offset = self.offset;
+        self.next().ok_or(ParseError::Truncated { offset })
+    }
+
+    /// Parses the next value, reporting errors relative to this input.
+    pub fn unpack<T: PackBytes>(&mut self) -> Result<T, ParseError> {
+        let base = self.offset;
+        T::unpack_from_iter(&mut *self).map_err(|err| err.offset_by(base))
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for Input<I> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.src.next()?;
+        self.offset += 1;
+        Some(b)
+    }
+}
+
+/// Returns the part of `buf` following `offset`.
+pub fn remaining(buf: &mut [u8], offset: usize) -> Result<&mut [u8], PackError> {
+    buf.get_mut(offset..).ok_or(PackError::BufferTooSmall)
+}
+
+/// Copies `src` to the start of `buf` and returns the number of bytes
+/// written.
+pub fn pack_slice(src: &[u8], buf: &mut [u8]) -> Result<usize, PackError> {
+    buf.get_mut(..src.len())
+        .ok_or(PackError::BufferTooSmall)?
+        .copy_from_slice(src);
+    Ok(src.len())
 }
 
 impl PackBytes for u8 {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        src.into_iter().next()
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        Input::new(src).byte()
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        pack_slice(&[*self], buf)
     }
 }
 
 impl PackBytes for i8 {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        src.into_iter().next().map(|b| b as i8)
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        Input::new(src).byte().map(|b| Self::from_le_bytes([b]))
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        pack_slice(&self.to_le_bytes(), buf)
     }
 }
 
 macro_rules! impl_primitive {
     ($ty:ty, $sz:literal) => {
         impl PackBytes for $ty {
-            fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-                let buf: Vec<u8, $sz> = src.into_iter().take($sz).collect();
-                Some(<$ty>::from_le_bytes(buf.into_array().unwrap()))
+            fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+                let mut src = Input::new(src);
+                let mut bytes = [0u8; $sz];
+                for b in &mut bytes {
+                    *b = src.byte()?;
+                }
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+
+            fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+                pack_slice(&self.to_le_bytes(), buf)
             }
         }
     };
@@ -43,8 +195,20 @@ impl_primitive!(i32, 4);
 impl_primitive!(i64, 8);
 
 impl<const N: usize> PackBytes for Vec<u8, N> {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        Some(FromIterator::from_iter(src))
+    /// Collects all remaining bytes, failing if they exceed the capacity.
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut vec = Self::new();
+        for (offset, b) in src.into_iter().enumerate() {
+            vec.push(b).map_err(|_| ParseError::LengthMismatch {
+                field: "payload",
+                offset,
+            })?;
+        }
+        Ok(vec)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        pack_slice(self, buf)
     }
 }
 
@@ -58,8 +222,17 @@ macro_rules! impl_pack_bytes {
         $v struct $name($vt $ty);
 
         impl $crate::common::parse::PackBytes for $name {
-            fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-                Some(Self($crate::common::parse::PackBytes::unpack_from_iter(src)?))
+            fn unpack_from_iter(
+                src: impl IntoIterator<Item = u8>,
+            ) -> Result<Self, $crate::common::parse::ParseError> {
+                Ok(Self($crate::common::parse::PackBytes::unpack_from_iter(src)?))
+            }
+
+            fn pack_into(
+                &self,
+                buf: &mut [u8],
+            ) -> Result<usize, $crate::common::parse::PackError> {
+                $crate::common::parse::PackBytes::pack_into(&self.0, buf)
             }
         }
     };
@@ -84,20 +257,97 @@ macro_rules! impl_pack_bytes {
         }
 
         impl $crate::common::parse::PackBytes for $name {
-            fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-                use core::iter::FromIterator;
-                let mut src = src.into_iter();
-                Some(Self {
+            fn unpack_from_iter(
+                src: impl IntoIterator<Item = u8>,
+            ) -> Result<Self, $crate::common::parse::ParseError> {
+                let mut src = $crate::common::parse::Input::new(src);
+                Ok(Self {
                     $(
                         $(
-                            $field_name: <$tp_ty>::unpack_from_iter(&mut src)?
+                            $field_name: src.unpack::<$tp_ty>()?
                         )?
                         $(
-                            $field_name: <$cl_ty>::from_iter(src)
+                            $field_name: src.unpack::<$cl_ty>()?
                         )?
                     ),+
                 })
             }
+
+            fn pack_into(
+                &self,
+                buf: &mut [u8],
+            ) -> Result<usize, $crate::common::parse::PackError> {
+                let mut offset = 0;
+                $(
+                    offset += $crate::common::parse::PackBytes::pack_into(
+                        &self.$field_name,
+                        $crate::common::parse::remaining(buf, offset)?,
+                    )?;
+                )+
+                Ok(offset)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_primitives_little_endian() {
+        let mut buf = [0u8; 8];
+
+        assert_eq!(0x1234u16.pack_into(&mut buf), Ok(2));
+        assert_eq!(buf[..2], [0x34, 0x12]);
+        assert_eq!(0x0102_0304u32.pack_into(&mut buf), Ok(4));
+        assert_eq!(buf[..4], [0x04, 0x03, 0x02, 0x01]);
+        assert_eq!((-2i8).pack_into(&mut buf), Ok(1));
+        assert_eq!(buf[0], 0xfe);
+    }
+
+    #[test]
+    fn pack_into_too_small_buffer_should_fail() {
+        let mut buf = [0u8; 3];
+
+        assert_eq!(0u64.pack_into(&mut buf), Err(PackError::BufferTooSmall));
+    }
+
+    #[test]
+    fn unpack_truncated_primitive_should_fail() {
+        let got = u32::unpack_from_slice(&[0x01, 0x02]);
+
+        assert_eq!(got, Err(ParseError::Truncated { offset: 2 }));
+    }
+
+    #[test]
+    fn unpack_oversized_vec_should_fail() {
+        let got = Vec::<u8, 2>::unpack_from_slice(&[0x01, 0x02, 0x03]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::LengthMismatch {
+                field: "payload",
+                offset: 2
+            })
+        );
+    }
+
+    #[test]
+    fn unpack_reports_offset_relative_to_input() {
+        let mut src = Input::new([0x01, 0x02, 0x03]);
+
+        assert_eq!(src.unpack::<u8>(), Ok(0x01));
+        assert_eq!(
+            src.unpack::<u32>(),
+            Err(ParseError::Truncated { offset: 3 })
+        );
+    }
+
+    #[test]
+    fn pack_to_vec() {
+        let got: Vec<u8, 8> = 0xaabb_ccddu32.pack_to_vec().unwrap();
+
+        assert_eq!(got, &[0xdd, 0xcc, 0xbb, 0xaa]);
+    }
+}
@@ -35,7 +35,7 @@ impl fmt::Debug for ShortAddress {
 
 impl_pack_bytes! {
     /// 64-bit network address
-    #[derive(Clone, Copy, PartialEq, Eq)]
+    #[derive(Clone, Copy, Default, PartialEq, Eq)]
     pub struct IeeeAddress(pub u64);
 }
 
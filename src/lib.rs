@@ -8,17 +8,38 @@
 //!
 //! **This is how it could look like in the future**
 //!
-//! ```rust
-//! let zigbee_device = zigbee::init(zigbee::Config { radio_channel: 11, ..Default::default() });
+//! ```rust,no_run
+//! # use core::convert::Infallible;
+//! # use core::time::Duration;
+//! # use zigbee::radio::{Radio, RxFrame, TxStatus};
+//! # use zigbee::types::{IeeeAddress, ShortAddress};
+//! # struct Transceiver;
+//! # impl Radio for Transceiver {
+//! #     type Error = Infallible;
+//! #     async fn set_channel(&mut self, _: u8) -> Result<(), Infallible> { Ok(()) }
+//! #     async fn set_pan_id(&mut self, _: u16) -> Result<(), Infallible> { Ok(()) }
+//! #     async fn set_short_address(&mut self, _: ShortAddress) -> Result<(), Infallible> { Ok(()) }
+//! #     async fn set_extended_address(&mut self, _: IeeeAddress) -> Result<(), Infallible> { Ok(()) }
+//! #     async fn transmit(&mut self, _: &[u8], _: bool) -> Result<TxStatus, Infallible> { Ok(TxStatus::NoAck) }
+//! #     async fn receive(&mut self, _: Duration) -> Result<Option<RxFrame>, Infallible> { Ok(None) }
+//! #     async fn energy_detect(&mut self, _: Duration) -> Result<u8, Infallible> { Ok(0) }
+//! #     async fn sleep(&mut self, _: Duration) -> Result<(), Infallible> { Ok(()) }
+//! #     fn now(&self) -> Duration { Duration::ZERO }
+//! #     fn random(&mut self) -> u32 { 4 }
+//! # }
+//! # async fn run(radio: Transceiver) {
+//! let mut zigbee_device = zigbee::init(zigbee::Config { radio_channel: 11, ..Default::default() }, radio);
 //!
-//! zigbee_device.try_to_connect();
-//! zigbee_device.send_data(&[0x7au8]);
+//! zigbee_device.try_to_connect().await.expect("no network to join");
+//! zigbee_device.send_data(&[0x7au8]).await.expect("radio failure");
+//! # }
 //! ```
 //!
 //! # ESP32 & nRF support
 //!
-//! This crate is currently only supporting devices in the EspresGM_Clamshell Parts Box Thirdssif ecosystem,
-//! but presumative this will expand to nordics nRF series.
+//! The stack is not tied to a specific transceiver. Devices in the Espressif
+//! ecosystem as well as the nordics nRF series are supported by implementing
+//! [`Radio`](radio::Radio) for their IEEE 802.15.4 peripheral.
 #![no_std]
 //#![deny(clippy::unwrap_used)]
 #![deny(clippy::panic, unused_must_use)]
@@ -45,8 +66,17 @@
     clippy::ignored_unit_patterns
 )]
 
+#[cfg(any(test, feature = "std"))]
+extern crate std;
+
 pub mod zdp;
 pub(crate) mod common;
+pub use common::parse::pack_slice;
+pub use common::parse::remaining;
+pub use common::parse::Input;
+pub use common::parse::PackBytes;
+pub use common::parse::PackError;
+pub use common::parse::ParseError;
 pub use common::types;
 
 /// The `application support sub-layer` provides an interface between the
@@ -62,17 +92,23 @@ pub use zdo::config::DiscoveryType;
 pub use zdo::ZigBeeNetwork;
 pub use zdo::ZigbeeDevice;
 
+/// IEEE 802.15.4 MAC sub-layer
+pub mod mac;
+
 /// 3.1 Network Layer
 pub mod nwk;
 
+/// Radio driver abstraction
+pub mod radio;
+
 /// 4.1 Security Service
 pub mod security;
 
 /// Initialize a new zigbee device with the default configuartion.
 ///
 /// Initialize a new zigbee device with a configuration
-pub fn init(config: Config) -> ZigbeeDevice {
-    let device = ZigbeeDevice::default();
+pub fn init<R: radio::Radio>(config: Config, radio: R) -> ZigbeeDevice<R> {
+    let mut device = ZigbeeDevice::new(radio);
     device.configure(config);
 
     device
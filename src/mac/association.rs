@@ -0,0 +1,172 @@
+//! MAC Association
+//!
+//! See IEEE 802.15.4-2015, Section 6.4.
+
+use core::time::Duration;
+
+use super::command::AssociationResponse;
+use super::command::AssociationStatus;
+use super::command::MacCommand;
+use super::frame::Address;
+use super::frame::FrameType;
+use super::frame::MacCommandFrame;
+use super::frame::MacFrame;
+use super::frame::MacHeader;
+use super::frame::BROADCAST;
+use super::Mac;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// macResponseWaitTime, 32 aBaseSuperframeDuration.
+pub const RESPONSE_WAIT_TIME: Duration = Duration::from_micros(491_520);
+
+/// macMaxFrameTotalWaitTime with the default CSMA-CA attributes of the
+/// 2.4 GHz band.
+pub(super) const MAX_FRAME_TOTAL_WAIT_TIME: Duration = Duration::from_micros(31_776);
+
+/// Result of an association attempt.
+///
+/// See IEEE 802.15.4-2015, Section 8.2.3.4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssociateConfirm {
+    /// The coordinator assigned a short address.
+    Associated(ShortAddress),
+    /// The coordinator rejected the association.
+    Rejected(AssociationStatus),
+    /// The channel was busy.
+    ChannelAccessFailure,
+    /// The coordinator did not acknowledge a request.
+    NoAck,
+    /// The coordinator did not send an association response.
+    NoData,
+}
+
+impl<R: Radio> Mac<R> {
+    /// Associates with the coordinator `coordinator` of `pan_id`.
+    ///
+    /// Sends the association request, polls for the response after
+    /// macResponseWaitTime and adopts the assigned short address and PAN ID on
+    /// success.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.4.1.
+    pub(crate) async fn associate(
+        &mut self,
+        channel: u8,
+        pan_id: u16,
+        coordinator: ShortAddress,
+        capability_information: MacCapabilities,
+    ) -> Result<AssociateConfirm, R::Error> {
+        let confirm = self
+            .request_association(channel, pan_id, coordinator, capability_information)
+            .await?;
+        if !matches!(confirm, AssociateConfirm::Associated(_)) {
+            // macPanId is reset to its default after a failed association
+            self.radio.set_pan_id(BROADCAST).await?;
+            self.pan_id = BROADCAST;
+        }
+        Ok(confirm)
+    }
+
+    async fn request_association(
+        &mut self,
+        channel: u8,
+        pan_id: u16,
+        coordinator: ShortAddress,
+        capability_information: MacCapabilities,
+    ) -> Result<AssociateConfirm, R::Error> {
+        self.radio
+            .set_extended_address(self.extended_address)
+            .await?;
+        self.radio.set_channel(channel).await?;
+        self.radio.set_pan_id(pan_id).await?;
+        self.channel = channel;
+        self.pan_id = pan_id;
+
+        let destination = Some((pan_id, Address::Short(coordinator)));
+        let mut header = MacHeader::new(
+            FrameType::MacCommand,
+            self.next_sequence_number(),
+            destination,
+            Some((BROADCAST, Address::Extended(self.extended_address))),
+        );
+        header.frame_control.set_ack_request(true);
+        let request = MacFrame::Command(MacCommandFrame {
+            header,
+            command: MacCommand::AssociationRequest(capability_information),
+        });
+        match self.transmit(&request).await? {
+            TxStatus::Acked { .. } => {}
+            TxStatus::ChannelAccessFailure => return Ok(AssociateConfirm::ChannelAccessFailure),
+            _ => return Ok(AssociateConfirm::NoAck),
+        }
+
+        self.radio.sleep(RESPONSE_WAIT_TIME).await?;
+        let mut header = MacHeader::new(
+            FrameType::MacCommand,
+            self.next_sequence_number(),
+            destination,
+            Some((pan_id, Address::Extended(self.extended_address))),
+        );
+        header.frame_control.set_ack_request(true);
+        let data_request = MacFrame::Command(MacCommandFrame {
+            header,
+            command: MacCommand::DataRequest,
+        });
+        match self.transmit(&data_request).await? {
+            TxStatus::Acked {
+                frame_pending: true,
+            } => {}
+            TxStatus::Acked { .. } => return Ok(AssociateConfirm::NoData),
+            TxStatus::ChannelAccessFailure => return Ok(AssociateConfirm::ChannelAccessFailure),
+            _ => return Ok(AssociateConfirm::NoAck),
+        }
+
+        let deadline = self.radio.now() + MAX_FRAME_TOTAL_WAIT_TIME;
+        while let Some(received) = self
+            .receive(deadline.saturating_sub(self.radio.now()))
+            .await?
+        {
+            let MacFrame::Command(MacCommandFrame {
+                command: MacCommand::AssociationResponse(response),
+                ..
+            }) = received.frame
+            else {
+                continue;
+            };
+            if response.status != AssociationStatus::Success {
+                return Ok(AssociateConfirm::Rejected(response.status));
+            }
+            self.radio.set_short_address(response.short_address).await?;
+            self.short_address = response.short_address;
+            self.pan_coordinator = false;
+            return Ok(AssociateConfirm::Associated(response.short_address));
+        }
+        Ok(AssociateConfirm::NoData)
+    }
+
+    /// Sends the association response to `device`, which polled for it with
+    /// a data request.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.4.1.
+    pub(crate) async fn send_association_response(
+        &mut self,
+        device: IeeeAddress,
+        response: AssociationResponse,
+    ) -> Result<TxStatus, R::Error> {
+        let mut header = MacHeader::new(
+            FrameType::MacCommand,
+            self.next_sequence_number(),
+            Some((self.pan_id, Address::Extended(device))),
+            Some((self.pan_id, Address::Extended(self.extended_address))),
+        );
+        header.frame_control.set_ack_request(true);
+        self.transmit(&MacFrame::Command(MacCommandFrame {
+            header,
+            command: MacCommand::AssociationResponse(response),
+        }))
+        .await
+    }
+}
@@ -0,0 +1,309 @@
+//! MAC Beacon Fields
+//!
+//! See IEEE 802.15.4-2015, Section 7.3.1.
+use core::fmt::Debug;
+
+use heapless::Vec;
+
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::impl_pack_bytes;
+
+/// Maximum number of GTS descriptors and of pending addresses of each kind.
+pub const MAX_BEACON_LIST_SIZE: usize = 7;
+
+/// Fields of a beacon frame preceding the beacon payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Beacon {
+    /// See Section 7.3.1.2.
+    pub superframe_specification: SuperframeSpecification,
+    /// The coordinator accepts GTS requests.
+    ///
+    /// See Section 7.3.1.3.
+    pub gts_permit: bool,
+    /// See Section 7.3.1.3.
+    pub gts_descriptors: Vec<GtsDescriptor, MAX_BEACON_LIST_SIZE>,
+    /// Short addresses of devices with pending data.
+    ///
+    /// See Section 7.3.1.4.
+    pub pending_short_addresses: Vec<ShortAddress, MAX_BEACON_LIST_SIZE>,
+    /// Extended addresses of devices with pending data.
+    ///
+    /// See Section 7.3.1.4.
+    pub pending_extended_addresses: Vec<IeeeAddress, MAX_BEACON_LIST_SIZE>,
+}
+
+impl Beacon {
+    /// Creates a beacon without GTS and pending addresses.
+    pub fn new(superframe_specification: SuperframeSpecification) -> Self {
+        Self {
+            superframe_specification,
+            gts_permit: false,
+            gts_descriptors: Vec::new(),
+            pending_short_addresses: Vec::new(),
+            pending_extended_addresses: Vec::new(),
+        }
+    }
+}
+
+impl PackBytes for Beacon {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let superframe_specification = src.unpack()?;
+
+        let gts_specification = src.byte()?;
+        let gts_count = gts_specification & 0b111;
+        let gts_permit = (gts_specification >> 7) != 0;
+        let mut gts_descriptors = Vec::new();
+        if gts_count > 0 {
+            let directions = src.byte()?;
+            for n in 0..gts_count {
+                let short_address = src.unpack()?;
+                let slot = src.byte()?;
+                // at most 7 descriptors fit into the 3-bit count
+                let _ = gts_descriptors.push(GtsDescriptor {
+                    short_address,
+                    starting_slot: slot & 0b1111,
+                    length: slot >> 4,
+                    receive_only: (directions >> n) & 0b1 != 0,
+                });
+            }
+        }
+
+        let offset = src.offset();
+        let pending_address_specification = src.byte()?;
+        let short_count = pending_address_specification & 0b111;
+        let extended_count = (pending_address_specification >> 4) & 0b111;
+        if usize::from(short_count + extended_count) > MAX_BEACON_LIST_SIZE {
+            return Err(ParseError::LengthMismatch {
+                field: "pending_address_specification",
+                offset,
+            });
+        }
+        let mut pending_short_addresses = Vec::new();
+        for _ in 0..short_count {
+            let _ = pending_short_addresses.push(src.unpack()?);
+        }
+        let mut pending_extended_addresses = Vec::new();
+        for _ in 0..extended_count {
+            let _ = pending_extended_addresses.push(src.unpack()?);
+        }
+
+        Ok(Self {
+            superframe_specification,
+            gts_permit,
+            gts_descriptors,
+            pending_short_addresses,
+            pending_extended_addresses,
+        })
+    }
+
+    // lists hold at most 7 entries and fit into their 3-bit counts
+    #[allow(clippy::cast_possible_truncation)]
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.superframe_specification.pack_into(buf)?;
+
+        let gts_specification = self.gts_descriptors.len() as u8 | (u8::from(self.gts_permit) << 7);
+        offset += gts_specification.pack_into(remaining(buf, offset)?)?;
+        if !self.gts_descriptors.is_empty() {
+            let directions = self
+                .gts_descriptors
+                .iter()
+                .enumerate()
+                .fold(0u8, |directions, (n, descriptor)| {
+                    directions | (u8::from(descriptor.receive_only) << n)
+                });
+            offset += directions.pack_into(remaining(buf, offset)?)?;
+            for descriptor in &self.gts_descriptors {
+                offset += descriptor
+                    .short_address
+                    .pack_into(remaining(buf, offset)?)?;
+                let slot = (descriptor.starting_slot & 0b1111) | (descriptor.length << 4);
+                offset += slot.pack_into(remaining(buf, offset)?)?;
+            }
+        }
+
+        let pending_address_specification = self.pending_short_addresses.len() as u8
+            | ((self.pending_extended_addresses.len() as u8) << 4);
+        offset += pending_address_specification.pack_into(remaining(buf, offset)?)?;
+        for address in &self.pending_short_addresses {
+            offset += address.pack_into(remaining(buf, offset)?)?;
+        }
+        for address in &self.pending_extended_addresses {
+            offset += address.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+/// GTS Descriptor
+///
+/// See IEEE 802.15.4-2015, Section 7.3.1.3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GtsDescriptor {
+    /// Device the GTS is allocated to.
+    pub short_address: ShortAddress,
+    /// First superframe slot of the GTS.
+    pub starting_slot: u8,
+    /// Number of superframe slots of the GTS.
+    pub length: u8,
+    /// The GTS is used for receiving instead of transmitting.
+    pub receive_only: bool,
+}
+
+impl_pack_bytes! {
+    /// Superframe Specification Field
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.1.2.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct SuperframeSpecification(pub u16);
+}
+
+impl Debug for SuperframeSpecification {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SuperframeSpecification")
+            .field("beacon_order", &self.beacon_order())
+            .field("superframe_order", &self.superframe_order())
+            .field("final_cap_slot", &self.final_cap_slot())
+            .field("battery_life_extension", &self.battery_life_extension())
+            .field("pan_coordinator", &self.pan_coordinator())
+            .field("association_permit", &self.association_permit())
+            .finish()
+    }
+}
+
+impl SuperframeSpecification {
+    /// Superframe specification of a beaconless network as used by Zigbee.
+    ///
+    /// Beacon order and superframe order are 15 and the final CAP slot is 15.
+    pub const NON_BEACON: Self = Self(0x0fff);
+
+    fn bit(self, n: u16) -> bool {
+        ((self.0 >> n) & 0b1) != 0
+    }
+
+    fn set_bit(&mut self, n: u16, value: bool) {
+        self.0 = (self.0 & !(1 << n)) | (u16::from(value) << n);
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn beacon_order(&self) -> u8 {
+        (self.0 & 0b1111) as u8
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn superframe_order(&self) -> u8 {
+        ((self.0 >> 4) & 0b1111) as u8
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn final_cap_slot(&self) -> u8 {
+        ((self.0 >> 8) & 0b1111) as u8
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn battery_life_extension(&self) -> bool {
+        self.bit(12)
+    }
+
+    /// The beacon is sent by the PAN coordinator.
+    ///
+    /// See Section 7.3.1.2.
+    pub fn pan_coordinator(&self) -> bool {
+        self.bit(14)
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn set_pan_coordinator(&mut self, pan_coordinator: bool) {
+        self.set_bit(14, pan_coordinator);
+    }
+
+    /// The coordinator accepts association requests.
+    ///
+    /// See Section 7.3.1.2.
+    pub fn association_permit(&self) -> bool {
+        self.bit(15)
+    }
+
+    /// See Section 7.3.1.2.
+    pub fn set_association_permit(&mut self, association_permit: bool) {
+        self.set_bit(15, association_permit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_zigbee_coordinator_beacon() {
+        let raw = [0xff, 0xcf, 0x00, 0x00];
+
+        let beacon = Beacon::unpack_from_slice(&raw).unwrap();
+
+        let superframe_specification = beacon.superframe_specification;
+        assert_eq!(superframe_specification.beacon_order(), 15);
+        assert_eq!(superframe_specification.superframe_order(), 15);
+        assert_eq!(superframe_specification.final_cap_slot(), 15);
+        assert!(superframe_specification.pan_coordinator());
+        assert!(superframe_specification.association_permit());
+        assert!(!beacon.gts_permit);
+        assert!(beacon.gts_descriptors.is_empty());
+        assert!(beacon.pending_short_addresses.is_empty());
+    }
+
+    #[test]
+    fn beacon_with_gts_and_pending_addresses_round_trip() {
+        let mut beacon = Beacon::new(SuperframeSpecification::NON_BEACON);
+        beacon.gts_permit = true;
+        beacon
+            .gts_descriptors
+            .push(GtsDescriptor {
+                short_address: ShortAddress(0x1234),
+                starting_slot: 9,
+                length: 2,
+                receive_only: true,
+            })
+            .unwrap();
+        beacon
+            .pending_short_addresses
+            .push(ShortAddress(0x796f))
+            .unwrap();
+        beacon
+            .pending_extended_addresses
+            .push(IeeeAddress(0x0015_8d00_01ab_cd12))
+            .unwrap();
+
+        let mut buf = [0u8; 32];
+        let len = beacon.pack_into(&mut buf).unwrap();
+
+        assert_eq!(
+            &buf[..len],
+            &[
+                0xff, 0x0f, 0x81, 0x01, 0x34, 0x12, 0x29, 0x11, 0x6f, 0x79, 0x12, 0xcd, 0xab, 0x01,
+                0x00, 0x8d, 0x15, 0x00
+            ]
+        );
+        assert_eq!(Beacon::unpack_from_slice(&buf[..len]), Ok(beacon));
+    }
+
+    #[test]
+    fn unpack_too_many_pending_addresses_should_fail() {
+        let raw = [0xff, 0xcf, 0x00, 0x44];
+
+        let got = Beacon::unpack_from_slice(&raw);
+
+        assert_eq!(
+            got,
+            Err(ParseError::LengthMismatch {
+                field: "pending_address_specification",
+                offset: 3
+            })
+        );
+    }
+}
@@ -0,0 +1,275 @@
+//! MAC Command Frames
+//!
+//! See IEEE 802.15.4-2015, Section 7.5.
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+use crate::common::types::ShortAddress;
+
+/// MAC command used by Zigbee devices, including its payload.
+///
+/// See IEEE 802.15.4-2015, Table 7-49.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MacCommand {
+    /// See Section 7.5.2.
+    AssociationRequest(MacCapabilities),
+    /// See Section 7.5.3.
+    AssociationResponse(AssociationResponse),
+    /// See Section 7.5.4.
+    DisassociationNotification(DisassociationReason),
+    /// See Section 7.5.5.
+    DataRequest,
+    /// See Section 7.5.6.
+    PanIdConflictNotification,
+    /// See Section 7.5.7.
+    OrphanNotification,
+    /// See Section 7.5.8.
+    BeaconRequest,
+    /// See Section 7.5.10.
+    CoordinatorRealignment(CoordinatorRealignment),
+}
+
+impl MacCommand {
+    /// The command frame identifier.
+    pub fn command_identifier(&self) -> u8 {
+        match self {
+            Self::AssociationRequest(_) => 0x01,
+            Self::AssociationResponse(_) => 0x02,
+            Self::DisassociationNotification(_) => 0x03,
+            Self::DataRequest => 0x04,
+            Self::PanIdConflictNotification => 0x05,
+            Self::OrphanNotification => 0x06,
+            Self::BeaconRequest => 0x07,
+            Self::CoordinatorRealignment(_) => 0x08,
+        }
+    }
+}
+
+impl PackBytes for MacCommand {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let command = match src.byte()? {
+            0x01 => Self::AssociationRequest(src.unpack()?),
+            0x02 => Self::AssociationResponse(src.unpack()?),
+            0x03 => Self::DisassociationNotification(match src.byte()? {
+                0x01 => DisassociationReason::CoordinatorWishesDeviceToLeave,
+                0x02 => DisassociationReason::DeviceWishesToLeave,
+                _ => {
+                    return Err(ParseError::InvalidValue {
+                        field: "disassociation_reason",
+                        offset: 1,
+                    })
+                }
+            }),
+            0x04 => Self::DataRequest,
+            0x05 => Self::PanIdConflictNotification,
+            0x06 => Self::OrphanNotification,
+            0x07 => Self::BeaconRequest,
+            0x08 => Self::CoordinatorRealignment(src.unpack()?),
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    field: "command_identifier",
+                    offset: 0,
+                })
+            }
+        };
+        Ok(command)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = self.command_identifier().pack_into(buf)?;
+        let buf = remaining(buf, offset)?;
+        let len = match self {
+            Self::AssociationRequest(capabilities) => capabilities.pack_into(buf)?,
+            Self::AssociationResponse(response) => response.pack_into(buf)?,
+            Self::DisassociationNotification(reason) => (*reason as u8).pack_into(buf)?,
+            Self::DataRequest
+            | Self::PanIdConflictNotification
+            | Self::OrphanNotification
+            | Self::BeaconRequest => 0,
+            Self::CoordinatorRealignment(realignment) => realignment.pack_into(buf)?,
+        };
+        Ok(offset + len)
+    }
+}
+
+/// Association Status Field
+///
+/// See IEEE 802.15.4-2015, Table 7-50.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum AssociationStatus {
+    Success = 0x00,
+    PanAtCapacity = 0x01,
+    PanAccessDenied = 0x02,
+}
+
+impl PackBytes for AssociationStatus {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        match Input::new(src).byte()? {
+            0x00 => Ok(Self::Success),
+            0x01 => Ok(Self::PanAtCapacity),
+            0x02 => Ok(Self::PanAccessDenied),
+            _ => Err(ParseError::InvalidValue {
+                field: "association_status",
+                offset: 0,
+            }),
+        }
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        (*self as u8).pack_into(buf)
+    }
+}
+
+/// Association Response Command
+///
+/// See IEEE 802.15.4-2015, Section 7.5.3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssociationResponse {
+    /// Address allocated by the coordinator, `0xfffe` if the device shall
+    /// use its extended address and `0xffff` on failure.
+    pub short_address: ShortAddress,
+    /// See Section 7.5.3.3.
+    pub status: AssociationStatus,
+}
+
+impl PackBytes for AssociationResponse {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        Ok(Self {
+            short_address: src.unpack()?,
+            status: src.unpack()?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = self.short_address.pack_into(buf)?;
+        Ok(offset + self.status.pack_into(remaining(buf, offset)?)?)
+    }
+}
+
+/// Disassociation Reason Field
+///
+/// See IEEE 802.15.4-2015, Table 7-51.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum DisassociationReason {
+    CoordinatorWishesDeviceToLeave = 0x01,
+    DeviceWishesToLeave = 0x02,
+}
+
+/// Coordinator Realignment Command
+///
+/// See IEEE 802.15.4-2015, Section 7.5.10.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CoordinatorRealignment {
+    /// PAN ID the coordinator will use.
+    pub pan_id: u16,
+    /// Short address of the coordinator.
+    pub coordinator_short_address: ShortAddress,
+    /// Channel the coordinator will use.
+    pub channel_number: u8,
+    /// Address of an orphaned device, otherwise `0xffff`.
+    pub short_address: ShortAddress,
+    /// Only present in frames with an IEEE 802.15.4-2006 or later frame
+    /// version.
+    pub channel_page: Option<u8>,
+}
+
+impl PackBytes for CoordinatorRealignment {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        Ok(Self {
+            pan_id: src.unpack()?,
+            coordinator_short_address: src.unpack()?,
+            channel_number: src.byte()?,
+            short_address: src.unpack()?,
+            channel_page: src.next(),
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.pan_id.pack_into(buf)?;
+        offset += self
+            .coordinator_short_address
+            .pack_into(remaining(buf, offset)?)?;
+        offset += self.channel_number.pack_into(remaining(buf, offset)?)?;
+        offset += self.short_address.pack_into(remaining(buf, offset)?)?;
+        if let Some(channel_page) = self.channel_page {
+            offset += channel_page.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(command: MacCommand, expected: &[u8]) {
+        let mut buf = [0u8; 16];
+        let len = command.pack_into(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], expected);
+        assert_eq!(MacCommand::unpack_from_slice(expected), Ok(command));
+    }
+
+    #[test]
+    fn association_request_round_trip() {
+        round_trip(
+            MacCommand::AssociationRequest(MacCapabilities::unpack_from_slice(&[0x8e]).unwrap()),
+            &[0x01, 0x8e],
+        );
+    }
+
+    #[test]
+    fn association_response_round_trip() {
+        round_trip(
+            MacCommand::AssociationResponse(AssociationResponse {
+                short_address: ShortAddress(0x796f),
+                status: AssociationStatus::PanAtCapacity,
+            }),
+            &[0x02, 0x6f, 0x79, 0x01],
+        );
+    }
+
+    #[test]
+    fn commands_without_payload_round_trip() {
+        round_trip(MacCommand::DataRequest, &[0x04]);
+        round_trip(MacCommand::BeaconRequest, &[0x07]);
+        round_trip(MacCommand::OrphanNotification, &[0x06]);
+    }
+
+    #[test]
+    fn coordinator_realignment_round_trip() {
+        round_trip(
+            MacCommand::CoordinatorRealignment(CoordinatorRealignment {
+                pan_id: 0x1a62,
+                coordinator_short_address: ShortAddress(0x0000),
+                channel_number: 15,
+                short_address: ShortAddress(0x796f),
+                channel_page: Some(0),
+            }),
+            &[0x08, 0x62, 0x1a, 0x00, 0x00, 0x0f, 0x6f, 0x79, 0x00],
+        );
+    }
+
+    #[test]
+    fn unpack_invalid_association_status_should_fail() {
+        let got = MacCommand::unpack_from_slice(&[0x02, 0x6f, 0x79, 0x03]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "association_status",
+                offset: 3
+            })
+        );
+    }
+}
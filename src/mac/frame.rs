@@ -0,0 +1,740 @@
+//! MAC Frame Formats
+//!
+//! See IEEE 802.15.4-2015, Section 7.2.
+use core::fmt::Debug;
+
+use heapless::Vec;
+
+use super::beacon::Beacon;
+use super::command::MacCommand;
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::impl_pack_bytes;
+
+/// aMaxPhyPacketSize - the maximum PSDU size the PHY shall be able to
+/// receive.
+pub const MAX_PHY_PACKET_SIZE: usize = 127;
+
+/// Size of the frame check sequence.
+pub const FCS_SIZE: usize = 2;
+
+/// PAN identifier and short address used for broadcasts.
+pub const BROADCAST: u16 = 0xffff;
+
+/// A MAC frame without its frame check sequence.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum MacFrame {
+    Beacon(MacBeaconFrame),
+    Data(MacDataFrame),
+    Acknowledgement(MacHeader),
+    Command(MacCommandFrame),
+}
+
+impl MacFrame {
+    /// Return the [`MacHeader`] of a [`MacFrame`].
+    pub fn header(&self) -> &MacHeader {
+        match self {
+            Self::Beacon(frame) => &frame.header,
+            Self::Data(frame) => &frame.header,
+            Self::Acknowledgement(header) => header,
+            Self::Command(frame) => &frame.header,
+        }
+    }
+
+    /// Parses a PSDU which ends with a frame check sequence.
+    ///
+    /// The frame check sequence is verified and stripped.
+    pub fn unpack_from_psdu(psdu: &[u8]) -> Result<Self, ParseError> {
+        let len = psdu
+            .len()
+            .checked_sub(FCS_SIZE)
+            .ok_or(ParseError::Truncated { offset: psdu.len() })?;
+        let (mpdu, fcs) = psdu.split_at(len);
+        if u16::from_le_bytes([fcs[0], fcs[1]]) != crc16_kermit(mpdu) {
+            return Err(ParseError::InvalidValue {
+                field: "fcs",
+                offset: len,
+            });
+        }
+        Self::unpack_from_slice(mpdu)
+    }
+
+    /// Writes the frame followed by its frame check sequence to the start of
+    /// `buf` and returns the size of the PSDU.
+    pub fn pack_into_psdu(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let len = self.pack_into(buf)?;
+        let fcs = crc16_kermit(&buf[..len]);
+        Ok(len + fcs.pack_into(remaining(buf, len)?)?)
+    }
+}
+
+impl PackBytes for MacFrame {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let header: MacHeader = src.unpack()?;
+        let frame = match header.frame_control.frame_type() {
+            FrameType::Beacon => Self::Beacon(MacBeaconFrame {
+                header,
+                beacon: src.unpack()?,
+                payload: src.unpack()?,
+            }),
+            FrameType::Data => Self::Data(MacDataFrame {
+                header,
+                payload: src.unpack()?,
+            }),
+            FrameType::Acknowledgement => Self::Acknowledgement(header),
+            FrameType::MacCommand => Self::Command(MacCommandFrame {
+                header,
+                command: src.unpack()?,
+            }),
+            FrameType::Reserved
+            | FrameType::Multipurpose
+            | FrameType::Fragment
+            | FrameType::Extended => {
+                return Err(ParseError::InvalidValue {
+                    field: "frame_type",
+                    offset: 0,
+                })
+            }
+        };
+        Ok(frame)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        match self {
+            Self::Beacon(frame) => frame.pack_into(buf),
+            Self::Data(frame) => frame.pack_into(buf),
+            Self::Acknowledgement(header) => header.pack_into(buf),
+            Self::Command(frame) => frame.pack_into(buf),
+        }
+    }
+}
+
+impl_pack_bytes! {
+    /// MAC Beacon Frame
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.1.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[allow(missing_docs)]
+    pub struct MacBeaconFrame {
+        #[transparent(MacHeader)]
+        pub header: MacHeader,
+        #[transparent(Beacon)]
+        pub beacon: Beacon,
+        #[collect(Vec<u8, MAX_PHY_PACKET_SIZE>)]
+        pub payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    }
+}
+
+impl_pack_bytes! {
+    /// MAC Data Frame
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.2.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[allow(missing_docs)]
+    pub struct MacDataFrame {
+        #[transparent(MacHeader)]
+        pub header: MacHeader,
+        #[collect(Vec<u8, MAX_PHY_PACKET_SIZE>)]
+        pub payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    }
+}
+
+impl_pack_bytes! {
+    /// MAC Command Frame
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.4.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[allow(missing_docs)]
+    pub struct MacCommandFrame {
+        #[transparent(MacHeader)]
+        pub header: MacHeader,
+        #[transparent(MacCommand)]
+        pub command: MacCommand,
+    }
+}
+
+/// Short or extended device address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum Address {
+    Short(ShortAddress),
+    Extended(IeeeAddress),
+}
+
+impl Address {
+    /// The broadcast short address.
+    pub const BROADCAST: Self = Self::Short(ShortAddress(BROADCAST));
+
+    fn addressing_mode(&self) -> AddressingMode {
+        match self {
+            Self::Short(_) => AddressingMode::Short,
+            Self::Extended(_) => AddressingMode::Extended,
+        }
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        match self {
+            Self::Short(address) => address.pack_into(buf),
+            Self::Extended(address) => address.pack_into(buf),
+        }
+    }
+}
+
+/// MAC Header
+///
+/// See IEEE 802.15.4-2015, Section 7.2.1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MacHeader {
+    /// See Section 7.2.2.
+    pub frame_control: FrameControl,
+    /// Absent if [`FrameControl::sequence_number_suppression`] is `true`.
+    ///
+    /// See Section 7.2.3.
+    pub sequence_number: Option<u8>,
+    /// See Section 7.2.4.
+    pub destination_pan_id: Option<u16>,
+    /// Set only if [`FrameControl::destination_addressing_mode`] is not
+    /// [`AddressingMode::None`].
+    ///
+    /// See Section 7.2.5.
+    pub destination: Option<Address>,
+    /// Absent if the PAN ID is compressed.
+    ///
+    /// See Section 7.2.6.
+    pub source_pan_id: Option<u16>,
+    /// Set only if [`FrameControl::source_addressing_mode`] is not
+    /// [`AddressingMode::None`].
+    ///
+    /// See Section 7.2.7.
+    pub source: Option<Address>,
+}
+
+impl MacHeader {
+    /// Creates an IEEE 802.15.4-2003 compatible header.
+    ///
+    /// The addressing modes are derived from the addresses and the source PAN
+    /// ID is compressed if it equals the destination PAN ID.
+    pub fn new(
+        frame_type: FrameType,
+        sequence_number: u8,
+        destination: Option<(u16, Address)>,
+        source: Option<(u16, Address)>,
+    ) -> Self {
+        let pan_id_compression = matches!(
+            (destination, source),
+            (Some((destination_pan_id, _)), Some((source_pan_id, _)))
+                if destination_pan_id == source_pan_id
+        );
+        let mut frame_control = FrameControl(frame_type as u16);
+        frame_control.set_bit(6, pan_id_compression);
+        frame_control.0 |= (destination.map_or(AddressingMode::None, |(_, address)| {
+            address.addressing_mode()
+        }) as u16)
+            << 10;
+        frame_control.0 |= (source.map_or(AddressingMode::None, |(_, address)| {
+            address.addressing_mode()
+        }) as u16)
+            << 14;
+
+        Self {
+            frame_control,
+            sequence_number: Some(sequence_number),
+            destination_pan_id: destination.map(|(pan_id, _)| pan_id),
+            destination: destination.map(|(_, address)| address),
+            source_pan_id: source
+                .filter(|_| !pan_id_compression)
+                .map(|(pan_id, _)| pan_id),
+            source: source.map(|(_, address)| address),
+        }
+    }
+
+    /// The PAN ID of the source, taking PAN ID compression into account.
+    pub fn source_pan_id(&self) -> Option<u16> {
+        if self.source.is_some() && self.frame_control.pan_id_compression() {
+            self.source_pan_id.or(self.destination_pan_id)
+        } else {
+            self.source_pan_id
+        }
+    }
+
+    /// Determines which PAN IDs are present.
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.2.2.6 and Table 7-2.
+    fn pan_ids_present(frame_control: FrameControl) -> (bool, bool) {
+        use AddressingMode::Extended;
+        use AddressingMode::None;
+
+        let destination = frame_control.destination_addressing_mode();
+        let source = frame_control.source_addressing_mode();
+        let compression = frame_control.pan_id_compression();
+        if frame_control.frame_version() == FrameVersion::Ieee802154 {
+            match (destination, source) {
+                (None, None) => (compression, false),
+                (_, None) | (Extended, Extended) => (!compression, false),
+                (None, _) => (false, !compression),
+                (_, _) => (true, !compression),
+            }
+        } else {
+            (
+                destination != None,
+                source != None && !(compression && destination != None),
+            )
+        }
+    }
+}
+
+impl PackBytes for MacHeader {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let frame_control: FrameControl = src.unpack()?;
+        if frame_control.security_enabled() {
+            return Err(ParseError::InvalidValue {
+                field: "security_enabled",
+                offset: 0,
+            });
+        }
+        if frame_control.ie_present() {
+            return Err(ParseError::InvalidValue {
+                field: "ie_present",
+                offset: 1,
+            });
+        }
+        if frame_control.frame_version() == FrameVersion::Reserved {
+            return Err(ParseError::InvalidValue {
+                field: "frame_version",
+                offset: 1,
+            });
+        }
+        let sequence_number = if frame_control.sequence_number_suppression() {
+            None
+        } else {
+            Some(src.byte()?)
+        };
+        let (destination_pan_id_present, source_pan_id_present) =
+            Self::pan_ids_present(frame_control);
+        let destination_pan_id = if destination_pan_id_present {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let destination = unpack_address(
+            &mut src,
+            frame_control.destination_addressing_mode(),
+            "destination_addressing_mode",
+        )?;
+        let source_pan_id = if source_pan_id_present {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let source = unpack_address(
+            &mut src,
+            frame_control.source_addressing_mode(),
+            "source_addressing_mode",
+        )?;
+        Ok(Self {
+            frame_control,
+            sequence_number,
+            destination_pan_id,
+            destination,
+            source_pan_id,
+            source,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.frame_control.pack_into(buf)?;
+        if let Some(sequence_number) = self.sequence_number {
+            offset += sequence_number.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(destination_pan_id) = self.destination_pan_id {
+            offset += destination_pan_id.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(destination) = &self.destination {
+            offset += destination.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(source_pan_id) = self.source_pan_id {
+            offset += source_pan_id.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(source) = &self.source {
+            offset += source.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+fn unpack_address<I: Iterator<Item = u8>>(
+    src: &mut Input<I>,
+    addressing_mode: AddressingMode,
+    field: &'static str,
+) -> Result<Option<Address>, ParseError> {
+    match addressing_mode {
+        AddressingMode::None => Ok(None),
+        AddressingMode::Short => Ok(Some(Address::Short(src.unpack()?))),
+        AddressingMode::Extended => Ok(Some(Address::Extended(src.unpack()?))),
+        AddressingMode::Reserved => Err(ParseError::InvalidValue { field, offset: 1 }),
+    }
+}
+
+impl_pack_bytes! {
+    /// Frame Control Field
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.2.2.
+    #[derive(Clone, Copy, PartialEq, Eq)]
+    pub struct FrameControl(pub u16);
+}
+
+impl Debug for FrameControl {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FrameControl")
+            .field("frame_type", &self.frame_type())
+            .field("security_enabled", &self.security_enabled())
+            .field("frame_pending", &self.frame_pending())
+            .field("ack_request", &self.ack_request())
+            .field("pan_id_compression", &self.pan_id_compression())
+            .field(
+                "sequence_number_suppression",
+                &self.sequence_number_suppression(),
+            )
+            .field("ie_present", &self.ie_present())
+            .field(
+                "destination_addressing_mode",
+                &self.destination_addressing_mode(),
+            )
+            .field("frame_version", &self.frame_version())
+            .field("source_addressing_mode", &self.source_addressing_mode())
+            .finish()
+    }
+}
+
+impl FrameControl {
+    fn bit(self, n: u16) -> bool {
+        ((self.0 >> n) & 0b1) != 0
+    }
+
+    fn set_bit(&mut self, n: u16, value: bool) {
+        self.0 = (self.0 & !(1 << n)) | (u16::from(value) << n);
+    }
+
+    /// See Section 7.2.2.2.
+    pub fn frame_type(&self) -> FrameType {
+        match self.0 & 0b111 {
+            0b000 => FrameType::Beacon,
+            0b001 => FrameType::Data,
+            0b010 => FrameType::Acknowledgement,
+            0b011 => FrameType::MacCommand,
+            0b100 => FrameType::Reserved,
+            0b101 => FrameType::Multipurpose,
+            0b110 => FrameType::Fragment,
+            _ => FrameType::Extended,
+        }
+    }
+
+    /// See Section 7.2.2.3.
+    pub fn security_enabled(&self) -> bool {
+        self.bit(3)
+    }
+
+    /// The sender has more data for the recipient.
+    ///
+    /// See Section 7.2.2.4.
+    pub fn frame_pending(&self) -> bool {
+        self.bit(4)
+    }
+
+    /// See Section 7.2.2.4.
+    pub fn set_frame_pending(&mut self, frame_pending: bool) {
+        self.set_bit(4, frame_pending);
+    }
+
+    /// The recipient shall send an acknowledgment frame.
+    ///
+    /// See Section 7.2.2.5.
+    pub fn ack_request(&self) -> bool {
+        self.bit(5)
+    }
+
+    /// See Section 7.2.2.5.
+    pub fn set_ack_request(&mut self, ack_request: bool) {
+        self.set_bit(5, ack_request);
+    }
+
+    /// See Section 7.2.2.6.
+    pub fn pan_id_compression(&self) -> bool {
+        self.bit(6)
+    }
+
+    /// See Section 7.2.2.7.
+    pub fn sequence_number_suppression(&self) -> bool {
+        self.bit(8)
+    }
+
+    /// See Section 7.2.2.8.
+    pub fn ie_present(&self) -> bool {
+        self.bit(9)
+    }
+
+    /// See Section 7.2.2.9.
+    pub fn destination_addressing_mode(&self) -> AddressingMode {
+        AddressingMode::from_bits(self.0 >> 10)
+    }
+
+    /// See Section 7.2.2.10.
+    pub fn frame_version(&self) -> FrameVersion {
+        match (self.0 >> 12) & 0b11 {
+            0b00 => FrameVersion::Ieee802154_2003,
+            0b01 => FrameVersion::Ieee802154_2006,
+            0b10 => FrameVersion::Ieee802154,
+            _ => FrameVersion::Reserved,
+        }
+    }
+
+    /// See Section 7.2.2.11.
+    pub fn source_addressing_mode(&self) -> AddressingMode {
+        AddressingMode::from_bits(self.0 >> 14)
+    }
+}
+
+/// Frame Type Sub-Field
+///
+/// See IEEE 802.15.4-2015, Table 7-1.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum FrameType {
+    Beacon = 0b000,
+    Data = 0b001,
+    Acknowledgement = 0b010,
+    MacCommand = 0b011,
+    Reserved = 0b100,
+    Multipurpose = 0b101,
+    Fragment = 0b110,
+    Extended = 0b111,
+}
+
+/// Addressing Mode Sub-Field
+///
+/// See IEEE 802.15.4-2015, Table 7-3.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum AddressingMode {
+    None = 0b00,
+    Reserved = 0b01,
+    Short = 0b10,
+    Extended = 0b11,
+}
+
+impl AddressingMode {
+    fn from_bits(bits: u16) -> Self {
+        match bits & 0b11 {
+            0b00 => Self::None,
+            0b01 => Self::Reserved,
+            0b10 => Self::Short,
+            _ => Self::Extended,
+        }
+    }
+}
+
+/// Frame Version Sub-Field
+///
+/// See IEEE 802.15.4-2015, Table 7-4.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum FrameVersion {
+    Ieee802154_2003 = 0b00,
+    Ieee802154_2006 = 0b01,
+    Ieee802154 = 0b10,
+    Reserved = 0b11,
+}
+
+/// Calculates the frame check sequence (CRC-16/KERMIT) of an MPDU.
+///
+/// See IEEE 802.15.4-2015, Section 7.2.10.
+pub fn crc16_kermit(data: &[u8]) -> u16 {
+    let mut crc = 0u16;
+    for b in data {
+        crc ^= u16::from(*b);
+        for _ in 0..8 {
+            crc = if crc & 0b1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0x8408
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mac::command::AssociationResponse;
+    use crate::mac::command::AssociationStatus;
+    use crate::nwk::frame::FrameTypeIdentifier;
+    use crate::nwk::frame::NwkFrame;
+
+    #[test]
+    fn crc16_kermit_check_value() {
+        assert_eq!(crc16_kermit(b"123456789"), 0x2189);
+    }
+
+    #[test]
+    fn unpack_beacon_request() {
+        let raw = [0x03, 0x08, 0xa5, 0xff, 0xff, 0xff, 0xff, 0x07];
+
+        let frame = MacFrame::unpack_from_slice(&raw).unwrap();
+
+        assert_eq!(
+            frame,
+            MacFrame::Command(MacCommandFrame {
+                header: MacHeader::new(
+                    FrameType::MacCommand,
+                    0xa5,
+                    Some((BROADCAST, Address::BROADCAST)),
+                    None
+                ),
+                command: MacCommand::BeaconRequest,
+            })
+        );
+    }
+
+    #[test]
+    fn unpack_data_frame_with_nwk_frame() {
+        let raw = [
+            0x41, 0x88, 0x2e, 0x62, 0x1a, 0xff, 0xff, 0x00, 0x00, 0x09, 0x00, 0xfd, 0xff, 0x00,
+            0x00, 0x01, 0x2a, 0x04, 0x60,
+        ];
+        let mut psdu = [0u8; 21];
+        psdu[..19].copy_from_slice(&raw);
+        psdu[19..].copy_from_slice(&crc16_kermit(&raw).to_le_bytes());
+
+        let frame = MacFrame::unpack_from_psdu(&psdu).unwrap();
+        let MacFrame::Data(data_frame) = frame else {
+            unreachable!("expected a data frame");
+        };
+        let header = &data_frame.header;
+        let nwk_frame = NwkFrame::unpack_from_slice(&data_frame.payload).unwrap();
+
+        assert!(!header.frame_control.ack_request());
+        assert_eq!(header.sequence_number, Some(0x2e));
+        assert_eq!(header.destination_pan_id, Some(0x1a62));
+        assert_eq!(header.source_pan_id(), Some(0x1a62));
+        assert_eq!(header.destination, Some(Address::BROADCAST));
+        assert_eq!(header.source, Some(Address::Short(ShortAddress(0x0000))));
+        assert_eq!(
+            nwk_frame.frame_type_identifier(),
+            FrameTypeIdentifier::NwkCommand
+        );
+    }
+
+    #[test]
+    fn unpack_psdu_with_invalid_fcs_should_fail() {
+        let psdu = [0x02, 0x00, 0x2e, 0x00, 0x00];
+
+        let got = MacFrame::unpack_from_psdu(&psdu);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "fcs",
+                offset: 3
+            })
+        );
+    }
+
+    #[test]
+    fn pack_ack_psdu() {
+        let mut header = MacHeader::new(FrameType::Acknowledgement, 0x2e, None, None);
+        header.frame_control.set_frame_pending(true);
+
+        let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+        let len = MacFrame::Acknowledgement(header.clone())
+            .pack_into_psdu(&mut buf)
+            .unwrap();
+
+        assert_eq!(&buf[..3], &[0x12, 0x00, 0x2e]);
+        assert_eq!(len, 5);
+        assert_eq!(
+            MacFrame::unpack_from_psdu(&buf[..len]),
+            Ok(MacFrame::Acknowledgement(header))
+        );
+    }
+
+    #[test]
+    fn association_response_round_trip() {
+        let mut header = MacHeader::new(
+            FrameType::MacCommand,
+            0x10,
+            Some((
+                0x1a62,
+                Address::Extended(IeeeAddress(0x0012_4b00_2a9a_7166)),
+            )),
+            Some((
+                0x1a62,
+                Address::Extended(IeeeAddress(0x0015_8d00_01ab_cd12)),
+            )),
+        );
+        header.frame_control.set_ack_request(true);
+        let frame = MacFrame::Command(MacCommandFrame {
+            header,
+            command: MacCommand::AssociationResponse(AssociationResponse {
+                short_address: ShortAddress(0x796f),
+                status: AssociationStatus::Success,
+            }),
+        });
+
+        let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+        let len = frame.pack_into_psdu(&mut buf).unwrap();
+
+        assert_eq!(&buf[..2], &[0x63, 0xcc]);
+        assert_eq!(len, 2 + 1 + 2 + 8 + 8 + 4 + 2);
+        assert_eq!(MacFrame::unpack_from_psdu(&buf[..len]), Ok(frame));
+    }
+
+    #[test]
+    fn unpack_2015_frame_with_compressed_extended_addresses() {
+        // data frame, version 2015, PAN ID compression, extended addresses
+        let raw = [
+            0x41, 0xec, 0x01, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x11, 0x22, 0x33,
+            0x44, 0x55, 0x66, 0x77, 0x88, 0xab,
+        ];
+
+        let frame = MacFrame::unpack_from_slice(&raw).unwrap();
+        let header = frame.header();
+
+        assert_eq!(
+            header.frame_control.frame_version(),
+            FrameVersion::Ieee802154
+        );
+        assert_eq!(header.destination_pan_id, None);
+        assert_eq!(header.source_pan_id(), None);
+        assert_eq!(
+            header.source,
+            Some(Address::Extended(IeeeAddress(0x8877_6655_4433_2211)))
+        );
+    }
+
+    #[test]
+    fn unpack_secured_frame_should_fail() {
+        let raw = [0x49, 0x88, 0x01, 0x62, 0x1a, 0xff, 0xff, 0x00, 0x00];
+
+        let got = MacFrame::unpack_from_slice(&raw);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "security_enabled",
+                offset: 0
+            })
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! IEEE 802.15.4 Medium Access Control
+//!
+//! Frame formats and procedures of the MAC sub-layer the network layer is
+//! built upon.
+//!
+//! See IEEE 802.15.4-2015, Section 7.
+use core::time::Duration;
+
+use beacon::Beacon;
+use beacon::SuperframeSpecification;
+use frame::Address;
+use frame::FrameType;
+use frame::MacBeaconFrame;
+use frame::MacDataFrame;
+use frame::MacFrame;
+use frame::MacHeader;
+use frame::BROADCAST;
+use heapless::Vec;
+
+use crate::common::parse::PackBytes;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+use crate::radio::MIN_CHANNEL;
+
+pub mod association;
+pub mod beacon;
+pub mod command;
+pub mod frame;
+mod poll;
+pub mod scan;
+
+/// MAC sub-layer entity driving the [`Radio`].
+///
+/// Holds the MAC PIB attributes the stack depends on, see IEEE 802.15.4-2015
+/// Section 8.4.3.
+pub(crate) struct Mac<R> {
+    pub(crate) radio: R,
+    /// macDsn
+    sequence_number: u8,
+    /// macBsn
+    beacon_sequence_number: u8,
+    /// phyCurrentChannel
+    pub(crate) channel: u8,
+    /// macPanId
+    pub(crate) pan_id: u16,
+    /// macShortAddress
+    pub(crate) short_address: ShortAddress,
+    /// macExtendedAddress
+    pub(crate) extended_address: IeeeAddress,
+    /// The device is the PAN coordinator.
+    pub(crate) pan_coordinator: bool,
+    /// macAssociationPermit
+    pub(crate) association_permit: bool,
+    /// macRxOnWhenIdle
+    pub(crate) rx_on_when_idle: bool,
+}
+
+/// A frame received by the [`Mac`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Received {
+    pub(crate) frame: MacFrame,
+    pub(crate) lqi: u8,
+    pub(crate) timestamp: Duration,
+}
+
+impl<R: Radio> Mac<R> {
+    pub(crate) fn new(radio: R) -> Self {
+        Self {
+            radio,
+            sequence_number: 0,
+            beacon_sequence_number: 0,
+            channel: MIN_CHANNEL,
+            pan_id: BROADCAST,
+            short_address: ShortAddress(BROADCAST),
+            extended_address: IeeeAddress(0),
+            pan_coordinator: false,
+            association_permit: false,
+            rx_on_when_idle: true,
+        }
+    }
+
+    /// Returns the next data sequence number.
+    pub(crate) fn next_sequence_number(&mut self) -> u8 {
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        self.sequence_number
+    }
+
+    /// Returns the next beacon sequence number.
+    fn next_beacon_sequence_number(&mut self) -> u8 {
+        self.beacon_sequence_number = self.beacon_sequence_number.wrapping_add(1);
+        self.beacon_sequence_number
+    }
+
+    /// Starts operating on a PAN as coordinator, in a non-beacon network
+    /// beacons are only sent on request.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.3.4.
+    pub(crate) async fn start(
+        &mut self,
+        channel: u8,
+        pan_id: u16,
+        short_address: ShortAddress,
+        pan_coordinator: bool,
+    ) -> Result<(), R::Error> {
+        self.radio
+            .set_extended_address(self.extended_address)
+            .await?;
+        self.radio.set_short_address(short_address).await?;
+        self.radio.set_pan_id(pan_id).await?;
+        self.radio.set_channel(channel).await?;
+        self.channel = channel;
+        self.pan_id = pan_id;
+        self.short_address = short_address;
+        self.pan_coordinator = pan_coordinator;
+        Ok(())
+    }
+
+    /// Sends a beacon carrying `payload` in response to a beacon request.
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.1.
+    pub(crate) async fn send_beacon(&mut self, payload: &[u8]) -> Result<TxStatus, R::Error> {
+        let mut superframe = SuperframeSpecification::NON_BEACON;
+        superframe.set_pan_coordinator(self.pan_coordinator);
+        superframe.set_association_permit(self.association_permit);
+        let Ok(payload) = Vec::from_slice(payload) else {
+            return Ok(TxStatus::FrameTooLong);
+        };
+        let beacon = MacFrame::Beacon(MacBeaconFrame {
+            header: MacHeader::new(
+                FrameType::Beacon,
+                self.next_beacon_sequence_number(),
+                None,
+                Some((self.pan_id, Address::Short(self.short_address))),
+            ),
+            beacon: Beacon::new(superframe),
+            payload,
+        });
+        self.transmit(&beacon).await
+    }
+
+    /// Sends `payload` to `destination` on the current PAN, requesting an
+    /// acknowledgment unless it is broadcast.
+    ///
+    /// The frame pending bit tells a polling device that more frames are
+    /// kept for it.
+    ///
+    /// See IEEE 802.15.4-2015, Section 7.3.2.
+    pub(crate) async fn send_data(
+        &mut self,
+        destination: Address,
+        payload: &[u8],
+        frame_pending: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let Ok(payload) = Vec::from_slice(payload) else {
+            return Ok(TxStatus::FrameTooLong);
+        };
+        let mut header = MacHeader::new(
+            FrameType::Data,
+            self.next_sequence_number(),
+            Some((self.pan_id, destination)),
+            Some((self.pan_id, Address::Short(self.short_address))),
+        );
+        header
+            .frame_control
+            .set_ack_request(destination != Address::BROADCAST);
+        header.frame_control.set_frame_pending(frame_pending);
+        self.transmit(&MacFrame::Data(MacDataFrame { header, payload }))
+            .await
+    }
+
+    /// Transmits a frame using CSMA-CA.
+    pub(crate) async fn transmit(&mut self, frame: &MacFrame) -> Result<TxStatus, R::Error> {
+        let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+        let Ok(len) = frame.pack_into(&mut buf) else {
+            return Ok(TxStatus::FrameTooLong);
+        };
+        self.radio.transmit(&buf[..len], true).await
+    }
+
+    /// Waits up to `timeout` for the next valid frame.
+    pub(crate) async fn receive(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Received>, R::Error> {
+        let deadline = self.radio.now() + timeout;
+        loop {
+            let remaining = deadline.saturating_sub(self.radio.now());
+            let Some(rx) = self.radio.receive(remaining).await? else {
+                return Ok(None);
+            };
+            if let Ok(frame) = MacFrame::unpack_from_slice(&rx.frame) {
+                return Ok(Some(Received {
+                    frame,
+                    lqi: rx.lqi,
+                    timestamp: rx.timestamp,
+                }));
+            }
+        }
+    }
+}
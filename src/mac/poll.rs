@@ -0,0 +1,67 @@
+//! MAC Data Polling
+//!
+//! A device polls its coordinator with a data request for the frames the
+//! coordinator keeps for it.
+//!
+//! See IEEE 802.15.4-2015, Section 6.7.3.
+use super::association::MAX_FRAME_TOTAL_WAIT_TIME;
+use super::command::MacCommand;
+use super::frame::Address;
+use super::frame::FrameType;
+use super::frame::MacCommandFrame;
+use super::frame::MacDataFrame;
+use super::frame::MacFrame;
+use super::frame::MacHeader;
+use super::Mac;
+use super::Received;
+use crate::common::types::ShortAddress;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+impl<R: Radio> Mac<R> {
+    /// Sends a data request to `coordinator` on the current PAN.
+    ///
+    /// The frame pending bit of the acknowledgment tells if the coordinator
+    /// keeps a frame for this device.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.7.3.
+    pub(crate) async fn poll(&mut self, coordinator: ShortAddress) -> Result<TxStatus, R::Error> {
+        let mut header = MacHeader::new(
+            FrameType::MacCommand,
+            self.next_sequence_number(),
+            Some((self.pan_id, Address::Short(coordinator))),
+            Some((self.pan_id, Address::Short(self.short_address))),
+        );
+        header.frame_control.set_ack_request(true);
+        self.transmit(&MacFrame::Command(MacCommandFrame {
+            header,
+            command: MacCommand::DataRequest,
+        }))
+        .await
+    }
+
+    /// Waits up to macMaxFrameTotalWaitTime for the data frame which
+    /// `coordinator` announced with the frame pending bit of its
+    /// acknowledgment.
+    ///
+    /// Other frames are dropped.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.7.3.
+    pub(crate) async fn receive_pending(
+        &mut self,
+        coordinator: ShortAddress,
+    ) -> Result<Option<Received>, R::Error> {
+        let deadline = self.radio.now() + MAX_FRAME_TOTAL_WAIT_TIME;
+        while let Some(received) = self
+            .receive(deadline.saturating_sub(self.radio.now()))
+            .await?
+        {
+            if let MacFrame::Data(MacDataFrame { header, .. }) = &received.frame {
+                if header.source == Some(Address::Short(coordinator)) {
+                    return Ok(Some(received));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,217 @@
+//! MAC Scanning
+//!
+//! See IEEE 802.15.4-2015, Section 6.3.
+
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::beacon::SuperframeSpecification;
+use super::command::MacCommand;
+use super::frame::Address;
+use super::frame::FrameType;
+use super::frame::MacCommandFrame;
+use super::frame::MacFrame;
+use super::frame::MacHeader;
+use super::frame::BROADCAST;
+use super::Mac;
+use crate::radio::ChannelMask;
+use crate::radio::Radio;
+
+/// aBaseSuperframeDuration in symbols.
+const BASE_SUPERFRAME_DURATION: u32 = 960;
+
+/// Duration of a symbol in the 2.4 GHz band.
+const SYMBOL_DURATION: Duration = Duration::from_micros(16);
+
+/// Largest valid scan duration exponent.
+pub const MAX_SCAN_DURATION: u8 = 14;
+
+/// Maximum number of PAN descriptors collected by an active scan.
+pub const MAX_PAN_DESCRIPTORS: usize = 16;
+
+/// Number of channels of the 2.4 GHz band.
+pub const MAX_SCAN_CHANNELS: usize = 16;
+
+/// aMaxBeaconPayloadLength
+pub const MAX_BEACON_PAYLOAD_LENGTH: usize = 52;
+
+/// Time spent on each channel, `aBaseSuperframeDuration * (2^n + 1)`
+/// symbols.
+fn scan_time(scan_duration: u8) -> Duration {
+    SYMBOL_DURATION * BASE_SUPERFRAME_DURATION * ((1 << scan_duration.min(MAX_SCAN_DURATION)) + 1)
+}
+
+/// Description of a coordinator found by an active scan.
+///
+/// See IEEE 802.15.4-2015, Section 8.2.5.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanDescriptor {
+    pub(crate) channel: u8,
+    pub(crate) pan_id: u16,
+    pub(crate) address: Address,
+    pub(crate) superframe_specification: SuperframeSpecification,
+    pub(crate) lqi: u8,
+    pub(crate) payload: Vec<u8, MAX_BEACON_PAYLOAD_LENGTH>,
+}
+
+impl<R: Radio> Mac<R> {
+    /// Sends a beacon request on each channel and collects the beacons
+    /// received within the scan time.
+    ///
+    /// Channel and PAN ID are restored afterwards.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.3.1.2.
+    pub(crate) async fn active_scan(
+        &mut self,
+        channels: ChannelMask,
+        scan_duration: u8,
+    ) -> Result<Vec<PanDescriptor, MAX_PAN_DESCRIPTORS>, R::Error> {
+        let mut descriptors: Vec<PanDescriptor, MAX_PAN_DESCRIPTORS> = Vec::new();
+        self.radio.set_pan_id(BROADCAST).await?;
+        for channel in channels.channels() {
+            self.radio.set_channel(channel).await?;
+            let request = MacFrame::Command(MacCommandFrame {
+                header: MacHeader::new(
+                    FrameType::MacCommand,
+                    self.next_sequence_number(),
+                    Some((BROADCAST, Address::BROADCAST)),
+                    None,
+                ),
+                command: MacCommand::BeaconRequest,
+            });
+            self.transmit(&request).await?;
+
+            let deadline = self.radio.now() + scan_time(scan_duration);
+            while let Some(received) = self
+                .receive(deadline.saturating_sub(self.radio.now()))
+                .await?
+            {
+                let MacFrame::Beacon(beacon) = received.frame else {
+                    continue;
+                };
+                let (Some(pan_id), Some(address)) =
+                    (beacon.header.source_pan_id(), beacon.header.source)
+                else {
+                    continue;
+                };
+                let known = descriptors.iter().any(|descriptor| {
+                    descriptor.channel == channel
+                        && descriptor.pan_id == pan_id
+                        && descriptor.address == address
+                });
+                if known {
+                    continue;
+                }
+                // further coordinators are dropped once the list is full
+                let _ = descriptors.push(PanDescriptor {
+                    channel,
+                    pan_id,
+                    address,
+                    superframe_specification: beacon.beacon.superframe_specification,
+                    lqi: received.lqi,
+                    payload: Vec::from_slice(&beacon.payload).unwrap_or_default(),
+                });
+            }
+        }
+        self.radio.set_channel(self.channel).await?;
+        self.radio.set_pan_id(self.pan_id).await?;
+        Ok(descriptors)
+    }
+
+    /// Measures the peak energy of each channel for the scan time.
+    ///
+    /// Returns the energy levels in the order of the channels. The channel is
+    /// restored afterwards.
+    ///
+    /// See IEEE 802.15.4-2015, Section 6.3.1.1.
+    pub(crate) async fn energy_scan(
+        &mut self,
+        channels: ChannelMask,
+        scan_duration: u8,
+    ) -> Result<Vec<u8, MAX_SCAN_CHANNELS>, R::Error> {
+        let mut energies = Vec::new();
+        for channel in channels.channels() {
+            self.radio.set_channel(channel).await?;
+            let energy = self.radio.energy_detect(scan_time(scan_duration)).await?;
+            // at most 16 channels are selected
+            let _ = energies.push(energy);
+        }
+        self.radio.set_channel(self.channel).await?;
+        Ok(energies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::ShortAddress;
+    use crate::mac::beacon::Beacon;
+    use crate::mac::frame::MacBeaconFrame;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+
+    fn beacon(pan_id: u16, address: u16, lqi: u8) -> RxFrame {
+        let frame = MacFrame::Beacon(MacBeaconFrame {
+            header: MacHeader::new(
+                FrameType::Beacon,
+                0,
+                None,
+                Some((pan_id, Address::Short(ShortAddress(address)))),
+            ),
+            beacon: Beacon::new(SuperframeSpecification::NON_BEACON),
+            payload: Vec::from_slice(&[0x00]).unwrap(),
+        });
+        RxFrame {
+            frame: frame.pack_to_vec().unwrap(),
+            lqi,
+            rssi: -60,
+            timestamp: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn scan_time_of_duration_3() {
+        assert_eq!(scan_time(3), Duration::from_micros(138_240));
+    }
+
+    #[test]
+    fn active_scan_sends_beacon_request_and_collects_beacons() {
+        let mut radio = MockRadio::new();
+        radio.received.push_back(beacon(0x1a62, 0x0000, 200));
+        radio.received.push_back(beacon(0x1a62, 0x0000, 200));
+        radio.received.push_back(beacon(0x1a62, 0x796f, 120));
+        let mut mac = Mac::new(radio);
+
+        let descriptors = block_on(mac.active_scan(ChannelMask::single(15), 3)).unwrap();
+
+        assert_eq!(descriptors.len(), 2);
+        assert_eq!(descriptors[0].channel, 15);
+        assert_eq!(descriptors[0].pan_id, 0x1a62);
+        assert_eq!(descriptors[1].address, Address::Short(ShortAddress(0x796f)));
+        assert_eq!(descriptors[1].lqi, 120);
+        assert_eq!(
+            mac.radio.transmitted,
+            [[0x03, 0x08, 0x01, 0xff, 0xff, 0xff, 0xff, 0x07]]
+        );
+        assert_eq!(mac.radio.channel, crate::radio::MIN_CHANNEL);
+        assert_eq!(mac.radio.pan_id, BROADCAST);
+    }
+
+    #[test]
+    fn energy_scan_measures_selected_channels() {
+        let mut radio = MockRadio::new();
+        radio.energy[0] = 0x10;
+        radio.energy[4] = 0xc0;
+        radio.energy[15] = 0x20;
+        let mut mac = Mac::new(radio);
+
+        let energies = block_on(mac.energy_scan(ChannelMask(0x0400_8800), 3)).unwrap();
+
+        assert_eq!(energies, [0x10, 0xc0, 0x20]);
+        assert_eq!(mac.radio.now, scan_time(3) * 3);
+        assert_eq!(mac.radio.channel, crate::radio::MIN_CHANNEL);
+    }
+}
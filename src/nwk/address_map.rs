@@ -0,0 +1,118 @@
+//! Address Map
+//!
+//! Associates the IEEE addresses of devices with their network addresses,
+//! as learned from frames carrying the IEEE address of their originator and
+//! from device announcements. Two IEEE addresses for one network address
+//! reveal an address conflict.
+//!
+//! See Section 3.6.1.9.
+use heapless::Vec;
+
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+
+/// Maximum number of address map entries.
+pub const MAX_ADDRESS_MAP_ENTRIES: usize = 32;
+
+/// Address map entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressMapEntry {
+    /// 64-bit IEEE address of the device
+    pub extended_address: IeeeAddress,
+    /// 16-bit network address of the device
+    pub network_address: ShortAddress,
+}
+
+/// nwkAddressMap
+#[derive(Debug, Default)]
+pub struct AddressMap {
+    entries: Vec<AddressMapEntry, MAX_ADDRESS_MAP_ENTRIES>,
+}
+
+impl AddressMap {
+    /// Creates an empty address map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries, the least recently updated first.
+    pub fn iter(&self) -> impl Iterator<Item = &AddressMapEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the IEEE address of the device with `network_address`.
+    pub fn extended_address(&self, network_address: ShortAddress) -> Option<IeeeAddress> {
+        self.entries
+            .iter()
+            .find(|entry| entry.network_address == network_address)
+            .map(|entry| entry.extended_address)
+    }
+
+    /// Returns the network address of the device with `extended_address`.
+    pub fn network_address(&self, extended_address: IeeeAddress) -> Option<ShortAddress> {
+        self.entries
+            .iter()
+            .find(|entry| entry.extended_address == extended_address)
+            .map(|entry| entry.network_address)
+    }
+
+    /// Adds an entry or updates the network address of the same device.
+    ///
+    /// A full map evicts the least recently updated entry.
+    pub(crate) fn insert(&mut self, entry: AddressMapEntry) {
+        self.entries.retain(|known| {
+            known.extended_address != entry.extended_address
+                && known.network_address != entry.network_address
+        });
+        if self.entries.is_full() {
+            self.entries.remove(0);
+        }
+        let _ = self.entries.push(entry);
+    }
+
+    /// Removes the entry of the device with `network_address`.
+    pub(crate) fn remove(&mut self, network_address: ShortAddress) {
+        self.entries
+            .retain(|entry| entry.network_address != network_address);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(extended_address: u64, network_address: u16) -> AddressMapEntry {
+        AddressMapEntry {
+            extended_address: IeeeAddress(extended_address),
+            network_address: ShortAddress(network_address),
+        }
+    }
+
+    #[test]
+    fn entries_are_updated_and_evicted_by_age() {
+        let mut map = AddressMap::new();
+        for address in (1..).take(MAX_ADDRESS_MAP_ENTRIES) {
+            map.insert(entry(u64::from(address - 1), address));
+        }
+        map.insert(entry(0, 0x796f));
+
+        assert_eq!(map.iter().count(), MAX_ADDRESS_MAP_ENTRIES);
+        assert_eq!(
+            map.network_address(IeeeAddress(0)),
+            Some(ShortAddress(0x796f))
+        );
+        assert_eq!(map.extended_address(ShortAddress(1)), None);
+
+        map.insert(entry(0x0015_8d00_0000_0001, 0x1234));
+
+        assert_eq!(map.network_address(IeeeAddress(1)), None);
+        assert_eq!(
+            map.extended_address(ShortAddress(0x1234)),
+            Some(IeeeAddress(0x0015_8d00_0000_0001))
+        );
+
+        map.remove(ShortAddress(0x796f));
+
+        assert_eq!(map.network_address(IeeeAddress(0)), None);
+    }
+}
@@ -0,0 +1,139 @@
+//! NWK Beacon Payload
+//!
+//! See Section 3.6.7.
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+
+/// Protocol identifier of Zigbee beacons.
+pub const ZIGBEE_PROTOCOL_ID: u8 = 0x00;
+
+/// Size of the beacon payload.
+pub const BEACON_PAYLOAD_SIZE: usize = 15;
+
+/// NWK Layer Information Fields
+///
+/// The payload of beacons sent by Zigbee coordinators and routers.
+///
+/// See Section 3.6.7.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BeaconPayload {
+    /// Always [`ZIGBEE_PROTOCOL_ID`] for Zigbee networks.
+    pub protocol_id: u8,
+    /// The nwkStackProfile of the sender.
+    pub stack_profile: u8,
+    /// The nwkcProtocolVersion of the sender.
+    pub protocol_version: u8,
+    /// The sender accepts join requests from router-capable devices.
+    pub router_capacity: bool,
+    /// Network depth of the sender.
+    pub device_depth: u8,
+    /// The sender accepts join requests from end devices.
+    pub end_device_capacity: bool,
+    /// The nwkExtendedPANId of the network.
+    pub extended_pan_id: u64,
+    /// Time difference between the sender and its parent, 24 bits.
+    pub tx_offset: u32,
+    /// The nwkUpdateId of the sender.
+    pub update_id: u8,
+}
+
+impl PackBytes for BeaconPayload {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let protocol_id = src.byte()?;
+        let profile = src.byte()?;
+        let capacity = src.byte()?;
+        let extended_pan_id = src.unpack()?;
+        let tx_offset = u32::from_le_bytes([src.byte()?, src.byte()?, src.byte()?, 0]);
+        let update_id = src.byte()?;
+        Ok(Self {
+            protocol_id,
+            stack_profile: profile & 0b1111,
+            protocol_version: profile >> 4,
+            router_capacity: (capacity >> 2) & 0b1 != 0,
+            device_depth: (capacity >> 3) & 0b1111,
+            end_device_capacity: (capacity >> 7) != 0,
+            extended_pan_id,
+            tx_offset,
+            update_id,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let profile = (self.stack_profile & 0b1111) | (self.protocol_version << 4);
+        let capacity = (u8::from(self.router_capacity) << 2)
+            | ((self.device_depth & 0b1111) << 3)
+            | (u8::from(self.end_device_capacity) << 7);
+        let mut offset = self.protocol_id.pack_into(buf)?;
+        offset += profile.pack_into(remaining(buf, offset)?)?;
+        offset += capacity.pack_into(remaining(buf, offset)?)?;
+        offset += self.extended_pan_id.pack_into(remaining(buf, offset)?)?;
+        for b in &self.tx_offset.to_le_bytes()[..3] {
+            offset += b.pack_into(remaining(buf, offset)?)?;
+        }
+        offset += self.update_id.pack_into(remaining(buf, offset)?)?;
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BEACON_PAYLOAD: [u8; 15] = [
+        0x00, 0x22, 0x84, 0x12, 0xcd, 0xab, 0x01, 0x00, 0x8d, 0x15, 0x00, 0xff, 0xff, 0xff, 0x00,
+    ];
+
+    #[test]
+    fn unpack_coordinator_beacon_payload() {
+        let payload = BeaconPayload::unpack_from_slice(&BEACON_PAYLOAD).unwrap();
+
+        assert_eq!(
+            payload,
+            BeaconPayload {
+                protocol_id: ZIGBEE_PROTOCOL_ID,
+                stack_profile: 2,
+                protocol_version: 2,
+                router_capacity: true,
+                device_depth: 0,
+                end_device_capacity: true,
+                extended_pan_id: 0x0015_8d00_01ab_cd12,
+                tx_offset: 0x00ff_ffff,
+                update_id: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn beacon_payload_round_trip() {
+        let payload = BeaconPayload {
+            protocol_id: ZIGBEE_PROTOCOL_ID,
+            stack_profile: 2,
+            protocol_version: 2,
+            router_capacity: false,
+            device_depth: 3,
+            end_device_capacity: true,
+            extended_pan_id: 0x0015_8d00_01ab_cd12,
+            tx_offset: 0x0001_0203,
+            update_id: 7,
+        };
+
+        let mut buf = [0u8; BEACON_PAYLOAD_SIZE];
+        let len = payload.pack_into(&mut buf).unwrap();
+
+        assert_eq!(len, BEACON_PAYLOAD_SIZE);
+        assert_eq!(buf[2], 0x98);
+        assert_eq!(&buf[11..14], &[0x03, 0x02, 0x01]);
+        assert_eq!(BeaconPayload::unpack_from_slice(&buf), Ok(payload));
+    }
+
+    #[test]
+    fn unpack_truncated_beacon_payload_should_fail() {
+        let got = BeaconPayload::unpack_from_slice(&BEACON_PAYLOAD[..12]);
+
+        assert_eq!(got, Err(ParseError::Truncated { offset: 12 }));
+    }
+}
@@ -0,0 +1,121 @@
+//! Broadcast Transaction Table
+//!
+//! Records the broadcasts a device received within
+//! nwkNetworkBroadcastDeliveryTime, to drop duplicates and to track the
+//! neighbors heard relaying them.
+//!
+//! See Section 3.6.5.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::common::types::ShortAddress;
+use crate::nwk::neighbor::MAX_NEIGHBORS;
+
+/// Maximum number of broadcast transaction records.
+pub const MAX_BROADCAST_TRANSACTIONS: usize = 16;
+
+/// Broadcast transaction record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BroadcastTransaction {
+    /// 16-bit network address of the originator of the broadcast
+    pub source: ShortAddress,
+    /// NWK sequence number of the broadcast
+    pub sequence_number: u8,
+    /// Time at which the record expires
+    pub expiration: Duration,
+    /// Neighbors heard relaying the broadcast
+    pub passive_acks: Vec<ShortAddress, MAX_NEIGHBORS>,
+}
+
+/// 3.6.5 Broadcast Transaction Table
+#[derive(Debug, Default)]
+pub struct BroadcastTransactionTable {
+    entries: Vec<BroadcastTransaction, MAX_BROADCAST_TRANSACTIONS>,
+}
+
+impl BroadcastTransactionTable {
+    /// Creates an empty broadcast transaction table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = &BroadcastTransaction> {
+        self.entries.iter()
+    }
+
+    /// Returns the record of the broadcast `sequence_number` of `source`.
+    pub fn get(&self, source: ShortAddress, sequence_number: u8) -> Option<&BroadcastTransaction> {
+        self.entries.iter().find(|transaction| {
+            transaction.source == source && transaction.sequence_number == sequence_number
+        })
+    }
+
+    /// Adds a broadcast transaction record.
+    ///
+    /// Returns the record if the table is full.
+    pub(crate) fn insert(
+        &mut self,
+        transaction: BroadcastTransaction,
+    ) -> Result<(), BroadcastTransaction> {
+        self.entries.push(transaction)
+    }
+
+    /// Records that `neighbor` relayed the broadcast `sequence_number` of
+    /// `source`.
+    ///
+    /// Returns `false` if the broadcast is unknown.
+    pub(crate) fn passive_ack(
+        &mut self,
+        source: ShortAddress,
+        sequence_number: u8,
+        neighbor: ShortAddress,
+    ) -> bool {
+        let Some(transaction) = self.entries.iter_mut().find(|transaction| {
+            transaction.source == source && transaction.sequence_number == sequence_number
+        }) else {
+            return false;
+        };
+        if !transaction.passive_acks.contains(&neighbor) {
+            let _ = transaction.passive_acks.push(neighbor);
+        }
+        true
+    }
+
+    /// Removes the records which expired at `now`.
+    pub(crate) fn expire(&mut self, now: Duration) {
+        self.entries
+            .retain(|transaction| transaction.expiration > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passive_acks_are_recorded_until_expiration() {
+        let mut table = BroadcastTransactionTable::new();
+        table
+            .insert(BroadcastTransaction {
+                source: ShortAddress(0x796f),
+                sequence_number: 1,
+                expiration: Duration::from_secs(9),
+                passive_acks: Vec::new(),
+            })
+            .unwrap();
+
+        assert!(table.passive_ack(ShortAddress(0x796f), 1, ShortAddress(0x0001)));
+        assert!(table.passive_ack(ShortAddress(0x796f), 1, ShortAddress(0x0001)));
+        assert!(!table.passive_ack(ShortAddress(0x796f), 2, ShortAddress(0x0001)));
+        assert_eq!(
+            table.get(ShortAddress(0x796f), 1).unwrap().passive_acks,
+            [ShortAddress(0x0001)]
+        );
+
+        table.expire(Duration::from_secs(9));
+
+        assert_eq!(table.iter().count(), 0);
+    }
+}
@@ -0,0 +1,1134 @@
+//! NWK Command Frames
+//!
+//! Typed payloads of the commands carried in a
+//! [`NwkCommandFrame`](super::frame::NwkCommandFrame).
+//!
+//! See Section 3.4.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::frame::CommandFrameIdentifier;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::command::AssociationStatus;
+
+/// Maximum number of relays in a [`RouteRecord`].
+pub const ROUTE_RECORD_RELAY_LIST_SIZE: usize = 16;
+/// Maximum number of entries in a [`LinkStatus`] command, limited by its
+/// 5-bit entry count.
+pub const LINK_STATUS_LIST_SIZE: usize = 31;
+/// Maximum number of PAN identifiers in a [`NetworkReport`], limited by its
+/// 5-bit report information count.
+pub const NETWORK_REPORT_LIST_SIZE: usize = 31;
+/// Maximum number of entries in a [`LinkPowerDelta`] command.
+pub const LINK_POWER_LIST_SIZE: usize = 40;
+
+/// A decoded NWK command.
+///
+/// See Section 3.4.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum NwkCommand {
+    RouteRequest(RouteRequest),
+    RouteReply(RouteReply),
+    NetworkStatus(NetworkStatus),
+    Leave(Leave),
+    RouteRecord(RouteRecord),
+    RejoinRequest(RejoinRequest),
+    RejoinResponse(RejoinResponse),
+    LinkStatus(LinkStatus),
+    NetworkReport(NetworkReport),
+    NetworkUpdate(NetworkUpdate),
+    EndDeviceTimeoutRequest(EndDeviceTimeoutRequest),
+    EndDeviceTimeoutResponse(EndDeviceTimeoutResponse),
+    LinkPowerDelta(LinkPowerDelta),
+}
+
+impl NwkCommand {
+    /// Return the [`CommandFrameIdentifier`] of a [`NwkCommand`].
+    pub fn command_identifier(&self) -> CommandFrameIdentifier {
+        match self {
+            Self::RouteRequest(_) => CommandFrameIdentifier::RouteRequest,
+            Self::RouteReply(_) => CommandFrameIdentifier::RouteReply,
+            Self::NetworkStatus(_) => CommandFrameIdentifier::NetworkStatus,
+            Self::Leave(_) => CommandFrameIdentifier::Leave,
+            Self::RouteRecord(_) => CommandFrameIdentifier::RouteRecord,
+            Self::RejoinRequest(_) => CommandFrameIdentifier::RejoinRequest,
+            Self::RejoinResponse(_) => CommandFrameIdentifier::RejoinResponse,
+            Self::LinkStatus(_) => CommandFrameIdentifier::LinkStatus,
+            Self::NetworkReport(_) => CommandFrameIdentifier::NetworkReport,
+            Self::NetworkUpdate(_) => CommandFrameIdentifier::NetworkUpdate,
+            Self::EndDeviceTimeoutRequest(_) => CommandFrameIdentifier::EndDeviceTimeoutRequest,
+            Self::EndDeviceTimeoutResponse(_) => CommandFrameIdentifier::EndDeviceTimeoutResponse,
+            Self::LinkPowerDelta(_) => CommandFrameIdentifier::LinkPowerDelta,
+        }
+    }
+}
+
+impl PackBytes for NwkCommand {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let command = match src.unpack()? {
+            CommandFrameIdentifier::RouteRequest => Self::RouteRequest(src.unpack()?),
+            CommandFrameIdentifier::RouteReply => Self::RouteReply(src.unpack()?),
+            CommandFrameIdentifier::NetworkStatus => Self::NetworkStatus(src.unpack()?),
+            CommandFrameIdentifier::Leave => Self::Leave(src.unpack()?),
+            CommandFrameIdentifier::RouteRecord => Self::RouteRecord(src.unpack()?),
+            CommandFrameIdentifier::RejoinRequest => Self::RejoinRequest(src.unpack()?),
+            CommandFrameIdentifier::RejoinResponse => Self::RejoinResponse(src.unpack()?),
+            CommandFrameIdentifier::LinkStatus => Self::LinkStatus(src.unpack()?),
+            CommandFrameIdentifier::NetworkReport => Self::NetworkReport(src.unpack()?),
+            CommandFrameIdentifier::NetworkUpdate => Self::NetworkUpdate(src.unpack()?),
+            CommandFrameIdentifier::EndDeviceTimeoutRequest => {
+                Self::EndDeviceTimeoutRequest(src.unpack()?)
+            }
+            CommandFrameIdentifier::EndDeviceTimeoutResponse => {
+                Self::EndDeviceTimeoutResponse(src.unpack()?)
+            }
+            CommandFrameIdentifier::LinkPowerDelta => Self::LinkPowerDelta(src.unpack()?),
+            CommandFrameIdentifier::Reserved(_) => {
+                return Err(ParseError::InvalidValue {
+                    field: "command_identifier",
+                    offset: 0,
+                })
+            }
+        };
+        Ok(command)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = self.command_identifier().pack_into(buf)?;
+        let buf = remaining(buf, offset)?;
+        let len = match self {
+            Self::RouteRequest(command) => command.pack_into(buf),
+            Self::RouteReply(command) => command.pack_into(buf),
+            Self::NetworkStatus(command) => command.pack_into(buf),
+            Self::Leave(command) => command.pack_into(buf),
+            Self::RouteRecord(command) => command.pack_into(buf),
+            Self::RejoinRequest(command) => command.pack_into(buf),
+            Self::RejoinResponse(command) => command.pack_into(buf),
+            Self::LinkStatus(command) => command.pack_into(buf),
+            Self::NetworkReport(command) => command.pack_into(buf),
+            Self::NetworkUpdate(command) => command.pack_into(buf),
+            Self::EndDeviceTimeoutRequest(command) => command.pack_into(buf),
+            Self::EndDeviceTimeoutResponse(command) => command.pack_into(buf),
+            Self::LinkPowerDelta(command) => command.pack_into(buf),
+        }?;
+        Ok(offset + len)
+    }
+}
+
+/// Reads an optional IEEE address which is present if `flag` is set.
+fn unpack_optional_ieee<I: Iterator<Item = u8>>(
+    src: &mut Input<I>,
+    flag: bool,
+) -> Result<Option<IeeeAddress>, ParseError> {
+    if flag {
+        Ok(Some(src.unpack()?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Writes `address` if present and returns the number of bytes written.
+fn pack_optional_ieee(address: Option<IeeeAddress>, buf: &mut [u8]) -> Result<usize, PackError> {
+    address.map_or(Ok(0), |address| address.pack_into(buf))
+}
+
+/// Many-to-one sub-field of the route request command options.
+///
+/// See Section 3.4.1.3.1.1.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ManyToOne {
+    /// The route request is not a many-to-one route request.
+    No = 0b00,
+    /// Many-to-one route request, the sender supports a route record table.
+    RouteRecordTable = 0b01,
+    /// Many-to-one route request, the sender does not support a route record
+    /// table.
+    NoRouteRecordTable = 0b10,
+}
+
+/// 3.4.1 Route Request Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteRequest {
+    /// See Section 3.4.1.3.1.1.
+    pub many_to_one: ManyToOne,
+    /// Set if the destination address is a group address.
+    ///
+    /// See Section 3.4.1.3.1.3.
+    pub multicast: bool,
+    /// See Section 3.4.1.3.2.
+    pub route_request_identifier: u8,
+    /// See Section 3.4.1.3.3.
+    pub destination_address: ShortAddress,
+    /// See Section 3.4.1.3.4.
+    pub path_cost: u8,
+    /// See Section 3.4.1.3.5.
+    pub destination_ieee_address: Option<IeeeAddress>,
+}
+
+impl PackBytes for RouteRequest {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let options = src.byte()?;
+        let many_to_one = match (options >> 3) & 0b11 {
+            0b00 => ManyToOne::No,
+            0b01 => ManyToOne::RouteRecordTable,
+            0b10 => ManyToOne::NoRouteRecordTable,
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    field: "many_to_one",
+                    offset: 0,
+                })
+            }
+        };
+        Ok(Self {
+            many_to_one,
+            multicast: (options >> 6) & 0b1 != 0,
+            route_request_identifier: src.byte()?,
+            destination_address: src.unpack()?,
+            path_cost: src.byte()?,
+            destination_ieee_address: unpack_optional_ieee(&mut src, (options >> 5) & 0b1 != 0)?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let options = (self.many_to_one as u8) << 3
+            | u8::from(self.destination_ieee_address.is_some()) << 5
+            | u8::from(self.multicast) << 6;
+        let mut offset = options.pack_into(buf)?;
+        offset += self
+            .route_request_identifier
+            .pack_into(remaining(buf, offset)?)?;
+        offset += self
+            .destination_address
+            .pack_into(remaining(buf, offset)?)?;
+        offset += self.path_cost.pack_into(remaining(buf, offset)?)?;
+        offset += pack_optional_ieee(self.destination_ieee_address, remaining(buf, offset)?)?;
+        Ok(offset)
+    }
+}
+
+/// 3.4.2 Route Reply Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteReply {
+    /// Set if the reply belongs to a route request for a group address.
+    ///
+    /// See Section 3.4.2.3.1.3.
+    pub multicast: bool,
+    /// See Section 3.4.2.3.2.
+    pub route_request_identifier: u8,
+    /// See Section 3.4.2.3.3.
+    pub originator_address: ShortAddress,
+    /// See Section 3.4.2.3.4.
+    pub responder_address: ShortAddress,
+    /// See Section 3.4.2.3.5.
+    pub path_cost: u8,
+    /// See Section 3.4.2.3.6.
+    pub originator_ieee_address: Option<IeeeAddress>,
+    /// See Section 3.4.2.3.7.
+    pub responder_ieee_address: Option<IeeeAddress>,
+}
+
+impl PackBytes for RouteReply {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let options = src.byte()?;
+        Ok(Self {
+            multicast: (options >> 6) & 0b1 != 0,
+            route_request_identifier: src.byte()?,
+            originator_address: src.unpack()?,
+            responder_address: src.unpack()?,
+            path_cost: src.byte()?,
+            originator_ieee_address: unpack_optional_ieee(&mut src, (options >> 4) & 0b1 != 0)?,
+            responder_ieee_address: unpack_optional_ieee(&mut src, (options >> 5) & 0b1 != 0)?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let options = u8::from(self.originator_ieee_address.is_some()) << 4
+            | u8::from(self.responder_ieee_address.is_some()) << 5
+            | u8::from(self.multicast) << 6;
+        let mut offset = options.pack_into(buf)?;
+        offset += self
+            .route_request_identifier
+            .pack_into(remaining(buf, offset)?)?;
+        offset += self.originator_address.pack_into(remaining(buf, offset)?)?;
+        offset += self.responder_address.pack_into(remaining(buf, offset)?)?;
+        offset += self.path_cost.pack_into(remaining(buf, offset)?)?;
+        offset += pack_optional_ieee(self.originator_ieee_address, remaining(buf, offset)?)?;
+        offset += pack_optional_ieee(self.responder_ieee_address, remaining(buf, offset)?)?;
+        Ok(offset)
+    }
+}
+
+/// Network status codes.
+///
+/// See Table 3-50.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum NetworkStatusCode {
+    NoRouteAvailable = 0x00,
+    TreeLinkFailure = 0x01,
+    NonTreeLinkFailure = 0x02,
+    LowBatteryLevel = 0x03,
+    NoRoutingCapacity = 0x04,
+    NoIndirectCapacity = 0x05,
+    IndirectTransactionExpiry = 0x06,
+    TargetDeviceUnavailable = 0x07,
+    TargetAddressUnallocated = 0x08,
+    ParentLinkFailure = 0x09,
+    ValidateRoute = 0x0a,
+    SourceRouteFailure = 0x0b,
+    ManyToOneRouteFailure = 0x0c,
+    AddressConflict = 0x0d,
+    VerifyAddresses = 0x0e,
+    PanIdentifierUpdate = 0x0f,
+    NetworkAddressUpdate = 0x10,
+    BadFrameCounter = 0x11,
+    BadKeySequenceNumber = 0x12,
+    UnknownCommand = 0x13,
+}
+
+impl NetworkStatusCode {
+    fn from_u8(b: u8) -> Option<Self> {
+        let code = match b {
+            0x00 => Self::NoRouteAvailable,
+            0x01 => Self::TreeLinkFailure,
+            0x02 => Self::NonTreeLinkFailure,
+            0x03 => Self::LowBatteryLevel,
+            0x04 => Self::NoRoutingCapacity,
+            0x05 => Self::NoIndirectCapacity,
+            0x06 => Self::IndirectTransactionExpiry,
+            0x07 => Self::TargetDeviceUnavailable,
+            0x08 => Self::TargetAddressUnallocated,
+            0x09 => Self::ParentLinkFailure,
+            0x0a => Self::ValidateRoute,
+            0x0b => Self::SourceRouteFailure,
+            0x0c => Self::ManyToOneRouteFailure,
+            0x0d => Self::AddressConflict,
+            0x0e => Self::VerifyAddresses,
+            0x0f => Self::PanIdentifierUpdate,
+            0x10 => Self::NetworkAddressUpdate,
+            0x11 => Self::BadFrameCounter,
+            0x12 => Self::BadKeySequenceNumber,
+            0x13 => Self::UnknownCommand,
+            _ => return None,
+        };
+        Some(code)
+    }
+}
+
+/// 3.4.3 Network Status Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkStatus {
+    /// See Section 3.4.3.3.1.
+    pub status_code: NetworkStatusCode,
+    /// The destination address of the frame that caused the status, or the
+    /// address the status refers to.
+    ///
+    /// See Section 3.4.3.3.2.
+    pub destination_address: ShortAddress,
+}
+
+impl PackBytes for NetworkStatus {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let status_code =
+            NetworkStatusCode::from_u8(src.byte()?).ok_or(ParseError::InvalidValue {
+                field: "status_code",
+                offset: 0,
+            })?;
+        Ok(Self {
+            status_code,
+            destination_address: src.unpack()?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = (self.status_code as u8).pack_into(buf)?;
+        Ok(offset
+            + self
+                .destination_address
+                .pack_into(remaining(buf, offset)?)?)
+    }
+}
+
+/// 3.4.4 Leave Command
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Leave {
+    /// The device is asked to rejoin the network after leaving.
+    ///
+    /// See Section 3.4.4.3.1.1.
+    pub rejoin: bool,
+    /// The command is a request for another device to leave, instead of an
+    /// indication that the sender leaves.
+    ///
+    /// See Section 3.4.4.3.1.2.
+    pub request: bool,
+    /// The children of the leaving device leave as well.
+    ///
+    /// See Section 3.4.4.3.1.3.
+    pub remove_children: bool,
+}
+
+impl PackBytes for Leave {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let options = u8::unpack_from_iter(src)?;
+        Ok(Self {
+            rejoin: (options >> 5) & 0b1 != 0,
+            request: (options >> 6) & 0b1 != 0,
+            remove_children: (options >> 7) & 0b1 != 0,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let options = u8::from(self.rejoin) << 5
+            | u8::from(self.request) << 6
+            | u8::from(self.remove_children) << 7;
+        options.pack_into(buf)
+    }
+}
+
+/// 3.4.5 Route Record Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteRecord {
+    /// Relays the frame has passed on its way to the concentrator, starting
+    /// with the relay closest to the originator.
+    ///
+    /// See Section 3.4.5.3.2.
+    pub relay_list: Vec<ShortAddress, ROUTE_RECORD_RELAY_LIST_SIZE>,
+}
+
+impl PackBytes for RouteRecord {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let relay_count = src.byte()?;
+        let mut relay_list = Vec::new();
+        for _ in 0..relay_count {
+            let relay = src.unpack()?;
+            relay_list
+                .push(relay)
+                .map_err(|_| ParseError::LengthMismatch {
+                    field: "relay_count",
+                    offset: 0,
+                })?;
+        }
+        Ok(Self { relay_list })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        // the capacity of the relay list fits into the count
+        #[allow(clippy::cast_possible_truncation)]
+        let mut offset = (self.relay_list.len() as u8).pack_into(buf)?;
+        for relay in &self.relay_list {
+            offset += relay.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+/// 3.4.6 Rejoin Request Command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RejoinRequest {
+    /// See Section 3.4.6.3.1.
+    pub capability_information: MacCapabilities,
+}
+
+impl PackBytes for RejoinRequest {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        Ok(Self {
+            capability_information: MacCapabilities::unpack_from_iter(src)?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        self.capability_information.pack_into(buf)
+    }
+}
+
+/// 3.4.7 Rejoin Response Command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RejoinResponse {
+    /// The new network address of the rejoining device.
+    ///
+    /// See Section 3.4.7.3.1.
+    pub network_address: ShortAddress,
+    /// Uses the values of the MAC association status.
+    ///
+    /// See Section 3.4.7.3.2.
+    pub rejoin_status: AssociationStatus,
+}
+
+impl PackBytes for RejoinResponse {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let network_address = src.unpack()?;
+        let rejoin_status = src.unpack()?;
+        Ok(Self {
+            network_address,
+            rejoin_status,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = self.network_address.pack_into(buf)?;
+        Ok(offset + self.rejoin_status.pack_into(remaining(buf, offset)?)?)
+    }
+}
+
+/// Entry of a [`LinkStatus`] command.
+///
+/// See Section 3.4.8.3.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStatusEntry {
+    /// Network address of the neighbor.
+    pub address: ShortAddress,
+    /// Estimated link cost from the neighbor to the sender, `1..=7`.
+    pub incoming_cost: u8,
+    /// Link cost from the sender to the neighbor as reported by the
+    /// neighbor, `0` if unknown.
+    pub outgoing_cost: u8,
+}
+
+/// 3.4.8 Link Status Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkStatus {
+    /// This is the first frame of the sender's link status.
+    ///
+    /// See Section 3.4.8.3.1.
+    pub first_frame: bool,
+    /// This is the last frame of the sender's link status.
+    ///
+    /// See Section 3.4.8.3.1.
+    pub last_frame: bool,
+    /// Link status entries, sorted by ascending network address.
+    ///
+    /// See Section 3.4.8.3.2.
+    pub entries: Vec<LinkStatusEntry, LINK_STATUS_LIST_SIZE>,
+}
+
+impl PackBytes for LinkStatus {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let options = src.byte()?;
+        let mut entries = Vec::new();
+        for _ in 0..(options & 0b1_1111) {
+            let address = src.unpack()?;
+            let link_status = src.byte()?;
+            // cannot overflow, the count is limited to 5 bits
+            let _ = entries.push(LinkStatusEntry {
+                address,
+                incoming_cost: link_status & 0b111,
+                outgoing_cost: (link_status >> 4) & 0b111,
+            });
+        }
+        Ok(Self {
+            first_frame: (options >> 5) & 0b1 != 0,
+            last_frame: (options >> 6) & 0b1 != 0,
+            entries,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        // the capacity of the entry list fits into the 5-bit count
+        #[allow(clippy::cast_possible_truncation)]
+        let options = self.entries.len() as u8
+            | u8::from(self.first_frame) << 5
+            | u8::from(self.last_frame) << 6;
+        let mut offset = options.pack_into(buf)?;
+        for entry in &self.entries {
+            offset += entry.address.pack_into(remaining(buf, offset)?)?;
+            let link_status = (entry.incoming_cost & 0b111) | (entry.outgoing_cost & 0b111) << 4;
+            offset += link_status.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+/// 3.4.9 Network Report Command
+///
+/// Reports a PAN identifier conflict, the only report command identifier
+/// defined by the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkReport {
+    /// See Section 3.4.9.3.2.
+    pub extended_pan_id: u64,
+    /// PAN identifiers of the neighboring networks.
+    ///
+    /// See Section 3.4.9.3.3.1.
+    pub pan_ids: Vec<u16, NETWORK_REPORT_LIST_SIZE>,
+}
+
+impl PackBytes for NetworkReport {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let options = src.byte()?;
+        if options >> 5 != 0x00 {
+            return Err(ParseError::InvalidValue {
+                field: "report_command_identifier",
+                offset: 0,
+            });
+        }
+        let extended_pan_id = src.unpack()?;
+        let mut pan_ids = Vec::new();
+        for _ in 0..(options & 0b1_1111) {
+            // cannot overflow, the count is limited to 5 bits
+            let _ = pan_ids.push(src.unpack()?);
+        }
+        Ok(Self {
+            extended_pan_id,
+            pan_ids,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        // the capacity of the list fits into the 5-bit count
+        #[allow(clippy::cast_possible_truncation)]
+        let mut offset = (self.pan_ids.len() as u8).pack_into(buf)?;
+        offset += self.extended_pan_id.pack_into(remaining(buf, offset)?)?;
+        for pan_id in &self.pan_ids {
+            offset += pan_id.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+/// 3.4.10 Network Update Command
+///
+/// Announces a PAN identifier update, the only update command identifier
+/// defined by the specification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkUpdate {
+    /// See Section 3.4.10.3.2.
+    pub extended_pan_id: u64,
+    /// See Section 3.4.10.3.3.
+    pub update_id: u8,
+    /// See Section 3.4.10.3.4.1.
+    pub new_pan_id: u16,
+}
+
+impl PackBytes for NetworkUpdate {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let options = src.byte()?;
+        if options >> 5 != 0x00 {
+            return Err(ParseError::InvalidValue {
+                field: "update_command_identifier",
+                offset: 0,
+            });
+        }
+        if options & 0b1_1111 != 1 {
+            return Err(ParseError::LengthMismatch {
+                field: "update_information_count",
+                offset: 0,
+            });
+        }
+        Ok(Self {
+            extended_pan_id: src.unpack()?,
+            update_id: src.byte()?,
+            new_pan_id: src.unpack()?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = 1u8.pack_into(buf)?;
+        offset += self.extended_pan_id.pack_into(remaining(buf, offset)?)?;
+        offset += self.update_id.pack_into(remaining(buf, offset)?)?;
+        offset += self.new_pan_id.pack_into(remaining(buf, offset)?)?;
+        Ok(offset)
+    }
+}
+
+/// Requested end device timeout, encoded as an index into Table 3-61.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EndDeviceTimeout(u8);
+
+impl EndDeviceTimeout {
+    /// The largest defined timeout index.
+    pub const MAX: Self = Self(14);
+
+    /// Creates a timeout from its index, `0..=14`.
+    pub fn new(index: u8) -> Option<Self> {
+        (index <= Self::MAX.0).then_some(Self(index))
+    }
+
+    /// The index of the timeout.
+    pub fn index(&self) -> u8 {
+        self.0
+    }
+
+    /// The timeout in seconds: 10 seconds for index 0, `2^index` minutes
+    /// otherwise.
+    pub fn as_secs(&self) -> u32 {
+        if self.0 == 0 {
+            10
+        } else {
+            60 << self.0
+        }
+    }
+
+    /// The timeout as [`Duration`].
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.as_secs().into())
+    }
+}
+
+/// 3.4.11 End Device Timeout Request Command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndDeviceTimeoutRequest {
+    /// See Section 3.4.11.3.1.
+    pub requested_timeout: EndDeviceTimeout,
+    /// Reserved for future use, shall be 0.
+    ///
+    /// See Section 3.4.11.3.2.
+    pub end_device_configuration: u8,
+}
+
+impl PackBytes for EndDeviceTimeoutRequest {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let requested_timeout =
+            EndDeviceTimeout::new(src.byte()?).ok_or(ParseError::InvalidValue {
+                field: "requested_timeout",
+                offset: 0,
+            })?;
+        Ok(Self {
+            requested_timeout,
+            end_device_configuration: src.byte()?,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let offset = self.requested_timeout.0.pack_into(buf)?;
+        Ok(offset
+            + self
+                .end_device_configuration
+                .pack_into(remaining(buf, offset)?)?)
+    }
+}
+
+/// Status of an [`EndDeviceTimeoutResponse`].
+///
+/// See Table 3-62.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum EndDeviceTimeoutStatus {
+    Success = 0x00,
+    IncorrectValue = 0x01,
+}
+
+/// 3.4.12 End Device Timeout Response Command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EndDeviceTimeoutResponse {
+    /// See Section 3.4.12.3.1.
+    pub status: EndDeviceTimeoutStatus,
+    /// The parent accepts MAC data polls as keep-alive.
+    ///
+    /// See Section 3.4.12.3.2.
+    pub mac_data_poll_keepalive: bool,
+    /// The parent accepts end device timeout requests as keep-alive.
+    ///
+    /// See Section 3.4.12.3.2.
+    pub timeout_request_keepalive: bool,
+    /// The parent supports power negotiation.
+    ///
+    /// See Section 3.4.12.3.2.
+    pub power_negotiation: bool,
+}
+
+impl PackBytes for EndDeviceTimeoutResponse {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let status = match src.byte()? {
+            0x00 => EndDeviceTimeoutStatus::Success,
+            0x01 => EndDeviceTimeoutStatus::IncorrectValue,
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    field: "status",
+                    offset: 0,
+                })
+            }
+        };
+        let parent_information = src.byte()?;
+        Ok(Self {
+            status,
+            mac_data_poll_keepalive: parent_information & 0b1 != 0,
+            timeout_request_keepalive: (parent_information >> 1) & 0b1 != 0,
+            power_negotiation: (parent_information >> 2) & 0b1 != 0,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let parent_information = u8::from(self.mac_data_poll_keepalive)
+            | u8::from(self.timeout_request_keepalive) << 1
+            | u8::from(self.power_negotiation) << 2;
+        let offset = (self.status as u8).pack_into(buf)?;
+        Ok(offset + parent_information.pack_into(remaining(buf, offset)?)?)
+    }
+}
+
+/// Type of a [`LinkPowerDelta`] command.
+///
+/// See Section 3.4.13.3.1.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum LinkPowerDeltaType {
+    Notification = 0b00,
+    Request = 0b01,
+    Response = 0b10,
+}
+
+/// Entry of a [`LinkPowerDelta`] command.
+///
+/// See Section 3.4.13.3.3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerDeltaEntry {
+    /// Network address of the neighbor.
+    pub address: ShortAddress,
+    /// Transmit power change requested from the neighbor, in dBm.
+    pub power_delta: i8,
+}
+
+/// 3.4.13 Link Power Delta Command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkPowerDelta {
+    /// See Section 3.4.13.3.1.
+    pub kind: LinkPowerDeltaType,
+    /// See Section 3.4.13.3.3.
+    pub entries: Vec<PowerDeltaEntry, LINK_POWER_LIST_SIZE>,
+}
+
+impl PackBytes for LinkPowerDelta {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let kind = match src.byte()? & 0b11 {
+            0b00 => LinkPowerDeltaType::Notification,
+            0b01 => LinkPowerDeltaType::Request,
+            0b10 => LinkPowerDeltaType::Response,
+            _ => {
+                return Err(ParseError::InvalidValue {
+                    field: "type",
+                    offset: 0,
+                })
+            }
+        };
+        let count = src.byte()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            let entry = PowerDeltaEntry {
+                address: src.unpack()?,
+                power_delta: src.unpack()?,
+            };
+            entries
+                .push(entry)
+                .map_err(|_| ParseError::LengthMismatch {
+                    field: "list_count",
+                    offset: 1,
+                })?;
+        }
+        Ok(Self { kind, entries })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = (self.kind as u8).pack_into(buf)?;
+        // the capacity of the list fits into the count
+        #[allow(clippy::cast_possible_truncation)]
+        let count = self.entries.len() as u8;
+        offset += count.pack_into(remaining(buf, offset)?)?;
+        for entry in &self.entries {
+            offset += entry.address.pack_into(remaining(buf, offset)?)?;
+            offset += entry.power_delta.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(raw: &[u8]) -> NwkCommand {
+        let command = NwkCommand::unpack_from_slice(raw).unwrap();
+        let packed: Vec<u8, 128> = command.pack_to_vec().unwrap();
+        assert_eq!(packed, raw);
+        command
+    }
+
+    #[test]
+    fn route_request_with_destination_ieee_address() {
+        let raw = [
+            0x01, 0x28, 0x07, 0x34, 0x12, 0x00, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11,
+        ];
+
+        let command = round_trip(&raw);
+
+        assert_eq!(
+            command,
+            NwkCommand::RouteRequest(RouteRequest {
+                many_to_one: ManyToOne::RouteRecordTable,
+                multicast: false,
+                route_request_identifier: 0x07,
+                destination_address: ShortAddress(0x1234),
+                path_cost: 0,
+                destination_ieee_address: Some(IeeeAddress(0x1122_3344_5566_7788)),
+            })
+        );
+    }
+
+    #[test]
+    fn route_request_with_reserved_many_to_one_should_fail() {
+        let raw = [0x01, 0x18, 0x07, 0x34, 0x12, 0x00];
+
+        let got = NwkCommand::unpack_from_slice(&raw);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "many_to_one",
+                offset: 1
+            })
+        );
+    }
+
+    #[test]
+    fn route_reply() {
+        let raw = [
+            0x02, 0x20, 0x07, 0x00, 0x00, 0x34, 0x12, 0x03, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33,
+            0x22, 0x11,
+        ];
+
+        let command = round_trip(&raw);
+
+        assert_eq!(
+            command,
+            NwkCommand::RouteReply(RouteReply {
+                multicast: false,
+                route_request_identifier: 0x07,
+                originator_address: ShortAddress(0x0000),
+                responder_address: ShortAddress(0x1234),
+                path_cost: 3,
+                originator_ieee_address: None,
+                responder_ieee_address: Some(IeeeAddress(0x1122_3344_5566_7788)),
+            })
+        );
+    }
+
+    #[test]
+    fn network_status() {
+        let command = round_trip(&[0x03, 0x0d, 0x34, 0x12]);
+
+        assert_eq!(
+            command,
+            NwkCommand::NetworkStatus(NetworkStatus {
+                status_code: NetworkStatusCode::AddressConflict,
+                destination_address: ShortAddress(0x1234),
+            })
+        );
+    }
+
+    #[test]
+    fn network_status_with_reserved_code_should_fail() {
+        let got = NwkCommand::unpack_from_slice(&[0x03, 0x14, 0x34, 0x12]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "status_code",
+                offset: 1
+            })
+        );
+    }
+
+    #[test]
+    fn leave() {
+        let command = round_trip(&[0x04, 0xa0]);
+
+        assert_eq!(
+            command,
+            NwkCommand::Leave(Leave {
+                rejoin: true,
+                request: false,
+                remove_children: true,
+            })
+        );
+    }
+
+    #[test]
+    fn route_record() {
+        let command = round_trip(&[0x05, 0x02, 0x34, 0x12, 0x78, 0x56]);
+
+        let NwkCommand::RouteRecord(route_record) = command else {
+            unreachable!("expected a route record");
+        };
+        assert_eq!(
+            route_record.relay_list,
+            &[ShortAddress(0x1234), ShortAddress(0x5678)]
+        );
+    }
+
+    #[test]
+    fn truncated_route_record_should_fail() {
+        let got = NwkCommand::unpack_from_slice(&[0x05, 0x02, 0x34, 0x12, 0x78]);
+
+        assert_eq!(got, Err(ParseError::Truncated { offset: 5 }));
+    }
+
+    #[test]
+    fn rejoin_request_and_response() {
+        let command = round_trip(&[0x06, 0x8e]);
+        assert_eq!(
+            command,
+            NwkCommand::RejoinRequest(RejoinRequest {
+                capability_information: MacCapabilities::unpack_from_slice(&[0x8e]).unwrap(),
+            })
+        );
+
+        let command = round_trip(&[0x07, 0x34, 0x12, 0x00]);
+        assert_eq!(
+            command,
+            NwkCommand::RejoinResponse(RejoinResponse {
+                network_address: ShortAddress(0x1234),
+                rejoin_status: AssociationStatus::Success,
+            })
+        );
+    }
+
+    #[test]
+    fn link_status() {
+        let command = round_trip(&[0x08, 0x62, 0x01, 0x00, 0x31, 0x34, 0x12, 0x77]);
+
+        let NwkCommand::LinkStatus(link_status) = command else {
+            unreachable!("expected a link status");
+        };
+        assert!(link_status.first_frame);
+        assert!(link_status.last_frame);
+        assert_eq!(
+            link_status.entries,
+            &[
+                LinkStatusEntry {
+                    address: ShortAddress(0x0001),
+                    incoming_cost: 1,
+                    outgoing_cost: 3,
+                },
+                LinkStatusEntry {
+                    address: ShortAddress(0x1234),
+                    incoming_cost: 7,
+                    outgoing_cost: 7,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn network_report() {
+        let command = round_trip(&[
+            0x09, 0x02, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0xcd, 0xab, 0x34, 0x12,
+        ]);
+
+        assert_eq!(
+            command,
+            NwkCommand::NetworkReport(NetworkReport {
+                extended_pan_id: 0x1122_3344_5566_7788,
+                pan_ids: Vec::from_slice(&[0xabcd, 0x1234]).unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn network_update() {
+        let command = round_trip(&[
+            0x0a, 0x01, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x05, 0xcd, 0xab,
+        ]);
+
+        assert_eq!(
+            command,
+            NwkCommand::NetworkUpdate(NetworkUpdate {
+                extended_pan_id: 0x1122_3344_5566_7788,
+                update_id: 5,
+                new_pan_id: 0xabcd,
+            })
+        );
+    }
+
+    #[test]
+    fn end_device_timeout_request_and_response() {
+        let command = round_trip(&[0x0b, 0x08, 0x00]);
+        let NwkCommand::EndDeviceTimeoutRequest(request) = command else {
+            unreachable!("expected an end device timeout request");
+        };
+        assert_eq!(request.requested_timeout.as_secs(), 256 * 60);
+
+        let command = round_trip(&[0x0c, 0x00, 0x03]);
+        assert_eq!(
+            command,
+            NwkCommand::EndDeviceTimeoutResponse(EndDeviceTimeoutResponse {
+                status: EndDeviceTimeoutStatus::Success,
+                mac_data_poll_keepalive: true,
+                timeout_request_keepalive: true,
+                power_negotiation: false,
+            })
+        );
+    }
+
+    #[test]
+    fn end_device_timeout_request_with_invalid_timeout_should_fail() {
+        let got = NwkCommand::unpack_from_slice(&[0x0b, 0x0f, 0x00]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "requested_timeout",
+                offset: 1
+            })
+        );
+    }
+
+    #[test]
+    fn link_power_delta() {
+        let command = round_trip(&[0x0d, 0x01, 0x02, 0x01, 0x00, 0xfd, 0x34, 0x12, 0x02]);
+
+        assert_eq!(
+            command,
+            NwkCommand::LinkPowerDelta(LinkPowerDelta {
+                kind: LinkPowerDeltaType::Request,
+                entries: Vec::from_slice(&[
+                    PowerDeltaEntry {
+                        address: ShortAddress(0x0001),
+                        power_delta: -3,
+                    },
+                    PowerDeltaEntry {
+                        address: ShortAddress(0x1234),
+                        power_delta: 2,
+                    },
+                ])
+                .unwrap(),
+            })
+        );
+    }
+
+    #[test]
+    fn reserved_command_identifier_should_fail() {
+        let got = NwkCommand::unpack_from_slice(&[0x0e]);
+
+        assert_eq!(
+            got,
+            Err(ParseError::InvalidValue {
+                field: "command_identifier",
+                offset: 0
+            })
+        );
+    }
+}
@@ -1,16 +1,21 @@
 //! NWK Frame Formats
 use core::fmt::Debug;
+use core::iter;
 use core::mem::{self};
 
 use heapless::Vec;
 
+use super::commands::NwkCommand;
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
 use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
 use crate::common::types::IeeeAddress;
 use crate::common::types::ShortAddress;
 use crate::impl_pack_bytes;
 
 /// 3.5.1.
-#[allow(dead_code)]
 const PROTOCOL_VERSION: u8 = 0x02;
 
 const PAYLOAD_SIZE: usize = 128;
@@ -20,7 +25,6 @@ const PAYLOAD_SIZE: usize = 128;
 pub enum NwkFrame {
     Data(NwkDataFrame),
     NwkCommand(NwkCommandFrame),
-    Reserved(NwkHeader),
     InterPan(NwkHeader),
 }
 
@@ -35,31 +39,42 @@ impl NwkFrame {
                 .header
                 .frame_control
                 .frame_type_identifier(),
-            Self::Reserved(nwk_header) | Self::InterPan(nwk_header) => {
-                nwk_header.frame_control.frame_type_identifier()
-            }
+            Self::InterPan(nwk_header) => nwk_header.frame_control.frame_type_identifier(),
         }
     }
 }
 
 impl PackBytes for NwkFrame {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        let mut src = src.into_iter();
-        let header = NwkHeader::unpack_from_iter(&mut src)?;
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let header: NwkHeader = src.unpack()?;
         let frame = match header.frame_control.frame_type_identifier() {
             FrameTypeIdentifier::Data => Self::Data(NwkDataFrame {
                 header,
-                payload: PackBytes::unpack_from_iter(&mut src)?,
+                payload: src.unpack()?,
             }),
             FrameTypeIdentifier::NwkCommand => Self::NwkCommand(NwkCommandFrame {
                 header,
-                command_identifier: PackBytes::unpack_from_iter(&mut src)?,
-                payload: PackBytes::unpack_from_iter(&mut src)?,
+                command_identifier: src.unpack()?,
+                payload: src.unpack()?,
             }),
-            FrameTypeIdentifier::Reserved => Self::Reserved(header),
+            FrameTypeIdentifier::Reserved => {
+                return Err(ParseError::InvalidValue {
+                    field: "frame_type",
+                    offset: 0,
+                })
+            }
             FrameTypeIdentifier::InterPan => Self::InterPan(header),
         };
-        Some(frame)
+        Ok(frame)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        match self {
+            Self::Data(nwk_data_frame) => nwk_data_frame.pack_into(buf),
+            Self::NwkCommand(nwk_command_frame) => nwk_command_frame.pack_into(buf),
+            Self::InterPan(nwk_header) => nwk_header.pack_into(buf),
+        }
     }
 }
 
@@ -93,11 +108,33 @@ impl_pack_bytes! {
     }
 }
 
+impl NwkCommandFrame {
+    /// Creates a command frame carrying `command`.
+    pub fn new(header: NwkHeader, command: &NwkCommand) -> Result<Self, PackError> {
+        let mut buf = [0u8; PAYLOAD_SIZE + 1];
+        let len = command.pack_into(&mut buf)?;
+        Ok(Self {
+            header,
+            command_identifier: command.command_identifier(),
+            payload: Vec::from_slice(&buf[1..len]).map_err(|()| PackError::BufferTooSmall)?,
+        })
+    }
+
+    /// Decodes the command carried by this frame.
+    ///
+    /// Offsets of parse errors are relative to the command identifier.
+    pub fn command(&self) -> Result<NwkCommand, ParseError> {
+        NwkCommand::unpack_from_iter(
+            iter::once(self.command_identifier.to_u8()).chain(self.payload.iter().copied()),
+        )
+    }
+}
+
 /// Comand Frame Identifiers.
 ///
 /// See Section 3.4.
-//#[repr(u8)]
-#[derive(Debug, PartialEq, Eq)]
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[allow(missing_docs)]
 pub enum CommandFrameIdentifier {
     RouteRequest = 0x01,
@@ -113,23 +150,62 @@ pub enum CommandFrameIdentifier {
     EndDeviceTimeoutRequest = 0x0b,
     EndDeviceTimeoutResponse = 0x0c,
     LinkPowerDelta = 0x0d,
-    Reserved,
+    /// Holds the raw value of a reserved identifier.
+    Reserved(u8),
 }
 
-impl PackBytes for CommandFrameIdentifier {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        let b = src.into_iter().next()?;
-        if b <= 0x0d {
-            // SAFETY: any byte with value <= 0x0d is a valid CommandFrameIdentifier
-            Some(unsafe { mem::transmute::<u8, Self>(b) })
-        } else {
-            Some(Self::Reserved)
+impl CommandFrameIdentifier {
+    fn from_u8(b: u8) -> Self {
+        match b {
+            0x01 => Self::RouteRequest,
+            0x02 => Self::RouteReply,
+            0x03 => Self::NetworkStatus,
+            0x04 => Self::Leave,
+            0x05 => Self::RouteRecord,
+            0x06 => Self::RejoinRequest,
+            0x07 => Self::RejoinResponse,
+            0x08 => Self::LinkStatus,
+            0x09 => Self::NetworkReport,
+            0x0a => Self::NetworkUpdate,
+            0x0b => Self::EndDeviceTimeoutRequest,
+            0x0c => Self::EndDeviceTimeoutResponse,
+            0x0d => Self::LinkPowerDelta,
+            _ => Self::Reserved(b),
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Self::RouteRequest => 0x01,
+            Self::RouteReply => 0x02,
+            Self::NetworkStatus => 0x03,
+            Self::Leave => 0x04,
+            Self::RouteRecord => 0x05,
+            Self::RejoinRequest => 0x06,
+            Self::RejoinResponse => 0x07,
+            Self::LinkStatus => 0x08,
+            Self::NetworkReport => 0x09,
+            Self::NetworkUpdate => 0x0a,
+            Self::EndDeviceTimeoutRequest => 0x0b,
+            Self::EndDeviceTimeoutResponse => 0x0c,
+            Self::LinkPowerDelta => 0x0d,
+            Self::Reserved(b) => b,
         }
     }
 }
 
+impl PackBytes for CommandFrameIdentifier {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        u8::unpack_from_iter(src).map(Self::from_u8)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        self.to_u8().pack_into(buf)
+    }
+}
+
 /// 3.3.1 General NPDU Frame Format
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NwkHeader {
     /// See Section 3.3.1.1.
     pub frame_control: FrameControl,
@@ -156,37 +232,71 @@ pub struct NwkHeader {
 }
 
 impl PackBytes for NwkHeader {
-    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Option<Self> {
-        let mut src = src.into_iter();
-        let frame_control = FrameControl::unpack_from_iter(&mut src)?;
-        Some(Self {
-            destination: PackBytes::unpack_from_iter(&mut src)?,
-            source: PackBytes::unpack_from_iter(&mut src)?,
-            radius: src.next()?,
-            sequence_number: src.next()?,
-            destination_ieee: frame_control
-                .destination_ieee_flag()
-                .then(|| PackBytes::unpack_from_iter(&mut src))
-                .flatten(),
-            source_ieee: frame_control
-                .source_ieee_flag()
-                .then(|| PackBytes::unpack_from_iter(&mut src))
-                .flatten(),
-            multicast_control: frame_control
-                .multicast_flag()
-                .then(|| MulticastControl::unpack_from_iter(&mut src))
-                .flatten(),
-            source_route_subframe: frame_control
-                .source_flag()
-                .then(|| SourceRouteSubframe::unpack_from_iter(src))
-                .flatten(),
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let frame_control: FrameControl = src.unpack()?;
+        let destination = src.unpack()?;
+        let source = src.unpack()?;
+        let radius = src.byte()?;
+        let sequence_number = src.byte()?;
+        let destination_ieee = if frame_control.destination_ieee_flag() {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let source_ieee = if frame_control.source_ieee_flag() {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let multicast_control = if frame_control.multicast_flag() {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let source_route_subframe = if frame_control.source_flag() {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        Ok(Self {
             frame_control,
+            destination,
+            source,
+            radius,
+            sequence_number,
+            destination_ieee,
+            source_ieee,
+            multicast_control,
+            source_route_subframe,
         })
     }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.frame_control.pack_into(buf)?;
+        offset += self.destination.pack_into(remaining(buf, offset)?)?;
+        offset += self.source.pack_into(remaining(buf, offset)?)?;
+        offset += self.radius.pack_into(remaining(buf, offset)?)?;
+        offset += self.sequence_number.pack_into(remaining(buf, offset)?)?;
+        if let Some(destination_ieee) = &self.destination_ieee {
+            offset += destination_ieee.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(source_ieee) = &self.source_ieee {
+            offset += source_ieee.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(multicast_control) = &self.multicast_control {
+            offset += multicast_control.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(source_route_subframe) = &self.source_route_subframe {
+            offset += source_route_subframe.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
 }
 
 impl_pack_bytes! {
     /// 3.3.1.1 Frame Control Field
+    #[derive(Clone, Copy)]
     pub struct FrameControl(pub u16);
 }
 
@@ -207,6 +317,16 @@ impl Debug for FrameControl {
 }
 
 impl FrameControl {
+    /// Creates the frame control field of a frame of the current protocol
+    /// version with route discovery suppressed.
+    pub fn new(frame_type: FrameTypeIdentifier) -> Self {
+        Self(frame_type as u16 | u16::from(PROTOCOL_VERSION) << 2)
+    }
+
+    fn set_bit(&mut self, n: u16, value: bool) {
+        self.0 = (self.0 & !(1 << n)) | (u16::from(value) << n);
+    }
+
     /// See Section 3.3.1.1.
     pub fn frame_type_identifier(&self) -> FrameTypeIdentifier {
         // SAFETY: any 2 bit permutation is a valid FrameType
@@ -223,11 +343,21 @@ impl FrameControl {
         DiscoverRoute::from_u8(((self.0 >> 6) & 0b11) as u8)
     }
 
+    /// See Section 3.3.1.1.3.
+    pub fn set_discover_route(&mut self, discover_route: DiscoverRoute) {
+        self.0 = (self.0 & !(0b11 << 6)) | u16::from(discover_route as u8) << 6;
+    }
+
     /// See Section 3.3.1.1.4.
     pub fn multicast_flag(&self) -> bool {
         ((self.0 >> 8) & 0b1) != 0
     }
 
+    /// See Section 3.3.1.1.4.
+    pub fn set_multicast_flag(&mut self, multicast: bool) {
+        self.set_bit(8, multicast);
+    }
+
     /// The security sub-field shall have a value of 1 if, and only if, the
     /// frame is to have NWK security operations enabled. If security for
     /// this frame is implemented at another layer or disabled entirely,
@@ -238,6 +368,11 @@ impl FrameControl {
         ((self.0 >> 9) & 0b1) != 0
     }
 
+    /// See Section 3.3.1.1.5.
+    pub fn set_security_flag(&mut self, security: bool) {
+        self.set_bit(9, security);
+    }
+
     /// The source route sub-field shall have a value of 1 if and only if a
     /// source route subframe is present in the NWK header. If the source
     /// route subframe is not present, the source route sub-field shall have
@@ -248,6 +383,11 @@ impl FrameControl {
         ((self.0 >> 10) & 0b1) != 0
     }
 
+    /// See Section 3.3.1.1.6.
+    pub fn set_source_flag(&mut self, source: bool) {
+        self.set_bit(10, source);
+    }
+
     /// The destination IEEE address sub-field shall have a value of 1 if, and
     /// only if, the NWK header is to include the full IEEE address of the
     /// destination.
@@ -257,6 +397,11 @@ impl FrameControl {
         ((self.0 >> 11) & 0b1) != 0
     }
 
+    /// See Section 3.3.1.1.7.
+    pub fn set_destination_ieee_flag(&mut self, destination_ieee: bool) {
+        self.set_bit(11, destination_ieee);
+    }
+
     /// The source IEEE address sub-field shall have a value of 1 if, and only
     /// if, the NWK header is to include the full IEEE address of the source
     /// device.
@@ -266,6 +411,11 @@ impl FrameControl {
         ((self.0 >> 12) & 0b1) != 0
     }
 
+    /// See Section 3.3.1.1.8.
+    pub fn set_source_ieee_flag(&mut self, source_ieee: bool) {
+        self.set_bit(12, source_ieee);
+    }
+
     /// See Section 3.3.1.1.9.
     pub fn end_device_initiator(&self) -> bool {
         ((self.0 >> 13) & 0b1) != 0
@@ -329,9 +479,25 @@ impl DiscoverRoute {
     }
 }
 
-impl_pack_bytes! {
-    /// 3.3.1.8 Multicast Control Field
-    pub struct MulticastControl(u8);
+/// 3.3.1.8 Multicast Control Field
+#[derive(Clone, Copy)]
+pub struct MulticastControl(u8);
+
+impl PackBytes for MulticastControl {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let multicast_control = Self(u8::unpack_from_iter(src)?);
+        if multicast_control.multicast_mode() == MulticastMode::Reserved {
+            return Err(ParseError::InvalidValue {
+                field: "multicast_mode",
+                offset: 0,
+            });
+        }
+        Ok(multicast_control)
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        self.0.pack_into(buf)
+    }
 }
 
 impl Debug for MulticastControl {
@@ -345,24 +511,52 @@ impl Debug for MulticastControl {
 }
 
 impl MulticastControl {
+    /// Non-member radius which is never decremented.
+    pub const INFINITE_RADIUS: u8 = 0b111;
+
+    /// Creates the multicast control field of a frame in `multicast_mode`,
+    /// which is relayed `non_member_radius` hops by non-members, reset to
+    /// `max_member_radius` by members.
+    pub fn new(
+        multicast_mode: MulticastMode,
+        non_member_radius: u8,
+        max_member_radius: u8,
+    ) -> Self {
+        Self(
+            (multicast_mode as u8 & 0b11)
+                | (non_member_radius & 0b111) << 2
+                | (max_member_radius & 0b111) << 5,
+        )
+    }
+
     /// See Section 3.3.1.8.1.
     pub fn multicast_mode(&self) -> MulticastMode {
-        match (self.0 >> 6) & 0b11 {
+        match self.0 & 0b11 {
             0b00 => MulticastMode::NonMemberMode,
             0b01 => MulticastMode::MemberMode,
             _ => MulticastMode::Reserved,
         }
     }
 
+    /// See Section 3.3.1.8.1.
+    pub fn set_multicast_mode(&mut self, multicast_mode: MulticastMode) {
+        self.0 = (self.0 & !0b11) | (multicast_mode as u8 & 0b11);
+    }
+
     /// See Section 3.3.1.8.2.
     pub fn non_member_radius(&self) -> u8 {
-        (self.0 >> 3) & 0b111
+        (self.0 >> 2) & 0b111
+    }
+
+    /// See Section 3.3.1.8.2.
+    pub fn set_non_member_radius(&mut self, non_member_radius: u8) {
+        self.0 = (self.0 & !(0b111 << 2)) | (non_member_radius & 0b111) << 2;
     }
 
     /// The maximum value of the [`MulticastControl::non_member_radius`]
     /// sub-field for this frame. See Section 3.3.1.8.3.
     pub fn max_member_radius(&self) -> u8 {
-        self.0 & 0b11
+        (self.0 >> 5) & 0b111
     }
 }
 
@@ -375,28 +569,66 @@ pub enum MulticastMode {
     Reserved,
 }
 
-const RELAY_LIST_SIZE: usize = 16;
+/// Maximum number of relays in a [`SourceRouteSubframe`].
+pub const RELAY_LIST_SIZE: usize = 16;
 
-impl_pack_bytes! {
-    /// Source Route Subframe
-    #[derive(Debug)]
-    pub struct SourceRouteSubframe {
-        /// Indicates the number of relays contained in [`SourceRouteSubframe::relay_list`].
-        ///
-        /// See Section 3.3.1.9.1.
-        #[transparent(u8)]
-        pub relay_count: u8,
-        /// Indicates the index of the next relay in [`SourceRouteSubframe::relay_list`] to
-        /// which the packet will be transmitted.
-        ///
-        /// See Section 3.3.1.9.2.
-        #[transparent(u8)]
-        pub relay_index: u8,
-        /// List of relay addresses from closest to the destination to closest to the originator.
-        ///
-        /// See Section 3.3.1.9.2.
-        #[collect(Vec<u8, RELAY_LIST_SIZE>)]
-        pub relay_list: Vec<u8, RELAY_LIST_SIZE>,
+/// Source Route Subframe
+#[derive(Debug, Clone)]
+pub struct SourceRouteSubframe {
+    /// Indicates the number of relays contained in
+    /// [`SourceRouteSubframe::relay_list`].
+    ///
+    /// See Section 3.3.1.9.1.
+    pub relay_count: u8,
+    /// Indicates the index of the next relay in
+    /// [`SourceRouteSubframe::relay_list`] to which the packet will be
+    /// transmitted.
+    ///
+    /// See Section 3.3.1.9.2.
+    pub relay_index: u8,
+    /// List of relay addresses from closest to the destination to closest to
+    /// the originator.
+    ///
+    /// See Section 3.3.1.9.3.
+    pub relay_list: Vec<ShortAddress, RELAY_LIST_SIZE>,
+}
+
+impl PackBytes for SourceRouteSubframe {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let relay_count = src.byte()?;
+        if usize::from(relay_count) > RELAY_LIST_SIZE {
+            return Err(ParseError::LengthMismatch {
+                field: "relay_count",
+                offset: 0,
+            });
+        }
+        let relay_index = src.byte()?;
+        if relay_count > 0 && relay_index >= relay_count {
+            return Err(ParseError::InvalidValue {
+                field: "relay_index",
+                offset: 1,
+            });
+        }
+        let mut relay_list = Vec::new();
+        for _ in 0..relay_count {
+            // cannot overflow, `relay_count` has been checked above
+            let _ = relay_list.push(src.unpack()?);
+        }
+        Ok(Self {
+            relay_count,
+            relay_index,
+            relay_list,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.relay_count.pack_into(buf)?;
+        offset += self.relay_index.pack_into(remaining(buf, offset)?)?;
+        for relay in &self.relay_list {
+            offset += relay.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
     }
 }
 
@@ -404,8 +636,8 @@ impl_pack_bytes! {
 mod tests {
     use super::*;
 
-    //const CMD_FRAME: &str =
-    //"0912fcff000008bf66719a2a004b120028e6ff3d001bc928c67f38c1a4008cf1882da4b1bbfcb9be";
+    const CMD_FRAME: &str =
+        "0912fcff000008bf66719a2a004b120028e6ff3d001bc928c67f38c1a4008cf1882da4b1bbfcb9be";
 
     #[test]
     fn multicast_control_multicast_mode() {
@@ -414,14 +646,93 @@ mod tests {
         assert_eq!(mode, MulticastMode::Reserved);
     }
 
+    #[test]
+    fn multicast_control_fields() {
+        let mut multicast_control = MulticastControl::new(MulticastMode::MemberMode, 2, 7);
+        assert_eq!(multicast_control.0, 0b1110_1001);
+
+        multicast_control.set_multicast_mode(MulticastMode::NonMemberMode);
+        multicast_control.set_non_member_radius(1);
+
+        assert_eq!(
+            multicast_control.multicast_mode(),
+            MulticastMode::NonMemberMode
+        );
+        assert_eq!(multicast_control.non_member_radius(), 1);
+        assert_eq!(multicast_control.max_member_radius(), 7);
+    }
+
     #[test]
     fn source_route_subframe_pack() {
         let got =
-            SourceRouteSubframe::unpack_from_slice(&[0x04, 0x03, 0xff, 0xff, 0xff, 0xff]).unwrap();
+            SourceRouteSubframe::unpack_from_slice(&[0x02, 0x01, 0x34, 0x12, 0xff, 0xff]).unwrap();
+
+        assert_eq!(got.relay_count, 0x02);
+        assert_eq!(got.relay_index, 0x01);
+        assert_eq!(
+            got.relay_list,
+            &[ShortAddress(0x1234), ShortAddress(0xffff)]
+        );
+    }
+
+    #[test]
+    fn unpack_truncated_source_route_subframe_should_fail() {
+        let got = SourceRouteSubframe::unpack_from_slice(&[0x04, 0x03, 0xff, 0xff, 0xff, 0xff]);
+
+        assert_eq!(got.unwrap_err(), ParseError::Truncated { offset: 6 });
+    }
+
+    #[test]
+    fn unpack_source_route_subframe_with_invalid_relay_index_should_fail() {
+        let got = SourceRouteSubframe::unpack_from_slice(&[0x01, 0x01, 0xff, 0xff]);
+
+        assert_eq!(
+            got.unwrap_err(),
+            ParseError::InvalidValue {
+                field: "relay_index",
+                offset: 1
+            }
+        );
+    }
+
+    #[test]
+    fn unpack_reserved_frame_type_should_fail() {
+        let got = NwkFrame::unpack_from_slice(&[0x0a, 0x00, 0xfc, 0xff, 0x00, 0x00, 0x08, 0xbf]);
+
+        assert_eq!(
+            got.unwrap_err(),
+            ParseError::InvalidValue {
+                field: "frame_type",
+                offset: 0
+            }
+        );
+    }
+
+    #[test]
+    fn unpack_truncated_nwk_header_should_fail() {
+        // source IEEE address flag is set, but the address is cut off
+        let raw = [
+            0x09, 0x12, 0xfc, 0xff, 0x00, 0x00, 0x08, 0xbf, 0x66, 0x71, 0x9a, 0x2a,
+        ];
+
+        let got = NwkHeader::unpack_from_slice(&raw);
 
-        assert_eq!(got.relay_count, 0x04);
-        assert_eq!(got.relay_index, 0x03);
-        assert_eq!(got.relay_list, &[0xff, 0xff, 0xff, 0xff]);
+        assert_eq!(got.unwrap_err(), ParseError::Truncated { offset: 12 });
+    }
+
+    #[test]
+    fn unpack_nwk_header_with_reserved_multicast_mode_should_fail() {
+        let raw = [0x08, 0x01, 0x01, 0x00, 0x00, 0x00, 0x08, 0xbf, 0x03];
+
+        let got = NwkHeader::unpack_from_slice(&raw);
+
+        assert_eq!(
+            got.unwrap_err(),
+            ParseError::InvalidValue {
+                field: "multicast_mode",
+                offset: 8
+            }
+        );
     }
 
     #[test]
@@ -460,6 +771,127 @@ mod tests {
         assert_eq!(header.sequence_number, 191);
     }
 
+    #[test]
+    fn pack_frame_control_round_trip() {
+        let raw = [0b0111_1100_u8, 0b0010_1010_u8];
+
+        let frame_control = FrameControl::unpack_from_slice(&raw).unwrap();
+        let packed: Vec<u8, 2> = frame_control.pack_to_vec().unwrap();
+
+        assert_eq!(packed, &raw);
+    }
+
+    #[test]
+    fn new_frame_control() {
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        frame_control.set_security_flag(true);
+        frame_control.set_source_ieee_flag(true);
+
+        assert_eq!(frame_control.0, 0x1209);
+        assert_eq!(frame_control.discover_route(), DiscoverRoute::Suppress);
+        assert!(!frame_control.destination_ieee_flag());
+
+        frame_control.set_security_flag(false);
+        assert!(!frame_control.security_flag());
+
+        frame_control.set_discover_route(DiscoverRoute::Enable);
+        assert_eq!(frame_control.0, 0x1049);
+        assert_eq!(frame_control.discover_route(), DiscoverRoute::Enable);
+
+        frame_control.set_source_flag(true);
+        assert_eq!(frame_control.0, 0x1449);
+        assert!(frame_control.source_flag());
+    }
+
+    #[test]
+    fn pack_source_route_subframe_round_trip() {
+        let raw = [0x02, 0x01, 0x34, 0x12, 0xff, 0xff];
+
+        let subframe = SourceRouteSubframe::unpack_from_slice(&raw).unwrap();
+        let mut buf = [0u8; 16];
+        let len = subframe.pack_into(&mut buf).unwrap();
+
+        assert_eq!(&buf[..len], &raw);
+    }
+
+    #[test]
+    fn pack_nwk_header_round_trip() {
+        let raw = [
+            0x09, 0x12, 0xfc, 0xff, 0x00, 0x00, 0x08, 0xbf, 0x66, 0x71, 0x9a, 0x2a, 0x00, 0x4b,
+            0x12, 0x00,
+        ];
+
+        let header = NwkHeader::unpack_from_slice(&raw).unwrap();
+        let packed: Vec<u8, 32> = header.pack_to_vec().unwrap();
+
+        assert_eq!(packed, &raw);
+    }
+
+    #[test]
+    fn pack_nwk_header_into_too_small_buffer_should_fail() {
+        let raw = [
+            0x09, 0x12, 0xfc, 0xff, 0x00, 0x00, 0x08, 0xbf, 0x66, 0x71, 0x9a, 0x2a, 0x00, 0x4b,
+            0x12, 0x00,
+        ];
+
+        let header = NwkHeader::unpack_from_slice(&raw).unwrap();
+        let mut buf = [0u8; 15];
+
+        assert_eq!(header.pack_into(&mut buf), Err(PackError::BufferTooSmall));
+    }
+
+    #[test]
+    fn pack_nwk_command_frame_round_trip() {
+        let mut raw = [0u8; CMD_FRAME.len() / 2];
+        hex::decode_to_slice(CMD_FRAME, &mut raw).unwrap();
+
+        let frame = NwkFrame::unpack_from_slice(&raw).unwrap();
+        let packed: Vec<u8, 128> = frame.pack_to_vec().unwrap();
+
+        assert_eq!(
+            frame.frame_type_identifier(),
+            FrameTypeIdentifier::NwkCommand
+        );
+        assert_eq!(packed, &raw);
+    }
+
+    #[test]
+    fn pack_nwk_data_frame_round_trip() {
+        let raw = [
+            0x08, 0x02, 0x00, 0x00, 0x3a, 0x9e, 0x1e, 0x5c, 0x40, 0x0a, 0x06, 0x00, 0x04, 0x01,
+            0x01, 0x1c,
+        ];
+
+        let frame = NwkFrame::unpack_from_slice(&raw).unwrap();
+        let packed: Vec<u8, 128> = frame.pack_to_vec().unwrap();
+
+        assert_eq!(frame.frame_type_identifier(), FrameTypeIdentifier::Data);
+        assert_eq!(packed, &raw);
+    }
+
+    #[test]
+    fn nwk_command_frame_round_trip() {
+        let raw = [0x09, 0x00, 0xfd, 0xff, 0x34, 0x12, 0x01, 0x2a, 0x04, 0x60];
+
+        let frame = NwkFrame::unpack_from_slice(&raw).unwrap();
+        let NwkFrame::NwkCommand(command_frame) = frame else {
+            unreachable!("expected a command frame");
+        };
+        let command = command_frame.command().unwrap();
+        let rebuilt = NwkCommandFrame::new(command_frame.header, &command).unwrap();
+        let packed: Vec<u8, 16> = rebuilt.pack_to_vec().unwrap();
+
+        assert_eq!(
+            command,
+            NwkCommand::Leave(crate::nwk::commands::Leave {
+                rejoin: true,
+                request: true,
+                remove_children: false,
+            })
+        );
+        assert_eq!(packed, &raw);
+    }
+
     //#[test]
     //fn unpack_command_frame_identifier() {
     //    let raw = [0b00000111u8];
@@ -1,2 +1,11 @@
+pub mod address_map;
+pub mod beacon;
+pub mod broadcast;
+pub mod commands;
 pub mod frame;
+pub mod neighbor;
+pub mod nib;
+pub mod nlde;
 pub mod nlme;
+pub mod routing;
+pub mod security;
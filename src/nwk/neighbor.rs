@@ -0,0 +1,649 @@
+//! Neighbor Table
+//!
+//! Information about the devices within radio range, gathered from received
+//! beacons and frames.
+//!
+//! See Section 3.6.1.5.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::nwk::commands::EndDeviceTimeout;
+use crate::nwk::commands::LinkStatus;
+
+/// Maximum number of neighbor table entries.
+pub const MAX_NEIGHBORS: usize = 32;
+
+/// Relationship between a neighbor and the current device.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relationship {
+    /// The neighbor is the parent of the current device.
+    Parent = 0x00,
+    /// The neighbor is a child of the current device.
+    Child = 0x01,
+    /// The neighbor is a sibling of the current device.
+    Sibling = 0x02,
+    /// None of the above.
+    None = 0x03,
+    /// The neighbor was a child of the current device.
+    PreviousChild = 0x04,
+    /// The neighbor is a child which has not yet been authenticated.
+    UnauthenticatedChild = 0x05,
+}
+
+/// Keep-alive of an end device child, which restarts its timeout.
+///
+/// See Section 3.6.10.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keepalive {
+    /// A MAC data poll.
+    MacDataPoll,
+    /// An End Device Timeout Request.
+    TimeoutRequest,
+}
+
+/// Neighbor table entry
+///
+/// See Table 3-63 and the network discovery fields of Table 3-64.
+// the flags are fields of the specified table
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Neighbor {
+    /// 64-bit IEEE address, if known
+    pub extended_address: Option<IeeeAddress>,
+    /// 16-bit network address
+    pub network_address: ShortAddress,
+    /// The type of the neighbor device
+    pub device_type: LogicalType,
+    /// The neighbor does not disable its receiver during idle periods
+    pub rx_on_when_idle: bool,
+    /// Relationship to the current device
+    pub relationship: Relationship,
+    /// Link quality of the last frame received from the neighbor
+    pub lqi: u8,
+    /// 16-bit PAN identifier of the neighbor's network
+    pub pan_id: u16,
+    /// 64-bit PAN identifier of the neighbor's network
+    pub extended_pan_id: u64,
+    /// The channel the neighbor operates on
+    pub logical_channel: u8,
+    /// Network depth of the neighbor
+    pub depth: u8,
+    /// The neighbor permits association
+    pub permit_joining: bool,
+    /// The neighbor accepts join requests from router-capable devices
+    pub router_capacity: bool,
+    /// The neighbor accepts join requests from end devices
+    pub end_device_capacity: bool,
+    /// The neighbor has not failed a join attempt of the current device
+    pub potential_parent: bool,
+    /// The nwkUpdateId of the neighbor
+    pub update_id: u8,
+    /// Cost of the link to the neighbor as reported by its link status, 0 if
+    /// unknown
+    pub outgoing_cost: u8,
+    /// Number of nwkLinkStatusPeriod intervals since the last link status of
+    /// the neighbor
+    pub age: u8,
+    /// Timeout of an end device child, `None` for other neighbors
+    pub device_timeout: Option<EndDeviceTimeout>,
+    /// The time an end device child is aged out unless it keeps alive
+    pub timeout_expiry: Option<Duration>,
+    /// The last keep-alive received from an end device child, if any
+    pub keepalive: Option<Keepalive>,
+    /// Frame counter of the last secured frame received from the neighbor, if
+    /// any
+    pub incoming_frame_counter: Option<u32>,
+}
+
+impl Neighbor {
+    /// Cost of the link from the neighbor, estimated from the LQI.
+    pub fn incoming_cost(&self) -> u8 {
+        link_cost(self.lqi)
+    }
+
+    /// Cost of the link in both directions, the higher of the incoming and
+    /// the known outgoing cost.
+    ///
+    /// See Section 3.6.3.1.
+    pub fn cost(&self) -> u8 {
+        self.incoming_cost().max(self.outgoing_cost)
+    }
+
+    /// The neighbor is a child of the current device.
+    pub fn is_child(&self) -> bool {
+        matches!(
+            self.relationship,
+            Relationship::Child | Relationship::UnauthenticatedChild
+        )
+    }
+
+    /// The neighbor is a router or the coordinator.
+    pub fn is_router(&self) -> bool {
+        self.device_type != LogicalType::EndDevice
+    }
+
+    /// The entry may be evicted to make room for another neighbor.
+    fn evictable(&self) -> bool {
+        matches!(
+            self.relationship,
+            Relationship::None | Relationship::Sibling | Relationship::PreviousChild
+        )
+    }
+}
+
+/// Link cost derived from the LQI of received frames, 1 for the best and 7
+/// for the worst links.
+///
+/// See Section 3.6.3.1.
+pub fn link_cost(lqi: u8) -> u8 {
+    match lqi {
+        200..=255 => 1,
+        150..=199 => 3,
+        100..=149 => 5,
+        _ => 7,
+    }
+}
+
+/// 3.6.1.5 Neighbor Table
+#[derive(Debug, Default)]
+pub struct NeighborTable {
+    entries: Vec<Neighbor, MAX_NEIGHBORS>,
+}
+
+impl NeighborTable {
+    /// Creates an empty neighbor table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = &Neighbor> {
+        self.entries.iter()
+    }
+
+    /// Returns the entry of the device with `network_address` on `pan_id`.
+    pub fn get(&self, pan_id: u16, network_address: ShortAddress) -> Option<&Neighbor> {
+        self.entries.iter().find(|neighbor| {
+            neighbor.pan_id == pan_id && neighbor.network_address == network_address
+        })
+    }
+
+    pub(crate) fn get_mut(
+        &mut self,
+        pan_id: u16,
+        network_address: ShortAddress,
+    ) -> Option<&mut Neighbor> {
+        self.entries.iter_mut().find(|neighbor| {
+            neighbor.pan_id == pan_id && neighbor.network_address == network_address
+        })
+    }
+
+    /// Returns the entry of the device with the IEEE `extended_address`.
+    pub fn get_by_extended_address(&self, extended_address: IeeeAddress) -> Option<&Neighbor> {
+        self.entries
+            .iter()
+            .find(|neighbor| neighbor.extended_address == Some(extended_address))
+    }
+
+    pub(crate) fn get_mut_by_extended_address(
+        &mut self,
+        extended_address: IeeeAddress,
+    ) -> Option<&mut Neighbor> {
+        self.entries
+            .iter_mut()
+            .find(|neighbor| neighbor.extended_address == Some(extended_address))
+    }
+
+    /// Returns the parent of the current device.
+    pub fn parent(&self) -> Option<&Neighbor> {
+        self.entries
+            .iter()
+            .find(|neighbor| neighbor.relationship == Relationship::Parent)
+    }
+
+    /// Iterates over the children of the current device.
+    pub fn children(&self) -> impl Iterator<Item = &Neighbor> {
+        self.entries.iter().filter(|neighbor| neighbor.is_child())
+    }
+
+    /// Iterates over the routers and the coordinator on `pan_id`.
+    pub fn routers(&self, pan_id: u16) -> impl Iterator<Item = &Neighbor> {
+        self.entries
+            .iter()
+            .filter(move |neighbor| neighbor.pan_id == pan_id && neighbor.is_router())
+    }
+
+    /// Adds a neighbor or replaces the entry of the same device, which is
+    /// found by its network or its IEEE address. The incoming frame counter
+    /// of the device is kept.
+    ///
+    /// A full table evicts the unrelated neighbor with the oldest link status
+    /// and the highest link cost. Returns the neighbor if all entries are a
+    /// parent or a child.
+    pub(crate) fn insert(&mut self, mut neighbor: Neighbor) -> Result<(), Neighbor> {
+        let same_device = self.entries.iter().position(|entry| {
+            (entry.pan_id == neighbor.pan_id && entry.network_address == neighbor.network_address)
+                || (entry.extended_address.is_some()
+                    && entry.extended_address == neighbor.extended_address)
+        });
+        if let Some(index) = same_device {
+            // replayed frames of the device are still detected
+            let entry = &self.entries[index];
+            if entry.extended_address == neighbor.extended_address {
+                neighbor.incoming_frame_counter = neighbor
+                    .incoming_frame_counter
+                    .or(entry.incoming_frame_counter);
+            }
+            self.entries[index] = neighbor;
+            return Ok(());
+        }
+        if self.entries.is_full() {
+            let Some(index) = self
+                .entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| entry.evictable())
+                .max_by_key(|(_, entry)| (entry.age, entry.cost()))
+                .map(|(index, _)| index)
+            else {
+                return Err(neighbor);
+            };
+            self.entries.swap_remove(index);
+        }
+        self.entries.push(neighbor)
+    }
+
+    /// Removes the entry of the device with `network_address` on `pan_id`.
+    pub(crate) fn remove(&mut self, pan_id: u16, network_address: ShortAddress) {
+        self.entries.retain(|neighbor| {
+            neighbor.pan_id != pan_id || neighbor.network_address != network_address
+        });
+    }
+
+    /// Records the link quality of a frame received from a known neighbor.
+    ///
+    /// Returns if the sender is a neighbor.
+    pub(crate) fn received(&mut self, pan_id: u16, network_address: ShortAddress, lqi: u8) -> bool {
+        self.get_mut(pan_id, network_address)
+            .map(|neighbor| neighbor.lqi = lqi)
+            .is_some()
+    }
+
+    /// Records the `frame_counter` of a secured frame received from the
+    /// neighbor with the IEEE address `source`.
+    ///
+    /// A neighbor whose IEEE address is not yet known is found by the network
+    /// address `sender` of the frame. Returns false if the frame counter is not
+    /// greater than the one of the last frame of the neighbor, the frame is
+    /// then a replay. Frames of other devices are not checked.
+    ///
+    /// See Section 4.3.1.2.
+    pub(crate) fn incoming_frame_counter(
+        &mut self,
+        pan_id: u16,
+        sender: Option<ShortAddress>,
+        source: IeeeAddress,
+        frame_counter: u32,
+    ) -> bool {
+        let index = self
+            .entries
+            .iter()
+            .position(|neighbor| neighbor.extended_address == Some(source))
+            .or_else(|| {
+                self.entries.iter().position(|neighbor| {
+                    neighbor.extended_address.is_none()
+                        && neighbor.pan_id == pan_id
+                        && Some(neighbor.network_address) == sender
+                })
+            });
+        let Some(neighbor) = index.map(|index| &mut self.entries[index]) else {
+            return true;
+        };
+        if neighbor
+            .incoming_frame_counter
+            .is_some_and(|last| frame_counter <= last)
+        {
+            return false;
+        }
+        neighbor.extended_address = Some(source);
+        neighbor.incoming_frame_counter = Some(frame_counter);
+        true
+    }
+
+    /// Ages the routers on `pan_id` by one nwkLinkStatusPeriod.
+    ///
+    /// The outgoing cost of routers without a link status for more than
+    /// `age_limit` periods is no longer known, unrelated ones are removed.
+    ///
+    /// See Section 3.6.3.4.2.
+    pub(crate) fn age_routers(&mut self, pan_id: u16, age_limit: u8) {
+        for neighbor in self
+            .entries
+            .iter_mut()
+            .filter(|neighbor| neighbor.pan_id == pan_id && neighbor.is_router())
+        {
+            neighbor.age = neighbor.age.saturating_add(1);
+            if neighbor.age > age_limit {
+                neighbor.outgoing_cost = 0;
+            }
+        }
+        self.entries.retain(|neighbor| {
+            neighbor.pan_id != pan_id
+                || !neighbor.is_router()
+                || neighbor.age <= age_limit
+                || !neighbor.evictable()
+        });
+    }
+
+    /// Records a keep-alive of the end device child with `network_address` on
+    /// `pan_id`, which restarts its timeout at `now`.
+    ///
+    /// Returns if the device is an end device child.
+    ///
+    /// See Section 3.6.10.3.
+    pub(crate) fn keepalive(
+        &mut self,
+        pan_id: u16,
+        network_address: ShortAddress,
+        keepalive: Keepalive,
+        now: Duration,
+    ) -> bool {
+        let Some(child) = self
+            .get_mut(pan_id, network_address)
+            .filter(|neighbor| neighbor.is_child() && !neighbor.is_router())
+        else {
+            return false;
+        };
+        child.keepalive = Some(keepalive);
+        child.timeout_expiry = child.device_timeout.map(|timeout| now + timeout.duration());
+        true
+    }
+
+    /// Records the link status of the router `source`, which reports the
+    /// cost of the link to the device with `own_address`.
+    ///
+    /// Returns if the sender is a neighbor.
+    ///
+    /// See Section 3.6.3.4.2.
+    pub(crate) fn link_status(
+        &mut self,
+        pan_id: u16,
+        source: ShortAddress,
+        own_address: ShortAddress,
+        link_status: &LinkStatus,
+    ) -> bool {
+        let Some(neighbor) = self.get_mut(pan_id, source) else {
+            return false;
+        };
+        neighbor.age = 0;
+        // the device is not listed if the neighbor does not hear it
+        neighbor.outgoing_cost = link_status
+            .entries
+            .iter()
+            .find(|entry| entry.address == own_address)
+            .map_or(0, |entry| entry.incoming_cost);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::commands::LinkStatusEntry;
+
+    fn neighbor(network_address: u16) -> Neighbor {
+        Neighbor {
+            extended_address: None,
+            network_address: ShortAddress(network_address),
+            device_type: LogicalType::Router,
+            rx_on_when_idle: true,
+            relationship: Relationship::None,
+            lqi: 255,
+            pan_id: 0x1a62,
+            extended_pan_id: 0x0015_8d00_01ab_cd12,
+            logical_channel: 15,
+            depth: 1,
+            permit_joining: true,
+            router_capacity: true,
+            end_device_capacity: true,
+            potential_parent: true,
+            update_id: 0,
+            outgoing_cost: 0,
+            age: 0,
+            device_timeout: None,
+            timeout_expiry: None,
+            keepalive: None,
+            incoming_frame_counter: None,
+        }
+    }
+
+    #[test]
+    fn insert_replaces_entry_of_the_same_device() {
+        let mut table = NeighborTable::new();
+        table.insert(neighbor(0x0000)).unwrap();
+        table.insert(neighbor(0x796f)).unwrap();
+
+        table
+            .insert(Neighbor {
+                lqi: 80,
+                ..neighbor(0x796f)
+            })
+            .unwrap();
+
+        assert_eq!(table.iter().count(), 2);
+        assert_eq!(table.get(0x1a62, ShortAddress(0x796f)).unwrap().lqi, 80);
+        assert!(table.get(0x1a63, ShortAddress(0x796f)).is_none());
+    }
+
+    #[test]
+    fn replayed_frame_counter_is_rejected() {
+        let mut table = NeighborTable::new();
+        table.insert(neighbor(0x796f)).unwrap();
+        let (sender, source) = (Some(ShortAddress(0x796f)), IeeeAddress(0x0001));
+
+        assert!(table.incoming_frame_counter(0x1a62, sender, source, 7));
+        assert!(!table.incoming_frame_counter(0x1a62, sender, source, 7));
+        assert!(!table.incoming_frame_counter(0x1a62, None, source, 6));
+        assert!(table.incoming_frame_counter(0x1a62, None, source, 8));
+        // a device which is not a neighbor is not checked
+        assert!(table.incoming_frame_counter(0x1a62, sender, IeeeAddress(0x0002), 0));
+
+        table
+            .insert(Neighbor {
+                extended_address: Some(source),
+                ..neighbor(0x796f)
+            })
+            .unwrap();
+        let entry = table.get(0x1a62, ShortAddress(0x796f)).unwrap();
+        assert_eq!(entry.extended_address, Some(source));
+        assert_eq!(entry.incoming_frame_counter, Some(8));
+    }
+
+    #[test]
+    fn table_full_of_children_rejects_new_devices() {
+        let mut table = NeighborTable::new();
+        for address in (0u16..).take(MAX_NEIGHBORS) {
+            table
+                .insert(Neighbor {
+                    relationship: Relationship::Child,
+                    ..neighbor(address)
+                })
+                .unwrap();
+        }
+
+        assert!(table.insert(neighbor(0x796f)).is_err());
+        assert!(table.insert(neighbor(0x0000)).is_ok());
+    }
+
+    #[test]
+    fn full_table_evicts_stalest_unrelated_neighbor() {
+        let mut table = NeighborTable::new();
+        for address in (0u16..).take(MAX_NEIGHBORS) {
+            table.insert(neighbor(address)).unwrap();
+        }
+        table.get_mut(0x1a62, ShortAddress(0x0003)).unwrap().age = 2;
+        table.get_mut(0x1a62, ShortAddress(0x0005)).unwrap().age = 2;
+        table.get_mut(0x1a62, ShortAddress(0x0005)).unwrap().lqi = 80;
+
+        table.insert(neighbor(0x796f)).unwrap();
+
+        assert_eq!(table.iter().count(), MAX_NEIGHBORS);
+        assert!(table.get(0x1a62, ShortAddress(0x0005)).is_none());
+        assert!(table.get(0x1a62, ShortAddress(0x0003)).is_some());
+    }
+
+    #[test]
+    fn device_with_new_address_replaces_its_entry() {
+        let mut table = NeighborTable::new();
+        let extended_address = Some(IeeeAddress(0x0002));
+        table
+            .insert(Neighbor {
+                extended_address,
+                ..neighbor(0x796f)
+            })
+            .unwrap();
+
+        table
+            .insert(Neighbor {
+                extended_address,
+                ..neighbor(0x1234)
+            })
+            .unwrap();
+
+        assert_eq!(table.iter().count(), 1);
+        assert_eq!(
+            table
+                .get_by_extended_address(IeeeAddress(0x0002))
+                .unwrap()
+                .network_address,
+            ShortAddress(0x1234)
+        );
+    }
+
+    #[test]
+    fn link_status_sets_outgoing_cost_and_age() {
+        let mut table = NeighborTable::new();
+        table
+            .insert(Neighbor {
+                age: 4,
+                ..neighbor(0x0001)
+            })
+            .unwrap();
+        let mut link_status = LinkStatus {
+            first_frame: true,
+            last_frame: true,
+            entries: Vec::new(),
+        };
+        link_status
+            .entries
+            .push(LinkStatusEntry {
+                address: ShortAddress(0x0000),
+                incoming_cost: 5,
+                outgoing_cost: 1,
+            })
+            .unwrap();
+
+        assert!(table.link_status(
+            0x1a62,
+            ShortAddress(0x0001),
+            ShortAddress(0x0000),
+            &link_status
+        ));
+        let entry = table.get(0x1a62, ShortAddress(0x0001)).unwrap();
+        assert_eq!((entry.outgoing_cost, entry.age), (5, 0));
+        assert_eq!(entry.cost(), 5);
+
+        // the neighbor no longer hears the device
+        assert!(table.link_status(
+            0x1a62,
+            ShortAddress(0x0001),
+            ShortAddress(0x796f),
+            &link_status
+        ));
+        let entry = table.get(0x1a62, ShortAddress(0x0001)).unwrap();
+        assert_eq!((entry.outgoing_cost, entry.cost()), (0, 1));
+        assert!(!table.link_status(
+            0x1a62,
+            ShortAddress(0x0002),
+            ShortAddress(0x0000),
+            &link_status
+        ));
+    }
+
+    #[test]
+    fn stale_routers_are_aged_out() {
+        let mut table = NeighborTable::new();
+        table
+            .insert(Neighbor {
+                outgoing_cost: 3,
+                age: 3,
+                ..neighbor(0x0001)
+            })
+            .unwrap();
+        table
+            .insert(Neighbor {
+                relationship: Relationship::Parent,
+                outgoing_cost: 3,
+                age: 3,
+                ..neighbor(0x0000)
+            })
+            .unwrap();
+        table
+            .insert(Neighbor {
+                device_type: LogicalType::EndDevice,
+                relationship: Relationship::Child,
+                age: 3,
+                ..neighbor(0x796f)
+            })
+            .unwrap();
+        table.insert(neighbor(0x0002)).unwrap();
+
+        table.age_routers(0x1a62, 3);
+
+        assert!(table.get(0x1a62, ShortAddress(0x0001)).is_none());
+        let parent = table.parent().unwrap();
+        assert_eq!((parent.age, parent.outgoing_cost), (4, 0));
+        assert_eq!(table.get(0x1a62, ShortAddress(0x796f)).unwrap().age, 3);
+        assert_eq!(table.get(0x1a62, ShortAddress(0x0002)).unwrap().age, 1);
+    }
+
+    #[test]
+    fn keepalive_restarts_timeout_of_end_device_child() {
+        let mut table = NeighborTable::new();
+        table
+            .insert(Neighbor {
+                device_type: LogicalType::EndDevice,
+                relationship: Relationship::Child,
+                device_timeout: EndDeviceTimeout::new(0),
+                timeout_expiry: Some(Duration::from_secs(10)),
+                ..neighbor(0x796f)
+            })
+            .unwrap();
+        table
+            .insert(Neighbor {
+                relationship: Relationship::Child,
+                ..neighbor(0x0001)
+            })
+            .unwrap();
+
+        let now = Duration::from_secs(5);
+        assert!(table.keepalive(0x1a62, ShortAddress(0x796f), Keepalive::MacDataPoll, now));
+        assert!(!table.keepalive(0x1a62, ShortAddress(0x0001), Keepalive::MacDataPoll, now));
+        assert!(!table.keepalive(0x1a62, ShortAddress(0x0002), Keepalive::MacDataPoll, now));
+        let child = table.get(0x1a62, ShortAddress(0x796f)).unwrap();
+        assert_eq!(child.timeout_expiry, Some(Duration::from_secs(15)));
+        assert_eq!(child.keepalive, Some(Keepalive::MacDataPoll));
+    }
+
+    #[test]
+    fn link_cost_decreases_with_link_quality() {
+        assert_eq!(link_cost(255), 1);
+        assert_eq!(link_cost(160), 3);
+        assert_eq!(link_cost(0), 7);
+    }
+}
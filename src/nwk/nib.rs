@@ -0,0 +1,529 @@
+//! Network Information Base
+//!
+//! The attributes the NWK layer keeps to operate on a network. Scalar
+//! attributes are read and written through NLME-GET and NLME-SET, tables are
+//! maintained by the procedures using them.
+//!
+//! See Section 3.5.2.
+
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::common::types::ShortAddress;
+use crate::mac::frame::BROADCAST;
+use crate::nwk::address_map::AddressMap;
+use crate::nwk::broadcast::BroadcastTransactionTable;
+use crate::nwk::commands::EndDeviceTimeout;
+use crate::nwk::neighbor::NeighborTable;
+use crate::nwk::nlme::management::NlmeSetStatus;
+use crate::nwk::nlme::STACK_PROFILE;
+use crate::nwk::routing::ConcentratorMode;
+use crate::nwk::routing::RouteDiscoveryTable;
+use crate::nwk::routing::RoutingTable;
+use crate::nwk::routing::SourceRouteTable;
+use crate::nwk::security::NetworkKey;
+
+/// nwkcMaxDepth
+pub const MAX_DEPTH: u8 = 0x0f;
+
+/// Highest valid PAN ID.
+pub const MAX_PAN_ID: u16 = 0x3fff;
+
+/// Lowest of the network addresses reserved for broadcasts.
+pub const MIN_BROADCAST_ADDRESS: u16 = 0xfff8;
+
+/// Maximum number of groups in nwkGroupIDTable.
+pub const MAX_GROUP_IDS: usize = 16;
+
+/// Method used to assign network addresses to joining devices.
+///
+/// See nwkAddrAlloc in Table 3-58.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressAllocation {
+    /// Tree addressing based on Cskip
+    Distributed = 0x00,
+    /// Reserved
+    Reserved = 0x01,
+    /// Random addresses with address conflict resolution
+    Stochastic = 0x02,
+}
+
+/// NIB attributes with their value
+///
+/// See Table 3-58.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NibAttribute {
+    /// nwkPANId
+    PanId(u16),
+    /// nwkSequenceNumber
+    SequenceNumber(u8),
+    /// nwkPassiveAckTimeout
+    PassiveAckTimeout(Duration),
+    /// nwkMaxBroadcastRetries
+    MaxBroadcastRetries(u8),
+    /// nwkMaxChildren
+    MaxChildren(u8),
+    /// nwkMaxDepth
+    MaxDepth(u8),
+    /// nwkMaxRouters
+    MaxRouters(u8),
+    /// nwkNetworkBroadcastDeliveryTime
+    NetworkBroadcastDeliveryTime(Duration),
+    /// nwkReportConstantCost
+    ReportConstantCost(bool),
+    /// nwkSymLink
+    SymLink(bool),
+    /// nwkAddrAlloc
+    AddrAlloc(AddressAllocation),
+    /// nwkUseTreeRouting
+    UseTreeRouting(bool),
+    /// nwkManagerAddr
+    ManagerAddr(ShortAddress),
+    /// nwkMaxSourceRoute
+    MaxSourceRoute(u8),
+    /// nwkUpdateId
+    UpdateId(u8),
+    /// nwkTransactionPersistenceTime in superframe periods
+    TransactionPersistenceTime(u16),
+    /// nwkNetworkAddress
+    NetworkAddress(ShortAddress),
+    /// nwkStackProfile
+    StackProfile(u8),
+    /// nwkExtendedPANID
+    ExtendedPanId(u64),
+    /// nwkUseMulticast
+    UseMulticast(bool),
+    /// nwkIsConcentrator
+    IsConcentrator(bool),
+    /// nwkConcentratorRadius
+    ConcentratorRadius(u8),
+    /// nwkConcentratorDiscoveryTime, zero disables periodic discoveries
+    ConcentratorDiscoveryTime(Duration),
+    /// nwkActiveKeySeqNumber
+    ActiveKeySeqNumber(u8),
+    /// nwkSecureAllFrames
+    SecureAllFrames(bool),
+    /// nwkLinkStatusPeriod
+    LinkStatusPeriod(Duration),
+    /// nwkRouterAgeLimit
+    RouterAgeLimit(u8),
+    /// nwkUniqueAddr
+    UniqueAddr(bool),
+    /// nwkLeaveRequestAllowed
+    LeaveRequestAllowed(bool),
+    /// nwkParentInformation
+    ParentInformation(u8),
+    /// nwkEndDeviceTimeoutDefault
+    EndDeviceTimeoutDefault(u8),
+    /// nwkLeaveRequestWithoutRejoinAllowed
+    LeaveRequestWithoutRejoinAllowed(bool),
+}
+
+impl NibAttribute {
+    /// The attribute identifier of Table 3-58.
+    pub fn id(&self) -> u8 {
+        match self {
+            Self::PanId(_) => 0x80,
+            Self::SequenceNumber(_) => 0x81,
+            Self::PassiveAckTimeout(_) => 0x82,
+            Self::MaxBroadcastRetries(_) => 0x83,
+            Self::MaxChildren(_) => 0x84,
+            Self::MaxDepth(_) => 0x85,
+            Self::MaxRouters(_) => 0x86,
+            // 0x87 nwkNeighborTable
+            Self::NetworkBroadcastDeliveryTime(_) => 0x88,
+            Self::ReportConstantCost(_) => 0x89,
+            // 0x8b nwkRouteTable
+            Self::SymLink(_) => 0x8e,
+            // 0x8f nwkCapabilityInformation is kept by the MAC
+            Self::AddrAlloc(_) => 0x90,
+            Self::UseTreeRouting(_) => 0x91,
+            Self::ManagerAddr(_) => 0x92,
+            Self::MaxSourceRoute(_) => 0x93,
+            Self::UpdateId(_) => 0x94,
+            Self::TransactionPersistenceTime(_) => 0x95,
+            Self::NetworkAddress(_) => 0x96,
+            Self::StackProfile(_) => 0x97,
+            // 0x98 nwkBroadcastTransactionTable, 0x99 nwkGroupIDTable
+            Self::ExtendedPanId(_) => 0x9a,
+            Self::UseMulticast(_) => 0x9b,
+            // 0x9c nwkRouteRecordTable
+            Self::IsConcentrator(_) => 0x9d,
+            Self::ConcentratorRadius(_) => 0x9e,
+            Self::ConcentratorDiscoveryTime(_) => 0x9f,
+            // 0xa0 nwkSecurityLevel is fixed, 0xa1 nwkSecurityMaterialSet
+            Self::ActiveKeySeqNumber(_) => 0xa2,
+            Self::SecureAllFrames(_) => 0xa5,
+            Self::LinkStatusPeriod(_) => 0xa6,
+            Self::RouterAgeLimit(_) => 0xa7,
+            Self::UniqueAddr(_) => 0xa8,
+            // 0xa9 nwkAddressMap
+            Self::LeaveRequestAllowed(_) => 0xaa,
+            Self::ParentInformation(_) => 0xab,
+            Self::EndDeviceTimeoutDefault(_) => 0xac,
+            Self::LeaveRequestWithoutRejoinAllowed(_) => 0xad,
+        }
+    }
+}
+
+/// Network Information Base
+///
+/// See Section 3.5.2.
+// the flags are attributes of the specified table
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug)]
+pub struct NetworkInformationBase {
+    /// nwkPANId
+    pub(crate) pan_id: u16,
+    /// nwkSequenceNumber
+    pub(crate) sequence_number: u8,
+    /// nwkPassiveAckTimeout
+    pub(crate) passive_ack_timeout: Duration,
+    /// nwkMaxBroadcastRetries
+    pub(crate) max_broadcast_retries: u8,
+    /// nwkMaxChildren
+    pub(crate) max_children: u8,
+    /// nwkMaxDepth
+    pub(crate) max_depth: u8,
+    /// nwkMaxRouters
+    pub(crate) max_routers: u8,
+    /// nwkNeighborTable
+    pub(crate) neighbor_table: NeighborTable,
+    /// nwkNetworkBroadcastDeliveryTime
+    pub(crate) network_broadcast_delivery_time: Duration,
+    /// nwkBroadcastTransactionTable
+    pub(crate) broadcast_transaction_table: BroadcastTransactionTable,
+    /// nwkReportConstantCost
+    pub(crate) report_constant_cost: bool,
+    /// nwkRouteTable
+    pub(crate) route_table: RoutingTable,
+    /// The route discovery table, which is not an attribute.
+    pub(crate) route_discovery_table: RouteDiscoveryTable,
+    /// Identifier of the last route request originated by the device.
+    pub(crate) route_request_id: u8,
+    /// nwkSymLink
+    pub(crate) sym_link: bool,
+    /// nwkAddrAlloc
+    pub(crate) addr_alloc: AddressAllocation,
+    /// nwkUseTreeRouting
+    pub(crate) use_tree_routing: bool,
+    /// nwkManagerAddr
+    pub(crate) manager_addr: ShortAddress,
+    /// nwkMaxSourceRoute
+    pub(crate) max_source_route: u8,
+    /// nwkUpdateId
+    pub(crate) update_id: u8,
+    /// nwkTransactionPersistenceTime
+    pub(crate) transaction_persistence_time: u16,
+    /// nwkNetworkAddress
+    pub(crate) network_address: ShortAddress,
+    /// nwkStackProfile
+    pub(crate) stack_profile: u8,
+    /// nwkGroupIDTable, the groups of the APS group table
+    pub(crate) group_id_table: Vec<u16, MAX_GROUP_IDS>,
+    /// nwkExtendedPANID
+    pub(crate) extended_pan_id: u64,
+    /// nwkUseMulticast
+    pub(crate) use_multicast: bool,
+    /// nwkIsConcentrator
+    pub(crate) is_concentrator: bool,
+    /// nwkConcentratorRadius
+    pub(crate) concentrator_radius: u8,
+    /// nwkConcentratorDiscoveryTime
+    pub(crate) concentrator_discovery_time: Duration,
+    /// Memory model of the concentrator, which is not an attribute.
+    pub(crate) concentrator_mode: ConcentratorMode,
+    /// The source route table of a concentrator, which is not an attribute.
+    pub(crate) source_route_table: SourceRouteTable,
+    /// The active network key of nwkSecurityMaterialSet, its key sequence
+    /// number is nwkActiveKeySeqNumber.
+    pub(crate) network_key: Option<NetworkKey>,
+    /// The outgoing frame counter of the active network key.
+    pub(crate) outgoing_frame_counter: u32,
+    /// nwkSecureAllFrames
+    pub(crate) secure_all_frames: bool,
+    /// nwkLinkStatusPeriod
+    pub(crate) link_status_period: Duration,
+    /// nwkRouterAgeLimit
+    pub(crate) router_age_limit: u8,
+    /// nwkUniqueAddr
+    pub(crate) unique_addr: bool,
+    /// nwkAddressMap
+    pub(crate) address_map: AddressMap,
+    /// nwkLeaveRequestAllowed
+    pub(crate) leave_request_allowed: bool,
+    /// nwkParentInformation, the keep-alive methods supported by the parent
+    /// of an end device
+    pub(crate) parent_information: u8,
+    /// nwkEndDeviceTimeoutDefault
+    pub(crate) end_device_timeout_default: u8,
+    /// nwkLeaveRequestWithoutRejoinAllowed
+    pub(crate) leave_request_without_rejoin_allowed: bool,
+}
+
+impl Default for NetworkInformationBase {
+    /// The defaults of Table 3-58 for the Zigbee PRO stack profile.
+    fn default() -> Self {
+        Self {
+            pan_id: BROADCAST,
+            sequence_number: 0,
+            passive_ack_timeout: Duration::from_millis(500),
+            max_broadcast_retries: 3,
+            max_children: 20,
+            max_depth: MAX_DEPTH,
+            max_routers: 5,
+            neighbor_table: NeighborTable::new(),
+            network_broadcast_delivery_time: Duration::from_secs(9),
+            broadcast_transaction_table: BroadcastTransactionTable::new(),
+            report_constant_cost: false,
+            route_table: RoutingTable::new(),
+            route_discovery_table: RouteDiscoveryTable::new(),
+            route_request_id: 0,
+            sym_link: true,
+            addr_alloc: AddressAllocation::Stochastic,
+            use_tree_routing: false,
+            manager_addr: ShortAddress(0x0000),
+            max_source_route: 0x0c,
+            update_id: 0,
+            transaction_persistence_time: 0x01f4,
+            network_address: ShortAddress(BROADCAST),
+            stack_profile: STACK_PROFILE,
+            group_id_table: Vec::new(),
+            extended_pan_id: 0,
+            use_multicast: true,
+            is_concentrator: false,
+            concentrator_radius: 0,
+            concentrator_discovery_time: Duration::ZERO,
+            concentrator_mode: ConcentratorMode::HighRam,
+            source_route_table: SourceRouteTable::new(),
+            network_key: None,
+            outgoing_frame_counter: 0,
+            secure_all_frames: true,
+            link_status_period: Duration::from_secs(15),
+            router_age_limit: 3,
+            unique_addr: false,
+            address_map: AddressMap::new(),
+            leave_request_allowed: true,
+            parent_information: 0,
+            end_device_timeout_default: 8,
+            leave_request_without_rejoin_allowed: true,
+        }
+    }
+}
+
+impl NetworkInformationBase {
+    /// Creates a NIB with the default attribute values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the scalar attribute with identifier `id`.
+    pub fn get(&self, id: u8) -> Option<NibAttribute> {
+        let attribute = match id {
+            0x80 => NibAttribute::PanId(self.pan_id),
+            0x81 => NibAttribute::SequenceNumber(self.sequence_number),
+            0x82 => NibAttribute::PassiveAckTimeout(self.passive_ack_timeout),
+            0x83 => NibAttribute::MaxBroadcastRetries(self.max_broadcast_retries),
+            0x84 => NibAttribute::MaxChildren(self.max_children),
+            0x85 => NibAttribute::MaxDepth(self.max_depth),
+            0x86 => NibAttribute::MaxRouters(self.max_routers),
+            0x88 => {
+                NibAttribute::NetworkBroadcastDeliveryTime(self.network_broadcast_delivery_time)
+            }
+            0x89 => NibAttribute::ReportConstantCost(self.report_constant_cost),
+            0x8e => NibAttribute::SymLink(self.sym_link),
+            0x90 => NibAttribute::AddrAlloc(self.addr_alloc),
+            0x91 => NibAttribute::UseTreeRouting(self.use_tree_routing),
+            0x92 => NibAttribute::ManagerAddr(self.manager_addr),
+            0x93 => NibAttribute::MaxSourceRoute(self.max_source_route),
+            0x94 => NibAttribute::UpdateId(self.update_id),
+            0x95 => NibAttribute::TransactionPersistenceTime(self.transaction_persistence_time),
+            0x96 => NibAttribute::NetworkAddress(self.network_address),
+            0x97 => NibAttribute::StackProfile(self.stack_profile),
+            0x9a => NibAttribute::ExtendedPanId(self.extended_pan_id),
+            0x9b => NibAttribute::UseMulticast(self.use_multicast),
+            0x9d => NibAttribute::IsConcentrator(self.is_concentrator),
+            0x9e => NibAttribute::ConcentratorRadius(self.concentrator_radius),
+            0x9f => NibAttribute::ConcentratorDiscoveryTime(self.concentrator_discovery_time),
+            0xa2 => NibAttribute::ActiveKeySeqNumber(
+                self.network_key
+                    .map_or(0, |network_key| network_key.sequence_number),
+            ),
+            0xa5 => NibAttribute::SecureAllFrames(self.secure_all_frames),
+            0xa6 => NibAttribute::LinkStatusPeriod(self.link_status_period),
+            0xa7 => NibAttribute::RouterAgeLimit(self.router_age_limit),
+            0xa8 => NibAttribute::UniqueAddr(self.unique_addr),
+            0xaa => NibAttribute::LeaveRequestAllowed(self.leave_request_allowed),
+            0xab => NibAttribute::ParentInformation(self.parent_information),
+            0xac => NibAttribute::EndDeviceTimeoutDefault(self.end_device_timeout_default),
+            0xad => NibAttribute::LeaveRequestWithoutRejoinAllowed(
+                self.leave_request_without_rejoin_allowed,
+            ),
+            _ => return None,
+        };
+        Some(attribute)
+    }
+
+    /// Writes a scalar attribute.
+    ///
+    /// Values out of the range of Table 3-58 are rejected, the active key
+    /// sequence number must match a key of the security material.
+    pub fn set(&mut self, attribute: NibAttribute) -> Result<(), NlmeSetStatus> {
+        match attribute {
+            NibAttribute::PanId(pan_id) if pan_id > MAX_PAN_ID && pan_id != BROADCAST => {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            NibAttribute::MaxDepth(max_depth) if max_depth > MAX_DEPTH => {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            NibAttribute::NetworkAddress(ShortAddress(address))
+                if (MIN_BROADCAST_ADDRESS..BROADCAST).contains(&address) =>
+            {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            NibAttribute::StackProfile(stack_profile) if stack_profile > 0x0f => {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            NibAttribute::EndDeviceTimeoutDefault(timeout)
+                if EndDeviceTimeout::new(timeout).is_none() =>
+            {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            NibAttribute::TransactionPersistenceTime(0)
+            | NibAttribute::LinkStatusPeriod(Duration::ZERO)
+            | NibAttribute::AddrAlloc(AddressAllocation::Reserved) => {
+                return Err(NlmeSetStatus::InvalidParameter)
+            }
+            _ => {}
+        }
+        match attribute {
+            NibAttribute::PanId(value) => self.pan_id = value,
+            NibAttribute::SequenceNumber(value) => self.sequence_number = value,
+            NibAttribute::PassiveAckTimeout(value) => self.passive_ack_timeout = value,
+            NibAttribute::MaxBroadcastRetries(value) => self.max_broadcast_retries = value,
+            NibAttribute::MaxChildren(value) => self.max_children = value,
+            NibAttribute::MaxDepth(value) => self.max_depth = value,
+            NibAttribute::MaxRouters(value) => self.max_routers = value,
+            NibAttribute::NetworkBroadcastDeliveryTime(value) => {
+                self.network_broadcast_delivery_time = value;
+            }
+            NibAttribute::ReportConstantCost(value) => self.report_constant_cost = value,
+            NibAttribute::SymLink(value) => self.sym_link = value,
+            NibAttribute::AddrAlloc(value) => self.addr_alloc = value,
+            NibAttribute::UseTreeRouting(value) => self.use_tree_routing = value,
+            NibAttribute::ManagerAddr(value) => self.manager_addr = value,
+            NibAttribute::MaxSourceRoute(value) => self.max_source_route = value,
+            NibAttribute::UpdateId(value) => self.update_id = value,
+            NibAttribute::TransactionPersistenceTime(value) => {
+                self.transaction_persistence_time = value;
+            }
+            NibAttribute::NetworkAddress(value) => self.network_address = value,
+            NibAttribute::StackProfile(value) => self.stack_profile = value,
+            NibAttribute::ExtendedPanId(value) => self.extended_pan_id = value,
+            NibAttribute::UseMulticast(value) => self.use_multicast = value,
+            NibAttribute::IsConcentrator(value) => self.is_concentrator = value,
+            NibAttribute::ConcentratorRadius(value) => self.concentrator_radius = value,
+            NibAttribute::ConcentratorDiscoveryTime(value) => {
+                self.concentrator_discovery_time = value;
+            }
+            NibAttribute::ActiveKeySeqNumber(value) => {
+                // only a single network key is kept
+                if self
+                    .network_key
+                    .map_or(true, |network_key| network_key.sequence_number != value)
+                {
+                    return Err(NlmeSetStatus::InvalidParameter);
+                }
+            }
+            NibAttribute::SecureAllFrames(value) => self.secure_all_frames = value,
+            NibAttribute::LinkStatusPeriod(value) => self.link_status_period = value,
+            NibAttribute::RouterAgeLimit(value) => self.router_age_limit = value,
+            NibAttribute::UniqueAddr(value) => self.unique_addr = value,
+            NibAttribute::LeaveRequestAllowed(value) => self.leave_request_allowed = value,
+            NibAttribute::ParentInformation(value) => self.parent_information = value,
+            NibAttribute::EndDeviceTimeoutDefault(value) => {
+                self.end_device_timeout_default = value;
+            }
+            NibAttribute::LeaveRequestWithoutRejoinAllowed(value) => {
+                self.leave_request_without_rejoin_allowed = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the next nwkSequenceNumber.
+    pub(crate) fn next_sequence_number(&mut self) -> u8 {
+        self.sequence_number = self.sequence_number.wrapping_add(1);
+        self.sequence_number
+    }
+
+    /// Returns the identifier of the next route request.
+    pub(crate) fn next_route_request_id(&mut self) -> u8 {
+        self.route_request_id = self.route_request_id.wrapping_add(1);
+        self.route_request_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_have_the_defaults_of_the_pro_stack_profile() {
+        let nib = NetworkInformationBase::new();
+
+        assert_eq!(nib.get(0x80), Some(NibAttribute::PanId(0xffff)));
+        assert_eq!(nib.get(0x85), Some(NibAttribute::MaxDepth(15)));
+        assert_eq!(
+            nib.get(0x90),
+            Some(NibAttribute::AddrAlloc(AddressAllocation::Stochastic))
+        );
+        assert_eq!(
+            nib.get(0xa6),
+            Some(NibAttribute::LinkStatusPeriod(Duration::from_secs(15)))
+        );
+        // tables are not scalar attributes
+        assert_eq!(nib.get(0x87), None);
+    }
+
+    #[test]
+    fn set_attribute_is_returned_by_get() {
+        let mut nib = NetworkInformationBase::new();
+        let attribute = NibAttribute::ExtendedPanId(0x0015_8d00_01ab_cd12);
+
+        assert_eq!(nib.set(attribute), Ok(()));
+
+        assert_eq!(nib.get(attribute.id()), Some(attribute));
+        assert_eq!(nib.extended_pan_id, 0x0015_8d00_01ab_cd12);
+    }
+
+    #[test]
+    fn values_out_of_range_are_rejected() {
+        let mut nib = NetworkInformationBase::new();
+
+        assert_eq!(
+            nib.set(NibAttribute::PanId(0x4000)),
+            Err(NlmeSetStatus::InvalidParameter)
+        );
+        assert_eq!(
+            nib.set(NibAttribute::MaxDepth(16)),
+            Err(NlmeSetStatus::InvalidParameter)
+        );
+        assert_eq!(
+            nib.set(NibAttribute::NetworkAddress(ShortAddress(0xfffc))),
+            Err(NlmeSetStatus::InvalidParameter)
+        );
+        assert_eq!(
+            nib.set(NibAttribute::ActiveKeySeqNumber(1)),
+            Err(NlmeSetStatus::InvalidParameter)
+        );
+        assert_eq!(
+            nib.set(NibAttribute::EndDeviceTimeoutDefault(15)),
+            Err(NlmeSetStatus::InvalidParameter)
+        );
+        assert_eq!(nib.pan_id, 0xffff);
+        assert_eq!(nib.max_depth, 15);
+    }
+}
@@ -0,0 +1,104 @@
+//! Network Data Entity
+//!
+//! Transports the data frames of the next higher layer to other devices or
+//! groups, and passes the data frames received for this device to it.
+//!
+//! See Section 3.2.1.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::common::types::ShortAddress;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::radio::TxStatus;
+
+/// Addressing mode of the destination of an NSDU.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NldeAddressMode {
+    /// 16-bit multicast group identifier
+    Group = 0x01,
+    /// 16-bit network address of a device or a broadcast address
+    Network = 0x02,
+}
+
+/// 3.2.1.1 - NLDE-DATA.request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NldeDataRequest {
+    /// addressing mode of the destination
+    pub destination_address_mode: NldeAddressMode,
+    /// network address or group identifier of the destination
+    pub destination: ShortAddress,
+    /// the payload of the frame
+    pub nsdu: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    /// number of hops the frame travels, 0 for twice nwkMaxDepth
+    pub radius: u8,
+    /// number of hops a multicast frame travels through non-members,
+    /// 0x07 for an infinite number
+    pub non_member_radius: u8,
+    /// routes to the destination may be discovered
+    pub discover_route: bool,
+    /// the frame is secured with the network key
+    pub security_enable: bool,
+}
+
+/// 3.2.1.2 - NLDE-DATA.confirm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NldeDataConfirm {
+    /// the result of the request
+    pub status: NldeDataStatus,
+    /// the time the frame was passed to the MAC
+    pub tx_time: Duration,
+}
+
+/// Status of an NLDE-DATA.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NldeDataStatus {
+    /// the frame was sent or waits for a route to its destination
+    Success,
+    /// the device is not on a network, or the parameters are invalid
+    InvalidRequest,
+    /// no route to the destination is known or can be discovered
+    RouteError,
+    /// the frame does not fit into a MAC frame
+    FrameTooLong,
+    /// the next hop did not acknowledge the frame
+    NoAck,
+    /// the channel was busy
+    ChannelAccessFailure,
+    /// the outgoing frame counter is exhausted, secured frames are no longer
+    /// sent
+    CounterError,
+}
+
+impl From<TxStatus> for NldeDataStatus {
+    fn from(status: TxStatus) -> Self {
+        match status {
+            TxStatus::Sent | TxStatus::Acked { .. } => Self::Success,
+            TxStatus::NoAck => Self::NoAck,
+            TxStatus::ChannelAccessFailure => Self::ChannelAccessFailure,
+            TxStatus::FrameTooLong => Self::FrameTooLong,
+            TxStatus::CounterError => Self::CounterError,
+        }
+    }
+}
+
+/// 3.2.1.3 - NLDE-DATA.indication
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NldeDataIndication {
+    /// addressing mode of the destination
+    pub destination_address_mode: NldeAddressMode,
+    /// network address of this device, or the broadcast address or group the
+    /// frame was sent to
+    pub destination: ShortAddress,
+    /// network address of the device which originated the frame
+    pub source: ShortAddress,
+    /// the payload of the frame
+    pub nsdu: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    /// link quality of the frame received from the last hop
+    pub link_quality: u8,
+    /// the time the frame was received
+    pub rx_time: Duration,
+    /// the frame was secured with the network key
+    pub security_use: bool,
+}
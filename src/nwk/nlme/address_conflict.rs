@@ -0,0 +1,419 @@
+//! Address Conflicts
+//!
+//! Stochastically assigned network addresses are not guaranteed to be
+//! unique. A device learns the IEEE address behind a network address from
+//! device announcements, route replies and frames carrying the IEEE address
+//! of their originator, and detects a conflict when two devices use the same
+//! network address.
+//!
+//! A router which finds its own address in use picks a new one. Any other
+//! conflict is broadcast in a Network Status command, upon which the routers
+//! using the address pick a new one and the parents of end devices using it
+//! assign them a new one in an unsolicited Rejoin Response. The coordinator
+//! keeps its address.
+//!
+//! See Section 3.6.1.9.
+use super::broadcast::RX_ON_WHEN_IDLE_ADDRESS;
+use super::management::NlmeIndication;
+use super::management::NlmeNwkStatusIndication;
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::parse::PackBytes;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::command::AssociationStatus;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::nwk::address_map::AddressMapEntry;
+use crate::nwk::commands::NetworkStatus;
+use crate::nwk::commands::NetworkStatusCode;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::commands::RejoinResponse;
+use crate::nwk::commands::RouteReply;
+use crate::nwk::frame::CommandFrameIdentifier;
+use crate::nwk::frame::NwkFrame;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::radio::Radio;
+
+impl<R: Radio> Nlme<R> {
+    /// Records that the device with `extended_address` uses
+    /// `network_address`, and resolves a conflict with another device using
+    /// the same address.
+    ///
+    /// The ZDO passes the addresses of received device announcements.
+    /// Returns the indication of a new network address of this device.
+    ///
+    /// See Section 3.6.1.9.2.
+    pub(crate) async fn record_address(
+        &mut self,
+        network_address: ShortAddress,
+        extended_address: IeeeAddress,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        if self.device_type.is_none()
+            || network_address.0 >= MIN_BROADCAST_ADDRESS
+            || extended_address == self.mac.extended_address
+        {
+            return Ok(None);
+        }
+        let pan_id = self.mac.pan_id;
+        let other = |known: IeeeAddress| known != extended_address;
+        let conflict = network_address == self.mac.short_address
+            || self
+                .nib
+                .address_map
+                .extended_address(network_address)
+                .is_some_and(other)
+            || self
+                .nib
+                .neighbor_table
+                .get(pan_id, network_address)
+                .and_then(|neighbor| neighbor.extended_address)
+                .is_some_and(other);
+        if !conflict {
+            self.nib.address_map.insert(AddressMapEntry {
+                extended_address,
+                network_address,
+            });
+            return Ok(None);
+        }
+
+        self.nib.address_map.remove(network_address);
+        // a router resolves the conflict with its own address by itself
+        let own = network_address == self.mac.short_address;
+        if !own || self.device_type != Some(LogicalType::Router) {
+            self.report_address_conflict(network_address).await?;
+        }
+        self.resolve_address_conflict(network_address).await
+    }
+
+    /// Records the addresses of the originator of a received frame which
+    /// carries its IEEE address.
+    pub(super) async fn record_originator(
+        &mut self,
+        frame: &NwkFrame,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let header = match frame {
+            NwkFrame::Data(frame) => &frame.header,
+            // a rejoining device is assigned an address by its parent
+            NwkFrame::NwkCommand(frame)
+                if frame.command_identifier != CommandFrameIdentifier::RejoinRequest =>
+            {
+                &frame.header
+            }
+            _ => return Ok(None),
+        };
+        match header.source_ieee {
+            Some(extended_address) => self.record_address(header.source, extended_address).await,
+            None => Ok(None),
+        }
+    }
+
+    /// Records the IEEE addresses of the originator and the responder of a
+    /// route reply.
+    pub(super) async fn record_reply_addresses(
+        &mut self,
+        reply: &RouteReply,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let addresses = [
+            (reply.originator_address, reply.originator_ieee_address),
+            (reply.responder_address, reply.responder_ieee_address),
+        ];
+        for (network_address, extended_address) in addresses {
+            let Some(extended_address) = extended_address else {
+                continue;
+            };
+            if let Some(indication) = self
+                .record_address(network_address, extended_address)
+                .await?
+            {
+                return Ok(Some(indication));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Broadcasts a Network Status command reporting that `network_address`
+    /// is used by multiple devices.
+    ///
+    /// See Section 3.6.1.9.3.
+    async fn report_address_conflict(
+        &mut self,
+        network_address: ShortAddress,
+    ) -> Result<(), R::Error> {
+        let command = NwkCommand::NetworkStatus(NetworkStatus {
+            status_code: NetworkStatusCode::AddressConflict,
+            destination_address: network_address,
+        });
+        let mut payload = [0u8; MAX_PHY_PACKET_SIZE];
+        let Ok(len) = command.pack_into(&mut payload) else {
+            return Ok(());
+        };
+        let header = self.command_header(RX_ON_WHEN_IDLE_ADDRESS, 2 * self.nib.max_depth);
+        self.send_broadcast(header, &payload[..len], true).await?;
+        Ok(())
+    }
+
+    /// Gives up `network_address` if this device or one of its end device
+    /// children uses it.
+    ///
+    /// Returns the indication of a new network address of this device.
+    ///
+    /// See Section 3.6.1.9.3.
+    pub(super) async fn resolve_address_conflict(
+        &mut self,
+        network_address: ShortAddress,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        self.nib.address_map.remove(network_address);
+        if network_address == self.mac.short_address {
+            if self.device_type != Some(LogicalType::Router) {
+                // end devices are assigned a new address by their parent
+                return Ok(None);
+            }
+            let address = self.stochastic_address();
+            return self.change_address(address).await.map(Some);
+        }
+
+        let pan_id = self.mac.pan_id;
+        let Some(extended_address) = self
+            .nib
+            .neighbor_table
+            .children()
+            .find(|child| {
+                child.pan_id == pan_id
+                    && child.network_address == network_address
+                    && !child.is_router()
+            })
+            .and_then(|child| child.extended_address)
+        else {
+            return Ok(None);
+        };
+        let address = self.stochastic_address();
+        if let Some(child) = self
+            .nib
+            .neighbor_table
+            .get_mut_by_extended_address(extended_address)
+        {
+            child.network_address = address;
+        }
+        self.nib.address_map.insert(AddressMapEntry {
+            extended_address,
+            network_address: address,
+        });
+        let response = NwkCommand::RejoinResponse(RejoinResponse {
+            network_address: address,
+            rejoin_status: AssociationStatus::Success,
+        });
+        self.send_command(network_address, Some(extended_address), &response, true)
+            .await?;
+        Ok(None)
+    }
+
+    /// Takes the network address assigned by the parent in an unsolicited
+    /// Rejoin Response received from the neighbor `sender`.
+    ///
+    /// See Section 3.6.1.9.3.
+    pub(super) async fn receive_new_address(
+        &mut self,
+        header: &NwkHeader,
+        response: RejoinResponse,
+        secured: bool,
+        sender: ShortAddress,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let from_parent = self
+            .nib
+            .neighbor_table
+            .parent()
+            .is_some_and(|parent| parent.network_address == sender);
+        if self.device_type != Some(LogicalType::EndDevice)
+            || !from_parent
+            || header.source != sender
+            || header.destination_ieee != Some(self.mac.extended_address)
+            || (self.nib.network_key.is_some() && !secured)
+            || response.rejoin_status != AssociationStatus::Success
+            || response.network_address.0 >= MIN_BROADCAST_ADDRESS
+        {
+            return Ok(None);
+        }
+        self.change_address(response.network_address)
+            .await
+            .map(Some)
+    }
+
+    /// Continues operating with `network_address`.
+    async fn change_address(
+        &mut self,
+        network_address: ShortAddress,
+    ) -> Result<NlmeIndication, R::Error> {
+        self.mac
+            .start(
+                self.mac.channel,
+                self.mac.pan_id,
+                network_address,
+                self.mac.pan_coordinator,
+            )
+            .await?;
+        self.nib.network_address = network_address;
+        Ok(NlmeIndication::NetworkStatus(NlmeNwkStatusIndication {
+            status: NetworkStatusCode::NetworkAddressUpdate,
+            network_address: network_address.0,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use heapless::Vec;
+
+    use super::*;
+    use crate::mac::frame::Address;
+    use crate::nwk::commands::LinkStatus;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nlme::routing::ROUTERS_ADDRESS;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+    use crate::radio::RxFrame;
+
+    /// Empty link status of the router 0x0001 claiming `extended_address`.
+    fn link_status_frame(extended_address: u64) -> RxFrame {
+        let mut header = nwk_command_header(ROUTERS_ADDRESS, 0x0001, 1);
+        header.source_ieee = Some(IeeeAddress(extended_address));
+        let command = NwkCommand::LinkStatus(LinkStatus {
+            first_frame: true,
+            last_frame: true,
+            entries: Vec::new(),
+        });
+        command_frame(header, &command, true)
+    }
+
+    #[test]
+    fn address_used_by_two_devices_is_reported() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::None));
+        nlme.mac.radio.received.push_back(link_status_frame(0x0001));
+        nlme.mac.radio.received.push_back(link_status_frame(0x0002));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.destination, RX_ON_WHEN_IDLE_ADDRESS);
+        let Ok(NwkCommand::NetworkStatus(status)) = frame.command() else {
+            unreachable!("no network status sent");
+        };
+        assert_eq!(status.status_code, NetworkStatusCode::AddressConflict);
+        assert_eq!(status.destination_address, ShortAddress(0x0001));
+        assert_eq!(
+            nlme.nib.address_map.extended_address(ShortAddress(0x0001)),
+            None
+        );
+    }
+
+    #[test]
+    fn router_gives_up_address_used_by_another_device() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::Router);
+        nlme.mac.short_address = ShortAddress(0x0001);
+        nlme.mac.radio.random = 0x0002;
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0002, Relationship::Parent));
+        nlme.mac.radio.received.push_back(link_status_frame(0x0003));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            indication,
+            Some(NlmeIndication::NetworkStatus(NlmeNwkStatusIndication {
+                status: NetworkStatusCode::NetworkAddressUpdate,
+                network_address: 0x0003,
+            }))
+        );
+        assert_eq!(nlme.mac.short_address, ShortAddress(0x0003));
+        assert_eq!(nlme.nib.network_address, ShortAddress(0x0003));
+        // the conflict is not reported
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+
+    #[test]
+    fn parent_assigns_new_address_to_end_device_in_conflict() {
+        let mut nlme = parent();
+        nlme.mac.radio.random = 0x1234;
+        let mut child = router(0x796f, Relationship::Child);
+        child.extended_address = Some(IeeeAddress(0x0001));
+        child.device_type = LogicalType::EndDevice;
+        let _ = nlme.nib.neighbor_table.insert(child);
+        let command = NwkCommand::NetworkStatus(NetworkStatus {
+            status_code: NetworkStatusCode::AddressConflict,
+            destination_address: ShortAddress(0x796f),
+        });
+        let header = nwk_command_header(RX_ON_WHEN_IDLE_ADDRESS, 0x0002, 30);
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &command, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        let (response, secured) = rejoin_response(&nlme);
+        assert!(secured);
+        assert_eq!(response.rejoin_status, AssociationStatus::Success);
+        assert_eq!(response.network_address, ShortAddress(0x1234));
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get_by_extended_address(IeeeAddress(0x0001))
+            .unwrap();
+        assert_eq!(child.network_address, ShortAddress(0x1234));
+    }
+
+    #[test]
+    fn end_device_takes_address_assigned_by_its_parent() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::EndDevice);
+        nlme.mac.short_address = ShortAddress(0x796f);
+        nlme.mac.extended_address = IeeeAddress(0x0001);
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0002, Relationship::Parent));
+        let response = NwkCommand::RejoinResponse(RejoinResponse {
+            network_address: ShortAddress(0x1234),
+            rejoin_status: AssociationStatus::Success,
+        });
+        let mut header = nwk_command_header(ShortAddress(0x796f), 0x0002, 1);
+        header.frame_control.set_destination_ieee_flag(true);
+        header.destination_ieee = Some(IeeeAddress(0x0001));
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header.clone(), &response, false));
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &response, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        // the unsecured response is ignored
+        assert_eq!(
+            indication,
+            Some(NlmeIndication::NetworkStatus(NlmeNwkStatusIndication {
+                status: NetworkStatusCode::NetworkAddressUpdate,
+                network_address: 0x1234,
+            }))
+        );
+        assert_eq!(nlme.mac.short_address, ShortAddress(0x1234));
+        assert_eq!(nlme.mac.radio.received.len(), 0);
+    }
+}
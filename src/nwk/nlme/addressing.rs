@@ -0,0 +1,222 @@
+//! Address Assignment
+//!
+//! Parents assign the network addresses of joining devices according to
+//! nwkAddrAlloc. Stochastic addresses are drawn at random and may conflict
+//! with devices out of reach, which is resolved later. Distributed addresses
+//! are taken from the address block of the parent, which is split into a
+//! sub-block of Cskip(d) addresses for every router child followed by a
+//! single address for every end device child.
+//!
+//! See Sections 3.6.1.6 and 3.6.1.7.
+use core::convert::TryFrom;
+
+use super::Nlme;
+use super::COORDINATOR_ADDRESS;
+use crate::common::types::ShortAddress;
+use crate::nwk::nib::AddressAllocation;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::radio::Radio;
+
+/// The size of the address sub-block of every router child of a parent at
+/// `depth`, Cskip(d).
+///
+/// Returns `None` if the parent may not have children or the tree does not
+/// fit into the address space.
+///
+/// See Section 3.6.1.6.
+pub(super) fn cskip(depth: u8, max_children: u8, max_routers: u8, max_depth: u8) -> Option<u16> {
+    if depth >= max_depth {
+        return None;
+    }
+    let (children, routers) = (i64::from(max_children), i64::from(max_routers));
+    let exponent = u32::from(max_depth - depth - 1);
+    let cskip = if routers == 1 {
+        1 + children * i64::from(exponent)
+    } else {
+        let subtree = children.checked_mul(routers.checked_pow(exponent)?)?;
+        (1 + children - routers - subtree) / (1 - routers)
+    };
+    u16::try_from(cskip).ok()
+}
+
+impl<R: Radio> Nlme<R> {
+    /// The network address of a joining router or end device according to
+    /// nwkAddrAlloc.
+    ///
+    /// Returns `None` if no address is left.
+    pub(super) fn allocate_address(&mut self, router: bool) -> Option<ShortAddress> {
+        match self.nib.addr_alloc {
+            AddressAllocation::Stochastic => Some(self.stochastic_address()),
+            AddressAllocation::Distributed => self.tree_address(router),
+            AddressAllocation::Reserved => None,
+        }
+    }
+
+    /// A random network address which is not used by a known device.
+    ///
+    /// See Section 3.6.1.7.
+    pub(super) fn stochastic_address(&mut self) -> ShortAddress {
+        let random = self.mac.radio.random() % u32::from(MIN_BROADCAST_ADDRESS);
+        let mut address = ShortAddress(u16::try_from(random).unwrap_or_default());
+        while address.0 == COORDINATOR_ADDRESS || self.is_address_in_use(address) {
+            address = ShortAddress((address.0 + 1) % MIN_BROADCAST_ADDRESS);
+        }
+        address
+    }
+
+    /// The first free address for a router or end device child in the
+    /// address block of this device.
+    ///
+    /// See Section 3.6.1.6.
+    fn tree_address(&self, router: bool) -> Option<ShortAddress> {
+        let nib = &self.nib;
+        let cskip = u32::from(cskip(
+            self.depth,
+            nib.max_children,
+            nib.max_routers,
+            nib.max_depth,
+        )?);
+        let (children, routers) = (u32::from(nib.max_children), u32::from(nib.max_routers));
+        let parent = u32::from(self.mac.short_address.0);
+        let end_devices = parent + cskip * routers;
+        // the addresses of all children must be below the broadcast addresses
+        if end_devices + children.saturating_sub(routers) >= u32::from(MIN_BROADCAST_ADDRESS) {
+            return None;
+        }
+        let (first, step, count) = if router {
+            (parent + 1, cskip, routers)
+        } else {
+            (end_devices + 1, 1, children.saturating_sub(routers))
+        };
+        (0..count).find_map(|n| {
+            let address = ShortAddress(u16::try_from(first + step * n).ok()?);
+            (!self.is_address_in_use(address)).then_some(address)
+        })
+    }
+
+    /// `address` is used by this device, a neighbor or a device in the
+    /// address map.
+    fn is_address_in_use(&self, address: ShortAddress) -> bool {
+        address == self.mac.short_address
+            || self
+                .nib
+                .neighbor_table
+                .get(self.mac.pan_id, address)
+                .is_some()
+            || self.nib.address_map.extended_address(address).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::apl::descriptors::node_descriptor::LogicalType;
+    use crate::mac::command::AssociationStatus;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nib::MAX_DEPTH;
+    use crate::nwk::nlme::management::NlmeIndication;
+    use crate::nwk::nlme::management::RejoinNetwork;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+
+    #[test]
+    fn cskip_splits_the_address_block_of_a_parent() {
+        assert_eq!(cskip(0, 6, 4, 3), Some(31));
+        assert_eq!(cskip(1, 6, 4, 3), Some(7));
+        assert_eq!(cskip(2, 6, 4, 3), Some(1));
+        assert_eq!(cskip(3, 6, 4, 3), None);
+        assert_eq!(cskip(0, 6, 1, 3), Some(13));
+        // the default tree does not fit into the address space
+        assert_eq!(cskip(0, 20, 5, MAX_DEPTH), None);
+    }
+
+    #[test]
+    fn parent_assigns_stochastic_address_to_associating_device() {
+        let mut nlme = parent();
+        nlme.mac.radio.random = 0x796f;
+        associate(&mut nlme, 0x0001, false);
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        // association is not permitted
+        assert_eq!(indication, None);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+
+        nlme.mac.association_permit = true;
+        associate(&mut nlme, 0x0001, false);
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Join(indication)) = indication else {
+            unreachable!("no join indication");
+        };
+        assert_eq!(indication.network_address, 0x796f);
+        assert_eq!(indication.extended_address, 0x0001);
+        assert_eq!(indication.rejoin_network, RejoinNetwork::Association);
+        let response = sent_association_response(&nlme, 0x0001);
+        assert_eq!(response.status, AssociationStatus::Success);
+        assert_eq!(response.short_address, ShortAddress(0x796f));
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.relationship, Relationship::UnauthenticatedChild);
+        assert_eq!(child.device_type, LogicalType::EndDevice);
+    }
+
+    #[test]
+    fn parent_assigns_tree_addresses_to_associating_devices() {
+        let mut nlme = parent();
+        nlme.mac.association_permit = true;
+        nlme.nib.addr_alloc = AddressAllocation::Distributed;
+        nlme.nib.max_children = 6;
+        nlme.nib.max_routers = 4;
+        nlme.nib.max_depth = 3;
+
+        for (extended_address, router, network_address) in [
+            (0x0001, true, 0x0001),
+            (0x0002, true, 0x0020),
+            (0x0003, false, 0x007d),
+            (0x0004, false, 0x007e),
+        ] {
+            associate(&mut nlme, extended_address, router);
+            let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+            let Some(NlmeIndication::Join(indication)) = indication else {
+                unreachable!("no join indication");
+            };
+            assert_eq!(indication.network_address, network_address);
+            let response = sent_association_response(&nlme, extended_address);
+            assert_eq!(response.short_address, ShortAddress(network_address));
+        }
+
+        // all end device addresses are assigned
+        associate(&mut nlme, 0x0005, false);
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        let response = sent_association_response(&nlme, 0x0005);
+        assert_eq!(response.status, AssociationStatus::PanAtCapacity);
+    }
+
+    #[test]
+    fn rejoin_with_tree_addressing_assigns_address_of_the_block() {
+        let mut nlme = parent();
+        nlme.nib.addr_alloc = AddressAllocation::Distributed;
+        nlme.nib.max_children = 6;
+        nlme.nib.max_routers = 4;
+        nlme.nib.max_depth = 3;
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Join(indication)) = indication else {
+            unreachable!("no join indication");
+        };
+        assert_eq!(indication.network_address, 0x0001);
+        let (response, _) = rejoin_response(&nlme);
+        assert_eq!(response.network_address, ShortAddress(0x0001));
+    }
+}
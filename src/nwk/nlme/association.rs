@@ -0,0 +1,206 @@
+//! Association of Children
+//!
+//! A parent permitting association admits a joining device with a network
+//! address assigned according to nwkAddrAlloc, as long as nwkMaxChildren and
+//! nwkMaxRouters leave room for it. The association response is kept until
+//! the device polls for it, at most macTransactionPersistenceTime.
+//!
+//! See Section 3.6.1.4.1.2.
+
+use core::time::Duration;
+
+use super::management::NlmeIndication;
+use super::management::NlmeJoinIndication;
+use super::management::RejoinNetwork;
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::apl::descriptors::node_descriptor::MacCapabilityFlag;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::command::AssociationResponse;
+use crate::mac::command::AssociationStatus;
+use crate::mac::frame::BROADCAST;
+use crate::nwk::neighbor::Relationship;
+use crate::nwk::nib::AddressAllocation;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// Maximum number of association responses waiting to be polled.
+pub(super) const MAX_PENDING_ASSOCIATIONS: usize = 4;
+
+/// aBaseSuperframeDuration, the unit of macTransactionPersistenceTime.
+pub(super) const BASE_SUPERFRAME_DURATION: Duration = Duration::from_micros(15_360);
+
+/// An association response waiting for the device to poll for it.
+pub(super) struct PendingAssociation {
+    extended_address: IeeeAddress,
+    capability_information: MacCapabilities,
+    response: AssociationResponse,
+    /// Time at which the response is discarded.
+    expiration: Duration,
+}
+
+impl<R: Radio> Nlme<R> {
+    /// This device may accept another router or end device child according
+    /// to nwkMaxChildren and nwkMaxRouters.
+    ///
+    /// With distributed addressing the end devices are limited to the
+    /// children which are not routers.
+    pub(super) fn has_capacity(&self, router: bool) -> bool {
+        let pan_id = self.mac.pan_id;
+        let (children, routers) = self
+            .nib
+            .neighbor_table
+            .children()
+            .filter(|child| child.pan_id == pan_id)
+            .fold((0u8, 0u8), |(children, routers), child| {
+                (
+                    children.saturating_add(1),
+                    routers.saturating_add(u8::from(child.is_router())),
+                )
+            });
+        let nib = &self.nib;
+        if children >= nib.max_children {
+            return false;
+        }
+        if router {
+            routers < nib.max_routers
+        } else {
+            nib.addr_alloc != AddressAllocation::Distributed
+                || children - routers < nib.max_children.saturating_sub(nib.max_routers)
+        }
+    }
+
+    /// Admits the device `extended_address` as child and keeps the
+    /// association response until the device polls for it.
+    ///
+    /// A device which is already a child keeps its network address.
+    pub(super) fn receive_association_request(
+        &mut self,
+        extended_address: IeeeAddress,
+        capability_information: MacCapabilities,
+        lqi: u8,
+    ) {
+        if !self.router_started || !self.mac.association_permit {
+            return;
+        }
+        let now = self.mac.radio.now();
+        self.associations.retain(|association| {
+            association.expiration > now && association.extended_address != extended_address
+        });
+        if self.associations.is_full() {
+            return;
+        }
+        let router = capability_information.is_set(MacCapabilityFlag::DeviceType);
+        let known = self
+            .nib
+            .neighbor_table
+            .get_by_extended_address(extended_address)
+            .filter(|neighbor| neighbor.pan_id == self.mac.pan_id && neighbor.is_child())
+            .map(|child| child.network_address);
+        let admitted = match known {
+            Some(network_address) => Some(network_address),
+            None if self.has_capacity(router) => self.allocate_address(router),
+            None => None,
+        }
+        .filter(|network_address| {
+            let child = self.child(
+                extended_address,
+                *network_address,
+                capability_information,
+                Relationship::UnauthenticatedChild,
+                lqi,
+            );
+            self.nib.neighbor_table.insert(child).is_ok()
+        });
+
+        let response = AssociationResponse {
+            short_address: admitted.unwrap_or(ShortAddress(BROADCAST)),
+            status: if admitted.is_some() {
+                AssociationStatus::Success
+            } else {
+                AssociationStatus::PanAtCapacity
+            },
+        };
+        let persistence =
+            BASE_SUPERFRAME_DURATION * u32::from(self.nib.transaction_persistence_time);
+        let _ = self.associations.push(PendingAssociation {
+            extended_address,
+            capability_information,
+            response,
+            expiration: now + persistence,
+        });
+    }
+
+    /// Sends the association response to the device `extended_address`
+    /// polling for it.
+    ///
+    /// Returns the join indication of a device which received the network
+    /// address assigned to it. The child is removed again if it did not
+    /// acknowledge the response.
+    pub(super) async fn send_association_response(
+        &mut self,
+        extended_address: IeeeAddress,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let now = self.mac.radio.now();
+        let Some(index) = self.associations.iter().position(|association| {
+            association.expiration > now && association.extended_address == extended_address
+        }) else {
+            return Ok(None);
+        };
+        let association = self.associations.swap_remove(index);
+        let response = association.response;
+        let status = self
+            .mac
+            .send_association_response(extended_address, response)
+            .await?;
+        if response.status != AssociationStatus::Success {
+            return Ok(None);
+        }
+        if !matches!(status, TxStatus::Acked { .. }) {
+            self.nib
+                .neighbor_table
+                .remove(self.mac.pan_id, response.short_address);
+            return Ok(None);
+        }
+        Ok(Some(NlmeIndication::Join(NlmeJoinIndication {
+            network_address: response.short_address.0,
+            extended_address: extended_address.0,
+            capability_information: association.capability_information,
+            rejoin_network: RejoinNetwork::Association,
+            secure_rejoin: false,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+
+    #[test]
+    fn parent_without_capacity_refuses_association() {
+        let mut nlme = parent();
+        nlme.mac.association_permit = true;
+        nlme.mac.radio.random = 0x796f;
+        nlme.nib.max_routers = 1;
+        associate(&mut nlme, 0x0001, true);
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        assert!(matches!(indication, Some(NlmeIndication::Join(_))));
+
+        associate(&mut nlme, 0x0002, true);
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        let response = sent_association_response(&nlme, 0x0002);
+        assert_eq!(response.status, AssociationStatus::PanAtCapacity);
+        let payload = nlme.beacon_payload();
+        assert!(!payload.router_capacity);
+        assert!(payload.end_device_capacity);
+
+        nlme.nib.max_children = 1;
+
+        assert!(!nlme.beacon_payload().end_device_capacity);
+    }
+}
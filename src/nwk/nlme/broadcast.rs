@@ -0,0 +1,424 @@
+//! Broadcast Delivery
+//!
+//! Broadcasts are delivered to the devices of their address class and
+//! relayed once by every router after a random jitter. A router repeats the
+//! broadcast until it heard all its router neighbors relay it, up to
+//! nwkMaxBroadcastRetries times.
+//!
+//! Route requests are not acknowledged passively, they are repeated a fixed
+//! number of times instead.
+//!
+//! See Sections 3.6.3.5 and 3.6.5.
+
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::routing::ROUTERS_ADDRESS;
+use super::Nlme;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::Address;
+use crate::mac::frame::BROADCAST;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::nwk::broadcast::BroadcastTransaction;
+use crate::nwk::frame::NwkHeader;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// nwkcMaxBroadcastJitter in milliseconds
+const MAX_BROADCAST_JITTER: u32 = 64;
+
+/// nwkcMinRREQJitter in 2 ms slots
+const MIN_RREQ_JITTER: u32 = 0x01;
+
+/// nwkcMaxRREQJitter in 2 ms slots
+const MAX_RREQ_JITTER: u32 = 0x40;
+
+/// nwkcRREQRetryInterval
+const RREQ_RETRY_INTERVAL: Duration = Duration::from_millis(0xfe);
+
+/// Broadcast address of all devices with the receiver on when idle.
+pub(super) const RX_ON_WHEN_IDLE_ADDRESS: ShortAddress = ShortAddress(0xfffd);
+
+/// Maximum number of broadcasts waiting to be relayed.
+pub(super) const MAX_REBROADCASTS: usize = 4;
+
+/// A broadcast relayed by this device.
+pub(super) struct Rebroadcast {
+    header: NwkHeader,
+    payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    secure: bool,
+    /// Time of the next transmission.
+    due: Duration,
+    /// Number of transmissions so far.
+    transmissions: u8,
+    /// Number of retries of a route request, `None` for broadcasts which are
+    /// repeated until acknowledged passively.
+    route_request_retries: Option<u8>,
+}
+
+impl<R: Radio> Nlme<R> {
+    /// The device is addressed by the broadcast address `destination`.
+    pub(super) fn is_broadcast_member(&self, destination: ShortAddress) -> bool {
+        match destination {
+            ShortAddress(BROADCAST) => true,
+            RX_ON_WHEN_IDLE_ADDRESS => self.mac.rx_on_when_idle,
+            ROUTERS_ADDRESS => self.is_router(),
+            // low power routers and reserved addresses
+            _ => false,
+        }
+    }
+
+    /// Records a broadcast received from the neighbor `sender` and schedules
+    /// routers to relay it after a random jitter.
+    ///
+    /// Returns `false` for a broadcast which was already received or
+    /// originated by this device, a neighbor relaying it is recorded as its
+    /// passive acknowledgement.
+    pub(super) fn receive_broadcast(
+        &mut self,
+        header: &NwkHeader,
+        payload: &[u8],
+        secured: bool,
+        sender: Option<ShortAddress>,
+    ) -> bool {
+        let (source, sequence_number) = (header.source, header.sequence_number);
+        let table = &mut self.nib.broadcast_transaction_table;
+        if table.get(source, sequence_number).is_some() {
+            if let Some(sender) = sender {
+                table.passive_ack(source, sequence_number, sender);
+            }
+            return false;
+        }
+        if source == self.mac.short_address {
+            return false;
+        }
+        let now = self.mac.radio.now();
+        let transaction = BroadcastTransaction {
+            source,
+            sequence_number,
+            expiration: now + self.nib.network_broadcast_delivery_time,
+            passive_acks: sender.into_iter().collect(),
+        };
+        if table.insert(transaction).is_err() {
+            return false;
+        }
+
+        if self.is_router() && header.radius > 1 {
+            let mut header = header.clone();
+            header.radius -= 1;
+            let jitter = self.mac.radio.random() % MAX_BROADCAST_JITTER;
+            let due = now + Duration::from_millis(u64::from(jitter));
+            self.schedule_rebroadcast(header, payload, secured, due, 0, None);
+        }
+        true
+    }
+
+    /// Broadcasts a frame originated by this device.
+    ///
+    /// Routers repeat it like a relayed broadcast until all router neighbors
+    /// were heard relaying it.
+    ///
+    /// See Section 3.6.5.
+    pub(super) async fn send_broadcast(
+        &mut self,
+        header: NwkHeader,
+        payload: &[u8],
+        secure: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let now = self.mac.radio.now();
+        let transaction = BroadcastTransaction {
+            source: header.source,
+            sequence_number: header.sequence_number,
+            expiration: now + self.nib.network_broadcast_delivery_time,
+            passive_acks: Vec::new(),
+        };
+        // the relays of the own broadcast are recorded as passive acks
+        let recorded = self
+            .nib
+            .broadcast_transaction_table
+            .insert(transaction)
+            .is_ok();
+        if recorded && self.is_router() {
+            let due = now + self.nib.passive_ack_timeout;
+            self.schedule_rebroadcast(header.clone(), payload, secure, due, 1, None);
+        }
+        self.send_frame(Address::BROADCAST, header, payload, secure)
+            .await
+    }
+
+    /// Broadcasts a route request originated by this device and repeats it
+    /// `retries` times after nwkcRREQRetryInterval.
+    ///
+    /// See Section 3.6.3.5.1.
+    pub(super) async fn broadcast_route_request(
+        &mut self,
+        header: NwkHeader,
+        payload: &[u8],
+        retries: u8,
+    ) -> Result<TxStatus, R::Error> {
+        let due = self.mac.radio.now() + RREQ_RETRY_INTERVAL;
+        self.schedule_rebroadcast(header.clone(), payload, true, due, 1, Some(retries));
+        self.send_frame(Address::BROADCAST, header, payload, true)
+            .await
+    }
+
+    /// Relays a route request after a random jitter and repeats it `retries`
+    /// times after nwkcRREQRetryInterval.
+    ///
+    /// A request relayed again, because it arrived over a cheaper path,
+    /// replaces the one waiting to be relayed.
+    ///
+    /// See Section 3.6.3.5.2.
+    pub(super) fn relay_route_request(&mut self, header: NwkHeader, payload: &[u8], retries: u8) {
+        let slots =
+            MIN_RREQ_JITTER + self.mac.radio.random() % (MAX_RREQ_JITTER - MIN_RREQ_JITTER + 1);
+        let due = self.mac.radio.now() + Duration::from_millis(2 * u64::from(slots));
+        self.schedule_rebroadcast(header, payload, true, due, 0, Some(retries));
+    }
+
+    /// Schedules the broadcast of `header` and `payload`, sent
+    /// `transmissions` times so far, at `due`, replacing a scheduled
+    /// transmission of the same frame.
+    ///
+    /// Broadcasts are not relayed while the buffer is full.
+    fn schedule_rebroadcast(
+        &mut self,
+        header: NwkHeader,
+        payload: &[u8],
+        secure: bool,
+        due: Duration,
+        transmissions: u8,
+        route_request_retries: Option<u8>,
+    ) {
+        let Ok(payload) = Vec::from_slice(payload) else {
+            return;
+        };
+        self.rebroadcasts.retain(|rebroadcast| {
+            rebroadcast.header.source != header.source
+                || rebroadcast.header.sequence_number != header.sequence_number
+        });
+        let _ = self.rebroadcasts.push(Rebroadcast {
+            header,
+            payload,
+            secure,
+            due,
+            transmissions,
+            route_request_retries,
+        });
+    }
+
+    /// The time the next broadcast is relayed.
+    pub(super) fn next_rebroadcast(&self) -> Option<Duration> {
+        self.rebroadcasts
+            .iter()
+            .map(|rebroadcast| rebroadcast.due)
+            .min()
+    }
+
+    /// Relays the broadcasts which are due.
+    ///
+    /// A broadcast is repeated after nwkPassiveAckTimeout until all router
+    /// neighbors were heard relaying it, a route request after
+    /// nwkcRREQRetryInterval until its retries are used up.
+    pub(super) async fn maintain_broadcasts(&mut self) -> Result<(), R::Error> {
+        let now = self.mac.radio.now();
+        self.nib.broadcast_transaction_table.expire(now);
+        while let Some(index) = self
+            .rebroadcasts
+            .iter()
+            .position(|rebroadcast| rebroadcast.due <= now)
+        {
+            let rebroadcast = &self.rebroadcasts[index];
+            let done = match rebroadcast.route_request_retries {
+                Some(retries) => rebroadcast.transmissions > retries,
+                None => {
+                    rebroadcast.transmissions > 0
+                        && (rebroadcast.transmissions > self.nib.max_broadcast_retries
+                            || self.is_acknowledged(&rebroadcast.header))
+                }
+            };
+            if done {
+                self.rebroadcasts.remove(index);
+                continue;
+            }
+            let header = rebroadcast.header.clone();
+            let payload = rebroadcast.payload.clone();
+            let secure = rebroadcast.secure;
+            let retry_interval = if rebroadcast.route_request_retries.is_some() {
+                RREQ_RETRY_INTERVAL
+            } else {
+                self.nib.passive_ack_timeout
+            };
+            let rebroadcast = &mut self.rebroadcasts[index];
+            rebroadcast.transmissions += 1;
+            rebroadcast.due = now + retry_interval;
+            self.send_frame(Address::BROADCAST, header, &payload, secure)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// All router neighbors were heard relaying the broadcast of `header`.
+    fn is_acknowledged(&self, header: &NwkHeader) -> bool {
+        let Some(transaction) = self
+            .nib
+            .broadcast_transaction_table
+            .get(header.source, header.sequence_number)
+        else {
+            return true;
+        };
+        self.nib
+            .neighbor_table
+            .routers(self.mac.pan_id)
+            .all(|router| transaction.passive_acks.contains(&router.network_address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apl::descriptors::node_descriptor::LogicalType;
+    use crate::common::parse::PackBytes;
+    use crate::nwk::commands::ManyToOne;
+    use crate::nwk::commands::NwkCommand;
+    use crate::nwk::frame::FrameControl;
+    use crate::nwk::frame::FrameTypeIdentifier;
+    use crate::nwk::frame::NwkDataFrame;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nlme::management::NlmeIndication;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+    use crate::radio::RxFrame;
+
+    #[test]
+    fn route_request_is_retried() {
+        let mut nlme = parent();
+        block_on(nlme.route_frame(data_header(0x1234, 5), &[1, 2, 3], true)).unwrap();
+
+        block_on(nlme.process(Duration::from_secs(1))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 4);
+        for index in 0..4 {
+            let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, index) else {
+                unreachable!("no command frame sent");
+            };
+            assert_eq!(destination, Some(Address::BROADCAST));
+            let Ok(NwkCommand::RouteRequest(request)) = frame.command() else {
+                unreachable!("no route request sent");
+            };
+            assert_eq!(request.destination_address, ShortAddress(0x1234));
+        }
+    }
+
+    #[test]
+    fn relayed_route_request_is_jittered_and_retried() {
+        let mut nlme = parent();
+        nlme.mac.radio.random = 9;
+        nlme.mac
+            .radio
+            .received
+            .push_back(route_request(ManyToOne::No, 0x1234, 3));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        assert!(nlme.mac.radio.transmitted.is_empty());
+
+        block_on(nlme.process(Duration::from_secs(1))).unwrap();
+        assert_eq!(nlme.mac.radio.transmitted.len(), 3);
+        let (_, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 2) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(frame.header.source, ShortAddress(0x796f));
+        assert!(matches!(frame.command(), Ok(NwkCommand::RouteRequest(_))));
+    }
+
+    /// Broadcast data frame `sequence_number` of 0x796f to `destination`,
+    /// relayed by the neighbor `sender`.
+    fn broadcast_frame(destination: u16, sequence_number: u8, sender: u16) -> RxFrame {
+        let mut header = data_header(destination, 3);
+        header.frame_control = FrameControl::new(FrameTypeIdentifier::Data);
+        header.sequence_number = sequence_number;
+        let frame = NwkFrame::Data(NwkDataFrame {
+            header,
+            payload: Vec::from_slice(&[1, 2, 3]).unwrap(),
+        });
+        mac_frame(
+            ShortAddress(BROADCAST),
+            ShortAddress(sender),
+            frame.pack_to_vec().unwrap(),
+        )
+    }
+
+    #[test]
+    fn router_relays_broadcast_until_neighbors_relayed_it() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::None));
+        nlme.mac
+            .radio
+            .received
+            .push_back(broadcast_frame(BROADCAST, 1, 0x796f));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Data(indication)) = indication else {
+            unreachable!("no data indication");
+        };
+        assert_eq!(indication.destination, ShortAddress(BROADCAST));
+        assert_eq!(indication.source, ShortAddress(0x796f));
+        assert_eq!(indication.nsdu, [1, 2, 3]);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+
+        // relayed and repeated after nwkPassiveAckTimeout
+        block_on(nlme.process(Duration::from_millis(600))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.source, ShortAddress(0x796f));
+        assert_eq!(frame.header.radius, 2);
+
+        nlme.mac
+            .radio
+            .received
+            .push_back(broadcast_frame(BROADCAST, 1, 0x0001));
+        let indication = block_on(nlme.process(Duration::from_secs(1))).unwrap();
+
+        assert_eq!(indication, None);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        assert!(nlme.rebroadcasts.is_empty());
+    }
+
+    #[test]
+    fn end_device_receives_broadcasts_of_its_class_once() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::EndDevice);
+        nlme.mac
+            .radio
+            .received
+            .push_back(broadcast_frame(ROUTERS_ADDRESS.0, 1, 0x796f));
+        nlme.mac
+            .radio
+            .received
+            .push_back(broadcast_frame(BROADCAST, 2, 0x796f));
+        nlme.mac
+            .radio
+            .received
+            .push_back(broadcast_frame(BROADCAST, 2, 0x0001));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Data(indication)) = indication else {
+            unreachable!("no data indication");
+        };
+        assert_eq!(indication.destination, ShortAddress(BROADCAST));
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        assert_eq!(indication, None);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+}
@@ -0,0 +1,223 @@
+//! Network Discovery
+//!
+//! A device discovers the networks operating around it through an active
+//! scan. Beacons of devices of the same network are merged into one network
+//! descriptor, and the routers answering with a Zigbee beacon are recorded
+//! as neighbors, the potential parents of a later join.
+//!
+//! See Section 3.6.1.3.
+use heapless::Vec;
+
+use super::management::NetworkDescriptor;
+use super::management::NlmeNetworkDiscoveryRequest;
+use super::management::NlmeNetworkDiscoveryStatus;
+use super::management::MAX_NETWORK_DESCRIPTORS;
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::parse::PackBytes;
+use crate::mac::frame::Address;
+use crate::mac::scan::PanDescriptor;
+use crate::mac::scan::MAX_SCAN_DURATION;
+use crate::nwk::beacon::BeaconPayload;
+use crate::nwk::beacon::ZIGBEE_PROTOCOL_ID;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::neighbor::Relationship;
+use crate::radio::Radio;
+
+impl<R: Radio> Nlme<R> {
+    /// Scans the requested channels for networks.
+    ///
+    /// Returns the descriptors of the discovered networks, at least one.
+    pub(super) async fn discover_networks(
+        &mut self,
+        request: &NlmeNetworkDiscoveryRequest,
+    ) -> Result<Vec<NetworkDescriptor, MAX_NETWORK_DESCRIPTORS>, NlmeNetworkDiscoveryStatus> {
+        let channels = request.scan_channels_list_structure;
+        if request.scan_duration > MAX_SCAN_DURATION || channels.is_empty() {
+            return Err(NlmeNetworkDiscoveryStatus::InvalidParameter);
+        }
+        let pans = self
+            .mac
+            .active_scan(channels, request.scan_duration)
+            .await
+            .map_err(|_| NlmeNetworkDiscoveryStatus::RadioError)?;
+
+        for pan in &pans {
+            self.record_neighbor(pan);
+        }
+        let mut network_descriptors: Vec<NetworkDescriptor, MAX_NETWORK_DESCRIPTORS> = Vec::new();
+        for descriptor in pans.iter().filter_map(network_descriptor) {
+            let known = network_descriptors.iter_mut().find(|network| {
+                network.extended_pan_id == descriptor.extended_pan_id
+                    && network.pan_id == descriptor.pan_id
+                    && network.logical_channel == descriptor.logical_channel
+            });
+            match known {
+                Some(network) => merge(network, &descriptor),
+                None => {
+                    // both lists have the same capacity
+                    let _ = network_descriptors.push(descriptor);
+                }
+            }
+        }
+        if network_descriptors.is_empty() {
+            return Err(NlmeNetworkDiscoveryStatus::NoBeacon);
+        }
+        Ok(network_descriptors)
+    }
+
+    /// Adds the sender of a Zigbee beacon to the neighbor table.
+    ///
+    /// See Section 3.6.1.3.
+    pub(super) fn record_neighbor(&mut self, pan: &PanDescriptor) {
+        let (Some(network), Address::Short(network_address)) =
+            (network_descriptor(pan), pan.address)
+        else {
+            return;
+        };
+        let superframe = pan.superframe_specification;
+        let device_type = if superframe.pan_coordinator() {
+            LogicalType::Coordinator
+        } else {
+            LogicalType::Router
+        };
+        let known = self.nib.neighbor_table.get(pan.pan_id, network_address);
+        let neighbor = Neighbor {
+            extended_address: known.and_then(|neighbor| neighbor.extended_address),
+            network_address,
+            device_type,
+            rx_on_when_idle: true,
+            relationship: known.map_or(Relationship::None, |neighbor| neighbor.relationship),
+            lqi: pan.lqi,
+            pan_id: pan.pan_id,
+            extended_pan_id: network.extended_pan_id,
+            logical_channel: pan.channel,
+            depth: network.device_depth,
+            permit_joining: network.permit_joining,
+            router_capacity: network.router_capacity,
+            end_device_capacity: network.end_device_capacity,
+            potential_parent: true,
+            update_id: network.update_id,
+            outgoing_cost: known.map_or(0, |neighbor| neighbor.outgoing_cost),
+            age: known.map_or(0, |neighbor| neighbor.age),
+            device_timeout: None,
+            timeout_expiry: None,
+            keepalive: None,
+            incoming_frame_counter: known.and_then(|neighbor| neighbor.incoming_frame_counter),
+        };
+        // beacons are ignored if the table is full of related devices
+        let _ = self.nib.neighbor_table.insert(neighbor);
+    }
+}
+
+/// Builds the network descriptor of a beacon carrying a Zigbee beacon
+/// payload.
+pub(super) fn network_descriptor(pan: &PanDescriptor) -> Option<NetworkDescriptor> {
+    let payload = BeaconPayload::unpack_from_slice(&pan.payload).ok()?;
+    if payload.protocol_id != ZIGBEE_PROTOCOL_ID {
+        return None;
+    }
+    let superframe = pan.superframe_specification;
+    Some(NetworkDescriptor {
+        extended_pan_id: payload.extended_pan_id,
+        pan_id: pan.pan_id,
+        update_id: payload.update_id,
+        logical_channel: pan.channel,
+        stack_profile: payload.stack_profile,
+        zigbee_version: payload.protocol_version,
+        beacon_order: superframe.beacon_order(),
+        superframe_order: superframe.superframe_order(),
+        permit_joining: superframe.association_permit(),
+        router_capacity: payload.router_capacity,
+        end_device_capacity: payload.end_device_capacity,
+        device_depth: payload.device_depth,
+        lqi: pan.lqi,
+    })
+}
+
+/// Merges the beacon of another device of the same network.
+fn merge(network: &mut NetworkDescriptor, other: &NetworkDescriptor) {
+    network.update_id = network.update_id.max(other.update_id);
+    network.permit_joining |= other.permit_joining;
+    network.router_capacity |= other.router_capacity;
+    network.end_device_capacity |= other.end_device_capacity;
+    network.device_depth = network.device_depth.min(other.device_depth);
+    network.lqi = network.lqi.max(other.lqi);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::ShortAddress;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+
+    #[test]
+    fn network_discovery_merges_beacons_of_a_network() {
+        let mut radio = MockRadio::new();
+        radio.received.push_back(beacon(0x0000, false, 0, 80));
+        radio.received.push_back(beacon(0x796f, true, 1, 200));
+        let mut nlme = Nlme::new(radio);
+
+        let confirm = block_on(nlme.network_discovery(request(15)));
+
+        assert_eq!(confirm.status, NlmeNetworkDiscoveryStatus::Successful);
+        assert_eq!(confirm.network_descriptors.len(), 1);
+        let network = &confirm.network_descriptors[0];
+        assert_eq!(network.extended_pan_id, EXTENDED_PAN_ID);
+        assert_eq!(network.pan_id, 0x1a62);
+        assert_eq!(network.logical_channel, 15);
+        assert_eq!(network.stack_profile, 2);
+        assert_eq!(network.zigbee_version, 2);
+        assert_eq!(network.beacon_order, 15);
+        assert!(network.permit_joining);
+        assert!(network.router_capacity);
+        assert!(network.end_device_capacity);
+        assert_eq!(network.device_depth, 0);
+        assert_eq!(network.lqi, 200);
+        let neighbor = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x0000))
+            .unwrap();
+        assert_eq!(neighbor.device_type, LogicalType::Router);
+        assert_eq!(neighbor.extended_pan_id, EXTENDED_PAN_ID);
+        assert!(!neighbor.permit_joining);
+        assert_eq!(nlme.nib.neighbor_table.iter().count(), 2);
+    }
+
+    #[test]
+    fn network_discovery_ignores_other_protocols() {
+        let mut radio = MockRadio::new();
+        let mut frame = beacon(0x0000, true, 0, 80);
+        // first byte of the beacon payload after header and superframe
+        frame.frame[11] = 0x01;
+        radio.received.push_back(frame);
+        let mut nlme = Nlme::new(radio);
+
+        let confirm = block_on(nlme.network_discovery(request(15)));
+
+        assert_eq!(confirm.status, NlmeNetworkDiscoveryStatus::NoBeacon);
+        assert!(confirm.network_descriptors.is_empty());
+    }
+
+    #[test]
+    fn network_discovery_rejects_invalid_parameters() {
+        let mut nlme = Nlme::new(MockRadio::new());
+
+        let empty = block_on(nlme.network_discovery(request(5)));
+        let too_long = block_on(nlme.network_discovery(NlmeNetworkDiscoveryRequest {
+            scan_duration: 15,
+            ..request(15)
+        }));
+
+        assert_eq!(empty.status, NlmeNetworkDiscoveryStatus::InvalidParameter);
+        assert_eq!(
+            too_long.status,
+            NlmeNetworkDiscoveryStatus::InvalidParameter
+        );
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+}
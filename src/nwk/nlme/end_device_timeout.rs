@@ -0,0 +1,385 @@
+//! End Device Timeout
+//!
+//! An end device negotiates with its parent the time after which the parent
+//! forgets about it unless it keeps alive. The parent answers with the
+//! keep-alive methods it accepts, MAC data polls and End Device Timeout
+//! Requests, and ages out the end device children whose timeout expired.
+//!
+//! See Section 3.6.10.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::types::ShortAddress;
+use crate::nwk::commands::EndDeviceTimeout;
+use crate::nwk::commands::EndDeviceTimeoutRequest;
+use crate::nwk::commands::EndDeviceTimeoutResponse;
+use crate::nwk::commands::EndDeviceTimeoutStatus;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::neighbor::Keepalive;
+use crate::nwk::neighbor::MAX_NEIGHBORS;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// Keep-alives an end device sends per timeout, leaving room for lost ones.
+const KEEPALIVES_PER_TIMEOUT: u32 = 3;
+
+/// nwkParentInformation bit of a parent accepting MAC data polls as
+/// keep-alive.
+const MAC_DATA_POLL_KEEPALIVE: u8 = 0b001;
+
+/// nwkParentInformation bit of a parent accepting End Device Timeout
+/// Requests as keep-alive.
+const TIMEOUT_REQUEST_KEEPALIVE: u8 = 0b010;
+
+impl<R: Radio> Nlme<R> {
+    /// The network address of the parent of this end device.
+    pub(super) fn end_device_parent(&self) -> Option<ShortAddress> {
+        if self.device_type != Some(LogicalType::EndDevice) {
+            return None;
+        }
+        self.nib
+            .neighbor_table
+            .parent()
+            .map(|parent| parent.network_address)
+    }
+
+    /// Asks the parent of this end device to forget about it after
+    /// `timeout` without keep-alive, and keeps alive from now on.
+    ///
+    /// The End Device Timeout Response of the parent is handled once
+    /// received.
+    ///
+    /// See Section 3.6.10.2.
+    pub(crate) async fn request_timeout(
+        &mut self,
+        timeout: EndDeviceTimeout,
+    ) -> Result<(), R::Error> {
+        let Some(parent) = self.end_device_parent() else {
+            return Ok(());
+        };
+        self.end_device_timeout = Some(timeout);
+        self.schedule_keepalive();
+        let request = NwkCommand::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest {
+            requested_timeout: timeout,
+            end_device_configuration: 0,
+        });
+        self.send_command(parent, None, &request, true).await?;
+        // the response is polled for
+        self.poll_fast();
+        Ok(())
+    }
+
+    /// Schedules the next keep-alive of this end device a fraction of its
+    /// timeout from now.
+    pub(super) fn schedule_keepalive(&mut self) {
+        let now = self.mac.radio.now();
+        self.next_keepalive = self
+            .end_device_timeout
+            .map(|timeout| now + timeout.duration() / KEEPALIVES_PER_TIMEOUT);
+    }
+
+    /// The parent of this end device accepts MAC data polls as keep-alive,
+    /// which is assumed until it answered the End Device Timeout Request.
+    pub(super) fn polls_keep_alive(&self) -> bool {
+        self.nib.parent_information & (MAC_DATA_POLL_KEEPALIVE | TIMEOUT_REQUEST_KEEPALIVE)
+            != TIMEOUT_REQUEST_KEEPALIVE
+    }
+
+    /// Sends a keep-alive to the parent of this end device, a MAC data poll
+    /// unless the parent only accepts End Device Timeout Requests.
+    ///
+    /// Returns if the parent acknowledged it.
+    ///
+    /// See Section 3.6.10.3.
+    pub(crate) async fn send_keepalive(&mut self) -> Result<bool, R::Error> {
+        let Some(parent) = self.end_device_parent() else {
+            self.next_keepalive = None;
+            return Ok(false);
+        };
+        self.schedule_keepalive();
+        let status = match self.end_device_timeout {
+            Some(timeout) if !self.polls_keep_alive() => {
+                let request = NwkCommand::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest {
+                    requested_timeout: timeout,
+                    end_device_configuration: 0,
+                });
+                self.send_command(parent, None, &request, true).await?
+            }
+            _ => self.mac.poll(parent).await?,
+        };
+        Ok(matches!(status, TxStatus::Acked { .. }))
+    }
+
+    /// Sends the keep-alive of this end device when it is due.
+    ///
+    /// An end device which turns its receiver off while idle polls its
+    /// parent instead, so that it receives the frames kept for it.
+    pub(super) async fn maintain_keepalive(&mut self) -> Result<(), R::Error> {
+        let now = self.mac.radio.now();
+        if self.next_keepalive.map_or(true, |due| due > now) {
+            return Ok(());
+        }
+        if self.poll_rates.is_some() && self.polls_keep_alive() {
+            self.schedule_keepalive();
+            self.next_poll = Some(now);
+        } else {
+            self.send_keepalive().await?;
+        }
+        Ok(())
+    }
+
+    /// Records the keep-alive methods accepted by the parent of this end
+    /// device from its End Device Timeout Response, received from the
+    /// neighbor `sender`.
+    ///
+    /// See Section 3.6.10.2.
+    pub(super) fn receive_timeout_response(
+        &mut self,
+        header: &NwkHeader,
+        response: EndDeviceTimeoutResponse,
+        secured: bool,
+        sender: ShortAddress,
+    ) {
+        if self.end_device_parent() != Some(sender)
+            || header.source != sender
+            || (self.nib.network_key.is_some() && !secured)
+            || response.status != EndDeviceTimeoutStatus::Success
+        {
+            return;
+        }
+        // the parent information bits of the response
+        self.nib.parent_information = u8::from(response.mac_data_poll_keepalive)
+            | u8::from(response.timeout_request_keepalive) << 1
+            | u8::from(response.power_negotiation) << 2;
+    }
+
+    /// Takes the requested timeout of the end device child `header.source`,
+    /// received from the neighbor `sender`, as its keep-alive and answers
+    /// with the keep-alive methods accepted by this parent.
+    ///
+    /// See Section 3.6.10.3.
+    pub(super) async fn receive_timeout_request(
+        &mut self,
+        header: &NwkHeader,
+        request: EndDeviceTimeoutRequest,
+        secured: bool,
+        sender: ShortAddress,
+    ) -> Result<(), R::Error> {
+        if !self.router_started
+            || header.source != sender
+            || (self.nib.network_key.is_some() && !secured)
+        {
+            return Ok(());
+        }
+        let pan_id = self.mac.pan_id;
+        let Some(child) = self
+            .nib
+            .neighbor_table
+            .get_mut(pan_id, sender)
+            .filter(|neighbor| neighbor.is_child() && !neighbor.is_router())
+        else {
+            return Ok(());
+        };
+        // the end device configuration is reserved
+        let status = if request.end_device_configuration == 0 {
+            child.device_timeout = Some(request.requested_timeout);
+            EndDeviceTimeoutStatus::Success
+        } else {
+            EndDeviceTimeoutStatus::IncorrectValue
+        };
+        let now = self.mac.radio.now();
+        let _ = self
+            .nib
+            .neighbor_table
+            .keepalive(pan_id, sender, Keepalive::TimeoutRequest, now);
+        let response = NwkCommand::EndDeviceTimeoutResponse(EndDeviceTimeoutResponse {
+            status,
+            mac_data_poll_keepalive: true,
+            timeout_request_keepalive: true,
+            power_negotiation: false,
+        });
+        self.send_command(sender, None, &response, true).await?;
+        Ok(())
+    }
+
+    /// Takes a MAC data poll of the device `source` as keep-alive if it is
+    /// an end device child, and sends it a frame kept for it.
+    pub(super) async fn receive_poll(&mut self, source: ShortAddress) -> Result<(), R::Error> {
+        let now = self.mac.radio.now();
+        let _ =
+            self.nib
+                .neighbor_table
+                .keepalive(self.mac.pan_id, source, Keepalive::MacDataPoll, now);
+        self.send_polled(source).await
+    }
+
+    /// Forgets about the end device children whose timeout expired.
+    ///
+    /// See Section 3.6.10.4.
+    pub(super) fn maintain_children(&mut self) {
+        let (now, pan_id) = (self.mac.radio.now(), self.mac.pan_id);
+        let expired: Vec<ShortAddress, MAX_NEIGHBORS> = self
+            .nib
+            .neighbor_table
+            .children()
+            .filter(|child| {
+                child.pan_id == pan_id && child.timeout_expiry.is_some_and(|expiry| expiry <= now)
+            })
+            .map(|child| child.network_address)
+            .collect();
+        for network_address in expired {
+            self.forget_device(network_address);
+        }
+    }
+
+    /// The time the next end device child is aged out.
+    pub(super) fn next_child_timeout(&self) -> Option<Duration> {
+        let pan_id = self.mac.pan_id;
+        self.nib
+            .neighbor_table
+            .children()
+            .filter(|child| child.pan_id == pan_id)
+            .filter_map(|child| child.timeout_expiry)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::IeeeAddress;
+    use crate::mac::command::MacCommand;
+    use crate::mac::frame::Address;
+    use crate::mac::frame::MacFrame;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::COORDINATOR_ADDRESS;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+
+    /// The NWK command sent by `nlme` in the frame at `index`.
+    fn sent_command(nlme: &Nlme<MockRadio>, index: usize) -> NwkCommand {
+        let (_, NwkFrame::NwkCommand(frame)) = sent_frame(nlme, index) else {
+            unreachable!("no command frame sent");
+        };
+        frame.command().unwrap()
+    }
+
+    #[test]
+    fn parent_answers_end_device_timeout_request() {
+        let mut nlme = parent();
+        let _ = nlme.nib.neighbor_table.insert(end_device_child());
+        let request = NwkCommand::EndDeviceTimeoutRequest(EndDeviceTimeoutRequest {
+            requested_timeout: EndDeviceTimeout::new(3).unwrap(),
+            end_device_configuration: 0,
+        });
+        let header = nwk_command_header(ShortAddress(COORDINATOR_ADDRESS), 0x796f, 1);
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &request, true));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.device_timeout, EndDeviceTimeout::new(3));
+        assert_eq!(child.timeout_expiry, Some(Duration::from_secs(8 * 60)));
+        assert_eq!(child.keepalive, Some(Keepalive::TimeoutRequest));
+        // the response is kept until the sleeping child polls for it
+        assert!(nlme.mac.radio.transmitted.is_empty());
+        nlme.mac.radio.received.push_back(data_poll(0x796f));
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let NwkCommand::EndDeviceTimeoutResponse(response) = sent_command(&nlme, 0) else {
+            unreachable!("no end device timeout response sent");
+        };
+        assert_eq!(response.status, EndDeviceTimeoutStatus::Success);
+        assert!(response.mac_data_poll_keepalive);
+        assert!(response.timeout_request_keepalive);
+    }
+
+    #[test]
+    fn end_device_child_is_aged_out_without_keepalive() {
+        let mut nlme = parent();
+        let _ = nlme.nib.neighbor_table.insert(end_device_child());
+
+        block_on(nlme.process(Duration::from_secs(6))).unwrap();
+        nlme.mac.radio.received.push_back(data_poll(0x796f));
+        block_on(nlme.process(Duration::from_secs(9))).unwrap();
+
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.keepalive, Some(Keepalive::MacDataPoll));
+        assert_eq!(child.timeout_expiry, Some(Duration::from_secs(16)));
+
+        block_on(nlme.process(Duration::from_secs(2))).unwrap();
+
+        assert!(nlme.nib.neighbor_table.children().next().is_none());
+        assert_eq!(nlme.take_departed_devices(), [IeeeAddress(0x796f)]);
+    }
+
+    #[test]
+    fn end_device_requests_timeout_and_polls_parent() {
+        let mut nlme = end_device();
+
+        block_on(nlme.request_timeout(EndDeviceTimeout::new(0).unwrap())).unwrap();
+        block_on(nlme.process(Duration::from_secs(4))).unwrap();
+
+        let (destination, _) = sent_frame(&nlme, 0);
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0002))));
+        let NwkCommand::EndDeviceTimeoutRequest(request) = sent_command(&nlme, 0) else {
+            unreachable!("no end device timeout request sent");
+        };
+        assert_eq!(request.requested_timeout, EndDeviceTimeout::new(0).unwrap());
+        // a parent which did not answer is polled
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        let Ok(MacFrame::Command(poll)) =
+            MacFrame::unpack_from_slice(&nlme.mac.radio.transmitted[1])
+        else {
+            unreachable!("no data poll sent");
+        };
+        assert_eq!(poll.command, MacCommand::DataRequest);
+        assert_eq!(
+            poll.header.destination,
+            Some(Address::Short(ShortAddress(0x0002)))
+        );
+    }
+
+    #[test]
+    fn end_device_keeps_alive_as_accepted_by_parent() {
+        let mut nlme = end_device();
+        block_on(nlme.request_timeout(EndDeviceTimeout::new(0).unwrap())).unwrap();
+        let response = NwkCommand::EndDeviceTimeoutResponse(EndDeviceTimeoutResponse {
+            status: EndDeviceTimeoutStatus::Success,
+            mac_data_poll_keepalive: false,
+            timeout_request_keepalive: true,
+            power_negotiation: false,
+        });
+        let header = nwk_command_header(ShortAddress(0x796f), 0x0002, 1);
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &response, true));
+
+        block_on(nlme.process(Duration::from_secs(4))).unwrap();
+
+        assert_eq!(nlme.nib.parent_information, 0b010);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        assert!(matches!(
+            sent_command(&nlme, 1),
+            NwkCommand::EndDeviceTimeoutRequest(_)
+        ));
+    }
+}
@@ -0,0 +1,280 @@
+//! Network Formation
+//!
+//! A coordinator forms a new network on the quietest of the scanned channels
+//! with the fewest networks, using a PAN ID not in use by the networks it
+//! heard.
+//!
+//! See Section 3.6.1.1.
+use heapless::Vec;
+
+use super::discovery::network_descriptor;
+use super::management::NlmeNetworkFormationConfirm;
+use super::management::NlmeNetworkFormationRequest;
+use super::management::NlmeNetworkFormationStatus;
+use super::Nlme;
+use super::COORDINATOR_ADDRESS;
+use super::NON_BEACON_ORDER;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::types::ShortAddress;
+use crate::mac::scan::MAX_SCAN_CHANNELS;
+use crate::mac::scan::MAX_SCAN_DURATION;
+use crate::nwk::nib::MAX_PAN_ID;
+use crate::radio::ChannelMask;
+use crate::radio::Radio;
+
+/// Highest energy level of a channel a network is formed on.
+const MAX_CHANNEL_ENERGY: u8 = 0x80;
+
+impl<R: Radio> Nlme<R> {
+    /// Establishes a new network as its coordinator.
+    ///
+    /// See Section 3.6.1.1.
+    pub(super) async fn form_network(
+        &mut self,
+        request: &NlmeNetworkFormationRequest,
+    ) -> Result<NlmeNetworkFormationConfirm, NlmeNetworkFormationStatus> {
+        if self.device_type.is_some() {
+            return Err(NlmeNetworkFormationStatus::InvalidRequest);
+        }
+        let channels = request.scan_channels_list_structure;
+        let extended_pan_id = match request.extended_pan_id {
+            0 => self.mac.extended_address.0,
+            extended_pan_id => extended_pan_id,
+        };
+        if request.scan_duration > MAX_SCAN_DURATION
+            || channels.is_empty()
+            || request.beacon_order != NON_BEACON_ORDER
+            || request.superframe_order != NON_BEACON_ORDER
+            || extended_pan_id == 0
+            || extended_pan_id == u64::MAX
+        {
+            return Err(NlmeNetworkFormationStatus::InvalidParameter);
+        }
+
+        let energies = self
+            .mac
+            .energy_scan(channels, request.scan_duration)
+            .await
+            .map_err(|_| NlmeNetworkFormationStatus::RadioError)?;
+        let acceptable: Vec<(u8, u8), MAX_SCAN_CHANNELS> = channels
+            .channels()
+            .zip(energies)
+            .filter(|(_, energy)| *energy <= MAX_CHANNEL_ENERGY)
+            .collect();
+        let acceptable_channels = acceptable
+            .iter()
+            .fold(ChannelMask(0), |mask, (channel, _)| {
+                ChannelMask(mask.0 | ChannelMask::single(*channel).0)
+            });
+        if acceptable_channels.is_empty() {
+            return Err(NlmeNetworkFormationStatus::StartupFailure);
+        }
+
+        let pans = self
+            .mac
+            .active_scan(acceptable_channels, request.scan_duration)
+            .await
+            .map_err(|_| NlmeNetworkFormationStatus::RadioError)?;
+        if pans
+            .iter()
+            .filter_map(network_descriptor)
+            .any(|network| network.extended_pan_id == extended_pan_id)
+        {
+            return Err(NlmeNetworkFormationStatus::StartupFailure);
+        }
+        // the quietest of the channels with the fewest networks
+        let channel = acceptable
+            .iter()
+            .min_by_key(|(channel, energy)| {
+                let networks = pans.iter().filter(|pan| pan.channel == *channel).count();
+                (networks, *energy)
+            })
+            .map(|(channel, _)| *channel)
+            .ok_or(NlmeNetworkFormationStatus::StartupFailure)?;
+        let pan_id = choose_pan_id(extended_pan_id, |pan_id| {
+            pans.iter().any(|pan| pan.pan_id == pan_id)
+        })
+        .ok_or(NlmeNetworkFormationStatus::StartupFailure)?;
+
+        self.mac
+            .start(channel, pan_id, ShortAddress(COORDINATOR_ADDRESS), true)
+            .await
+            .map_err(|_| NlmeNetworkFormationStatus::RadioError)?;
+        self.nib.pan_id = pan_id;
+        self.nib.network_address = ShortAddress(COORDINATOR_ADDRESS);
+        self.nib.extended_pan_id = extended_pan_id;
+        self.nib.update_id = 0;
+        self.depth = 0;
+        self.device_type = Some(LogicalType::Coordinator);
+        self.router_started = true;
+
+        Ok(NlmeNetworkFormationConfirm {
+            status: NlmeNetworkFormationStatus::Success,
+            pan_id,
+            extended_pan_id,
+            logical_channel: channel,
+        })
+    }
+}
+
+/// Picks the first PAN ID not `in_use`, starting at a value derived from the
+/// extended PAN ID.
+fn choose_pan_id(extended_pan_id: u64, in_use: impl Fn(u16) -> bool) -> Option<u16> {
+    let start = extended_pan_id
+        .to_le_bytes()
+        .chunks_exact(2)
+        .fold(0u16, |folded, word| {
+            folded ^ u16::from_le_bytes([word[0], word[1]])
+        });
+    (0..=MAX_PAN_ID)
+        .map(|offset| start.wrapping_add(offset) & MAX_PAN_ID)
+        .find(|pan_id| !in_use(*pan_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::IeeeAddress;
+    use crate::mac::frame::Address;
+    use crate::mac::frame::MacFrame;
+    use crate::nwk::beacon::BeaconPayload;
+    use crate::nwk::nib::NibAttribute;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::nwk::nlme::STACK_PROFILE;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+
+    fn formation_request(channels: ChannelMask) -> NlmeNetworkFormationRequest {
+        NlmeNetworkFormationRequest {
+            scan_channels_list_structure: channels,
+            scan_duration: 3,
+            beacon_order: 15,
+            superframe_order: 15,
+            battery_life_extension: false,
+            extended_pan_id: 0,
+        }
+    }
+
+    #[test]
+    fn network_formation_picks_quiet_channel_without_networks() {
+        let mut radio = MockRadio::new();
+        radio.energy[4] = 0x10;
+        radio.energy[5] = 0x40;
+        radio.energy[6] = 0xf0;
+        // received while scanning channel 15
+        radio.received.push_back(beacon(0x0000, true, 0, 200));
+        let mut nlme = Nlme::new(radio);
+        nlme.mac.extended_address = IeeeAddress(0x0015_8d00_0000_0001);
+
+        let confirm = block_on(nlme.network_formation(formation_request(ChannelMask(0x0003_8000))));
+
+        assert_eq!(confirm.status, NlmeNetworkFormationStatus::Success);
+        assert_eq!(confirm.logical_channel, 16);
+        assert_eq!(confirm.pan_id, 0x0d14);
+        assert_eq!(confirm.extended_pan_id, 0x0015_8d00_0000_0001);
+        assert_eq!(nlme.mac.radio.channel, 16);
+        assert_eq!(nlme.mac.radio.pan_id, 0x0d14);
+        assert_eq!(nlme.mac.radio.short_address, Some(ShortAddress(0x0000)));
+        assert_eq!(nlme.device_type, Some(LogicalType::Coordinator));
+        assert_eq!(nlme.get(0x80).attribute, Some(NibAttribute::PanId(0x0d14)));
+        assert_eq!(
+            nlme.get(0x96).attribute,
+            Some(NibAttribute::NetworkAddress(ShortAddress(0x0000)))
+        );
+    }
+
+    #[test]
+    fn network_formation_on_noisy_channels_fails() {
+        let mut radio = MockRadio::new();
+        radio.energy = [0xff; 16];
+        let mut nlme = Nlme::new(radio);
+
+        let confirm = block_on(nlme.network_formation(NlmeNetworkFormationRequest {
+            extended_pan_id: EXTENDED_PAN_ID,
+            ..formation_request(ChannelMask::ALL_2_4_GHZ)
+        }));
+
+        assert_eq!(confirm.status, NlmeNetworkFormationStatus::StartupFailure);
+        assert_eq!(nlme.device_type, None);
+    }
+
+    #[test]
+    fn network_formation_with_known_extended_pan_id_fails() {
+        let mut radio = MockRadio::new();
+        radio.received.push_back(beacon(0x0000, true, 0, 200));
+        let mut nlme = Nlme::new(radio);
+
+        let confirm = block_on(nlme.network_formation(NlmeNetworkFormationRequest {
+            extended_pan_id: EXTENDED_PAN_ID,
+            ..formation_request(ChannelMask::single(11))
+        }));
+
+        assert_eq!(confirm.status, NlmeNetworkFormationStatus::StartupFailure);
+    }
+
+    #[test]
+    fn network_formation_on_a_network_is_invalid() {
+        let mut nlme = Nlme::new(MockRadio::new());
+        nlme.device_type = Some(LogicalType::Router);
+
+        let confirm = block_on(nlme.network_formation(NlmeNetworkFormationRequest {
+            extended_pan_id: EXTENDED_PAN_ID,
+            ..formation_request(ChannelMask::single(11))
+        }));
+
+        assert_eq!(confirm.status, NlmeNetworkFormationStatus::InvalidRequest);
+    }
+
+    #[test]
+    fn pan_id_in_use_is_skipped() {
+        let in_use = |pan_id| pan_id == 0x01ac || pan_id == 0x01ad;
+
+        assert_eq!(choose_pan_id(EXTENDED_PAN_ID, in_use), Some(0x01ae));
+        assert_eq!(choose_pan_id(EXTENDED_PAN_ID, |_| true), None);
+    }
+
+    #[test]
+    fn coordinator_answers_beacon_requests() {
+        let mut radio = MockRadio::new();
+        radio.received.push_back(RxFrame {
+            frame: Vec::from_slice(&[0x03, 0x08, 0x01, 0xff, 0xff, 0xff, 0xff, 0x07]).unwrap(),
+            lqi: 255,
+            rssi: -40,
+            timestamp: Duration::ZERO,
+        });
+        let mut nlme = Nlme::new(radio);
+        nlme.mac.extended_address = IeeeAddress(EXTENDED_PAN_ID);
+        block_on(nlme.network_formation(formation_request(ChannelMask::single(11))));
+        nlme.mac.radio.transmitted.clear();
+        nlme.mac.radio.received.push_back(RxFrame {
+            frame: Vec::from_slice(&[0x03, 0x08, 0x02, 0xff, 0xff, 0xff, 0xff, 0x07]).unwrap(),
+            lqi: 255,
+            rssi: -40,
+            timestamp: Duration::ZERO,
+        });
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Ok(MacFrame::Beacon(beacon)) =
+            MacFrame::unpack_from_slice(&nlme.mac.radio.transmitted[0])
+        else {
+            unreachable!("no beacon sent");
+        };
+        let superframe = beacon.beacon.superframe_specification;
+        assert!(superframe.pan_coordinator());
+        assert!(!superframe.association_permit());
+        assert_eq!(
+            beacon.header.source,
+            Some(Address::Short(ShortAddress(0x0000)))
+        );
+        let payload = BeaconPayload::unpack_from_slice(&beacon.payload).unwrap();
+        assert_eq!(payload.extended_pan_id, EXTENDED_PAN_ID);
+        assert_eq!(payload.stack_profile, STACK_PROFILE);
+        assert_eq!(payload.device_depth, 0);
+    }
+}
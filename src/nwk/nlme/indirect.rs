@@ -0,0 +1,190 @@
+//! Indirect Transmission
+//!
+//! A parent keeps the frames for its children which turn their receiver off
+//! while idle until they poll for them with a MAC data request, at most
+//! macTransactionPersistenceTime. The frame pending bit of a delivered frame
+//! tells the child that more frames are kept for it.
+//!
+//! See IEEE 802.15.4-2015, Section 6.7.3.
+
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::association::BASE_SUPERFRAME_DURATION;
+use super::Nlme;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::Address;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// Maximum number of frames kept for sleeping children.
+pub(super) const MAX_INDIRECT_FRAMES: usize = 8;
+
+/// A frame waiting for a sleeping child to poll for it.
+pub(super) struct IndirectFrame {
+    /// The network address of the child.
+    child: ShortAddress,
+    /// The MAC destination of the frame.
+    destination: Address,
+    frame: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    /// Time at which the frame is discarded.
+    expiration: Duration,
+}
+
+impl<R: Radio> Nlme<R> {
+    /// The network address of the child addressed by the MAC `destination`
+    /// if it turns its receiver off while idle.
+    ///
+    /// A child is known to poll once it kept alive, until then, e.g. while it
+    /// joins or rejoins, it listens for the frames of this parent.
+    pub(super) fn sleeping_child(&self, destination: Address) -> Option<ShortAddress> {
+        let pan_id = self.mac.pan_id;
+        self.nib
+            .neighbor_table
+            .children()
+            .find(|child| {
+                child.pan_id == pan_id
+                    && !child.rx_on_when_idle
+                    && child.keepalive.is_some()
+                    && match destination {
+                        Address::Short(address) => child.network_address == address,
+                        Address::Extended(address) => child.extended_address == Some(address),
+                    }
+            })
+            .map(|child| child.network_address)
+    }
+
+    /// Keeps `frame` for the sleeping `child` until it polls for it.
+    ///
+    /// Returns [`TxStatus::Sent`] as the delivery is not confirmed, or
+    /// [`TxStatus::NoAck`] if no more frames can be kept.
+    pub(super) fn send_indirect(
+        &mut self,
+        child: ShortAddress,
+        destination: Address,
+        frame: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    ) -> TxStatus {
+        let now = self.mac.radio.now();
+        self.indirect_frames.retain(|frame| frame.expiration > now);
+        let persistence =
+            BASE_SUPERFRAME_DURATION * u32::from(self.nib.transaction_persistence_time);
+        let kept = self.indirect_frames.push(IndirectFrame {
+            child,
+            destination,
+            frame,
+            expiration: now + persistence,
+        });
+        if kept.is_ok() {
+            TxStatus::Sent
+        } else {
+            TxStatus::NoAck
+        }
+    }
+
+    /// Sends the oldest frame kept for the child `child`, which polled for
+    /// it, with the frame pending bit set if more frames are kept.
+    ///
+    /// A frame which is not acknowledged is kept for the next poll.
+    pub(super) async fn send_polled(&mut self, child: ShortAddress) -> Result<(), R::Error> {
+        let now = self.mac.radio.now();
+        self.indirect_frames.retain(|frame| frame.expiration > now);
+        let mut kept = self
+            .indirect_frames
+            .iter()
+            .enumerate()
+            .filter(|(_, frame)| frame.child == child);
+        let Some((index, frame)) = kept.next() else {
+            return Ok(());
+        };
+        let frame_pending = kept.next().is_some();
+        let (destination, frame) = (frame.destination, frame.frame.clone());
+        let status = self
+            .mac
+            .send_data(destination, &frame, frame_pending)
+            .await?;
+        if matches!(status, TxStatus::Acked { .. }) {
+            self.indirect_frames.remove(index);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::mac::frame::MacFrame;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::neighbor::Keepalive;
+    use crate::nwk::neighbor::Neighbor;
+    use crate::nwk::nlde::NldeDataStatus;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+
+    /// The frame pending bit of the `index`th frame sent by `nlme`.
+    fn sent_frame_pending(nlme: &Nlme<MockRadio>, index: usize) -> bool {
+        let Ok(MacFrame::Data(frame)) =
+            MacFrame::unpack_from_slice(&nlme.mac.radio.transmitted[index])
+        else {
+            unreachable!("no data frame sent");
+        };
+        frame.header.frame_control.frame_pending()
+    }
+
+    #[test]
+    fn parent_keeps_frames_until_sleeping_child_polls() {
+        let mut nlme = parent();
+        let _ = nlme.nib.neighbor_table.insert(Neighbor {
+            keepalive: Some(Keepalive::MacDataPoll),
+            incoming_frame_counter: None,
+            ..end_device_child()
+        });
+        nlme.mac.radio.tx_status = Some(TxStatus::Acked {
+            frame_pending: false,
+        });
+
+        for nsdu in [[1], [2]] {
+            let confirm = block_on(nlme.data_request(unicast_request(0x796f, &nsdu))).unwrap();
+            assert_eq!(confirm.status, NldeDataStatus::Success);
+        }
+        assert!(nlme.mac.radio.transmitted.is_empty());
+        for _ in 0..3 {
+            nlme.mac.radio.received.push_back(data_poll(0x796f));
+            block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        }
+
+        // the frames are sent in order, once per poll
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        for (index, nsdu) in [[1], [2]].iter().enumerate() {
+            let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, index) else {
+                unreachable!("no data frame sent");
+            };
+            assert_eq!(destination, Some(Address::Short(ShortAddress(0x796f))));
+            assert_eq!(frame.payload.as_slice(), nsdu);
+        }
+        assert!(sent_frame_pending(&nlme, 0));
+        assert!(!sent_frame_pending(&nlme, 1));
+    }
+
+    #[test]
+    fn parent_discards_frames_not_polled_in_time() {
+        let mut nlme = parent();
+        let _ = nlme.nib.neighbor_table.insert(Neighbor {
+            keepalive: Some(Keepalive::MacDataPoll),
+            incoming_frame_counter: None,
+            timeout_expiry: None,
+            ..end_device_child()
+        });
+
+        block_on(nlme.data_request(unicast_request(0x796f, &[1]))).unwrap();
+        // macTransactionPersistenceTime of 0x01f4 superframes
+        block_on(nlme.process(Duration::from_millis(7_680))).unwrap();
+        nlme.mac.radio.received.push_back(data_poll(0x796f));
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+}
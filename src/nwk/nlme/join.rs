@@ -0,0 +1,300 @@
+//! Joining through Association
+//!
+//! A device joins a network discovered before through MAC association with
+//! the potential parent of the best link which permits joining and has
+//! capacity for it. A parent which refuses the device is not tried again.
+//!
+//! See Section 3.6.1.4.1.1.
+use super::management::NlmeJoinConfirm;
+use super::management::NlmeJoinRequest;
+use super::management::NlmeJoinStatus;
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::apl::descriptors::node_descriptor::MacCapabilityFlag;
+use crate::mac::association::AssociateConfirm;
+use crate::mac::command::AssociationStatus;
+use crate::nwk::neighbor::link_cost;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::neighbor::Relationship;
+use crate::radio::Radio;
+
+/// Highest link cost of a potential parent.
+const MAX_PARENT_LINK_COST: u8 = 3;
+
+impl<R: Radio> Nlme<R> {
+    /// Joins the network through MAC association with the best suitable
+    /// parent found by the previous network discovery.
+    ///
+    /// See Section 3.6.1.4.1.1.
+    pub(super) async fn join_through_association(
+        &mut self,
+        request: &NlmeJoinRequest,
+    ) -> Result<NlmeJoinConfirm, NlmeJoinStatus> {
+        if self.device_type.is_some() {
+            return Err(NlmeJoinStatus::InvalidRequest);
+        }
+        let router = request
+            .capability_information
+            .is_set(MacCapabilityFlag::DeviceType);
+        let parent = self.find_parent(request.extended_pan_id, router, |neighbor| {
+            neighbor.permit_joining
+        });
+        let Some(parent) = parent else {
+            let discovered = self
+                .nib
+                .neighbor_table
+                .iter()
+                .any(|neighbor| neighbor.extended_pan_id == request.extended_pan_id);
+            return Err(if discovered {
+                NlmeJoinStatus::NotPermitted
+            } else {
+                NlmeJoinStatus::NoNetworks
+            });
+        };
+
+        let confirm = self
+            .mac
+            .associate(
+                parent.logical_channel,
+                parent.pan_id,
+                parent.network_address,
+                request.capability_information,
+            )
+            .await
+            .map_err(|_| NlmeJoinStatus::RadioError)?;
+        let status = match confirm {
+            AssociateConfirm::Associated(network_address) => {
+                self.adopt_parent(&parent, router);
+                return Ok(NlmeJoinConfirm {
+                    status: NlmeJoinStatus::Success,
+                    network_address: network_address.0,
+                    extended_pan_id: parent.extended_pan_id,
+                    active_channel: parent.logical_channel,
+                    enhanced_beacon_type: false,
+                    mac_interface_index: 0,
+                });
+            }
+            AssociateConfirm::Rejected(status) => rejected(status),
+            AssociateConfirm::ChannelAccessFailure => NlmeJoinStatus::ChannelAccessFailure,
+            AssociateConfirm::NoAck => NlmeJoinStatus::NoAck,
+            AssociateConfirm::NoData => NlmeJoinStatus::NoData,
+        };
+        // a parent which failed is not tried again
+        if let Some(entry) = self
+            .nib
+            .neighbor_table
+            .get_mut(parent.pan_id, parent.network_address)
+        {
+            entry.potential_parent = false;
+        }
+        Err(status)
+    }
+
+    /// The neighbor with the best link among the potential parents of
+    /// `extended_pan_id` with capacity for a router or end device which are
+    /// `eligible`.
+    ///
+    /// See Section 3.6.1.4.1.1.
+    pub(super) fn find_parent(
+        &self,
+        extended_pan_id: u64,
+        router: bool,
+        eligible: impl Fn(&Neighbor) -> bool,
+    ) -> Option<Neighbor> {
+        self.nib
+            .neighbor_table
+            .iter()
+            .filter(|neighbor| {
+                neighbor.extended_pan_id == extended_pan_id
+                    && neighbor.potential_parent
+                    && if router {
+                        neighbor.router_capacity
+                    } else {
+                        neighbor.end_device_capacity
+                    }
+                    && neighbor.depth < self.nib.max_depth
+                    && link_cost(neighbor.lqi) <= MAX_PARENT_LINK_COST
+                    && eligible(neighbor)
+            })
+            .min_by_key(|neighbor| (link_cost(neighbor.lqi), neighbor.depth))
+            .cloned()
+    }
+
+    /// Operates on the network of `parent` as its child.
+    pub(super) fn adopt_parent(&mut self, parent: &Neighbor, router: bool) {
+        self.nib.pan_id = self.mac.pan_id;
+        self.nib.network_address = self.mac.short_address;
+        self.nib.extended_pan_id = parent.extended_pan_id;
+        self.nib.update_id = parent.update_id;
+        self.depth = parent.depth + 1;
+        // the timeout is negotiated with the new parent
+        self.end_device_timeout = None;
+        self.next_keepalive = None;
+        self.nib.parent_information = 0;
+        self.device_type = Some(if router {
+            LogicalType::Router
+        } else {
+            LogicalType::EndDevice
+        });
+        // the new parent may keep frames, like responses to the join
+        self.next_poll = None;
+        self.poll_fast();
+        if let Some(previous) = self.nib.neighbor_table.parent().cloned() {
+            if let Some(entry) = self
+                .nib
+                .neighbor_table
+                .get_mut(previous.pan_id, previous.network_address)
+            {
+                entry.relationship = Relationship::None;
+            }
+        }
+        if let Some(entry) = self
+            .nib
+            .neighbor_table
+            .get_mut(parent.pan_id, parent.network_address)
+        {
+            entry.relationship = Relationship::Parent;
+        }
+    }
+}
+
+/// The join status of a rejoin or association rejected by the parent.
+pub(super) fn rejected(status: AssociationStatus) -> NlmeJoinStatus {
+    match status {
+        AssociationStatus::PanAtCapacity => NlmeJoinStatus::PanAtCapacity,
+        _ => NlmeJoinStatus::PanAccessDenied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::IeeeAddress;
+    use crate::common::types::ShortAddress;
+    use crate::mac::command::AssociationResponse;
+    use crate::mac::command::MacCommand;
+    use crate::mac::frame::Address;
+    use crate::mac::frame::FrameType;
+    use crate::mac::frame::MacCommandFrame;
+    use crate::mac::frame::MacFrame;
+    use crate::mac::frame::MacHeader;
+    use crate::mac::frame::BROADCAST;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+    use crate::radio::TxStatus;
+
+    fn association_response(status: AssociationStatus) -> RxFrame {
+        let frame = MacFrame::Command(MacCommandFrame {
+            header: MacHeader::new(
+                FrameType::MacCommand,
+                1,
+                Some((0x1a62, Address::Extended(IeeeAddress(0x0001)))),
+                Some((0x1a62, Address::Extended(IeeeAddress(0x00ff)))),
+            ),
+            command: MacCommand::AssociationResponse(AssociationResponse {
+                short_address: ShortAddress(0x1234),
+                status,
+            }),
+        });
+        RxFrame {
+            frame: frame.pack_to_vec().unwrap(),
+            lqi: 255,
+            rssi: -40,
+            timestamp: Duration::ZERO,
+        }
+    }
+
+    /// Discovers a coordinator and a router with a better link on channel 15.
+    fn discovered() -> Nlme<MockRadio> {
+        let mut radio = MockRadio::new();
+        radio.received.push_back(beacon(0x0000, true, 0, 160));
+        radio.received.push_back(beacon(0x796f, true, 1, 220));
+        radio.tx_status = Some(TxStatus::Acked {
+            frame_pending: true,
+        });
+        let mut nlme = Nlme::new(radio);
+        nlme.mac.extended_address = IeeeAddress(0x0001);
+        block_on(nlme.network_discovery(request(15)));
+        nlme.mac.radio.transmitted.clear();
+        nlme
+    }
+
+    #[test]
+    fn join_associates_with_parent_of_best_link() {
+        let mut nlme = discovered();
+        nlme.mac
+            .radio
+            .received
+            .push_back(association_response(AssociationStatus::Success));
+
+        let confirm = block_on(nlme.join(join_request(EXTENDED_PAN_ID)));
+
+        assert_eq!(confirm.status, NlmeJoinStatus::Success);
+        assert_eq!(confirm.network_address, 0x1234);
+        assert_eq!(confirm.extended_pan_id, EXTENDED_PAN_ID);
+        assert_eq!(confirm.active_channel, 15);
+        // association request to the router followed by a data request
+        assert_eq!(
+            nlme.mac.radio.transmitted[0],
+            [
+                0x23, 0xc8, 0x02, 0x62, 0x1a, 0x6f, 0x79, 0xff, 0xff, 0x01, 0x00, 0x00, 0x00, 0x00,
+                0x00, 0x00, 0x00, 0x01, 0x00
+            ]
+        );
+        assert_eq!(nlme.mac.radio.transmitted[1].last(), Some(&0x04));
+        assert_eq!(nlme.mac.radio.short_address, Some(ShortAddress(0x1234)));
+        assert_eq!(nlme.mac.radio.pan_id, 0x1a62);
+        assert_eq!(nlme.device_type, Some(LogicalType::EndDevice));
+        assert_eq!(nlme.depth, 2);
+        assert_eq!(nlme.nib.extended_pan_id, EXTENDED_PAN_ID);
+        let parent = nlme.nib.neighbor_table.parent().unwrap();
+        assert_eq!(parent.network_address, ShortAddress(0x796f));
+    }
+
+    #[test]
+    fn join_rejected_by_parent_excludes_it_as_potential_parent() {
+        let mut nlme = discovered();
+        nlme.mac
+            .radio
+            .received
+            .push_back(association_response(AssociationStatus::PanAtCapacity));
+
+        let confirm = block_on(nlme.join(join_request(EXTENDED_PAN_ID)));
+
+        assert_eq!(confirm.status, NlmeJoinStatus::PanAtCapacity);
+        assert_eq!(nlme.mac.radio.pan_id, BROADCAST);
+        assert_eq!(nlme.device_type, None);
+        let router = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert!(!router.potential_parent);
+        assert!(nlme.nib.neighbor_table.parent().is_none());
+    }
+
+    #[test]
+    fn join_without_suitable_parent_fails() {
+        let mut nlme = discovered();
+
+        let unknown = block_on(nlme.join(join_request(0x0015_8d00_0000_0001)));
+        for address in [0x0000, 0x796f] {
+            let neighbor = nlme
+                .nib
+                .neighbor_table
+                .get_mut(0x1a62, ShortAddress(address));
+            neighbor.unwrap().permit_joining = false;
+        }
+        let not_permitted = block_on(nlme.join(join_request(EXTENDED_PAN_ID)));
+
+        assert_eq!(unknown.status, NlmeJoinStatus::NoNetworks);
+        assert_eq!(not_permitted.status, NlmeJoinStatus::NotPermitted);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+}
@@ -0,0 +1,487 @@
+//! Leaving the Network
+//!
+//! A device leaves the network on its own or when asked by its parent or the
+//! trust center, and announces it to its neighbors, which forget about it.
+//! A parent removes a child by asking it to leave. If requested, the children
+//! of a leaving router leave as well, and a device which is asked to rejoin
+//! keeps its network address for the rejoin.
+//!
+//! See Section 3.6.1.10.
+use heapless::Vec;
+
+use super::broadcast::RX_ON_WHEN_IDLE_ADDRESS;
+use super::management::NlmeIndication;
+use super::management::NlmeLeaveConfirm;
+use super::management::NlmeLeaveIndication;
+use super::management::NlmeLeaveRequest;
+use super::management::NlmeLeaveStatus;
+use super::Nlme;
+use super::COORDINATOR_ADDRESS;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::Address;
+use crate::mac::frame::BROADCAST;
+use crate::nwk::address_map::AddressMap;
+use crate::nwk::broadcast::BroadcastTransactionTable;
+use crate::nwk::commands::Leave;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::neighbor::NeighborTable;
+use crate::nwk::neighbor::MAX_NEIGHBORS;
+use crate::nwk::routing::RouteDiscoveryTable;
+use crate::nwk::routing::RoutingTable;
+use crate::nwk::routing::SourceRouteTable;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+impl<R: Radio> Nlme<R> {
+    /// Removes this device from the network or, if the request names a
+    /// device, the child with this IEEE address.
+    ///
+    /// See Sections 3.6.1.10.1 and 3.6.1.10.2.
+    pub(super) async fn leave_network(
+        &mut self,
+        request: &NlmeLeaveRequest,
+    ) -> Result<NlmeLeaveConfirm, NlmeLeaveStatus> {
+        if self.device_type.is_none() {
+            return Err(NlmeLeaveStatus::InvalidRequest);
+        }
+        let (rejoin, remove_children) = (request.rejoin, request.remove_children);
+        match request.device_address {
+            None => self
+                .leave(rejoin, remove_children)
+                .await
+                .map_err(|_| NlmeLeaveStatus::RadioError)?,
+            Some(device_address) => {
+                self.remove_child(IeeeAddress(device_address), rejoin, remove_children)
+                    .await?;
+            }
+        }
+        Ok(NlmeLeaveConfirm {
+            status: NlmeLeaveStatus::Success,
+            device_address: request.device_address,
+        })
+    }
+
+    /// Asks the child `extended_address` to leave and forgets about it, even
+    /// if it did not acknowledge the request.
+    ///
+    /// See Section 3.6.1.10.2.
+    async fn remove_child(
+        &mut self,
+        extended_address: IeeeAddress,
+        rejoin: bool,
+        remove_children: bool,
+    ) -> Result<(), NlmeLeaveStatus> {
+        let pan_id = self.mac.pan_id;
+        let network_address = self
+            .nib
+            .neighbor_table
+            .get_by_extended_address(extended_address)
+            .filter(|child| child.pan_id == pan_id && child.is_child())
+            .map(|child| child.network_address)
+            .ok_or(NlmeLeaveStatus::UnknownDevice)?;
+        let request = NwkCommand::Leave(Leave {
+            rejoin,
+            request: true,
+            remove_children,
+        });
+        let status = self
+            .send_command(network_address, Some(extended_address), &request, true)
+            .await
+            .map_err(|_| NlmeLeaveStatus::RadioError)?;
+        self.forget_device(network_address);
+        match status {
+            // a sleeping child receives the request once it polls
+            TxStatus::Acked { .. } | TxStatus::Sent => Ok(()),
+            TxStatus::ChannelAccessFailure => Err(NlmeLeaveStatus::ChannelAccessFailure),
+            _ => Err(NlmeLeaveStatus::NoAck),
+        }
+    }
+
+    /// Handles a Leave command of the device `header.source`, received from
+    /// the neighbor `sender`.
+    ///
+    /// This device leaves if its parent or the trust center asks it to, or
+    /// if its parent leaves along with its children. Other devices which
+    /// leave are forgotten.
+    ///
+    /// See Section 3.6.1.10.3.
+    pub(super) async fn receive_leave(
+        &mut self,
+        header: &NwkHeader,
+        leave: Leave,
+        secured: bool,
+        sender: Option<ShortAddress>,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let Some(extended_address) = header.source_ieee else {
+            return Ok(None);
+        };
+        if self.device_type.is_none() || (self.nib.network_key.is_some() && !secured) {
+            return Ok(None);
+        }
+        let from_parent = sender == Some(header.source)
+            && self
+                .nib
+                .neighbor_table
+                .parent()
+                .is_some_and(|parent| parent.network_address == header.source);
+        if leave.request {
+            // the trust center is the coordinator
+            let authorized = from_parent || header.source.0 == COORDINATOR_ADDRESS;
+            let addressed = header.destination == self.mac.short_address
+                && header
+                    .destination_ieee
+                    .map_or(true, |address| address == self.mac.extended_address);
+            if !authorized
+                || !addressed
+                || !self.nib.leave_request_allowed
+                || (!leave.rejoin && !self.nib.leave_request_without_rejoin_allowed)
+            {
+                return Ok(None);
+            }
+        } else {
+            self.forget_device(header.source);
+            if !from_parent || !leave.remove_children {
+                return Ok(Some(NlmeIndication::Leave(NlmeLeaveIndication {
+                    device_address: Some(extended_address.0),
+                    rejoin: leave.rejoin,
+                })));
+            }
+        }
+        self.leave(leave.rejoin, leave.remove_children).await?;
+        Ok(Some(NlmeIndication::Leave(NlmeLeaveIndication {
+            device_address: None,
+            rejoin: leave.rejoin,
+        })))
+    }
+
+    /// Asks the children to leave if `remove_children` is set, announces to
+    /// the neighbors that this device leaves and forgets about the network.
+    ///
+    /// See Section 3.6.1.10.1.
+    async fn leave(&mut self, rejoin: bool, remove_children: bool) -> Result<(), R::Error> {
+        if remove_children {
+            let pan_id = self.mac.pan_id;
+            let children: Vec<(ShortAddress, IeeeAddress), MAX_NEIGHBORS> = self
+                .nib
+                .neighbor_table
+                .children()
+                .filter(|child| child.pan_id == pan_id)
+                .filter_map(|child| Some((child.network_address, child.extended_address?)))
+                .collect();
+            let request = NwkCommand::Leave(Leave {
+                rejoin,
+                request: true,
+                remove_children,
+            });
+            for (network_address, extended_address) in children {
+                self.send_command(network_address, Some(extended_address), &request, true)
+                    .await?;
+            }
+        }
+        let announcement = NwkCommand::Leave(Leave {
+            rejoin,
+            request: false,
+            remove_children,
+        });
+        let header = self.command_header(RX_ON_WHEN_IDLE_ADDRESS, 1);
+        self.send_nwk_command(Address::BROADCAST, header, &announcement, true)
+            .await?;
+        self.forget_network(rejoin).await
+    }
+
+    /// Clears the neighbors, routes and pending transactions of the network
+    /// this device left.
+    ///
+    /// The network address and the extended PAN ID are kept for a rejoin.
+    async fn forget_network(&mut self, rejoin: bool) -> Result<(), R::Error> {
+        self.device_type = None;
+        self.router_started = false;
+        self.depth = 0;
+        self.pending_frames.clear();
+        self.next_many_to_one_request = None;
+        self.rebroadcasts.clear();
+        self.next_link_status = None;
+        self.associations.clear();
+        self.permit_joining_until = None;
+        self.mac.association_permit = false;
+        self.end_device_timeout = None;
+        self.next_keepalive = None;
+        self.indirect_frames.clear();
+        self.next_poll = None;
+        self.fast_poll_until = None;
+        let nib = &mut self.nib;
+        nib.neighbor_table = NeighborTable::new();
+        nib.broadcast_transaction_table = BroadcastTransactionTable::new();
+        nib.route_table = RoutingTable::new();
+        nib.route_discovery_table = RouteDiscoveryTable::new();
+        nib.source_route_table = SourceRouteTable::new();
+        nib.address_map = AddressMap::new();
+        nib.parent_information = 0;
+        if rejoin {
+            return Ok(());
+        }
+        nib.pan_id = BROADCAST;
+        nib.network_address = ShortAddress(BROADCAST);
+        nib.extended_pan_id = 0;
+        nib.update_id = 0;
+        self.mac
+            .start(self.mac.channel, BROADCAST, ShortAddress(BROADCAST), false)
+            .await
+    }
+
+    /// Forgets about the neighbor and the routes of the device
+    /// `network_address` which left the network.
+    ///
+    /// Its IEEE address is kept until the bindings to it are removed.
+    pub(super) fn forget_device(&mut self, network_address: ShortAddress) {
+        let nib = &mut self.nib;
+        let extended_address = nib
+            .neighbor_table
+            .get(self.mac.pan_id, network_address)
+            .and_then(|neighbor| neighbor.extended_address)
+            .or_else(|| nib.address_map.extended_address(network_address));
+        if let Some(extended_address) = extended_address {
+            // bindings are kept if too many devices left at once
+            let _ = self.departed_devices.push(extended_address);
+        }
+        nib.neighbor_table.remove(self.mac.pan_id, network_address);
+        nib.route_table.remove_device(network_address);
+        nib.source_route_table.remove_device(network_address);
+        nib.address_map.remove(network_address);
+    }
+
+    /// Takes the IEEE addresses of the devices which left the network since
+    /// the last call.
+    pub(crate) fn take_departed_devices(&mut self) -> Vec<IeeeAddress, MAX_NEIGHBORS> {
+        core::mem::take(&mut self.departed_devices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::apl::descriptors::node_descriptor::LogicalType;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::nwk::routing::Route;
+    use crate::nwk::routing::RouteStatus;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+
+    /// Decodes the Leave command sent by `nlme` as `index`th frame.
+    fn sent_leave(nlme: &Nlme<MockRadio>, index: usize) -> (Option<Address>, NwkHeader, Leave) {
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(nlme, index) else {
+            unreachable!("no command frame sent");
+        };
+        let Ok(NwkCommand::Leave(leave)) = frame.command() else {
+            unreachable!("no leave command sent");
+        };
+        (destination, frame.header, leave)
+    }
+
+    fn leave_request(device_address: Option<u64>, rejoin: bool) -> NlmeLeaveRequest {
+        NlmeLeaveRequest {
+            device_address,
+            remove_children: false,
+            rejoin,
+        }
+    }
+
+    #[test]
+    fn router_leaves_after_asking_its_children_to_leave() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::Router);
+        nlme.mac.short_address = ShortAddress(0x0002);
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0000, Relationship::Parent));
+        let mut child = router(0x796f, Relationship::Child);
+        child.extended_address = Some(IeeeAddress(0x0001));
+        let _ = nlme.nib.neighbor_table.insert(child);
+
+        let confirm = block_on(NlmeSap::leave(
+            &mut nlme,
+            NlmeLeaveRequest {
+                remove_children: true,
+                ..leave_request(None, false)
+            },
+        ));
+
+        assert_eq!(confirm.status, NlmeLeaveStatus::Success);
+        let (destination, header, request) = sent_leave(&nlme, 0);
+        assert_eq!(destination, Some(Address::Extended(IeeeAddress(0x0001))));
+        assert_eq!(header.destination, ShortAddress(0x796f));
+        assert!(request.request && request.remove_children && !request.rejoin);
+        let (destination, header, announcement) = sent_leave(&nlme, 1);
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(header.destination, RX_ON_WHEN_IDLE_ADDRESS);
+        assert_eq!(header.radius, 1);
+        assert!(!announcement.request && announcement.remove_children);
+        assert_eq!(nlme.device_type, None);
+        assert_eq!(nlme.nib.neighbor_table.iter().count(), 0);
+        assert_eq!(nlme.mac.short_address, ShortAddress(BROADCAST));
+        assert_eq!(nlme.nib.extended_pan_id, 0);
+    }
+
+    #[test]
+    fn parent_removes_child() {
+        let mut nlme = parent();
+        let mut child = router(0x796f, Relationship::Child);
+        child.extended_address = Some(IeeeAddress(0x0001));
+        let _ = nlme.nib.neighbor_table.insert(child);
+        nlme.nib
+            .route_table
+            .insert(Route {
+                destination: ShortAddress(0x1234),
+                status: RouteStatus::Active,
+                no_route_cache: false,
+                many_to_one: false,
+                route_record_required: false,
+                next_hop: ShortAddress(0x796f),
+            })
+            .unwrap();
+
+        let unknown = block_on(NlmeSap::leave(&mut nlme, leave_request(Some(0x0002), true)));
+        let confirm = block_on(NlmeSap::leave(&mut nlme, leave_request(Some(0x0001), true)));
+
+        assert_eq!(unknown.status, NlmeLeaveStatus::UnknownDevice);
+        assert_eq!(confirm.status, NlmeLeaveStatus::Success);
+        assert_eq!(confirm.device_address, Some(0x0001));
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (_, header, request) = sent_leave(&nlme, 0);
+        assert_eq!(header.destination_ieee, Some(IeeeAddress(0x0001)));
+        assert!(request.request && request.rejoin);
+        assert!(nlme
+            .nib
+            .neighbor_table
+            .get_by_extended_address(IeeeAddress(0x0001))
+            .is_none());
+        assert!(nlme.nib.route_table.get(ShortAddress(0x1234)).is_none());
+        assert_eq!(nlme.take_departed_devices(), [IeeeAddress(0x0001)]);
+        // the coordinator remains on the network
+        assert_eq!(nlme.device_type, Some(LogicalType::Coordinator));
+    }
+
+    #[test]
+    fn end_device_leaves_on_request_of_its_parent() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::EndDevice);
+        nlme.mac.short_address = ShortAddress(0x796f);
+        nlme.mac.extended_address = IeeeAddress(0x0001);
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0002, Relationship::Parent));
+        let command = NwkCommand::Leave(Leave {
+            rejoin: true,
+            request: true,
+            remove_children: false,
+        });
+        let header = nwk_command_header(ShortAddress(0x796f), 0x0002, 1);
+        let received = &mut nlme.mac.radio.received;
+        // neither a router other than the parent nor an unsecured request
+        received.push_back(command_frame(
+            nwk_command_header(ShortAddress(0x796f), 0x0003, 1),
+            &command,
+            true,
+        ));
+        received.push_back(command_frame(header.clone(), &command, false));
+        received.push_back(command_frame(header, &command, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            indication,
+            Some(NlmeIndication::Leave(NlmeLeaveIndication {
+                device_address: None,
+                rejoin: true,
+            }))
+        );
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (_, _, announcement) = sent_leave(&nlme, 0);
+        assert!(!announcement.request && announcement.rejoin);
+        assert_eq!(nlme.device_type, None);
+        // the address is kept for the rejoin
+        assert_eq!(nlme.mac.short_address, ShortAddress(0x796f));
+        assert_eq!(nlme.nib.extended_pan_id, EXTENDED_PAN_ID);
+    }
+
+    #[test]
+    fn leave_request_without_rejoin_can_be_refused() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::Router);
+        nlme.mac.short_address = ShortAddress(0x796f);
+        nlme.nib.leave_request_without_rejoin_allowed = false;
+        let command = NwkCommand::Leave(Leave {
+            rejoin: false,
+            request: true,
+            remove_children: false,
+        });
+        // a request of the trust center
+        let header = nwk_command_header(ShortAddress(0x796f), 0x0000, 5);
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &command, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        assert_eq!(nlme.device_type, Some(LogicalType::Router));
+    }
+
+    #[test]
+    fn neighbor_which_left_is_forgotten() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::None));
+        nlme.nib
+            .route_table
+            .insert(Route {
+                destination: ShortAddress(0x0001),
+                status: RouteStatus::Active,
+                no_route_cache: false,
+                many_to_one: false,
+                route_record_required: false,
+                next_hop: ShortAddress(0x0001),
+            })
+            .unwrap();
+        let command = NwkCommand::Leave(Leave {
+            rejoin: false,
+            request: false,
+            remove_children: true,
+        });
+        let header = nwk_command_header(RX_ON_WHEN_IDLE_ADDRESS, 0x0001, 1);
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &command, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            indication,
+            Some(NlmeIndication::Leave(NlmeLeaveIndication {
+                device_address: Some(0x0001),
+                rejoin: false,
+            }))
+        );
+        assert!(nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x0001))
+            .is_none());
+        assert_eq!(nlme.nib.route_table.iter().count(), 0);
+        // the coordinator only leaves if its parent asks it to
+        assert_eq!(nlme.device_type, Some(LogicalType::Coordinator));
+    }
+}
@@ -0,0 +1,286 @@
+//! Link Status
+//!
+//! Routers broadcast the cost of the links to their router neighbors to the
+//! neighbors in range every nwkLinkStatusPeriod. The link status of a
+//! neighbor tells the cost of the link towards it, which makes link costs
+//! symmetric. Neighbors which stop sending link status are aged out.
+//!
+//! See Section 3.6.3.4.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::routing::ROUTERS_ADDRESS;
+use super::Nlme;
+use super::COORDINATOR_ADDRESS;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::mac::frame::Address;
+use crate::nwk::commands::LinkStatus;
+use crate::nwk::commands::LinkStatusEntry;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::commands::LINK_STATUS_LIST_SIZE;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::neighbor::Relationship;
+use crate::nwk::neighbor::MAX_NEIGHBORS;
+use crate::radio::Radio;
+
+impl<R: Radio> Nlme<R> {
+    /// Sends the link status when it is due and ages the router neighbors.
+    ///
+    /// The first link status is sent one nwkLinkStatusPeriod after the
+    /// device started operating as router.
+    pub(super) async fn maintain_link_status(&mut self) -> Result<(), R::Error> {
+        if !self.is_router() {
+            self.next_link_status = None;
+            return Ok(());
+        }
+        let now = self.mac.radio.now();
+        match self.next_link_status {
+            Some(due) if due <= now => {}
+            Some(_) => return Ok(()),
+            None => {
+                self.next_link_status = Some(now + self.nib.link_status_period);
+                return Ok(());
+            }
+        }
+        self.next_link_status = Some(now + self.nib.link_status_period);
+        self.nib
+            .neighbor_table
+            .age_routers(self.mac.pan_id, self.nib.router_age_limit);
+        self.send_link_status().await
+    }
+
+    /// The time the router sends its next link status.
+    pub(super) fn link_status_due(&self) -> Option<Duration> {
+        self.next_link_status.filter(|_| self.is_router())
+    }
+
+    /// Broadcasts the link status of all router neighbors to the neighbors
+    /// in range, split into multiple frames if they do not fit into one.
+    ///
+    /// See Section 3.6.3.4.1.
+    async fn send_link_status(&mut self) -> Result<(), R::Error> {
+        let mut commands: Vec<LinkStatus, { MAX_NEIGHBORS / LINK_STATUS_LIST_SIZE + 1 }> =
+            Vec::new();
+        {
+            let mut routers: Vec<&Neighbor, MAX_NEIGHBORS> =
+                self.nib.neighbor_table.routers(self.mac.pan_id).collect();
+            routers.sort_unstable_by_key(|router| router.network_address.0);
+            for chunk in routers.chunks(LINK_STATUS_LIST_SIZE) {
+                let _ = commands.push(LinkStatus {
+                    first_frame: commands.is_empty(),
+                    last_frame: false,
+                    entries: chunk
+                        .iter()
+                        .map(|router| LinkStatusEntry {
+                            address: router.network_address,
+                            incoming_cost: router.incoming_cost(),
+                            outgoing_cost: router.outgoing_cost,
+                        })
+                        .collect(),
+                });
+            }
+        }
+        // a router without neighbors still announces itself
+        if commands.is_empty() {
+            let _ = commands.push(LinkStatus {
+                first_frame: true,
+                last_frame: false,
+                entries: Vec::new(),
+            });
+        }
+        if let Some(last) = commands.last_mut() {
+            last.last_frame = true;
+        }
+
+        for link_status in commands {
+            // link status commands are not relayed
+            let header = self.command_header(ROUTERS_ADDRESS, 1);
+            let command = NwkCommand::LinkStatus(link_status);
+            self.send_nwk_command(Address::BROADCAST, header, &command, true)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Updates the neighbor table from the link status of a router, which
+    /// is added if it is not yet known.
+    ///
+    /// See Section 3.6.3.4.2.
+    pub(super) fn record_link_status(
+        &mut self,
+        header: &NwkHeader,
+        link_status: &LinkStatus,
+        lqi: u8,
+    ) {
+        // link status commands are only exchanged by neighboring routers
+        if !self.is_router() || header.radius != 1 {
+            return;
+        }
+        let pan_id = self.mac.pan_id;
+        let table = &mut self.nib.neighbor_table;
+        if table.get(pan_id, header.source).is_none() {
+            let _ = table.insert(Neighbor {
+                extended_address: header.source_ieee,
+                network_address: header.source,
+                device_type: if header.source.0 == COORDINATOR_ADDRESS {
+                    LogicalType::Coordinator
+                } else {
+                    LogicalType::Router
+                },
+                rx_on_when_idle: true,
+                relationship: Relationship::None,
+                lqi,
+                pan_id,
+                extended_pan_id: self.nib.extended_pan_id,
+                logical_channel: self.mac.channel,
+                depth: 0,
+                permit_joining: false,
+                router_capacity: false,
+                end_device_capacity: false,
+                potential_parent: false,
+                update_id: self.nib.update_id,
+                outgoing_cost: 0,
+                age: 0,
+                device_timeout: None,
+                timeout_expiry: None,
+                keepalive: None,
+                incoming_frame_counter: None,
+            });
+        }
+        let _ = table.link_status(pan_id, header.source, self.mac.short_address, link_status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::IeeeAddress;
+    use crate::common::types::ShortAddress;
+    use crate::mac::frame::BROADCAST;
+    use crate::nwk::frame::FrameControl;
+    use crate::nwk::frame::FrameTypeIdentifier;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+
+    #[test]
+    fn link_status_of_unknown_router_adds_neighbor() {
+        let mut nlme = parent();
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        frame_control.set_source_ieee_flag(true);
+        let header = NwkHeader {
+            frame_control,
+            destination: ShortAddress(BROADCAST - 3),
+            source: ShortAddress(0x0001),
+            radius: 1,
+            sequence_number: 1,
+            destination_ieee: None,
+            source_ieee: Some(IeeeAddress(0x0001)),
+            multicast_control: None,
+            source_route_subframe: None,
+        };
+        let mut entries = Vec::new();
+        let _ = entries.push(LinkStatusEntry {
+            address: ShortAddress(COORDINATOR_ADDRESS),
+            incoming_cost: 3,
+            outgoing_cost: 1,
+        });
+        let command = NwkCommand::LinkStatus(LinkStatus {
+            first_frame: true,
+            last_frame: true,
+            entries,
+        });
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &command, true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        let router = nlme
+            .nib
+            .neighbor_table
+            .get_by_extended_address(IeeeAddress(0x0001))
+            .unwrap();
+        assert_eq!(router.network_address, ShortAddress(0x0001));
+        assert_eq!(router.device_type, LogicalType::Router);
+        assert_eq!(router.relationship, Relationship::None);
+        assert_eq!((router.lqi, router.outgoing_cost, router.age), (230, 3, 0));
+    }
+
+    #[test]
+    fn router_sends_link_status_every_period() {
+        let mut nlme = parent();
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        frame_control.set_source_ieee_flag(true);
+        let header = NwkHeader {
+            frame_control,
+            destination: ROUTERS_ADDRESS,
+            source: ShortAddress(0x0001),
+            radius: 1,
+            sequence_number: 1,
+            destination_ieee: None,
+            source_ieee: Some(IeeeAddress(0x0001)),
+            multicast_control: None,
+            source_route_subframe: None,
+        };
+        let mut entries = Vec::new();
+        let _ = entries.push(LinkStatusEntry {
+            address: ShortAddress(COORDINATOR_ADDRESS),
+            incoming_cost: 3,
+            outgoing_cost: 1,
+        });
+        let command = NwkCommand::LinkStatus(LinkStatus {
+            first_frame: true,
+            last_frame: true,
+            entries,
+        });
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &command, true));
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        block_on(nlme.process(Duration::from_secs(16))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.destination, ROUTERS_ADDRESS);
+        assert_eq!(frame.header.radius, 1);
+        let Ok(NwkCommand::LinkStatus(link_status)) = frame.command() else {
+            unreachable!("no link status sent");
+        };
+        assert!(link_status.first_frame && link_status.last_frame);
+        assert_eq!(
+            link_status.entries,
+            [LinkStatusEntry {
+                address: ShortAddress(0x0001),
+                incoming_cost: 1,
+                outgoing_cost: 3,
+            }]
+        );
+
+        // the router stays silent for more than nwkRouterAgeLimit periods
+        block_on(nlme.process(Duration::from_secs(45))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 4);
+        let (_, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 3) else {
+            unreachable!("no command frame sent");
+        };
+        let Ok(NwkCommand::LinkStatus(link_status)) = frame.command() else {
+            unreachable!("no link status sent");
+        };
+        assert!(link_status.entries.is_empty());
+        assert!(nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x0001))
+            .is_none());
+    }
+}
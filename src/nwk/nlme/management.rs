@@ -1,24 +1,43 @@
 #![allow(dead_code)]
 
+use heapless::Vec;
+
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::mac::scan::MAX_SCAN_CHANNELS;
+use crate::nwk::commands::NetworkStatusCode;
+use crate::nwk::nib::NibAttribute;
+use crate::nwk::nlde::NldeDataIndication;
+use crate::radio::ChannelMask;
+
+/// Maximum number of networks reported by a network discovery.
+pub const MAX_NETWORK_DESCRIPTORS: usize = 16;
+
 /// 3.2.2.3 NLME-NETWORK-DISCOVERY.request
 pub struct NlmeNetworkDiscoveryRequest {
-    pub(crate) scan_channels_list_structure: [u8; 8],
+    pub(crate) scan_channels_list_structure: ChannelMask,
     pub(crate) scan_duration: u8,
 }
 
 /// 3.2.2.4 - NLME-NETWORK-DISCOVERY.confirm
 pub struct NlmeNetworkDiscoveryConfirm {
     pub status: NlmeNetworkDiscoveryStatus,
-    pub(crate) network_count: u8,
-    pub(crate) network_descriptor: NetworkDescriptor,
+    /// the networks found, one entry per network
+    pub network_descriptors: Vec<NetworkDescriptor, MAX_NETWORK_DESCRIPTORS>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum NlmeNetworkDiscoveryStatus {
     Successful,
+    /// the scan duration or the channel list is invalid
+    InvalidParameter,
+    /// no beacon was received on any of the scanned channels
+    NoBeacon,
+    /// the radio driver reported an error
+    RadioError,
 }
 
 /// Network descriptor
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NetworkDescriptor {
     /// 64-bit PAN identifier
     pub extended_pan_id: u64,
@@ -42,73 +61,311 @@ pub struct NetworkDescriptor {
     pub router_capacity: bool,
     /// set to TRUE if the device is capable of accepting join requests from end devices
     pub end_device_capacity: bool,
+    /// lowest network depth of the devices that sent a beacon
+    pub device_depth: u8,
+    /// best link quality of the received beacons
+    pub lqi: u8,
 }
 
 /// 3.2.2.5 - NLME-NETWORK-FORMATION.request
-pub struct NlmeNetworkFormationRequest {}
+pub struct NlmeNetworkFormationRequest {
+    pub(crate) scan_channels_list_structure: ChannelMask,
+    pub(crate) scan_duration: u8,
+    /// only non-beacon networks (15) are supported
+    pub(crate) beacon_order: u8,
+    pub(crate) superframe_order: u8,
+    pub(crate) battery_life_extension: bool,
+    /// the nwkExtendedPANId to use, 0 uses the extended address of the device
+    pub(crate) extended_pan_id: u64,
+}
 /// 3.2.2.6 - NLME-NETWORK-FORMATION.confirm
-pub struct NlmeNetworkFormationConfirm {}
+pub struct NlmeNetworkFormationConfirm {
+    /// the result of the network formation
+    pub status: NlmeNetworkFormationStatus,
+    /// the PAN ID of the new network
+    pub pan_id: u16,
+    /// the extended PAN ID of the new network
+    pub extended_pan_id: u64,
+    /// the channel the network operates on
+    pub logical_channel: u8,
+}
+
+/// Status of an NLME-NETWORK-FORMATION.request
+#[derive(Debug, PartialEq, Eq)]
+pub enum NlmeNetworkFormationStatus {
+    /// the network was formed and the device operates as coordinator
+    Success,
+    /// the device is already operating on a network
+    InvalidRequest,
+    /// the scan duration, channel list or superframe configuration is invalid
+    InvalidParameter,
+    /// no channel with an acceptable energy level or no free PAN ID was found
+    StartupFailure,
+    /// the radio driver reported an error
+    RadioError,
+}
 
 /// 3.2.2.7 - NLME-PERMIT-JOINING.request
-pub struct NlmePermitJoiningRequest {}
+pub struct NlmePermitJoiningRequest {
+    /// seconds joining is permitted, 0x00 disables joining and 0xff permits
+    /// it indefinitely
+    pub(crate) permit_duration: u8,
+}
 /// 3.2.2.8 - NLME-PERMIT-JOINING.confirm
-pub struct NlmePermitJoiningConfirm {}
+pub struct NlmePermitJoiningConfirm {
+    /// the result of updating the permit joining period
+    pub status: NlmePermitJoiningStatus,
+}
+
+/// Status of an NLME-PERMIT-JOINING.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmePermitJoiningStatus {
+    /// joining is permitted for the requested duration
+    Success,
+    /// the device is not a router or coordinator operating on a network
+    InvalidRequest,
+    /// the radio driver reported an error
+    RadioError,
+}
 /// 3.2.2.9 - NLME-START-ROUTER.request
-pub struct NlmeStartRouterRequest {}
+pub struct NlmeStartRouterRequest {
+    /// only non-beacon networks (15) are supported
+    pub(crate) beacon_order: u8,
+    pub(crate) superframe_order: u8,
+    pub(crate) battery_life_extension: bool,
+}
 /// 3.2.2.10 - NLME-START-ROUTER.confirm
-pub struct NlmeStartRouterConfirm {}
+pub struct NlmeStartRouterConfirm {
+    /// the result of starting the router
+    pub status: NlmeStartRouterStatus,
+}
+
+/// Status of an NLME-START-ROUTER.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmeStartRouterStatus {
+    /// the device routes frames and accepts joining devices
+    Success,
+    /// the device did not join a network as router
+    InvalidRequest,
+    /// the superframe configuration is invalid
+    InvalidParameter,
+    /// the radio driver reported an error
+    RadioError,
+}
 /// 3.2.2.11 - NLME-ED-SCAN.request
-pub struct NlmeEdScanRequest {}
+pub struct NlmeEdScanRequest {
+    pub(crate) scan_channels_list_structure: ChannelMask,
+    pub(crate) scan_duration: u8,
+}
 /// 3.2.2.12 - NLME-ED-SCAN.confirm
-pub struct NlmeEdScanConfirm {}
+pub struct NlmeEdScanConfirm {
+    /// the result of the energy detection scan
+    pub status: NlmeEdScanStatus,
+    /// the measured energy of each scanned channel in ascending channel order
+    pub energy_detect_list: Vec<ChannelEnergy, MAX_SCAN_CHANNELS>,
+}
+
+/// Status of an NLME-ED-SCAN.request
+#[derive(Debug, PartialEq, Eq)]
+pub enum NlmeEdScanStatus {
+    /// all requested channels were scanned
+    Success,
+    /// the scan duration or the channel list is invalid
+    InvalidParameter,
+    /// the radio driver reported an error
+    RadioError,
+}
+
+/// Energy measured on a channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelEnergy {
+    /// the scanned channel
+    pub channel: u8,
+    /// peak energy in the range of 0 to 255
+    pub energy: u8,
+}
 /// 3.2.2.13 - NLME-JOIN.request
 pub struct NlmeJoinRequest {
     pub(crate) extended_pan_id: u64,
-    pub(crate) rejoin_network: u8,
-    // ScanChannelsListStructure
+    pub(crate) rejoin_network: RejoinNetwork,
+    pub(crate) scan_channels_list_structure: ChannelMask,
     pub(crate) scan_duration: u8,
-    // CapabilityInformation
+    pub(crate) capability_information: MacCapabilities,
     pub(crate) security_enabled: bool,
 }
+
+/// The method used to join a network
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejoinNetwork {
+    /// join through MAC association
+    Association = 0x00,
+    /// join directly or through orphaning
+    Orphan = 0x01,
+    /// rejoin using the NWK rejoin procedure
+    Rejoin = 0x02,
+    /// change the operating channel
+    ChangeChannel = 0x03,
+}
+
 /// 3.2.2.14 - NLME-JOIN.indication
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct NlmeJoinIndication {
-    pub(crate) network_address: u16,
-    pub(crate) extended_address: u64,
-    //CapabilityInformation
-    pub(crate) rejoin_network: u8,
-    pub(crate) secure_rejoin: bool,
+    /// network address of the device which joined
+    pub network_address: u16,
+    /// IEEE address of the device which joined
+    pub extended_address: u64,
+    /// capabilities reported by the device
+    pub capability_information: MacCapabilities,
+    /// the method used to join
+    pub rejoin_network: RejoinNetwork,
+    /// the rejoin request was secured with the network key
+    pub secure_rejoin: bool,
+}
+
+/// 3.2.2.30 - NLME-NWK-STATUS.indication
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NlmeNwkStatusIndication {
+    /// the event or failure which occurred
+    pub status: NetworkStatusCode,
+    /// network address of the device the status refers to
+    pub network_address: u16,
+}
+
+/// Indication passed to the next higher layer while frames are processed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NlmeIndication {
+    /// 3.2.2.14 - NLME-JOIN.indication
+    Join(NlmeJoinIndication),
+    /// 3.2.1.3 - NLDE-DATA.indication
+    Data(NldeDataIndication),
+    /// 3.2.2.30 - NLME-NWK-STATUS.indication
+    NetworkStatus(NlmeNwkStatusIndication),
+    /// 3.2.2.19 - NLME-LEAVE.indication
+    Leave(NlmeLeaveIndication),
 }
 /// 3.2.2.15 - NLME-JOIN.confirm
 pub struct NlmeJoinConfirm {
     pub(crate) status: NlmeJoinStatus,
     pub(crate) network_address: u16,
     pub(crate) extended_pan_id: u64,
-    // Channel List Structure
+    pub(crate) active_channel: u8,
     pub(crate) enhanced_beacon_type: bool,
     pub(crate) mac_interface_index: u8,
 }
 
-pub(crate) enum NlmeJoinStatus {
+/// Status of an NLME-JOIN.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmeJoinStatus {
+    /// the device joined the network
     Success,
+    /// the device is already on a network or the join method is unsupported
     InvalidRequest,
+    /// no network with the extended PAN ID permits joining
     NotPermitted,
+    /// no network with the extended PAN ID was discovered
     NoNetworks,
-    // TODO: add more from 3.2.2.13.3
+    /// the parent has no capacity left
+    PanAtCapacity,
+    /// the parent denied the association
+    PanAccessDenied,
+    /// the channel was busy
+    ChannelAccessFailure,
+    /// the parent did not acknowledge a request
+    NoAck,
+    /// the parent did not respond
+    NoData,
+    /// the radio driver reported an error
+    RadioError,
 }
-
 /// 3.2.2.16 - NLME-DIRECT-JOIN.request
 pub struct NlmeDirectJoinRequest {}
 /// 3.2.2.17 - NLME-DIRECT-JOIN.confirm
 pub struct NlmeDirectJoinConfirm {}
 
 /// 3.2.2.18 - NLME-LEAVE.request
-pub struct NlmeLeaveRequest {}
+pub struct NlmeLeaveRequest {
+    /// IEEE address of the child to remove, `None` if this device leaves
+    pub(crate) device_address: Option<u64>,
+    /// the children of the leaving device leave as well
+    pub(crate) remove_children: bool,
+    /// the leaving device rejoins the network
+    pub(crate) rejoin: bool,
+}
+
 /// 3.2.2.19 - NLME-LEAVE.indication
-pub struct NlmeLeaveIndication {}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NlmeLeaveIndication {
+    /// IEEE address of the device which left, `None` if this device left
+    pub device_address: Option<u64>,
+    /// the device which left rejoins the network
+    pub rejoin: bool,
+}
+
 /// 3.2.2.20 - NLME-LEAVE.confirm
-pub struct NlmeLeaveConfirm {}
+pub struct NlmeLeaveConfirm {
+    /// the result of the leave request
+    pub status: NlmeLeaveStatus,
+    /// IEEE address of the removed child, `None` if this device left
+    pub device_address: Option<u64>,
+}
+
+/// Status of an NLME-LEAVE.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmeLeaveStatus {
+    /// the device left the network or the child was removed
+    Success,
+    /// the device is not operating on a network
+    InvalidRequest,
+    /// the device to remove is not a child of this device
+    UnknownDevice,
+    /// the channel was busy
+    ChannelAccessFailure,
+    /// the child did not acknowledge the leave request
+    NoAck,
+    /// the radio driver reported an error
+    RadioError,
+}
 
 /// 3.2.2.21 - NLME-RESET.request
 pub struct NlmeResetRequest {}
 /// 3.2.2.22 - NLME-RESET.confirm
 pub struct NlmeResetConfirm {}
+
+/// 3.2.2.27 - NLME-GET.confirm
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NlmeGetConfirm {
+    /// the result of reading the attribute
+    pub status: NlmeGetStatus,
+    /// identifier of the requested attribute
+    pub attribute_id: u8,
+    /// the value of the attribute, if it is supported
+    pub attribute: Option<NibAttribute>,
+}
+
+/// Status of an NLME-GET.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmeGetStatus {
+    /// the attribute was read
+    Success,
+    /// the identifier is not a scalar NIB attribute
+    UnsupportedAttribute,
+}
+
+/// 3.2.2.29 - NLME-SET.confirm
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NlmeSetConfirm {
+    /// the result of writing the attribute
+    pub status: NlmeSetStatus,
+    /// identifier of the written attribute
+    pub attribute_id: u8,
+}
+
+/// Status of an NLME-SET.request
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NlmeSetStatus {
+    /// the attribute was written
+    Success,
+    /// the value is out of the valid range of the attribute
+    InvalidParameter,
+}
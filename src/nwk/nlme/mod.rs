@@ -14,21 +14,111 @@
 //! * routing
 #![allow(dead_code)]
 
+use core::time::Duration;
+
+use association::PendingAssociation;
+use association::MAX_PENDING_ASSOCIATIONS;
+use broadcast::Rebroadcast;
+use broadcast::MAX_REBROADCASTS;
+use heapless::Vec;
+use indirect::IndirectFrame;
+use indirect::MAX_INDIRECT_FRAMES;
+use management::ChannelEnergy;
 use management::NlmeEdScanConfirm;
 use management::NlmeEdScanRequest;
+use management::NlmeEdScanStatus;
+use management::NlmeGetConfirm;
+use management::NlmeGetStatus;
+use management::NlmeIndication;
 use management::NlmeJoinConfirm;
 use management::NlmeJoinRequest;
+use management::NlmeJoinStatus;
+use management::NlmeLeaveConfirm;
+use management::NlmeLeaveRequest;
 use management::NlmeNetworkDiscoveryConfirm;
 use management::NlmeNetworkDiscoveryRequest;
+use management::NlmeNetworkDiscoveryStatus;
 use management::NlmeNetworkFormationConfirm;
 use management::NlmeNetworkFormationRequest;
 use management::NlmePermitJoiningConfirm;
 use management::NlmePermitJoiningRequest;
+use management::NlmeSetConfirm;
+use management::NlmeSetStatus;
 use management::NlmeStartRouterConfirm;
 use management::NlmeStartRouterRequest;
+use management::NlmeStartRouterStatus;
+use management::RejoinNetwork;
+pub use poll::PollRates;
+use routing::PendingFrame;
+use routing::MAX_PENDING_FRAMES;
+pub use routing::ROUTERS_ADDRESS;
+
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::apl::descriptors::node_descriptor::MacCapabilityFlag;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::command::MacCommand;
+use crate::mac::frame::Address;
+use crate::mac::frame::MacCommandFrame;
+use crate::mac::frame::MacFrame;
+use crate::mac::frame::BROADCAST;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::mac::scan::MAX_SCAN_DURATION;
+use crate::mac::Mac;
+use crate::mac::Received;
+use crate::nwk::beacon::BeaconPayload;
+use crate::nwk::beacon::BEACON_PAYLOAD_SIZE;
+use crate::nwk::beacon::ZIGBEE_PROTOCOL_ID;
+use crate::nwk::commands::EndDeviceTimeout;
+use crate::nwk::commands::NetworkStatusCode;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::frame::CommandFrameIdentifier;
+use crate::nwk::frame::DiscoverRoute;
+use crate::nwk::frame::FrameControl;
+use crate::nwk::frame::FrameTypeIdentifier;
+use crate::nwk::frame::MulticastControl;
+use crate::nwk::frame::NwkDataFrame;
+use crate::nwk::frame::NwkFrame;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::neighbor::Relationship;
+use crate::nwk::neighbor::MAX_NEIGHBORS;
+use crate::nwk::nib::NetworkInformationBase;
+use crate::nwk::nib::NibAttribute;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::nwk::nlde::NldeAddressMode;
+use crate::nwk::nlde::NldeDataConfirm;
+use crate::nwk::nlde::NldeDataIndication;
+use crate::nwk::nlde::NldeDataRequest;
+use crate::nwk::nlde::NldeDataStatus;
+use crate::nwk::security;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
 
+mod address_conflict;
+mod addressing;
+mod association;
+mod broadcast;
+mod discovery;
+mod end_device_timeout;
+mod formation;
+mod indirect;
+mod join;
+mod leave;
+mod link_status;
 /// Network management entity
 pub mod management;
+mod multicast;
+mod permit_joining;
+mod poll;
+mod rejoin;
+mod routing;
+mod start_router;
+#[cfg(test)]
+mod test_helpers;
 
 /// Network management service - service access point
 ///
@@ -36,73 +126,836 @@ pub mod management;
 ///
 /// allows the transport of management commands between the next higher layer
 /// and the NLME.
+#[allow(async_fn_in_trait)]
 pub trait NlmeSap {
     /// 3.2.2.3
-    fn network_discovery(
-        &self,
+    async fn network_discovery(
+        &mut self,
         request: NlmeNetworkDiscoveryRequest,
     ) -> NlmeNetworkDiscoveryConfirm;
     /// 3.2.2.5
-    fn network_formation(
-        &self,
+    async fn network_formation(
+        &mut self,
         request: NlmeNetworkFormationRequest,
     ) -> NlmeNetworkFormationConfirm;
     /// 3.2.2.7
-    fn permit_joining(&self, request: NlmePermitJoiningRequest) -> NlmePermitJoiningConfirm;
+    async fn permit_joining(
+        &mut self,
+        request: NlmePermitJoiningRequest,
+    ) -> NlmePermitJoiningConfirm;
     /// 3.2.2.9
-    fn start_router(&self, request: NlmeStartRouterRequest) -> NlmeStartRouterConfirm;
+    async fn start_router(&mut self, request: NlmeStartRouterRequest) -> NlmeStartRouterConfirm;
     /// 3.2.2.11
-    fn ed_scan(&self, request: NlmeEdScanRequest) -> NlmeEdScanConfirm;
+    async fn ed_scan(&mut self, request: NlmeEdScanRequest) -> NlmeEdScanConfirm;
     // 3.2.2.13
-    fn join(&self, request: NlmeJoinRequest) -> NlmeJoinConfirm;
+    async fn join(&mut self, request: NlmeJoinRequest) -> NlmeJoinConfirm;
+    /// 3.2.2.18
+    async fn leave(&mut self, request: NlmeLeaveRequest) -> NlmeLeaveConfirm;
+    /// 3.2.2.26
+    fn get(&self, attribute: u8) -> NlmeGetConfirm;
+    /// 3.2.2.28
+    fn set(&mut self, attribute: NibAttribute) -> NlmeSetConfirm;
 }
 
-pub(crate) struct Nlme {}
+/// nwkStackProfile of Zigbee PRO
+pub(crate) const STACK_PROFILE: u8 = 0x02;
 
-impl Nlme {
-    pub(crate) fn new() -> Self {
-        Self {}
-    }
+/// nwkcProtocolVersion
+pub(crate) const PROTOCOL_VERSION: u8 = 0x02;
+
+/// Network address of the coordinator.
+pub(crate) const COORDINATOR_ADDRESS: u16 = 0x0000;
+
+/// Beacon and superframe order of non-beacon networks.
+const NON_BEACON_ORDER: u8 = 15;
+
+pub(crate) struct Nlme<R> {
+    pub(crate) mac: Mac<R>,
+    /// NWK information base
+    pub(crate) nib: NetworkInformationBase,
+    /// Network depth of the device.
+    pub(crate) depth: u8,
+    /// The role of the device while it operates on a network.
+    pub(crate) device_type: Option<LogicalType>,
+    /// The device operates as the coordinator or a started router, which
+    /// answers beacon requests and admits joining devices.
+    router_started: bool,
+    /// Frames waiting for the discovery of a route.
+    pending_frames: Vec<PendingFrame, MAX_PENDING_FRAMES>,
+    /// The time the concentrator sends its next many-to-one route request.
+    next_many_to_one_request: Option<Duration>,
+    /// Broadcasts relayed by this device.
+    rebroadcasts: Vec<Rebroadcast, MAX_REBROADCASTS>,
+    /// The time the router sends its next link status.
+    next_link_status: Option<Duration>,
+    /// Association responses waiting to be polled by joining devices.
+    associations: Vec<PendingAssociation, MAX_PENDING_ASSOCIATIONS>,
+    /// The time joining is no longer permitted.
+    permit_joining_until: Option<Duration>,
+    /// The timeout this end device negotiated with its parent.
+    end_device_timeout: Option<EndDeviceTimeout>,
+    /// The time this end device sends its next keep-alive.
+    next_keepalive: Option<Duration>,
+    /// Frames kept for children which turn their receiver off while idle.
+    indirect_frames: Vec<IndirectFrame, MAX_INDIRECT_FRAMES>,
+    /// The poll rates of this end device if it turns its receiver off while
+    /// idle.
+    poll_rates: Option<PollRates>,
+    /// The time this end device polls its parent next.
+    next_poll: Option<Duration>,
+    /// The time this end device returns to the slow poll rate.
+    fast_poll_until: Option<Duration>,
+    /// The devices which left the network, until the bindings to them are
+    /// removed.
+    departed_devices: Vec<IeeeAddress, MAX_NEIGHBORS>,
 }
 
-impl NlmeSap for Nlme {
-    fn network_discovery(
+impl<R: Radio> Nlme<R> {
+    pub(crate) fn new(radio: R) -> Self {
+        Self {
+            mac: Mac::new(radio),
+            nib: NetworkInformationBase::new(),
+            depth: 0,
+            device_type: None,
+            router_started: false,
+            pending_frames: Vec::new(),
+            next_many_to_one_request: None,
+            rebroadcasts: Vec::new(),
+            next_link_status: None,
+            associations: Vec::new(),
+            permit_joining_until: None,
+            end_device_timeout: None,
+            next_keepalive: None,
+            indirect_frames: Vec::new(),
+            poll_rates: None,
+            next_poll: None,
+            fast_poll_until: None,
+            departed_devices: Vec::new(),
+        }
+    }
+
+    /// Handles the frames received within `timeout`.
+    ///
+    /// An end device which turns its receiver off while idle sleeps until it
+    /// polls its parent. Returns early with the first indication for the
+    /// next higher layer.
+    pub(crate) async fn process(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let deadline = self.mac.radio.now() + timeout;
+        loop {
+            self.maintain_routes().await?;
+            self.maintain_broadcasts().await?;
+            self.maintain_link_status().await?;
+            self.maintain_permit_joining();
+            self.maintain_children();
+            self.maintain_keepalive().await?;
+            if let Some(indication) = self.maintain_polling().await? {
+                return Ok(Some(indication));
+            }
+            let wake_up = [
+                self.many_to_one_request_due(),
+                self.next_rebroadcast(),
+                self.link_status_due(),
+                self.permit_joining_until,
+                self.next_child_timeout(),
+                self.next_keepalive,
+                self.next_poll,
+            ]
+            .iter()
+            .flatten()
+            .fold(deadline, |wake_up, &due| wake_up.min(due));
+            let timeout = wake_up.saturating_sub(self.mac.radio.now());
+            let received = if self.mac.rx_on_when_idle {
+                self.mac.receive(timeout).await?
+            } else {
+                self.mac.radio.sleep(timeout).await?;
+                None
+            };
+            let Some(received) = received else {
+                if self.mac.radio.now() < deadline {
+                    continue;
+                }
+                return Ok(None);
+            };
+            if let Some(indication) = self.handle(received).await? {
+                return Ok(Some(indication));
+            }
+        }
+    }
+
+    async fn handle(&mut self, received: Received) -> Result<Option<NlmeIndication>, R::Error> {
+        match received.frame {
+            MacFrame::Command(frame) => self.receive_mac_command(frame, received.lqi).await,
+            MacFrame::Data(data) => {
+                let sender = match data.header.source {
+                    Some(Address::Short(source)) => Some(source),
+                    _ => None,
+                };
+                let Some((frame, secured)) = self.open_frame(&data.payload, sender) else {
+                    return Ok(None);
+                };
+                if let Some(sender) = sender {
+                    let _ = self
+                        .nib
+                        .neighbor_table
+                        .received(self.mac.pan_id, sender, received.lqi);
+                }
+                // frames for the previous address of this device are dropped
+                if let Some(indication) = self.record_originator(&frame).await? {
+                    return Ok(Some(indication));
+                }
+                let frame = match frame {
+                    NwkFrame::Data(frame) => {
+                        return self
+                            .receive_data(frame, secured, sender, received.lqi, received.timestamp)
+                            .await;
+                    }
+                    NwkFrame::NwkCommand(frame) => frame,
+                    NwkFrame::InterPan(_) => return Ok(None),
+                };
+                // route requests are relayed by the route discovery
+                if frame.header.destination.0 >= MIN_BROADCAST_ADDRESS
+                    && frame.command_identifier != CommandFrameIdentifier::RouteRequest
+                {
+                    let mut payload: Vec<u8, MAX_PHY_PACKET_SIZE> = Vec::new();
+                    let _ = payload.push(frame.command_identifier.to_u8());
+                    let _ = payload.extend_from_slice(&frame.payload);
+                    if !self.receive_broadcast(&frame.header, &payload, secured, sender)
+                        || !self.is_broadcast_member(frame.header.destination)
+                    {
+                        return Ok(None);
+                    }
+                }
+                match (frame.command(), sender) {
+                    (Ok(NwkCommand::RejoinRequest(request)), _) => {
+                        self.accept_rejoin(&frame.header, request, secured, received.lqi)
+                            .await
+                    }
+                    (Ok(NwkCommand::LinkStatus(link_status)), _) => {
+                        self.record_link_status(&frame.header, &link_status, received.lqi);
+                        Ok(None)
+                    }
+                    (Ok(NwkCommand::RouteRequest(request)), Some(sender)) => {
+                        self.receive_route_request(frame.header, request, sender)
+                            .await?;
+                        Ok(None)
+                    }
+                    (Ok(NwkCommand::RouteRecord(record)), _) => {
+                        self.receive_route_record(frame.header, record).await?;
+                        Ok(None)
+                    }
+                    (Ok(NwkCommand::RouteReply(reply)), Some(sender)) => {
+                        self.receive_route_reply(frame.header, reply.clone(), sender)
+                            .await?;
+                        self.record_reply_addresses(&reply).await
+                    }
+                    (Ok(NwkCommand::NetworkStatus(status)), _)
+                        if status.status_code == NetworkStatusCode::AddressConflict =>
+                    {
+                        self.resolve_address_conflict(status.destination_address)
+                            .await
+                    }
+                    (Ok(NwkCommand::RejoinResponse(response)), Some(sender)) => {
+                        self.receive_new_address(&frame.header, response, secured, sender)
+                            .await
+                    }
+                    (Ok(NwkCommand::Leave(leave)), _) => {
+                        self.receive_leave(&frame.header, leave, secured, sender)
+                            .await
+                    }
+                    (Ok(NwkCommand::EndDeviceTimeoutRequest(request)), Some(sender)) => {
+                        self.receive_timeout_request(&frame.header, request, secured, sender)
+                            .await?;
+                        Ok(None)
+                    }
+                    (Ok(NwkCommand::EndDeviceTimeoutResponse(response)), Some(sender)) => {
+                        self.receive_timeout_response(&frame.header, response, secured, sender);
+                        Ok(None)
+                    }
+                    _ => Ok(None),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Answers beacon requests and the requests of devices associating
+    /// through this device, and sends children polling for them the frames
+    /// kept for them.
+    async fn receive_mac_command(
+        &mut self,
+        frame: MacCommandFrame,
+        lqi: u8,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        match (frame.command, frame.header.source) {
+            (MacCommand::BeaconRequest, _) if self.router_started => {
+                let mut payload = [0u8; BEACON_PAYLOAD_SIZE];
+                let len = self
+                    .beacon_payload()
+                    .pack_into(&mut payload)
+                    .unwrap_or_default();
+                self.mac.send_beacon(&payload[..len]).await?;
+                Ok(None)
+            }
+            (
+                MacCommand::AssociationRequest(capability_information),
+                Some(Address::Extended(device)),
+            ) => {
+                self.receive_association_request(device, capability_information, lqi);
+                Ok(None)
+            }
+            (MacCommand::DataRequest, Some(Address::Extended(device))) => {
+                self.send_association_response(device).await
+            }
+            (MacCommand::DataRequest, Some(Address::Short(device))) => {
+                self.receive_poll(device).await?;
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Passes a data frame for this device to the next higher layer, and
+    /// relays frames for other devices.
+    ///
+    /// See Section 3.6.2.2.
+    async fn receive_data(
+        &mut self,
+        frame: NwkDataFrame,
+        secured: bool,
+        sender: Option<ShortAddress>,
+        lqi: u8,
+        rx_time: Duration,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let destination = frame.header.destination;
+        let mut destination_address_mode = NldeAddressMode::Network;
+        if frame.header.frame_control.multicast_flag() {
+            if !self
+                .receive_multicast(&frame.header, &frame.payload, secured, sender)
+                .await?
+            {
+                return Ok(None);
+            }
+            destination_address_mode = NldeAddressMode::Group;
+        } else if destination.0 >= MIN_BROADCAST_ADDRESS {
+            if !self.receive_broadcast(&frame.header, &frame.payload, secured, sender)
+                || !self.is_broadcast_member(destination)
+            {
+                return Ok(None);
+            }
+        } else if destination != self.mac.short_address {
+            self.relay(frame, secured).await?;
+            return Ok(None);
+        }
+        let Ok(nsdu) = Vec::from_slice(&frame.payload) else {
+            return Ok(None);
+        };
+        Ok(Some(NlmeIndication::Data(NldeDataIndication {
+            destination_address_mode,
+            destination,
+            source: frame.header.source,
+            nsdu,
+            link_quality: lqi,
+            rx_time,
+            security_use: secured,
+        })))
+    }
+
+    /// Sends the NSDU of the next higher layer to a device, a broadcast
+    /// address or a group.
+    ///
+    /// Group destinations require nwkUseMulticast, otherwise the next higher
+    /// layer addresses groups through broadcasts.
+    ///
+    /// See Section 3.6.2.1.
+    pub(crate) async fn data_request(
+        &mut self,
+        request: NldeDataRequest,
+    ) -> Result<NldeDataConfirm, R::Error> {
+        let tx_time = self.mac.radio.now();
+        let group = request.destination_address_mode == NldeAddressMode::Group;
+        let broadcast = !group && request.destination.0 >= MIN_BROADCAST_ADDRESS;
+        if self.device_type.is_none()
+            || (group && !self.nib.use_multicast)
+            || request.non_member_radius > MulticastControl::INFINITE_RADIUS
+        {
+            return Ok(NldeDataConfirm {
+                status: NldeDataStatus::InvalidRequest,
+                tx_time,
+            });
+        }
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::Data);
+        if request.discover_route && !group && !broadcast {
+            frame_control.set_discover_route(DiscoverRoute::Enable);
+        }
+        let header = NwkHeader {
+            frame_control,
+            destination: request.destination,
+            source: self.mac.short_address,
+            radius: match request.radius {
+                0 => 2 * self.nib.max_depth,
+                radius => radius,
+            },
+            sequence_number: self.nib.next_sequence_number(),
+            destination_ieee: None,
+            source_ieee: None,
+            multicast_control: None,
+            source_route_subframe: None,
+        };
+        let (nsdu, secure) = (&request.nsdu, request.security_enable);
+        let status = if group {
+            self.send_multicast(header, nsdu, request.non_member_radius, secure)
+                .await?
+                .into()
+        } else if broadcast {
+            self.send_broadcast(header, nsdu, secure).await?.into()
+        } else {
+            self.route_frame(header, nsdu, secure).await?
+        };
+        // a response is polled for
+        self.poll_fast();
+        Ok(NldeDataConfirm { status, tx_time })
+    }
+
+    /// Parses a NWK frame received from the neighbor `sender` and removes
+    /// its security.
+    ///
+    /// Returns the frame and if it was secured. Frames which can not be
+    /// authenticated and replayed frames, whose frame counter is not greater
+    /// than the one of the last frame of their source, are dropped.
+    ///
+    /// See Section 4.3.1.2.
+    fn open_frame(
+        &mut self,
+        frame: &[u8],
+        sender: Option<ShortAddress>,
+    ) -> Option<(NwkFrame, bool)> {
+        let header = NwkHeader::unpack_from_slice(frame).ok()?;
+        if header.frame_control.security_flag() {
+            let (frame, aux_header) =
+                security::unsecure(frame, self.nib.network_key.as_ref()?).ok()?;
+            let source = aux_header.source_address?;
+            self.nib
+                .neighbor_table
+                .incoming_frame_counter(self.mac.pan_id, sender, source, aux_header.frame_counter)
+                .then_some((frame, true))
+        } else {
+            Some((NwkFrame::unpack_from_slice(frame).ok()?, false))
+        }
+    }
+
+    /// Sends `command` to the neighbor `destination` in a single hop.
+    ///
+    /// The frame carries the IEEE address of the source and, if given, of the
+    /// destination, which is then also addressed by the MAC. It is secured
+    /// with the network key if `secure` is set and a key is available.
+    async fn send_command(
+        &mut self,
+        destination: ShortAddress,
+        destination_ieee: Option<IeeeAddress>,
+        command: &NwkCommand,
+        secure: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let mut header = self.command_header(destination, 1);
+        header
+            .frame_control
+            .set_destination_ieee_flag(destination_ieee.is_some());
+        header.destination_ieee = destination_ieee;
+        let mac_destination =
+            destination_ieee.map_or(Address::Short(destination), Address::Extended);
+        self.send_nwk_command(mac_destination, header, command, secure)
+            .await
+    }
+
+    /// Sends the command frame consisting of `header` and `command` to the
+    /// MAC `destination`.
+    async fn send_nwk_command(
+        &mut self,
+        destination: Address,
+        header: NwkHeader,
+        command: &NwkCommand,
+        secure: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let mut payload = [0u8; MAX_PHY_PACKET_SIZE];
+        let Ok(len) = command.pack_into(&mut payload) else {
+            return Ok(TxStatus::FrameTooLong);
+        };
+        self.send_frame(destination, header, &payload[..len], secure)
+            .await
+    }
+
+    /// Header of a command originated by this device, carrying its IEEE
+    /// address.
+    fn command_header(&mut self, destination: ShortAddress, radius: u8) -> NwkHeader {
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        frame_control.set_source_ieee_flag(true);
+        NwkHeader {
+            frame_control,
+            destination,
+            source: self.mac.short_address,
+            radius,
+            sequence_number: self.nib.next_sequence_number(),
+            destination_ieee: None,
+            source_ieee: Some(self.mac.extended_address),
+            multicast_control: None,
+            source_route_subframe: None,
+        }
+    }
+
+    /// Sends the NWK frame consisting of `header` and `payload` to the MAC
+    /// `destination`.
+    ///
+    /// The frame is secured with the network key if `secure` is set and a
+    /// key is available, unless the outgoing frame counter is exhausted.
+    /// Frames for children which turn their receiver off
+    /// while idle are kept until they poll for them.
+    async fn send_frame(
+        &mut self,
+        destination: Address,
+        mut header: NwkHeader,
+        payload: &[u8],
+        secure: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let frame = if let Some(key) = self.nib.network_key.filter(|_| secure) {
+            header.frame_control.set_security_flag(true);
+            // the frame counter is never reused (4.3.1.1)
+            let frame_counter = self.nib.outgoing_frame_counter;
+            let Some(next) = frame_counter.checked_add(1) else {
+                return Ok(TxStatus::CounterError);
+            };
+            self.nib.outgoing_frame_counter = next;
+            security::secure(
+                &header,
+                payload,
+                &key,
+                frame_counter,
+                self.mac.extended_address,
+            )
+        } else {
+            header.frame_control.set_security_flag(false);
+            let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+            header.pack_into(&mut buf).and_then(|len| {
+                buf.get_mut(len..len + payload.len())
+                    .ok_or(PackError::BufferTooSmall)?
+                    .copy_from_slice(payload);
+                Vec::from_slice(&buf[..len + payload.len()]).map_err(|()| PackError::BufferTooSmall)
+            })
+        };
+        let Ok(frame) = frame else {
+            return Ok(TxStatus::FrameTooLong);
+        };
+        if let Some(child) = self.sleeping_child(destination) {
+            return Ok(self.send_indirect(child, destination, frame));
+        }
+        self.mac.send_data(destination, &frame, false).await
+    }
+
+    /// The payload of beacons sent by this device.
+    ///
+    /// See Section 3.6.7.
+    fn beacon_payload(&self) -> BeaconPayload {
+        BeaconPayload {
+            protocol_id: ZIGBEE_PROTOCOL_ID,
+            stack_profile: self.nib.stack_profile,
+            protocol_version: PROTOCOL_VERSION,
+            router_capacity: self.has_capacity(true),
+            device_depth: self.depth,
+            end_device_capacity: self.has_capacity(false),
+            extended_pan_id: self.nib.extended_pan_id,
+            tx_offset: 0x00ff_ffff,
+            update_id: self.nib.update_id,
+        }
+    }
+
+    /// Neighbor table entry of a child joining through this device.
+    ///
+    /// End devices are aged out after nwkEndDeviceTimeoutDefault unless they
+    /// negotiate another timeout or keep alive.
+    fn child(
         &self,
-        _request: NlmeNetworkDiscoveryRequest,
+        extended_address: IeeeAddress,
+        network_address: ShortAddress,
+        capability_information: MacCapabilities,
+        relationship: Relationship,
+        lqi: u8,
+    ) -> Neighbor {
+        let router = capability_information.is_set(MacCapabilityFlag::DeviceType);
+        let device_timeout =
+            EndDeviceTimeout::new(self.nib.end_device_timeout_default).filter(|_| !router);
+        let now = self.mac.radio.now();
+        Neighbor {
+            extended_address: Some(extended_address),
+            network_address,
+            device_type: if router {
+                LogicalType::Router
+            } else {
+                LogicalType::EndDevice
+            },
+            rx_on_when_idle: capability_information.is_set(MacCapabilityFlag::ReceiverOnWhenIdle),
+            relationship,
+            lqi,
+            pan_id: self.mac.pan_id,
+            extended_pan_id: self.nib.extended_pan_id,
+            logical_channel: self.mac.channel,
+            depth: self.depth + 1,
+            permit_joining: false,
+            router_capacity: false,
+            end_device_capacity: false,
+            potential_parent: false,
+            update_id: self.nib.update_id,
+            outgoing_cost: 0,
+            age: 0,
+            device_timeout,
+            timeout_expiry: device_timeout.map(|timeout| now + timeout.duration()),
+            keepalive: None,
+            incoming_frame_counter: None,
+        }
+    }
+}
+
+impl<R: Radio> NlmeSap for Nlme<R> {
+    async fn network_discovery(
+        &mut self,
+        request: NlmeNetworkDiscoveryRequest,
     ) -> NlmeNetworkDiscoveryConfirm {
-        // TODO: perform an active network scan
-        todo!()
+        match self.discover_networks(&request).await {
+            Ok(network_descriptors) => NlmeNetworkDiscoveryConfirm {
+                status: NlmeNetworkDiscoveryStatus::Successful,
+                network_descriptors,
+            },
+            Err(status) => NlmeNetworkDiscoveryConfirm {
+                status,
+                network_descriptors: Vec::new(),
+            },
+        }
     }
 
-    fn network_formation(
-        &self,
-        _request: NlmeNetworkFormationRequest,
+    async fn network_formation(
+        &mut self,
+        request: NlmeNetworkFormationRequest,
     ) -> NlmeNetworkFormationConfirm {
-        todo!()
+        match self.form_network(&request).await {
+            Ok(confirm) => confirm,
+            Err(status) => NlmeNetworkFormationConfirm {
+                status,
+                pan_id: self.mac.pan_id,
+                extended_pan_id: self.nib.extended_pan_id,
+                logical_channel: self.mac.channel,
+            },
+        }
     }
 
-    fn permit_joining(&self, _request: NlmePermitJoiningRequest) -> NlmePermitJoiningConfirm {
-        todo!()
+    async fn permit_joining(
+        &mut self,
+        request: NlmePermitJoiningRequest,
+    ) -> NlmePermitJoiningConfirm {
+        NlmePermitJoiningConfirm {
+            status: self.permit_joining_for(request.permit_duration),
+        }
     }
 
-    fn start_router(&self, _request: NlmeStartRouterRequest) -> NlmeStartRouterConfirm {
-        todo!()
+    async fn start_router(&mut self, request: NlmeStartRouterRequest) -> NlmeStartRouterConfirm {
+        let status = match self.start_as_router(&request).await {
+            Ok(()) => NlmeStartRouterStatus::Success,
+            Err(status) => status,
+        };
+        NlmeStartRouterConfirm { status }
     }
 
-    fn ed_scan(&self, _request: NlmeEdScanRequest) -> NlmeEdScanConfirm {
-        todo!()
+    async fn ed_scan(&mut self, request: NlmeEdScanRequest) -> NlmeEdScanConfirm {
+        let mut energy_detect_list = Vec::new();
+        let channels = request.scan_channels_list_structure;
+        if request.scan_duration > MAX_SCAN_DURATION || channels.is_empty() {
+            return NlmeEdScanConfirm {
+                status: NlmeEdScanStatus::InvalidParameter,
+                energy_detect_list,
+            };
+        }
+        let Ok(energies) = self.mac.energy_scan(channels, request.scan_duration).await else {
+            return NlmeEdScanConfirm {
+                status: NlmeEdScanStatus::RadioError,
+                energy_detect_list,
+            };
+        };
+
+        for (channel, energy) in channels.channels().zip(energies) {
+            // one entry per channel of the 2.4 GHz band at most
+            let _ = energy_detect_list.push(ChannelEnergy { channel, energy });
+        }
+        NlmeEdScanConfirm {
+            status: NlmeEdScanStatus::Success,
+            energy_detect_list,
+        }
     }
 
-    fn join(&self, _request: NlmeJoinRequest) -> NlmeJoinConfirm {
+    async fn join(&mut self, request: NlmeJoinRequest) -> NlmeJoinConfirm {
         // Figure 3-39
-        // TODO: update neighbor table if join is successful
-        // TODO: start routing (3.6.4.1)
-        NlmeJoinConfirm {
-            status: management::NlmeJoinStatus::InvalidRequest,
-            network_address: 0u16,
-            extended_pan_id: 0u64,
-            enhanced_beacon_type: false,
-            mac_interface_index: 0u8,
+        let result = match request.rejoin_network {
+            RejoinNetwork::Association => self.join_through_association(&request).await,
+            RejoinNetwork::Rejoin => self.rejoin(&request).await,
+            RejoinNetwork::Orphan | RejoinNetwork::ChangeChannel => {
+                Err(NlmeJoinStatus::InvalidRequest)
+            }
+        };
+        match result {
+            Ok(confirm) => confirm,
+            Err(status) => NlmeJoinConfirm {
+                status,
+                network_address: BROADCAST,
+                extended_pan_id: request.extended_pan_id,
+                active_channel: self.mac.channel,
+                enhanced_beacon_type: false,
+                mac_interface_index: 0,
+            },
+        }
+    }
+
+    async fn leave(&mut self, request: NlmeLeaveRequest) -> NlmeLeaveConfirm {
+        match self.leave_network(&request).await {
+            Ok(confirm) => confirm,
+            Err(status) => NlmeLeaveConfirm {
+                status,
+                device_address: request.device_address,
+            },
+        }
+    }
+
+    fn get(&self, attribute: u8) -> NlmeGetConfirm {
+        let value = self.nib.get(attribute);
+        NlmeGetConfirm {
+            status: if value.is_some() {
+                NlmeGetStatus::Success
+            } else {
+                NlmeGetStatus::UnsupportedAttribute
+            },
+            attribute_id: attribute,
+            attribute: value,
+        }
+    }
+
+    fn set(&mut self, attribute: NibAttribute) -> NlmeSetConfirm {
+        NlmeSetConfirm {
+            status: match self.nib.set(attribute) {
+                Ok(()) => NlmeSetStatus::Success,
+                Err(status) => status,
+            },
+            attribute_id: attribute.id(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::ChannelMask;
+
+    #[test]
+    fn ed_scan_reports_energy_per_channel() {
+        let mut radio = MockRadio::new();
+        radio.energy[4] = 0xc0;
+        radio.energy[5] = 0x08;
+        let mut nlme = Nlme::new(radio);
+
+        let confirm = block_on(nlme.ed_scan(NlmeEdScanRequest {
+            scan_channels_list_structure: ChannelMask(0x0001_8000),
+            scan_duration: 3,
+        }));
+
+        assert_eq!(confirm.status, NlmeEdScanStatus::Success);
+        assert_eq!(
+            confirm.energy_detect_list,
+            [
+                ChannelEnergy {
+                    channel: 15,
+                    energy: 0xc0
+                },
+                ChannelEnergy {
+                    channel: 16,
+                    energy: 0x08
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn ed_scan_rejects_invalid_parameters() {
+        let mut nlme = Nlme::new(MockRadio::new());
+
+        let confirm = block_on(nlme.ed_scan(NlmeEdScanRequest {
+            scan_channels_list_structure: ChannelMask(0),
+            scan_duration: 3,
+        }));
+
+        assert_eq!(confirm.status, NlmeEdScanStatus::InvalidParameter);
+        assert!(confirm.energy_detect_list.is_empty());
+    }
+
+    #[test]
+    fn nlme_set_confirms_the_attribute() {
+        let mut nlme = Nlme::new(MockRadio::new());
+
+        let confirm = nlme.set(NibAttribute::MaxChildren(4));
+        assert_eq!(confirm.status, NlmeSetStatus::Success);
+        assert_eq!(confirm.attribute_id, 0x84);
+        assert_eq!(nlme.get(0x84).attribute, Some(NibAttribute::MaxChildren(4)));
+
+        let confirm = nlme.set(NibAttribute::MaxDepth(16));
+        assert_eq!(confirm.status, NlmeSetStatus::InvalidParameter);
+        assert_eq!(nlme.get(0x87).status, NlmeGetStatus::UnsupportedAttribute);
+    }
+
+    #[test]
+    fn replayed_secured_frame_is_dropped() {
+        let mut nlme = parent();
+        let mut child = router(0x796f, Relationship::Child);
+        child.extended_address = Some(IeeeAddress(0x0001));
+        let _ = nlme.nib.neighbor_table.insert(child);
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        let replayed = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert!(matches!(indication, Some(NlmeIndication::Join(_))));
+        assert!(replayed.is_none());
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.incoming_frame_counter, Some(1));
+    }
+
+    #[test]
+    fn exhausted_frame_counter_stops_secured_frames() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::Sibling));
+        nlme.nib.outgoing_frame_counter = u32::MAX - 1;
+        let request = NldeDataRequest {
+            destination_address_mode: NldeAddressMode::Network,
+            destination: ShortAddress(0x0001),
+            nsdu: Vec::from_slice(&[1, 2, 3]).unwrap(),
+            radius: 0,
+            non_member_radius: 0,
+            discover_route: false,
+            security_enable: true,
+        };
+
+        let last = block_on(nlme.data_request(request.clone())).unwrap();
+        let exhausted = block_on(nlme.data_request(request)).unwrap();
+
+        assert_eq!(last.status, NldeDataStatus::Success);
+        assert_eq!(exhausted.status, NldeDataStatus::CounterError);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        assert_eq!(nlme.nib.outgoing_frame_counter, u32::MAX);
+    }
+}
@@ -0,0 +1,410 @@
+//! Multicast Delivery
+//!
+//! Multicast frames are addressed to a group. In member mode they are
+//! broadcast, members of the group pass them to the next higher layer and
+//! relay them like broadcasts. Non-members relay them only while the
+//! non-member radius permits, which members reset to the maximum non-member
+//! radius.
+//!
+//! Devices which are not members of the group send their multicast frames
+//! in non-member mode towards the group, end devices through their parent
+//! and routers along a route to the group. The first member of the group
+//! continues them in member mode. Routes to groups are not discovered, a
+//! non-member mode frame without a route is dropped.
+//!
+//! See Section 3.6.6.
+
+use super::Nlme;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::Address;
+use crate::nwk::frame::MulticastControl;
+use crate::nwk::frame::MulticastMode;
+use crate::nwk::frame::NwkHeader;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+impl<R: Radio> Nlme<R> {
+    /// The group `group` is in nwkGroupIDTable.
+    pub(super) fn is_group_member(&self, group: ShortAddress) -> bool {
+        self.nib.group_id_table.contains(&group.0)
+    }
+
+    /// The neighbor a non-member mode frame to `group` is sent to, the parent
+    /// of an end device or the next hop of the route to the group.
+    fn multicast_next_hop(&self, group: ShortAddress) -> Option<ShortAddress> {
+        if self.is_router() {
+            self.nib.route_table.next_hop(group)
+        } else {
+            self.next_hop(group)
+        }
+    }
+
+    /// Sends a multicast frame originated by this device to the group
+    /// addressed by `header`.
+    ///
+    /// See Section 3.6.6.1.
+    pub(super) async fn send_multicast(
+        &mut self,
+        mut header: NwkHeader,
+        payload: &[u8],
+        non_member_radius: u8,
+        secure: bool,
+    ) -> Result<TxStatus, R::Error> {
+        let mode = if self.is_group_member(header.destination) {
+            MulticastMode::MemberMode
+        } else {
+            MulticastMode::NonMemberMode
+        };
+        header.frame_control.set_multicast_flag(true);
+        header.multicast_control = Some(MulticastControl::new(
+            mode,
+            non_member_radius,
+            non_member_radius,
+        ));
+        if mode == MulticastMode::MemberMode {
+            return self.send_broadcast(header, payload, secure).await;
+        }
+        let Some(next_hop) = self.multicast_next_hop(header.destination) else {
+            return Ok(TxStatus::NoAck);
+        };
+        self.send_frame(Address::Short(next_hop), header, payload, secure)
+            .await
+    }
+
+    /// Records a multicast frame received from the neighbor `sender` and
+    /// schedules its relay according to the radius rules.
+    ///
+    /// Returns if the frame is passed to the next higher layer, which is
+    /// the case for the first reception by a member of the group.
+    ///
+    /// See Section 3.6.6.2.
+    pub(super) async fn receive_multicast(
+        &mut self,
+        header: &NwkHeader,
+        payload: &[u8],
+        secured: bool,
+        sender: Option<ShortAddress>,
+    ) -> Result<bool, R::Error> {
+        let Some(mut control) = header.multicast_control else {
+            return Ok(false);
+        };
+        let member = self.is_group_member(header.destination);
+        match control.multicast_mode() {
+            MulticastMode::MemberMode => {}
+            // continued in member mode by the first member of the group
+            MulticastMode::NonMemberMode if self.is_router() && member => {
+                control.set_multicast_mode(MulticastMode::MemberMode);
+            }
+            MulticastMode::NonMemberMode if self.is_router() => {
+                self.forward_multicast(header.clone(), payload, secured)
+                    .await?;
+                return Ok(false);
+            }
+            _ => return Ok(false),
+        }
+        if member {
+            control.set_non_member_radius(control.max_member_radius());
+        } else {
+            match control.non_member_radius() {
+                0 => return Ok(false),
+                MulticastControl::INFINITE_RADIUS => {}
+                radius => control.set_non_member_radius(radius - 1),
+            }
+        }
+        let mut header = header.clone();
+        header.multicast_control = Some(control);
+        Ok(self.receive_broadcast(&header, payload, secured, sender) && member)
+    }
+
+    /// Forwards a non-member mode frame of another device along the route to
+    /// its group, it is dropped without a route.
+    async fn forward_multicast(
+        &mut self,
+        mut header: NwkHeader,
+        payload: &[u8],
+        secured: bool,
+    ) -> Result<(), R::Error> {
+        if header.radius <= 1 {
+            return Ok(());
+        }
+        let Some(next_hop) = self.nib.route_table.next_hop(header.destination) else {
+            return Ok(());
+        };
+        header.radius -= 1;
+        self.send_frame(Address::Short(next_hop), header, payload, secured)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use heapless::Vec;
+
+    use super::*;
+    use crate::apl::descriptors::node_descriptor::LogicalType;
+    use crate::common::parse::PackBytes;
+    use crate::mac::frame::BROADCAST;
+    use crate::nwk::frame::FrameControl;
+    use crate::nwk::frame::FrameTypeIdentifier;
+    use crate::nwk::frame::NwkDataFrame;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nib::MAX_DEPTH;
+    use crate::nwk::nlde::NldeAddressMode;
+    use crate::nwk::nlde::NldeDataRequest;
+    use crate::nwk::nlde::NldeDataStatus;
+    use crate::nwk::nlme::management::NlmeIndication;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::routing::Route;
+    use crate::nwk::routing::RouteStatus;
+    use crate::radio::mock::block_on;
+    use crate::radio::RxFrame;
+
+    /// Multicast data frame `sequence_number` of 0x796f to `group`, sent to
+    /// the MAC address `destination`.
+    fn multicast_frame(
+        destination: u16,
+        group: u16,
+        sequence_number: u8,
+        control: MulticastControl,
+    ) -> RxFrame {
+        let mut header = data_header(group, 3);
+        header.frame_control = FrameControl::new(FrameTypeIdentifier::Data);
+        header.frame_control.set_multicast_flag(true);
+        header.sequence_number = sequence_number;
+        header.multicast_control = Some(control);
+        let frame = NwkFrame::Data(NwkDataFrame {
+            header,
+            payload: Vec::from_slice(&[1, 2, 3]).unwrap(),
+        });
+        mac_frame(
+            ShortAddress(destination),
+            ShortAddress(0x796f),
+            frame.pack_to_vec().unwrap(),
+        )
+    }
+
+    #[test]
+    fn group_member_receives_and_relays_multicast() {
+        let mut nlme = parent();
+        nlme.nib.group_id_table.push(0x0123).unwrap();
+        nlme.mac.radio.received.push_back(multicast_frame(
+            BROADCAST,
+            0x0123,
+            1,
+            MulticastControl::new(MulticastMode::MemberMode, 0, 2),
+        ));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Data(indication)) = indication else {
+            unreachable!("no data indication");
+        };
+        assert_eq!(indication.destination_address_mode, NldeAddressMode::Group);
+        assert_eq!(indication.destination, ShortAddress(0x0123));
+        assert_eq!(indication.nsdu, [1, 2, 3]);
+
+        block_on(nlme.process(Duration::from_millis(100))).unwrap();
+
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.radius, 2);
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::MemberMode);
+        assert_eq!(control.non_member_radius(), 2);
+    }
+
+    #[test]
+    fn non_member_relays_multicast_within_non_member_radius() {
+        let mut nlme = parent();
+        nlme.mac.radio.received.push_back(multicast_frame(
+            BROADCAST,
+            0x0123,
+            1,
+            MulticastControl::new(MulticastMode::MemberMode, 1, 2),
+        ));
+        nlme.mac.radio.received.push_back(multicast_frame(
+            BROADCAST,
+            0x0123,
+            2,
+            MulticastControl::new(MulticastMode::MemberMode, 0, 2),
+        ));
+
+        let indication = block_on(nlme.process(Duration::from_millis(100))).unwrap();
+
+        assert_eq!(indication, None);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (_, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(frame.header.sequence_number, 1);
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.non_member_radius(), 0);
+    }
+
+    /// Group data request to 0x0123.
+    fn group_request(non_member_radius: u8) -> NldeDataRequest {
+        NldeDataRequest {
+            destination_address_mode: NldeAddressMode::Group,
+            destination: ShortAddress(0x0123),
+            nsdu: Vec::from_slice(&[1, 2, 3]).unwrap(),
+            radius: 0,
+            non_member_radius,
+            discover_route: true,
+            security_enable: true,
+        }
+    }
+
+    /// Active route to the group 0x0123 through the neighbor 0x4444.
+    fn group_route() -> Route {
+        Route {
+            destination: ShortAddress(0x0123),
+            status: RouteStatus::Active,
+            no_route_cache: false,
+            many_to_one: false,
+            route_record_required: false,
+            next_hop: ShortAddress(0x4444),
+        }
+    }
+
+    #[test]
+    fn member_router_continues_non_member_multicast_in_member_mode() {
+        let mut nlme = parent();
+        nlme.nib.group_id_table.push(0x0123).unwrap();
+        nlme.mac.radio.received.push_back(multicast_frame(
+            0x0000,
+            0x0123,
+            1,
+            MulticastControl::new(MulticastMode::NonMemberMode, 1, 2),
+        ));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert!(matches!(indication, Some(NlmeIndication::Data(_))));
+        block_on(nlme.process(Duration::from_millis(100))).unwrap();
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::MemberMode);
+    }
+
+    #[test]
+    fn non_member_router_forwards_non_member_multicast_along_route() {
+        let mut nlme = parent();
+        nlme.mac.radio.received.push_back(multicast_frame(
+            0x0000,
+            0x0123,
+            1,
+            MulticastControl::new(MulticastMode::NonMemberMode, 1, 2),
+        ));
+
+        // dropped without a route to the group
+        let indication = block_on(nlme.process(Duration::from_millis(100))).unwrap();
+
+        assert_eq!(indication, None);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+
+        nlme.nib.route_table.insert(group_route()).unwrap();
+        nlme.mac.radio.received.push_back(multicast_frame(
+            0x0000,
+            0x0123,
+            2,
+            MulticastControl::new(MulticastMode::NonMemberMode, 1, 2),
+        ));
+
+        let indication = block_on(nlme.process(Duration::from_millis(100))).unwrap();
+
+        assert_eq!(indication, None);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x4444))));
+        assert_eq!(frame.header.sequence_number, 2);
+        assert_eq!(frame.header.radius, 2);
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::NonMemberMode);
+    }
+
+    #[test]
+    fn non_member_router_sends_multicast_along_route() {
+        let mut nlme = parent();
+        nlme.nib.route_table.insert(group_route()).unwrap();
+
+        let confirm = block_on(nlme.data_request(group_request(2))).unwrap();
+
+        assert_eq!(confirm.status, NldeDataStatus::Success);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x4444))));
+        assert_eq!(frame.header.destination, ShortAddress(0x0123));
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::NonMemberMode);
+    }
+
+    #[test]
+    fn member_router_sends_multicast_in_member_mode() {
+        let mut nlme = parent();
+        nlme.nib.group_id_table.push(0x0123).unwrap();
+
+        let confirm = block_on(nlme.data_request(group_request(2))).unwrap();
+
+        assert_eq!(confirm.status, NldeDataStatus::Success);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.destination, ShortAddress(0x0123));
+        assert_eq!(frame.header.radius, 2 * MAX_DEPTH);
+        assert!(frame.header.frame_control.multicast_flag());
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::MemberMode);
+        assert_eq!(control.non_member_radius(), 2);
+        assert_eq!(control.max_member_radius(), 2);
+        assert_eq!(frame.payload, [1, 2, 3]);
+    }
+
+    #[test]
+    fn end_device_sends_multicast_to_its_parent() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::EndDevice);
+        nlme.mac.short_address = ShortAddress(0x796f);
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::Parent));
+        let request = NldeDataRequest {
+            destination_address_mode: NldeAddressMode::Group,
+            destination: ShortAddress(0x0123),
+            nsdu: Vec::from_slice(&[1, 2, 3]).unwrap(),
+            radius: 5,
+            non_member_radius: 1,
+            discover_route: true,
+            security_enable: true,
+        };
+
+        let confirm = block_on(nlme.data_request(request.clone())).unwrap();
+
+        assert_eq!(confirm.status, NldeDataStatus::Success);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0001))));
+        let control = frame.header.multicast_control.unwrap();
+        assert_eq!(control.multicast_mode(), MulticastMode::NonMemberMode);
+
+        nlme.nib.use_multicast = false;
+        let confirm = block_on(nlme.data_request(request)).unwrap();
+
+        assert_eq!(confirm.status, NldeDataStatus::InvalidRequest);
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+    }
+}
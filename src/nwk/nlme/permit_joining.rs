@@ -0,0 +1,101 @@
+//! Permit Joining
+//!
+//! Routers and the coordinator admit associating devices only while joining
+//! is permitted, which they advertise in the association permit bit of their
+//! beacons. Joining is permitted for a number of seconds, after which it is
+//! disabled again, or indefinitely as in earlier versions of the
+//! specification.
+//!
+//! See Section 3.2.2.7.
+
+use core::time::Duration;
+
+use super::management::NlmePermitJoiningStatus;
+use super::Nlme;
+use crate::radio::Radio;
+
+/// Permit duration which permits joining until it is disabled.
+pub(super) const PERMIT_JOINING_INDEFINITELY: u8 = 0xff;
+
+impl<R: Radio> Nlme<R> {
+    /// Permits joining through this device for `permit_duration` seconds.
+    ///
+    /// A duration of 0x00 disables joining, 0xff permits it indefinitely.
+    pub(super) fn permit_joining_for(&mut self, permit_duration: u8) -> NlmePermitJoiningStatus {
+        if !self.router_started {
+            return NlmePermitJoiningStatus::InvalidRequest;
+        }
+        self.mac.association_permit = permit_duration != 0;
+        self.permit_joining_until = match permit_duration {
+            0 | PERMIT_JOINING_INDEFINITELY => None,
+            seconds => Some(self.mac.radio.now() + Duration::from_secs(u64::from(seconds))),
+        };
+        NlmePermitJoiningStatus::Success
+    }
+
+    /// Disables joining once the permitted time elapsed.
+    pub(super) fn maintain_permit_joining(&mut self) {
+        let now = self.mac.radio.now();
+        if self.permit_joining_until.is_some_and(|until| until <= now) {
+            self.permit_joining_until = None;
+            self.mac.association_permit = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apl::descriptors::node_descriptor::LogicalType;
+    use crate::nwk::nlme::management::NlmePermitJoiningRequest;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::radio::mock::block_on;
+
+    fn permit_joining_request(permit_duration: u8) -> NlmePermitJoiningRequest {
+        NlmePermitJoiningRequest { permit_duration }
+    }
+
+    #[test]
+    fn joining_is_permitted_for_the_requested_time() {
+        let mut nlme = parent();
+
+        let confirm = block_on(nlme.permit_joining(permit_joining_request(60)));
+
+        assert_eq!(confirm.status, NlmePermitJoiningStatus::Success);
+        assert!(nlme.mac.association_permit);
+        block_on(nlme.process(Duration::from_secs(59))).unwrap();
+        assert!(nlme.mac.association_permit);
+        block_on(nlme.process(Duration::from_secs(2))).unwrap();
+        assert!(!nlme.mac.association_permit);
+        assert_eq!(nlme.permit_joining_until, None);
+    }
+
+    #[test]
+    fn joining_is_permitted_indefinitely_until_disabled() {
+        let mut nlme = parent();
+        block_on(nlme.permit_joining(permit_joining_request(60)));
+
+        block_on(nlme.permit_joining(permit_joining_request(PERMIT_JOINING_INDEFINITELY)));
+        block_on(nlme.process(Duration::from_secs(300))).unwrap();
+
+        assert!(nlme.mac.association_permit);
+
+        let confirm = block_on(nlme.permit_joining(permit_joining_request(0)));
+
+        assert_eq!(confirm.status, NlmePermitJoiningStatus::Success);
+        assert!(!nlme.mac.association_permit);
+    }
+
+    #[test]
+    fn router_permits_joining_only_once_started() {
+        let mut nlme = parent();
+        nlme.device_type = Some(LogicalType::Router);
+        nlme.router_started = false;
+
+        let confirm = block_on(nlme.permit_joining(permit_joining_request(60)));
+
+        assert_eq!(confirm.status, NlmePermitJoiningStatus::InvalidRequest);
+        assert!(!nlme.mac.association_permit);
+    }
+}
@@ -0,0 +1,226 @@
+//! Polling of the Parent
+//!
+//! An end device which turns its receiver off while idle polls its parent
+//! for the frames kept for it. It polls at the fast poll rate while it
+//! expects frames, after it joined, sent a request or received a frame, and
+//! at the slow poll rate otherwise. The polls keep the end device alive if
+//! the parent accepts MAC data polls as keep-alive.
+//!
+//! See IEEE 802.15.4-2015, Section 6.7.3.
+use core::time::Duration;
+
+use super::management::NlmeIndication;
+use super::Nlme;
+use crate::mac::frame::MacFrame;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+/// Poll rates of an end device which turns its receiver off while idle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PollRates {
+    /// The interval between polls while frames are expected.
+    pub fast: Duration,
+    /// The interval between polls otherwise.
+    pub slow: Duration,
+    /// The time frames are expected after a request was sent or a frame
+    /// was received.
+    pub fast_poll_timeout: Duration,
+}
+
+impl<R: Radio> Nlme<R> {
+    /// Turns the receiver off while idle and polls the parent at `rates`
+    /// while operating as end device, or keeps the receiver on if `None`.
+    pub(crate) fn set_poll_rates(&mut self, rates: Option<PollRates>) {
+        self.poll_rates = rates;
+        self.mac.rx_on_when_idle = rates.is_none();
+        self.next_poll = None;
+        self.fast_poll_until = None;
+    }
+
+    /// Polls the parent at the fast poll rate for the fast poll timeout.
+    pub(super) fn poll_fast(&mut self) {
+        let Some(rates) = self.poll_rates else {
+            return;
+        };
+        let now = self.mac.radio.now();
+        self.fast_poll_until = Some(now + rates.fast_poll_timeout);
+        let due = now + rates.fast;
+        self.next_poll = Some(self.next_poll.map_or(due, |next| next.min(due)));
+    }
+
+    /// Polls the parent of this end device for a frame kept for it.
+    ///
+    /// Returns the indication of the received frame. The parent is polled
+    /// again right away if it keeps more frames.
+    pub(super) async fn poll_parent(&mut self) -> Result<Option<NlmeIndication>, R::Error> {
+        let (Some(rates), Some(parent)) = (self.poll_rates, self.end_device_parent()) else {
+            self.next_poll = None;
+            return Ok(None);
+        };
+        let now = self.mac.radio.now();
+        let interval = if self.fast_poll_until.is_some_and(|until| now < until) {
+            rates.fast
+        } else {
+            rates.slow
+        };
+        self.next_poll = Some(now + interval);
+        let status = self.mac.poll(parent).await?;
+        if !matches!(status, TxStatus::Acked { .. }) {
+            return Ok(None);
+        }
+        if self.polls_keep_alive() {
+            self.schedule_keepalive();
+        }
+        if status
+            != (TxStatus::Acked {
+                frame_pending: true,
+            })
+        {
+            return Ok(None);
+        }
+        let Some(received) = self.mac.receive_pending(parent).await? else {
+            return Ok(None);
+        };
+        self.poll_fast();
+        if let MacFrame::Data(frame) = &received.frame {
+            if frame.header.frame_control.frame_pending() {
+                self.next_poll = Some(self.mac.radio.now());
+            }
+        }
+        self.handle(received).await
+    }
+
+    /// Polls the parent of this end device when due.
+    pub(super) async fn maintain_polling(&mut self) -> Result<Option<NlmeIndication>, R::Error> {
+        if self
+            .next_poll
+            .is_some_and(|due| due <= self.mac.radio.now())
+        {
+            return self.poll_parent().await;
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use heapless::Vec;
+
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::ShortAddress;
+    use crate::mac::command::MacCommand;
+    use crate::mac::frame::Address;
+    use crate::mac::frame::FrameType;
+    use crate::mac::frame::MacCommandFrame;
+    use crate::mac::frame::MacDataFrame;
+    use crate::mac::frame::MacHeader;
+    use crate::nwk::frame::NwkDataFrame;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+
+    /// End device turning its receiver off while idle.
+    fn sleepy_end_device() -> Nlme<MockRadio> {
+        let mut nlme = end_device();
+        nlme.set_poll_rates(Some(PollRates {
+            fast: Duration::from_millis(250),
+            slow: Duration::from_secs(5),
+            fast_poll_timeout: Duration::from_secs(2),
+        }));
+        nlme
+    }
+
+    /// MAC data frame of the parent 0x0002 to the end device 0x796f.
+    fn parent_frame(payload: &[u8], frame_pending: bool) -> RxFrame {
+        let mut header = data_header(0x796f, 1);
+        header.source = ShortAddress(0x0002);
+        let frame = NwkFrame::Data(NwkDataFrame {
+            header,
+            payload: Vec::from_slice(payload).unwrap(),
+        });
+        let mut header = MacHeader::new(
+            FrameType::Data,
+            1,
+            Some((0x1a62, Address::Short(ShortAddress(0x796f)))),
+            Some((0x1a62, Address::Short(ShortAddress(0x0002)))),
+        );
+        header.frame_control.set_frame_pending(frame_pending);
+        let frame = MacFrame::Data(MacDataFrame {
+            header,
+            payload: frame.pack_to_vec().unwrap(),
+        });
+        RxFrame {
+            frame: frame.pack_to_vec().unwrap(),
+            lqi: 230,
+            rssi: -40,
+            timestamp: Duration::ZERO,
+        }
+    }
+
+    /// The `frame` is a MAC data request.
+    fn is_poll(frame: &[u8]) -> bool {
+        matches!(
+            MacFrame::unpack_from_slice(frame),
+            Ok(MacFrame::Command(MacCommandFrame {
+                command: MacCommand::DataRequest,
+                ..
+            }))
+        )
+    }
+
+    #[test]
+    fn sleepy_end_device_polls_for_pending_frames() {
+        let mut nlme = sleepy_end_device();
+        nlme.mac.radio.tx_status = Some(TxStatus::Acked {
+            frame_pending: true,
+        });
+        nlme.mac.radio.received.push_back(parent_frame(&[1], true));
+        nlme.mac.radio.received.push_back(parent_frame(&[2], false));
+
+        block_on(nlme.data_request(unicast_request(0x0002, &[0]))).unwrap();
+        let mut received = Vec::<u8, 2>::new();
+        for _ in 0..2 {
+            let Some(NlmeIndication::Data(indication)) =
+                block_on(nlme.process(Duration::from_secs(1))).unwrap()
+            else {
+                unreachable!("no data indication");
+            };
+            received.extend_from_slice(&indication.nsdu).unwrap();
+        }
+
+        assert_eq!(received, [1, 2]);
+        // the data request and a poll for each frame
+        assert_eq!(nlme.mac.radio.transmitted.len(), 3);
+        assert!(nlme.mac.radio.transmitted[1..]
+            .iter()
+            .all(|frame| is_poll(frame)));
+        // the pending frame is polled for right away
+        assert_eq!(nlme.mac.radio.now, Duration::from_millis(250));
+    }
+
+    #[test]
+    fn sleepy_end_device_slows_down_polling_when_idle() {
+        let mut nlme = sleepy_end_device();
+        nlme.mac.radio.tx_status = Some(TxStatus::Acked {
+            frame_pending: false,
+        });
+
+        block_on(nlme.data_request(unicast_request(0x0002, &[0]))).unwrap();
+        block_on(nlme.process(Duration::from_secs(2))).unwrap();
+        let fast_polls = nlme.mac.radio.transmitted.len() - 1;
+        block_on(nlme.process(Duration::from_secs(10))).unwrap();
+        let slow_polls = nlme.mac.radio.transmitted.len() - 1 - fast_polls;
+
+        // every 250 ms for 2 s, then every 5 s
+        assert_eq!(fast_polls, 7);
+        assert_eq!(slow_polls, 2);
+        assert!(nlme.mac.radio.transmitted[1..]
+            .iter()
+            .all(|frame| is_poll(frame)));
+        // the receiver stays off in between
+        assert!(nlme.mac.radio.received.is_empty());
+    }
+}
@@ -0,0 +1,452 @@
+//! Rejoining
+//!
+//! A device which lost its parent rejoins the network through the best
+//! router answering its scan with a Rejoin Request, keeping its network
+//! address if possible. A parent admits a rejoining device as its child, or
+//! as an unauthenticated child if the request was not secured.
+//!
+//! See Section 3.6.1.4.2 and Section 3.6.1.4.3.2.
+use super::join::rejected;
+use super::management::NlmeIndication;
+use super::management::NlmeJoinConfirm;
+use super::management::NlmeJoinIndication;
+use super::management::NlmeJoinRequest;
+use super::management::NlmeJoinStatus;
+use super::management::RejoinNetwork;
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::apl::descriptors::node_descriptor::MacCapabilityFlag;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::association::RESPONSE_WAIT_TIME;
+use crate::mac::command::AssociationStatus;
+use crate::mac::frame::Address;
+use crate::mac::frame::MacFrame;
+use crate::mac::frame::BROADCAST;
+use crate::mac::scan::MAX_SCAN_DURATION;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::commands::RejoinRequest;
+use crate::nwk::commands::RejoinResponse;
+use crate::nwk::frame::NwkFrame;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::neighbor::Relationship;
+use crate::nwk::nib::AddressAllocation;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::radio::Radio;
+use crate::radio::TxStatus;
+
+impl<R: Radio> Nlme<R> {
+    /// Rejoins the network of the request through the best router heard on
+    /// the scanned channels, keeping the network address if possible.
+    ///
+    /// The Rejoin Request is secured with the network key if requested and
+    /// available. If a secured request is not answered, the device falls back
+    /// to a trust center rejoin with an unsecured request.
+    ///
+    /// See Section 3.6.1.4.2.
+    pub(super) async fn rejoin(
+        &mut self,
+        request: &NlmeJoinRequest,
+    ) -> Result<NlmeJoinConfirm, NlmeJoinStatus> {
+        let channels = request.scan_channels_list_structure;
+        if self.device_type == Some(LogicalType::Coordinator)
+            || request.extended_pan_id == 0
+            || request.scan_duration > MAX_SCAN_DURATION
+            || channels.is_empty()
+        {
+            return Err(NlmeJoinStatus::InvalidRequest);
+        }
+        let pans = self
+            .mac
+            .active_scan(channels, request.scan_duration)
+            .await
+            .map_err(|_| NlmeJoinStatus::RadioError)?;
+        for pan in &pans {
+            self.record_neighbor(pan);
+        }
+        let router = request
+            .capability_information
+            .is_set(MacCapabilityFlag::DeviceType);
+        // only routers which answered the scan are still reachable
+        let parent = self
+            .find_parent(request.extended_pan_id, router, |neighbor| {
+                pans.iter().any(|pan| {
+                    pan.pan_id == neighbor.pan_id
+                        && pan.address == Address::Short(neighbor.network_address)
+                })
+            })
+            .ok_or(NlmeJoinStatus::NoNetworks)?;
+
+        self.mac
+            .start(
+                parent.logical_channel,
+                parent.pan_id,
+                self.mac.short_address,
+                false,
+            )
+            .await
+            .map_err(|_| NlmeJoinStatus::RadioError)?;
+        let mut secure = request.security_enabled && self.nib.network_key.is_some();
+        let result = loop {
+            let result = self
+                .request_rejoin(&parent, request.capability_information, secure)
+                .await
+                .map_err(|_| NlmeJoinStatus::RadioError)?;
+            match result {
+                Err(NlmeJoinStatus::NoData) if secure => secure = false,
+                result => break result,
+            }
+        };
+        let network_address = match result {
+            Ok(network_address) => network_address,
+            Err(status) => {
+                // a parent which failed is not tried again
+                if let Some(entry) = self
+                    .nib
+                    .neighbor_table
+                    .get_mut(parent.pan_id, parent.network_address)
+                {
+                    entry.potential_parent = false;
+                }
+                return Err(status);
+            }
+        };
+
+        self.mac
+            .start(
+                parent.logical_channel,
+                parent.pan_id,
+                network_address,
+                false,
+            )
+            .await
+            .map_err(|_| NlmeJoinStatus::RadioError)?;
+        self.adopt_parent(&parent, router);
+        Ok(NlmeJoinConfirm {
+            status: NlmeJoinStatus::Success,
+            network_address: network_address.0,
+            extended_pan_id: parent.extended_pan_id,
+            active_channel: parent.logical_channel,
+            enhanced_beacon_type: false,
+            mac_interface_index: 0,
+        })
+    }
+
+    /// Sends a Rejoin Request to `parent` and waits macResponseWaitTime for
+    /// the Rejoin Response.
+    ///
+    /// Returns the network address assigned by the parent.
+    ///
+    /// See Section 3.4.6.
+    async fn request_rejoin(
+        &mut self,
+        parent: &Neighbor,
+        capability_information: MacCapabilities,
+        secure: bool,
+    ) -> Result<Result<ShortAddress, NlmeJoinStatus>, R::Error> {
+        let request = NwkCommand::RejoinRequest(RejoinRequest {
+            capability_information,
+        });
+        match self
+            .send_command(parent.network_address, None, &request, secure)
+            .await?
+        {
+            TxStatus::Acked { .. } => {}
+            TxStatus::ChannelAccessFailure => return Ok(Err(NlmeJoinStatus::ChannelAccessFailure)),
+            _ => return Ok(Err(NlmeJoinStatus::NoAck)),
+        }
+
+        let deadline = self.mac.radio.now() + RESPONSE_WAIT_TIME;
+        while let Some(received) = self
+            .mac
+            .receive(deadline.saturating_sub(self.mac.radio.now()))
+            .await?
+        {
+            let MacFrame::Data(frame) = received.frame else {
+                continue;
+            };
+            let sender = match frame.header.source {
+                Some(Address::Short(source)) => Some(source),
+                _ => None,
+            };
+            let Some((NwkFrame::NwkCommand(frame), secured)) =
+                self.open_frame(&frame.payload, sender)
+            else {
+                continue;
+            };
+            // a secured request is only answered with a secured response
+            if frame.header.source != parent.network_address || (secure && !secured) {
+                continue;
+            }
+            let Ok(NwkCommand::RejoinResponse(response)) = frame.command() else {
+                continue;
+            };
+            return Ok(match response.rejoin_status {
+                AssociationStatus::Success => Ok(response.network_address),
+                status => Err(rejected(status)),
+            });
+        }
+        Ok(Err(NlmeJoinStatus::NoData))
+    }
+
+    /// Admits a device rejoining through this device.
+    ///
+    /// An unsecured request is a trust center rejoin, the device remains an
+    /// unauthenticated child until it is authenticated by the trust center.
+    ///
+    /// See Section 3.6.1.4.3.2.
+    pub(super) async fn accept_rejoin(
+        &mut self,
+        header: &NwkHeader,
+        request: RejoinRequest,
+        secured: bool,
+        lqi: u8,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let parent = matches!(
+            self.device_type,
+            Some(LogicalType::Coordinator | LogicalType::Router)
+        );
+        let Some(extended_address) = header.source_ieee else {
+            return Ok(None);
+        };
+        if !parent || header.destination != self.mac.short_address {
+            return Ok(None);
+        }
+        let capability_information = request.capability_information;
+        let router = capability_information.is_set(MacCapabilityFlag::DeviceType);
+        let relationship = if secured {
+            Relationship::Child
+        } else {
+            Relationship::UnauthenticatedChild
+        };
+        let admitted = self
+            .rejoin_address(extended_address, header.source, router)
+            .filter(|network_address| {
+                let child = self.child(
+                    extended_address,
+                    *network_address,
+                    capability_information,
+                    relationship,
+                    lqi,
+                );
+                self.nib.neighbor_table.insert(child).is_ok()
+            });
+
+        let response = NwkCommand::RejoinResponse(RejoinResponse {
+            network_address: admitted.unwrap_or(ShortAddress(BROADCAST)),
+            rejoin_status: if admitted.is_some() {
+                AssociationStatus::Success
+            } else {
+                AssociationStatus::PanAtCapacity
+            },
+        });
+        let status = self
+            .send_command(header.source, Some(extended_address), &response, secured)
+            .await?;
+        if !matches!(status, TxStatus::Acked { .. }) {
+            return Ok(None);
+        }
+        Ok(admitted.map(|network_address| {
+            NlmeIndication::Join(NlmeJoinIndication {
+                network_address: network_address.0,
+                extended_address: extended_address.0,
+                capability_information,
+                rejoin_network: RejoinNetwork::Rejoin,
+                secure_rejoin: secured,
+            })
+        }))
+    }
+
+    /// The network address of a rejoining router or end device.
+    ///
+    /// With stochastic addressing the device keeps its address unless it is
+    /// reserved or used by another device. With distributed addressing only
+    /// a child keeps its address. Other devices are assigned a new address.
+    /// Returns `None` if no address is left or a device which is not a child
+    /// finds no capacity left.
+    ///
+    /// See Section 3.6.1.4.3.2.
+    fn rejoin_address(
+        &mut self,
+        extended_address: IeeeAddress,
+        requested: ShortAddress,
+        router: bool,
+    ) -> Option<ShortAddress> {
+        let pan_id = self.mac.pan_id;
+        let child = self
+            .nib
+            .neighbor_table
+            .get_by_extended_address(extended_address)
+            .is_some_and(|neighbor| neighbor.pan_id == pan_id && neighbor.is_child());
+        if !child && !self.has_capacity(router) {
+            return None;
+        }
+        let keep = match self.nib.addr_alloc {
+            AddressAllocation::Stochastic => {
+                requested != self.mac.short_address
+                    && requested.0 < MIN_BROADCAST_ADDRESS
+                    && !self.nib.neighbor_table.iter().any(|neighbor| {
+                        neighbor.pan_id == pan_id
+                            && neighbor.network_address == requested
+                            && neighbor
+                                .extended_address
+                                .is_some_and(|address| address != extended_address)
+                    })
+            }
+            _ => self
+                .nib
+                .neighbor_table
+                .get(pan_id, requested)
+                .is_some_and(|neighbor| {
+                    neighbor.is_child() && neighbor.extended_address == Some(extended_address)
+                }),
+        };
+        if keep {
+            Some(requested)
+        } else {
+            self.allocate_address(router)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use super::*;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::nwk::security::NetworkKey;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::ChannelMask;
+
+    #[test]
+    fn parent_answers_secured_rejoin_request() {
+        let mut nlme = parent();
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Join(indication)) = indication else {
+            unreachable!("no join indication");
+        };
+        assert_eq!(indication.network_address, 0x796f);
+        assert_eq!(indication.extended_address, 0x0001);
+        assert_eq!(indication.rejoin_network, RejoinNetwork::Rejoin);
+        assert!(indication.secure_rejoin);
+        let (response, secured) = rejoin_response(&nlme);
+        assert!(secured);
+        assert_eq!(response.network_address, ShortAddress(0x796f));
+        assert_eq!(response.rejoin_status, AssociationStatus::Success);
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.relationship, Relationship::Child);
+        assert_eq!(child.device_type, LogicalType::Router);
+        assert_eq!(child.extended_address, Some(IeeeAddress(0x0001)));
+        assert_eq!(child.depth, 1);
+        assert!(child.rx_on_when_idle);
+    }
+
+    #[test]
+    fn unsecured_rejoin_request_is_a_trust_center_rejoin() {
+        let mut nlme = parent();
+        nlme.mac.radio.received.push_back(rejoin_request(false));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Join(indication)) = indication else {
+            unreachable!("no join indication");
+        };
+        assert!(!indication.secure_rejoin);
+        let (response, secured) = rejoin_response(&nlme);
+        assert!(!secured);
+        assert_eq!(response.rejoin_status, AssociationStatus::Success);
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.relationship, Relationship::UnauthenticatedChild);
+    }
+
+    #[test]
+    fn rejoin_request_with_unknown_key_is_dropped() {
+        let mut nlme = parent();
+        nlme.nib.network_key = Some(NetworkKey {
+            sequence_number: 1,
+            ..KEY
+        });
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(indication, None);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+
+    #[test]
+    fn rejoin_without_known_network_is_invalid() {
+        let mut nlme = Nlme::new(MockRadio::new());
+
+        let confirm = block_on(nlme.join(NlmeJoinRequest {
+            rejoin_network: RejoinNetwork::Rejoin,
+            scan_channels_list_structure: ChannelMask::single(15),
+            ..join_request(0)
+        }));
+
+        assert_eq!(confirm.status, NlmeJoinStatus::InvalidRequest);
+        assert!(nlme.mac.radio.transmitted.is_empty());
+    }
+
+    #[test]
+    fn rejoin_with_address_of_other_device_assigns_new_address() {
+        let mut nlme = parent();
+        nlme.mac.radio.random = 0x1234;
+        nlme.mac.radio.received.push_back(rejoin_request(true));
+        let _ = nlme.nib.neighbor_table.insert(Neighbor {
+            extended_address: Some(IeeeAddress(0x0002)),
+            network_address: ShortAddress(0x796f),
+            device_type: LogicalType::EndDevice,
+            rx_on_when_idle: false,
+            relationship: Relationship::Child,
+            lqi: 200,
+            pan_id: 0x1a62,
+            extended_pan_id: EXTENDED_PAN_ID,
+            logical_channel: 15,
+            depth: 1,
+            permit_joining: false,
+            router_capacity: false,
+            end_device_capacity: false,
+            potential_parent: false,
+            update_id: 0,
+            outgoing_cost: 0,
+            age: 0,
+            device_timeout: None,
+            timeout_expiry: None,
+            keepalive: None,
+            incoming_frame_counter: None,
+        });
+
+        let indication = block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let Some(NlmeIndication::Join(indication)) = indication else {
+            unreachable!("no join indication");
+        };
+        assert_eq!(indication.network_address, 0x1234);
+        let (response, _) = rejoin_response(&nlme);
+        assert_eq!(response.rejoin_status, AssociationStatus::Success);
+        assert_eq!(response.network_address, ShortAddress(0x1234));
+        let child = nlme
+            .nib
+            .neighbor_table
+            .get(0x1a62, ShortAddress(0x796f))
+            .unwrap();
+        assert_eq!(child.extended_address, Some(IeeeAddress(0x0002)));
+    }
+}
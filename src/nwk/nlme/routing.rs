@@ -0,0 +1,1180 @@
+//! Mesh Routing
+//!
+//! Routers forward unicast frames over the next hop towards their
+//! destination and discover routes with route requests broadcast through
+//! the network, answered by a route reply along the cheapest path.
+//!
+//! Concentrators broadcast many-to-one route requests, which set up the
+//! routes of all routers towards them. Devices answer with route records
+//! of the relays on the way, which the concentrator uses to source route
+//! frames back to them.
+//!
+//! Routers which can not relay a frame report the failure to its originator
+//! with a Network Status.
+//!
+//! See Section 3.6.3.
+
+use core::convert::TryFrom;
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::Nlme;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::common::parse::PackBytes;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::Address;
+use crate::mac::frame::BROADCAST;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::nwk::commands::ManyToOne;
+use crate::nwk::commands::NetworkStatus;
+use crate::nwk::commands::NetworkStatusCode;
+use crate::nwk::commands::NwkCommand;
+use crate::nwk::commands::RouteRecord;
+use crate::nwk::commands::RouteReply;
+use crate::nwk::commands::RouteRequest;
+use crate::nwk::frame::DiscoverRoute;
+use crate::nwk::frame::NwkDataFrame;
+use crate::nwk::frame::NwkHeader;
+use crate::nwk::frame::SourceRouteSubframe;
+use crate::nwk::neighbor::Neighbor;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::nwk::nlde::NldeDataStatus;
+use crate::nwk::routing::Route;
+use crate::nwk::routing::RouteDiscovery;
+use crate::nwk::routing::RouteStatus;
+use crate::nwk::routing::SourceRoute;
+use crate::radio::Radio;
+
+/// nwkcRouteDiscoveryTime
+const ROUTE_DISCOVERY_TIME: Duration = Duration::from_secs(10);
+
+/// nwkcInitialRREQRetries
+const INITIAL_RREQ_RETRIES: u8 = 3;
+
+/// nwkcRREQRetries
+const RREQ_RETRIES: u8 = 2;
+
+/// Cost of a link of unknown quality.
+const MAX_LINK_COST: u8 = 7;
+
+/// Residual cost of a route discovery without a route reply.
+const UNKNOWN_PATH_COST: u8 = 0xff;
+
+/// Broadcast address of all routers and the coordinator.
+pub const ROUTERS_ADDRESS: ShortAddress = ShortAddress(0xfffc);
+
+/// Maximum number of frames waiting for the discovery of a route.
+pub(super) const MAX_PENDING_FRAMES: usize = 4;
+
+/// A frame waiting for the discovery of a route to its destination.
+pub(super) struct PendingFrame {
+    header: NwkHeader,
+    payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    secure: bool,
+}
+
+impl<R: Radio> Nlme<R> {
+    /// The device operates as router or coordinator.
+    pub(super) fn is_router(&self) -> bool {
+        matches!(
+            self.device_type,
+            Some(LogicalType::Coordinator | LogicalType::Router)
+        )
+    }
+
+    /// Cost of the link to the neighbor `address`.
+    ///
+    /// See Section 3.6.3.1.
+    fn link_cost_to(&self, address: ShortAddress) -> u8 {
+        if self.nib.report_constant_cost {
+            return MAX_LINK_COST;
+        }
+        self.nib
+            .neighbor_table
+            .get(self.mac.pan_id, address)
+            .map_or(MAX_LINK_COST, Neighbor::cost)
+    }
+
+    /// The destination is a router in range or a child, which receives frames
+    /// directly.
+    fn is_reachable_neighbor(&self, destination: ShortAddress) -> bool {
+        self.nib
+            .neighbor_table
+            .get(self.mac.pan_id, destination)
+            .is_some_and(|neighbor| neighbor.is_router() || neighbor.is_child())
+    }
+
+    /// The neighbor frames to `destination` are sent to, which is the
+    /// destination itself if it is a neighbor.
+    pub(super) fn next_hop(&self, destination: ShortAddress) -> Option<ShortAddress> {
+        if !self.is_router() {
+            // end devices send all frames through their parent
+            return self
+                .nib
+                .neighbor_table
+                .parent()
+                .map(|parent| parent.network_address);
+        }
+        if self.is_reachable_neighbor(destination) {
+            return Some(destination);
+        }
+        self.nib.route_table.next_hop(destination)
+    }
+
+    /// The source route subframe of a frame of the concentrator to
+    /// `destination`, if the destination is no neighbor and sent a route
+    /// record.
+    fn source_route(&self, destination: ShortAddress) -> Option<SourceRouteSubframe> {
+        if !self.nib.is_concentrator || self.is_reachable_neighbor(destination) {
+            return None;
+        }
+        let route = self
+            .nib
+            .source_route_table
+            .get(destination)
+            .filter(|route| !route.relay_list.is_empty())?;
+        let relay_count = u8::try_from(route.relay_list.len()).ok()?;
+        Some(SourceRouteSubframe {
+            relay_count,
+            relay_index: relay_count - 1,
+            relay_list: route.relay_list.clone(),
+        })
+    }
+
+    /// Forwards a unicast data frame addressed to another device.
+    ///
+    /// A frame which can not be relayed is reported to its originator.
+    ///
+    /// See Section 3.6.3.3.
+    pub(super) async fn relay(
+        &mut self,
+        frame: NwkDataFrame,
+        secured: bool,
+    ) -> Result<(), R::Error> {
+        let mut header = frame.header;
+        if !self.is_router()
+            || header.destination == self.mac.short_address
+            || header.destination.0 >= MIN_BROADCAST_ADDRESS
+            || header.radius <= 1
+        {
+            return Ok(());
+        }
+        header.radius -= 1;
+        if let Some(subframe) = header.source_route_subframe.as_mut() {
+            // See Section 3.6.3.3.2.
+            let index = usize::from(subframe.relay_index);
+            if subframe.relay_list.get(index) != Some(&self.mac.short_address) {
+                return Ok(());
+            }
+            let next_hop = match index.checked_sub(1) {
+                Some(next) => {
+                    subframe.relay_index -= 1;
+                    subframe.relay_list[next]
+                }
+                None => header.destination,
+            };
+            let (source, destination) = (header.source, header.destination);
+            let status = self
+                .send_frame(Address::Short(next_hop), header, &frame.payload, secured)
+                .await?;
+            if NldeDataStatus::from(status) == NldeDataStatus::NoAck {
+                self.report_route_failure(
+                    source,
+                    destination,
+                    NetworkStatusCode::SourceRouteFailure,
+                )
+                .await?;
+            }
+            return Ok(());
+        }
+        let (source, destination) = (header.source, header.destination);
+        let status_code = match self.route_frame(header, &frame.payload, secured).await? {
+            NldeDataStatus::RouteError => NetworkStatusCode::NoRouteAvailable,
+            NldeDataStatus::NoAck => NetworkStatusCode::NonTreeLinkFailure,
+            _ => return Ok(()),
+        };
+        self.report_route_failure(source, destination, status_code)
+            .await
+    }
+
+    /// Tells the originator `source` of a frame to `destination` why it could
+    /// not be relayed.
+    ///
+    /// The Network Status is dropped if there is no route to the originator.
+    ///
+    /// See Section 3.6.3.7.
+    async fn report_route_failure(
+        &mut self,
+        source: ShortAddress,
+        destination: ShortAddress,
+        status_code: NetworkStatusCode,
+    ) -> Result<(), R::Error> {
+        let Some(next_hop) = self.next_hop(source) else {
+            return Ok(());
+        };
+        let status = NwkCommand::NetworkStatus(NetworkStatus {
+            status_code,
+            destination_address: destination,
+        });
+        let header = self.command_header(source, 2 * self.nib.max_depth);
+        self.send_nwk_command(Address::Short(next_hop), header, &status, true)
+            .await?;
+        Ok(())
+    }
+
+    /// Sends a frame over the next hop towards its destination.
+    ///
+    /// Frames originated by a concentrator follow the source route of their
+    /// destination. Frames to a concentrator are preceded by a route record
+    /// if it asked for one. Without a route the frame is kept while a route
+    /// is discovered, if its header permits route discovery.
+    ///
+    /// Returns [`NldeDataStatus::RouteError`] if the frame is dropped for
+    /// lack of a route.
+    ///
+    /// See Section 3.6.3.3.
+    pub(super) async fn route_frame(
+        &mut self,
+        mut header: NwkHeader,
+        payload: &[u8],
+        secure: bool,
+    ) -> Result<NldeDataStatus, R::Error> {
+        let destination = header.destination;
+        if header.source == self.mac.short_address {
+            if let Some(subframe) = self.source_route(destination) {
+                let next_hop = subframe.relay_list[usize::from(subframe.relay_index)];
+                header.frame_control.set_source_flag(true);
+                header
+                    .frame_control
+                    .set_discover_route(DiscoverRoute::Suppress);
+                header.source_route_subframe = Some(subframe);
+                let status = self
+                    .send_frame(Address::Short(next_hop), header, payload, secure)
+                    .await?;
+                return Ok(status.into());
+            }
+            self.send_route_record(destination).await?;
+        }
+        if let Some(next_hop) = self.next_hop(destination) {
+            let status = self
+                .send_frame(Address::Short(next_hop), header, payload, secure)
+                .await?;
+            return Ok(status.into());
+        }
+        if !self.is_router() || header.frame_control.discover_route() != DiscoverRoute::Enable {
+            return Ok(NldeDataStatus::RouteError);
+        }
+        let Ok(payload) = Vec::from_slice(payload) else {
+            return Ok(NldeDataStatus::FrameTooLong);
+        };
+        // frames are dropped while the buffer is full
+        if self.pending_frames.is_full() || !self.discover_route(destination).await? {
+            return Ok(NldeDataStatus::RouteError);
+        }
+        let _ = self.pending_frames.push(PendingFrame {
+            header,
+            payload,
+            secure,
+        });
+        Ok(NldeDataStatus::Success)
+    }
+
+    /// Broadcasts a route request for `destination` unless a discovery is
+    /// already underway, and repeats it nwkcInitialRREQRetries times.
+    ///
+    /// Returns whether a discovery is underway, which is not the case if the
+    /// route or the route discovery table is full.
+    ///
+    /// See Section 3.6.3.5.1.
+    async fn discover_route(&mut self, destination: ShortAddress) -> Result<bool, R::Error> {
+        let underway = self
+            .nib
+            .route_table
+            .get(destination)
+            .is_some_and(|route| route.status == RouteStatus::DiscoveryUnderway);
+        if underway {
+            return Ok(true);
+        }
+        let source = self.mac.short_address;
+        let route_request_id = self.nib.next_route_request_id();
+        let discovery = RouteDiscovery {
+            route_request_id,
+            source,
+            sender: source,
+            forward_cost: 0,
+            residual_cost: UNKNOWN_PATH_COST,
+            expiration: self.mac.radio.now() + ROUTE_DISCOVERY_TIME,
+            destination,
+        };
+        let route = Route {
+            destination,
+            status: RouteStatus::DiscoveryUnderway,
+            no_route_cache: false,
+            many_to_one: false,
+            route_record_required: false,
+            next_hop: ShortAddress(BROADCAST),
+        };
+        if self.nib.route_table.insert(route).is_err() {
+            return Ok(false);
+        }
+        if self.nib.route_discovery_table.insert(discovery).is_err() {
+            if let Some(route) = self.nib.route_table.get_mut(destination) {
+                route.status = RouteStatus::DiscoveryFailed;
+            }
+            return Ok(false);
+        }
+
+        let request = NwkCommand::RouteRequest(RouteRequest {
+            many_to_one: ManyToOne::No,
+            multicast: false,
+            route_request_identifier: route_request_id,
+            destination_address: destination,
+            path_cost: 0,
+            destination_ieee_address: None,
+        });
+        let header = self.command_header(ROUTERS_ADDRESS, 2 * self.nib.max_depth);
+        self.send_route_request(header, &request).await?;
+        Ok(true)
+    }
+
+    /// Broadcasts the route request `request` originated by this device.
+    async fn send_route_request(
+        &mut self,
+        header: NwkHeader,
+        request: &NwkCommand,
+    ) -> Result<(), R::Error> {
+        let mut payload = [0u8; MAX_PHY_PACKET_SIZE];
+        let Ok(len) = request.pack_into(&mut payload) else {
+            return Ok(());
+        };
+        self.broadcast_route_request(header, &payload[..len], INITIAL_RREQ_RETRIES)
+            .await?;
+        Ok(())
+    }
+
+    /// Answers or relays a route request received from the neighbor
+    /// `sender`.
+    ///
+    /// The request is answered by its destination and by the parent of an
+    /// end device it is looking for. Other routers relay the first request
+    /// and those arriving over a cheaper path after a random jitter, and
+    /// repeat it nwkcRREQRetries times. Many-to-one route requests are
+    /// relayed by all routers, which record the route to the concentrator.
+    ///
+    /// See Section 3.6.3.5.2.
+    pub(super) async fn receive_route_request(
+        &mut self,
+        mut header: NwkHeader,
+        mut request: RouteRequest,
+        sender: ShortAddress,
+    ) -> Result<(), R::Error> {
+        let own_address = self.mac.short_address;
+        if !self.is_router() || header.source == own_address {
+            return Ok(());
+        }
+        let originator = header.source;
+        let route_request_id = request.route_request_identifier;
+        let path_cost = request.path_cost.saturating_add(self.link_cost_to(sender));
+        match self
+            .nib
+            .route_discovery_table
+            .get_mut(originator, route_request_id)
+        {
+            Some(discovery) if path_cost >= discovery.forward_cost => return Ok(()),
+            Some(discovery) => {
+                discovery.forward_cost = path_cost;
+                discovery.sender = sender;
+            }
+            None => {
+                let discovery = RouteDiscovery {
+                    route_request_id,
+                    source: originator,
+                    sender,
+                    forward_cost: path_cost,
+                    residual_cost: UNKNOWN_PATH_COST,
+                    expiration: self.mac.radio.now() + ROUTE_DISCOVERY_TIME,
+                    destination: request.destination_address,
+                };
+                if self.nib.route_discovery_table.insert(discovery).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        let destination = request.destination_address;
+        if request.many_to_one == ManyToOne::No {
+            let end_device_child = self
+                .nib
+                .neighbor_table
+                .get(self.mac.pan_id, destination)
+                .filter(|neighbor| neighbor.is_child() && !neighbor.is_router())
+                .map(|child| child.extended_address);
+            if destination == own_address || end_device_child.is_some() {
+                let reply = NwkCommand::RouteReply(RouteReply {
+                    multicast: false,
+                    route_request_identifier: route_request_id,
+                    originator_address: originator,
+                    responder_address: destination,
+                    path_cost: if end_device_child.is_some() {
+                        self.link_cost_to(destination)
+                    } else {
+                        0
+                    },
+                    originator_ieee_address: header.source_ieee,
+                    responder_ieee_address: end_device_child
+                        .unwrap_or(Some(self.mac.extended_address)),
+                });
+                let header = self.command_header(originator, 2 * self.nib.max_depth);
+                self.send_nwk_command(Address::Short(sender), header, &reply, true)
+                    .await?;
+                return Ok(());
+            }
+            if self.nib.route_table.next_hop(destination).is_none() {
+                let _ = self.nib.route_table.insert(Route {
+                    destination,
+                    status: RouteStatus::DiscoveryUnderway,
+                    no_route_cache: false,
+                    many_to_one: false,
+                    route_record_required: false,
+                    next_hop: ShortAddress(BROADCAST),
+                });
+            }
+        } else {
+            // the cheapest many-to-one route request sets up the route to the
+            // concentrator without a reply
+            let _ = self.nib.route_table.insert(Route {
+                destination: originator,
+                status: RouteStatus::Active,
+                no_route_cache: request.many_to_one == ManyToOne::NoRouteRecordTable,
+                many_to_one: true,
+                route_record_required: true,
+                next_hop: sender,
+            });
+        }
+        if header.radius <= 1 {
+            return Ok(());
+        }
+        header.radius -= 1;
+        request.path_cost = path_cost;
+        let mut payload = [0u8; MAX_PHY_PACKET_SIZE];
+        if let Ok(len) = NwkCommand::RouteRequest(request).pack_into(&mut payload) {
+            self.relay_route_request(header, &payload[..len], RREQ_RETRIES);
+        }
+        Ok(())
+    }
+
+    /// Records the route of a route reply received from the neighbor
+    /// `sender` and relays it towards the originator of the request.
+    ///
+    /// Replies over a path which is not cheaper than a previous reply are
+    /// dropped.
+    ///
+    /// See Section 3.6.3.5.3.
+    pub(super) async fn receive_route_reply(
+        &mut self,
+        mut header: NwkHeader,
+        mut reply: RouteReply,
+        sender: ShortAddress,
+    ) -> Result<(), R::Error> {
+        if !self.is_router() {
+            return Ok(());
+        }
+        let path_cost = reply.path_cost.saturating_add(self.link_cost_to(sender));
+        let Some(discovery) = self
+            .nib
+            .route_discovery_table
+            .get_mut(reply.originator_address, reply.route_request_identifier)
+        else {
+            return Ok(());
+        };
+        if path_cost >= discovery.residual_cost {
+            return Ok(());
+        }
+        discovery.residual_cost = path_cost;
+        let previous_hop = discovery.sender;
+        let destination = reply.responder_address;
+        let _ = self.nib.route_table.insert(Route {
+            destination,
+            status: RouteStatus::Active,
+            no_route_cache: false,
+            many_to_one: false,
+            route_record_required: false,
+            next_hop: sender,
+        });
+
+        if reply.originator_address == self.mac.short_address {
+            return self.send_pending_frames(destination).await;
+        }
+        if header.radius <= 1 {
+            return Ok(());
+        }
+        header.radius -= 1;
+        reply.path_cost = path_cost;
+        self.send_nwk_command(
+            Address::Short(previous_hop),
+            header,
+            &NwkCommand::RouteReply(reply),
+            true,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Sends a route record to the concentrator `destination` if it asked
+    /// for one.
+    ///
+    /// Concentrators without a route record table receive a route record
+    /// before every frame.
+    ///
+    /// See Section 3.4.5.
+    async fn send_route_record(&mut self, destination: ShortAddress) -> Result<(), R::Error> {
+        let Some(route) = self.nib.route_table.get_mut(destination).filter(|route| {
+            route.status == RouteStatus::Active && route.many_to_one && route.route_record_required
+        }) else {
+            return Ok(());
+        };
+        route.route_record_required = route.no_route_cache;
+        let next_hop = route.next_hop;
+        let record = NwkCommand::RouteRecord(RouteRecord {
+            relay_list: Vec::new(),
+        });
+        let header = self.command_header(destination, 2 * self.nib.max_depth);
+        self.send_nwk_command(Address::Short(next_hop), header, &record, true)
+            .await?;
+        Ok(())
+    }
+
+    /// Stores a route record addressed to this concentrator in the source
+    /// route table, or adds this router to its relays and forwards it.
+    ///
+    /// See Section 3.4.5.
+    pub(super) async fn receive_route_record(
+        &mut self,
+        mut header: NwkHeader,
+        mut record: RouteRecord,
+    ) -> Result<(), R::Error> {
+        let own_address = self.mac.short_address;
+        if header.destination == own_address {
+            let max_source_route = usize::from(self.nib.max_source_route);
+            if self.nib.is_concentrator && record.relay_list.len() <= max_source_route {
+                if let Ok(relay_list) = Vec::from_slice(&record.relay_list) {
+                    self.nib.source_route_table.insert(SourceRoute {
+                        destination: header.source,
+                        relay_list,
+                    });
+                }
+            }
+            return Ok(());
+        }
+        if !self.is_router() || header.radius <= 1 || record.relay_list.push(own_address).is_err() {
+            return Ok(());
+        }
+        let Some(next_hop) = self.next_hop(header.destination) else {
+            return Ok(());
+        };
+        header.radius -= 1;
+        self.send_nwk_command(
+            Address::Short(next_hop),
+            header,
+            &NwkCommand::RouteRecord(record),
+            true,
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Sends the frames which waited for the route to `destination`.
+    async fn send_pending_frames(&mut self, destination: ShortAddress) -> Result<(), R::Error> {
+        let Some(next_hop) = self.nib.route_table.next_hop(destination) else {
+            return Ok(());
+        };
+        while let Some(index) = self
+            .pending_frames
+            .iter()
+            .position(|frame| frame.header.destination == destination)
+        {
+            let frame = self.pending_frames.remove(index);
+            self.send_frame(
+                Address::Short(next_hop),
+                frame.header,
+                &frame.payload,
+                frame.secure,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Removes the expired route discoveries and lets a concentrator send its
+    /// next many-to-one route request.
+    ///
+    /// Relayed frames dropped for lack of a route are reported to their
+    /// originators.
+    pub(super) async fn maintain_routes(&mut self) -> Result<(), R::Error> {
+        for (source, destination) in self.expire_route_discoveries() {
+            self.report_route_failure(source, destination, NetworkStatusCode::NoRouteAvailable)
+                .await?;
+        }
+        if self
+            .many_to_one_request_due()
+            .is_some_and(|due| due <= self.mac.radio.now())
+        {
+            self.request_many_to_one_routes().await?;
+        }
+        Ok(())
+    }
+
+    /// The time the concentrator sends its next many-to-one route request.
+    pub(super) fn many_to_one_request_due(&self) -> Option<Duration> {
+        if !self.nib.is_concentrator || !self.is_router() {
+            return None;
+        }
+        Some(self.next_many_to_one_request.unwrap_or_default())
+    }
+
+    /// Broadcasts a many-to-one route request of the concentrator.
+    ///
+    /// The request is repeated every nwkConcentratorDiscoveryTime, or sent
+    /// once if it is zero. Each request is retried like other route requests.
+    ///
+    /// See Section 3.6.3.5.1.
+    async fn request_many_to_one_routes(&mut self) -> Result<(), R::Error> {
+        let discovery_time = self.nib.concentrator_discovery_time;
+        self.next_many_to_one_request = Some(if discovery_time.is_zero() {
+            Duration::MAX
+        } else {
+            self.mac.radio.now() + discovery_time
+        });
+        let request = NwkCommand::RouteRequest(RouteRequest {
+            many_to_one: self.nib.concentrator_mode.many_to_one(),
+            multicast: false,
+            route_request_identifier: self.nib.next_route_request_id(),
+            destination_address: ROUTERS_ADDRESS,
+            path_cost: 0,
+            destination_ieee_address: None,
+        });
+        let radius = match self.nib.concentrator_radius {
+            0 => 2 * self.nib.max_depth,
+            radius => radius,
+        };
+        let header = self.command_header(ROUTERS_ADDRESS, radius);
+        self.send_route_request(header, &request).await
+    }
+
+    /// Removes the expired route discoveries.
+    ///
+    /// Routes which were not found are marked as failed, their pending frames
+    /// are dropped. Returns the originator and destination of the dropped
+    /// frames which were relayed for other devices.
+    fn expire_route_discoveries(
+        &mut self,
+    ) -> Vec<(ShortAddress, ShortAddress), MAX_PENDING_FRAMES> {
+        let now = self.mac.radio.now();
+        let own_address = self.mac.short_address;
+        let mut dropped = Vec::new();
+        for discovery in self.nib.route_discovery_table.expire(now) {
+            let destination = discovery.destination;
+            if let Some(route) = self.nib.route_table.get_mut(destination) {
+                if route.status == RouteStatus::DiscoveryUnderway {
+                    route.status = RouteStatus::DiscoveryFailed;
+                }
+            }
+            if self.nib.route_table.next_hop(destination).is_none() {
+                self.pending_frames.retain(|frame| {
+                    let header = &frame.header;
+                    if header.destination != destination {
+                        return true;
+                    }
+                    if header.source != own_address {
+                        let _ = dropped.push((header.source, destination));
+                    }
+                    false
+                });
+            }
+        }
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nwk::frame::FrameControl;
+    use crate::nwk::frame::FrameTypeIdentifier;
+    use crate::nwk::frame::NwkFrame;
+    use crate::nwk::neighbor::Relationship;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::COORDINATOR_ADDRESS;
+    use crate::nwk::routing::Route;
+    use crate::nwk::routing::RouteStatus;
+    use crate::nwk::routing::MAX_ROUTE_DISCOVERIES;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+    use crate::radio::TxStatus;
+
+    /// Unsecured data frame sent by the neighbor 0x796f.
+    fn data_frame(header: NwkHeader, payload: &[u8]) -> RxFrame {
+        let frame = NwkFrame::Data(NwkDataFrame {
+            header,
+            payload: Vec::from_slice(payload).unwrap(),
+        });
+        mac_frame(
+            ShortAddress(COORDINATOR_ADDRESS),
+            ShortAddress(0x796f),
+            frame.pack_to_vec().unwrap(),
+        )
+    }
+
+    #[test]
+    fn router_relays_route_request_with_accumulated_cost() {
+        let mut nlme = parent();
+        nlme.mac
+            .radio
+            .received
+            .push_back(route_request(ManyToOne::No, 0x1234, 3));
+        nlme.mac
+            .radio
+            .received
+            .push_back(route_request(ManyToOne::No, 0x1234, 5));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.source, ShortAddress(0x796f));
+        assert_eq!(frame.header.radius, 4);
+        let Ok(NwkCommand::RouteRequest(request)) = frame.command() else {
+            unreachable!("no route request sent");
+        };
+        assert_eq!(request.route_request_identifier, 7);
+        assert_eq!(request.path_cost, 10);
+        let discovery = nlme
+            .nib
+            .route_discovery_table
+            .get(ShortAddress(0x796f), 7)
+            .unwrap();
+        assert_eq!(discovery.forward_cost, 10);
+        assert_eq!(
+            nlme.nib
+                .route_table
+                .get(ShortAddress(0x1234))
+                .unwrap()
+                .status,
+            RouteStatus::DiscoveryUnderway
+        );
+    }
+
+    #[test]
+    fn destination_answers_route_request() {
+        let mut nlme = parent();
+        nlme.mac
+            .radio
+            .received
+            .push_back(route_request(ManyToOne::No, COORDINATOR_ADDRESS, 3));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x796f))));
+        assert_eq!(frame.header.destination, ShortAddress(0x796f));
+        let Ok(NwkCommand::RouteReply(reply)) = frame.command() else {
+            unreachable!("no route reply sent");
+        };
+        assert_eq!(reply.route_request_identifier, 7);
+        assert_eq!(reply.originator_address, ShortAddress(0x796f));
+        assert_eq!(reply.responder_address, ShortAddress(COORDINATOR_ADDRESS));
+        assert_eq!(reply.path_cost, 0);
+    }
+
+    #[test]
+    fn route_reply_sends_pending_frame() {
+        let mut nlme = parent();
+        block_on(nlme.route_frame(data_header(0x1234, 5), &[1, 2, 3], true)).unwrap();
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        assert_eq!(frame.header.destination, ROUTERS_ADDRESS);
+        let Ok(NwkCommand::RouteRequest(request)) = frame.command() else {
+            unreachable!("no route request sent");
+        };
+        assert_eq!(request.destination_address, ShortAddress(0x1234));
+
+        let mut header = data_header(COORDINATOR_ADDRESS, 5);
+        header.frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        header.source = ShortAddress(0x1234);
+        let reply = NwkCommand::RouteReply(RouteReply {
+            multicast: false,
+            route_request_identifier: request.route_request_identifier,
+            originator_address: ShortAddress(COORDINATOR_ADDRESS),
+            responder_address: ShortAddress(0x1234),
+            path_cost: 0,
+            originator_ieee_address: None,
+            responder_ieee_address: None,
+        });
+        nlme.mac
+            .radio
+            .received
+            .push_back(command_frame(header, &reply, true));
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            nlme.nib.route_table.next_hop(ShortAddress(0x1234)),
+            Some(ShortAddress(0x1234))
+        );
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 1) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x1234))));
+        assert_eq!(frame.header.source, ShortAddress(0x796f));
+        assert_eq!(frame.payload, [1, 2, 3]);
+    }
+
+    #[test]
+    fn router_forwards_data_frame() {
+        let mut nlme = parent();
+        nlme.nib
+            .route_table
+            .insert(Route {
+                destination: ShortAddress(0x1234),
+                status: RouteStatus::Active,
+                no_route_cache: false,
+                many_to_one: false,
+                route_record_required: false,
+                next_hop: ShortAddress(0x0001),
+            })
+            .unwrap();
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(data_header(0x1234, 3), &[1, 2, 3]));
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(data_header(0x1234, 1), &[4, 5, 6]));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0001))));
+        assert_eq!(frame.header.destination, ShortAddress(0x1234));
+        assert_eq!(frame.header.radius, 2);
+        assert_eq!(frame.payload, [1, 2, 3]);
+    }
+
+    #[test]
+    fn expired_route_discovery_fails() {
+        let mut nlme = parent();
+        block_on(nlme.route_frame(data_header(0x1234, 5), &[1, 2, 3], true)).unwrap();
+
+        nlme.mac.radio.now = Duration::from_secs(10);
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            nlme.nib
+                .route_table
+                .get(ShortAddress(0x1234))
+                .unwrap()
+                .status,
+            RouteStatus::DiscoveryFailed
+        );
+        assert!(nlme.pending_frames.is_empty());
+        assert_eq!(nlme.nib.route_discovery_table.iter().count(), 0);
+    }
+
+    #[test]
+    fn frame_is_refused_without_room_for_discovery() {
+        let mut nlme = parent();
+        for route_request_id in (0u8..).take(MAX_ROUTE_DISCOVERIES) {
+            let _ = nlme.nib.route_discovery_table.insert(RouteDiscovery {
+                route_request_id,
+                source: ShortAddress(0x796f),
+                sender: ShortAddress(0x796f),
+                forward_cost: 1,
+                residual_cost: UNKNOWN_PATH_COST,
+                expiration: ROUTE_DISCOVERY_TIME,
+                destination: ShortAddress(0x4321),
+            });
+        }
+
+        let status = block_on(nlme.route_frame(data_header(0x1234, 5), &[1, 2, 3], true)).unwrap();
+
+        assert_eq!(status, NldeDataStatus::RouteError);
+        assert!(nlme.pending_frames.is_empty());
+        assert!(nlme.mac.radio.transmitted.is_empty());
+        assert_eq!(
+            nlme.nib
+                .route_table
+                .get(ShortAddress(0x1234))
+                .map(|route| route.status),
+            Some(RouteStatus::DiscoveryFailed)
+        );
+    }
+
+    /// The Network Status `nlme` sent to 0x796f in the frame at `index`.
+    fn sent_network_status(nlme: &Nlme<MockRadio>, index: usize) -> NetworkStatus {
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(nlme, index) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x796f))));
+        assert_eq!(frame.header.destination, ShortAddress(0x796f));
+        let Ok(NwkCommand::NetworkStatus(status)) = frame.command() else {
+            unreachable!("no network status sent");
+        };
+        status
+    }
+
+    #[test]
+    fn router_reports_frame_without_route() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x796f, Relationship::Child));
+        let mut header = data_header(0x1234, 5);
+        header
+            .frame_control
+            .set_discover_route(DiscoverRoute::Suppress);
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(header, &[1, 2, 3]));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 1);
+        let status = sent_network_status(&nlme, 0);
+        assert_eq!(status.status_code, NetworkStatusCode::NoRouteAvailable);
+        assert_eq!(status.destination_address, ShortAddress(0x1234));
+    }
+
+    #[test]
+    fn router_reports_failed_link() {
+        let mut nlme = parent();
+        nlme.mac.radio.tx_status = Some(TxStatus::NoAck);
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x796f, Relationship::Child));
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x0001, Relationship::Sibling));
+        let mut header = data_header(0x0001, 5);
+        header
+            .frame_control
+            .set_discover_route(DiscoverRoute::Suppress);
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(header, &[1, 2, 3]));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        let status = sent_network_status(&nlme, 1);
+        assert_eq!(status.status_code, NetworkStatusCode::NonTreeLinkFailure);
+        assert_eq!(status.destination_address, ShortAddress(0x0001));
+    }
+
+    #[test]
+    fn expired_route_discovery_reports_relayed_frames() {
+        let mut nlme = parent();
+        let _ = nlme
+            .nib
+            .neighbor_table
+            .insert(router(0x796f, Relationship::Child));
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(data_header(0x1234, 5), &[1, 2, 3]));
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+        let sent = nlme.mac.radio.transmitted.len();
+
+        nlme.mac.radio.now = Duration::from_secs(10);
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let status = (sent..nlme.mac.radio.transmitted.len())
+            .find_map(|index| match sent_frame(&nlme, index) {
+                (_, NwkFrame::NwkCommand(frame)) => match frame.command() {
+                    Ok(NwkCommand::NetworkStatus(status)) => Some(status),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(status.status_code, NetworkStatusCode::NoRouteAvailable);
+        assert_eq!(status.destination_address, ShortAddress(0x1234));
+        assert!(nlme.pending_frames.is_empty());
+    }
+
+    /// Route Record of 0x1234 to `destination` which passed `relays`.
+    fn route_record(destination: u16, relays: &[ShortAddress]) -> RxFrame {
+        let mut header = data_header(destination, 5);
+        header.frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        header.source = ShortAddress(0x1234);
+        let command = NwkCommand::RouteRecord(RouteRecord {
+            relay_list: Vec::from_slice(relays).unwrap(),
+        });
+        command_frame(header, &command, true)
+    }
+
+    #[test]
+    fn many_to_one_route_request_requires_route_record() {
+        let mut nlme = parent();
+        nlme.mac.radio.received.push_back(route_request(
+            ManyToOne::RouteRecordTable,
+            ROUTERS_ADDRESS.0,
+            0,
+        ));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let route = nlme.nib.route_table.get(ShortAddress(0x796f)).unwrap();
+        assert_eq!(route.status, RouteStatus::Active);
+        assert_eq!(route.next_hop, ShortAddress(0x796f));
+        assert!(route.many_to_one && route.route_record_required);
+        assert!(!route.no_route_cache);
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        let Ok(NwkCommand::RouteRequest(request)) = frame.command() else {
+            unreachable!("no route request sent");
+        };
+        assert_eq!(request.many_to_one, ManyToOne::RouteRecordTable);
+
+        let mut header = data_header(0x796f, 5);
+        header.source = ShortAddress(COORDINATOR_ADDRESS);
+        block_on(nlme.route_frame(header, &[1, 2, 3], true)).unwrap();
+
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 1) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x796f))));
+        assert_eq!(frame.header.destination, ShortAddress(0x796f));
+        let Ok(NwkCommand::RouteRecord(record)) = frame.command() else {
+            unreachable!("no route record sent");
+        };
+        assert!(record.relay_list.is_empty());
+        let (_, NwkFrame::Data(frame)) = sent_frame(&nlme, 2) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(frame.payload, [1, 2, 3]);
+        let route = nlme.nib.route_table.get(ShortAddress(0x796f)).unwrap();
+        assert!(!route.route_record_required);
+    }
+
+    #[test]
+    fn concentrator_source_routes_frames() {
+        let mut nlme = parent();
+        nlme.nib.is_concentrator = true;
+        nlme.mac.radio.received.push_back(route_record(
+            COORDINATOR_ADDRESS,
+            &[ShortAddress(0x0002), ShortAddress(0x0001)],
+        ));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::BROADCAST));
+        let Ok(NwkCommand::RouteRequest(request)) = frame.command() else {
+            unreachable!("no route request sent");
+        };
+        assert_eq!(request.many_to_one, ManyToOne::RouteRecordTable);
+        assert_eq!(request.destination_address, ROUTERS_ADDRESS);
+
+        let mut header = data_header(0x1234, 5);
+        header.source = ShortAddress(COORDINATOR_ADDRESS);
+        block_on(nlme.route_frame(header, &[1, 2, 3], true)).unwrap();
+
+        assert_eq!(nlme.mac.radio.transmitted.len(), 2);
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 1) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0001))));
+        assert_eq!(
+            frame.header.frame_control.discover_route(),
+            DiscoverRoute::Suppress
+        );
+        let subframe = frame.header.source_route_subframe.unwrap();
+        assert_eq!((subframe.relay_count, subframe.relay_index), (2, 1));
+        assert_eq!(
+            subframe.relay_list,
+            [ShortAddress(0x0002), ShortAddress(0x0001)]
+        );
+    }
+
+    #[test]
+    fn router_relays_source_routed_frame() {
+        let mut nlme = parent();
+        let mut header = data_header(0x1234, 5);
+        header.frame_control.set_source_flag(true);
+        header.source_route_subframe = Some(SourceRouteSubframe {
+            relay_count: 2,
+            relay_index: 1,
+            relay_list: Vec::from_slice(&[ShortAddress(0x0002), ShortAddress(COORDINATOR_ADDRESS)])
+                .unwrap(),
+        });
+        nlme.mac
+            .radio
+            .received
+            .push_back(data_frame(header, &[1, 2, 3]));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let (destination, NwkFrame::Data(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no data frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0002))));
+        assert_eq!(frame.header.radius, 4);
+        assert_eq!(frame.header.source_route_subframe.unwrap().relay_index, 0);
+    }
+
+    #[test]
+    fn router_relays_route_record() {
+        let mut nlme = parent();
+        nlme.nib
+            .route_table
+            .insert(Route {
+                destination: ShortAddress(0x5678),
+                status: RouteStatus::Active,
+                no_route_cache: false,
+                many_to_one: true,
+                route_record_required: false,
+                next_hop: ShortAddress(0x0001),
+            })
+            .unwrap();
+        nlme.mac
+            .radio
+            .received
+            .push_back(route_record(0x5678, &[ShortAddress(0x0002)]));
+
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        let (destination, NwkFrame::NwkCommand(frame)) = sent_frame(&nlme, 0) else {
+            unreachable!("no command frame sent");
+        };
+        assert_eq!(destination, Some(Address::Short(ShortAddress(0x0001))));
+        assert_eq!(frame.header.radius, 4);
+        let Ok(NwkCommand::RouteRecord(record)) = frame.command() else {
+            unreachable!("no route record sent");
+        };
+        assert_eq!(
+            record.relay_list,
+            [ShortAddress(0x0002), ShortAddress(COORDINATOR_ADDRESS)]
+        );
+    }
+}
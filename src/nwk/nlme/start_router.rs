@@ -0,0 +1,132 @@
+//! Starting a Router
+//!
+//! A router which joined a network starts to operate as a parent. It answers
+//! beacon requests and admits joining devices while joining is permitted and
+//! it has capacity for them, which it advertises in its beacons.
+//!
+//! See Section 3.2.2.9.
+use super::management::NlmeStartRouterRequest;
+use super::management::NlmeStartRouterStatus;
+use super::Nlme;
+use super::NON_BEACON_ORDER;
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::radio::Radio;
+
+impl<R: Radio> Nlme<R> {
+    /// Starts operating as a router on the network this device joined.
+    ///
+    /// A router which already started is started again, for instance after
+    /// a rejoin on another channel.
+    pub(super) async fn start_as_router(
+        &mut self,
+        request: &NlmeStartRouterRequest,
+    ) -> Result<(), NlmeStartRouterStatus> {
+        if self.device_type != Some(LogicalType::Router) {
+            return Err(NlmeStartRouterStatus::InvalidRequest);
+        }
+        if request.beacon_order != NON_BEACON_ORDER || request.superframe_order != NON_BEACON_ORDER
+        {
+            return Err(NlmeStartRouterStatus::InvalidParameter);
+        }
+        self.mac
+            .start(
+                self.mac.channel,
+                self.mac.pan_id,
+                self.mac.short_address,
+                false,
+            )
+            .await
+            .map_err(|_| NlmeStartRouterStatus::RadioError)?;
+        self.router_started = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use heapless::Vec;
+
+    use super::*;
+    use crate::common::parse::PackBytes;
+    use crate::common::types::ShortAddress;
+    use crate::mac::frame::Address;
+    use crate::mac::frame::MacBeaconFrame;
+    use crate::mac::frame::MacFrame;
+    use crate::nwk::beacon::BeaconPayload;
+    use crate::nwk::nlme::test_helpers::*;
+    use crate::nwk::nlme::NlmeSap;
+    use crate::radio::mock::block_on;
+    use crate::radio::mock::MockRadio;
+    use crate::radio::RxFrame;
+
+    fn beacon_request() -> RxFrame {
+        RxFrame {
+            frame: Vec::from_slice(&[0x03, 0x08, 0x02, 0xff, 0xff, 0xff, 0xff, 0x07]).unwrap(),
+            lqi: 255,
+            rssi: -40,
+            timestamp: Duration::ZERO,
+        }
+    }
+
+    fn start_router_request(beacon_order: u8) -> NlmeStartRouterRequest {
+        NlmeStartRouterRequest {
+            beacon_order,
+            superframe_order: beacon_order,
+            battery_life_extension: false,
+        }
+    }
+
+    /// The beacons sent by `nlme`.
+    fn sent_beacons(nlme: &Nlme<MockRadio>) -> impl Iterator<Item = MacBeaconFrame> + '_ {
+        nlme.mac.radio.transmitted.iter().filter_map(|frame| {
+            match MacFrame::unpack_from_slice(frame) {
+                Ok(MacFrame::Beacon(beacon)) => Some(beacon),
+                _ => None,
+            }
+        })
+    }
+
+    #[test]
+    fn started_router_answers_beacon_requests() {
+        let mut nlme = parent();
+        block_on(nlme.mac.start(15, 0x1a62, ShortAddress(0x796f), false)).unwrap();
+        nlme.device_type = Some(LogicalType::EndDevice);
+        nlme.router_started = false;
+        nlme.depth = 1;
+
+        let end_device = block_on(nlme.start_router(start_router_request(15)));
+        nlme.device_type = Some(LogicalType::Router);
+        nlme.mac.radio.received.push_back(beacon_request());
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(end_device.status, NlmeStartRouterStatus::InvalidRequest);
+        assert_eq!(sent_beacons(&nlme).count(), 0);
+
+        let beacon_enabled = block_on(nlme.start_router(start_router_request(14)));
+        let started = block_on(nlme.start_router(start_router_request(15)));
+        nlme.mac.radio.received.push_back(beacon_request());
+        block_on(nlme.process(Duration::from_millis(10))).unwrap();
+
+        assert_eq!(
+            beacon_enabled.status,
+            NlmeStartRouterStatus::InvalidParameter
+        );
+        assert_eq!(started.status, NlmeStartRouterStatus::Success);
+        let Some(beacon) = sent_beacons(&nlme).next() else {
+            unreachable!("no beacon sent");
+        };
+        let superframe = beacon.beacon.superframe_specification;
+        assert!(!superframe.pan_coordinator());
+        assert!(!superframe.association_permit());
+        assert_eq!(
+            beacon.header.source,
+            Some(Address::Short(ShortAddress(0x796f)))
+        );
+        let payload = BeaconPayload::unpack_from_slice(&beacon.payload).unwrap();
+        assert_eq!(payload.device_depth, 1);
+        assert!(payload.router_capacity);
+        assert!(payload.end_device_capacity);
+    }
+}
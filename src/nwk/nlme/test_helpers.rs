@@ -0,0 +1,418 @@
+//! Builders shared by the NLME unit tests.
+
+use heapless::FnvIndexSet;
+
+use super::*;
+use crate::mac::beacon::Beacon;
+use crate::mac::beacon::SuperframeSpecification;
+use crate::mac::command::AssociationResponse;
+use crate::mac::frame::FrameType;
+use crate::mac::frame::MacBeaconFrame;
+use crate::mac::frame::MacDataFrame;
+use crate::mac::frame::MacHeader;
+use crate::nwk::commands::ManyToOne;
+use crate::nwk::commands::RejoinRequest;
+use crate::nwk::commands::RejoinResponse;
+use crate::nwk::commands::RouteRequest;
+use crate::nwk::frame::NwkCommandFrame;
+use crate::nwk::security::NetworkKey;
+use crate::radio::mock::block_on;
+use crate::radio::mock::MockRadio;
+use crate::radio::ChannelMask;
+use crate::radio::RxFrame;
+
+pub(super) const EXTENDED_PAN_ID: u64 = 0x0015_8d00_01ab_cd12;
+
+pub(super) fn beacon(address: u16, permit_joining: bool, depth: u8, lqi: u8) -> RxFrame {
+    let mut superframe = SuperframeSpecification::NON_BEACON;
+    superframe.set_association_permit(permit_joining);
+    let payload = BeaconPayload {
+        protocol_id: ZIGBEE_PROTOCOL_ID,
+        stack_profile: 2,
+        protocol_version: 2,
+        router_capacity: true,
+        device_depth: depth,
+        end_device_capacity: permit_joining,
+        extended_pan_id: EXTENDED_PAN_ID,
+        tx_offset: 0x00ff_ffff,
+        update_id: 0,
+    };
+    let frame = MacFrame::Beacon(MacBeaconFrame {
+        header: MacHeader::new(
+            FrameType::Beacon,
+            0,
+            None,
+            Some((0x1a62, Address::Short(ShortAddress(address)))),
+        ),
+        beacon: Beacon::new(superframe),
+        payload: payload.pack_to_vec().unwrap(),
+    });
+    RxFrame {
+        frame: frame.pack_to_vec().unwrap(),
+        lqi,
+        rssi: -60,
+        timestamp: Duration::ZERO,
+    }
+}
+
+pub(super) fn request(channel: u8) -> NlmeNetworkDiscoveryRequest {
+    NlmeNetworkDiscoveryRequest {
+        scan_channels_list_structure: ChannelMask::single(channel),
+        scan_duration: 3,
+    }
+}
+
+pub(super) const KEY: NetworkKey = NetworkKey {
+    key: [
+        0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a, 0x0c,
+        0x0d,
+    ],
+    sequence_number: 0,
+};
+
+/// A coordinator of [`EXTENDED_PAN_ID`] on channel 15 holding [`KEY`].
+pub(super) fn parent() -> Nlme<MockRadio> {
+    let mut radio = MockRadio::new();
+    radio.tx_status = Some(TxStatus::Acked {
+        frame_pending: false,
+    });
+    let mut nlme = Nlme::new(radio);
+    nlme.mac.extended_address = IeeeAddress(0x00ff);
+    block_on(
+        nlme.mac
+            .start(15, 0x1a62, ShortAddress(COORDINATOR_ADDRESS), true),
+    )
+    .unwrap();
+    nlme.device_type = Some(LogicalType::Coordinator);
+    nlme.router_started = true;
+    nlme.nib.extended_pan_id = EXTENDED_PAN_ID;
+    nlme.nib.network_key = Some(KEY);
+    nlme
+}
+
+/// Rejoin Request of the router 0x0001 keeping the address 0x796f.
+pub(super) fn rejoin_request(secure: bool) -> RxFrame {
+    let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+    frame_control.set_source_ieee_flag(true);
+    let header = NwkHeader {
+        frame_control,
+        destination: ShortAddress(COORDINATOR_ADDRESS),
+        source: ShortAddress(0x796f),
+        radius: 1,
+        sequence_number: 1,
+        destination_ieee: None,
+        source_ieee: Some(IeeeAddress(0x0001)),
+        multicast_control: None,
+        source_route_subframe: None,
+    };
+    let mut flags = FnvIndexSet::new();
+    let _ = flags.insert(MacCapabilityFlag::DeviceType);
+    let _ = flags.insert(MacCapabilityFlag::ReceiverOnWhenIdle);
+    let command = NwkCommand::RejoinRequest(RejoinRequest {
+        capability_information: MacCapabilities::new(flags),
+    });
+    command_frame(header, &command, secure)
+}
+
+/// `command` sent by the neighbor `header.source`, secured by the device
+/// `header.source_ieee` or else 0x0001.
+pub(super) fn command_frame(mut header: NwkHeader, command: &NwkCommand, secure: bool) -> RxFrame {
+    header.frame_control.set_security_flag(secure);
+    let (destination, source) = (header.destination, header.source);
+    let payload = if secure {
+        let command: Vec<u8, MAX_PHY_PACKET_SIZE> = command.pack_to_vec().unwrap();
+        let device = header.source_ieee.unwrap_or(IeeeAddress(0x0001));
+        security::secure(&header, &command, &KEY, 1, device).unwrap()
+    } else {
+        let frame = NwkCommandFrame::new(header, command).unwrap();
+        NwkFrame::NwkCommand(frame).pack_to_vec().unwrap()
+    };
+    mac_frame(destination, source, payload)
+}
+
+/// MAC data frame of the NWK frame `payload` on the PAN 0x1a62.
+pub(super) fn mac_frame(
+    destination: ShortAddress,
+    source: ShortAddress,
+    payload: Vec<u8, MAX_PHY_PACKET_SIZE>,
+) -> RxFrame {
+    let frame = MacFrame::Data(MacDataFrame {
+        header: MacHeader::new(
+            FrameType::Data,
+            1,
+            Some((0x1a62, Address::Short(destination))),
+            Some((0x1a62, Address::Short(source))),
+        ),
+        payload,
+    });
+    RxFrame {
+        frame: frame.pack_to_vec().unwrap(),
+        lqi: 230,
+        rssi: -40,
+        timestamp: Duration::ZERO,
+    }
+}
+
+/// Parses a NWK frame sent by `nlme` and removes its security.
+///
+/// Returns the frame and if it was secured.
+pub(super) fn open_sent(nlme: &Nlme<MockRadio>, payload: &[u8]) -> Option<(NwkFrame, bool)> {
+    let header = NwkHeader::unpack_from_slice(payload).ok()?;
+    if header.frame_control.security_flag() {
+        let key = nlme.nib.network_key.as_ref()?;
+        Some((security::unsecure(payload, key).ok()?.0, true))
+    } else {
+        Some((NwkFrame::unpack_from_slice(payload).ok()?, false))
+    }
+}
+
+/// Decodes the Rejoin Response sent by `nlme` and if it was secured.
+pub(super) fn rejoin_response(nlme: &Nlme<MockRadio>) -> (RejoinResponse, bool) {
+    let Ok(MacFrame::Data(frame)) = MacFrame::unpack_from_slice(&nlme.mac.radio.transmitted[0])
+    else {
+        unreachable!("no data frame sent");
+    };
+    assert_eq!(
+        frame.header.destination,
+        Some(Address::Extended(IeeeAddress(0x0001)))
+    );
+    let Some((NwkFrame::NwkCommand(command), secured)) = open_sent(nlme, &frame.payload) else {
+        unreachable!("no command frame sent");
+    };
+    assert_eq!(command.header.destination, ShortAddress(0x796f));
+    assert_eq!(command.header.destination_ieee, Some(IeeeAddress(0x0001)));
+    let Ok(NwkCommand::RejoinResponse(response)) = command.command() else {
+        unreachable!("no rejoin response sent");
+    };
+    (response, secured)
+}
+
+/// Route Request of 0x796f for `destination` with the cost `path_cost`.
+pub(super) fn route_request(many_to_one: ManyToOne, destination: u16, path_cost: u8) -> RxFrame {
+    let header = NwkHeader {
+        frame_control: FrameControl::new(FrameTypeIdentifier::NwkCommand),
+        destination: ROUTERS_ADDRESS,
+        source: ShortAddress(0x796f),
+        radius: 5,
+        sequence_number: 1,
+        destination_ieee: None,
+        source_ieee: None,
+        multicast_control: None,
+        source_route_subframe: None,
+    };
+    let command = NwkCommand::RouteRequest(RouteRequest {
+        many_to_one,
+        multicast: false,
+        route_request_identifier: 7,
+        destination_address: ShortAddress(destination),
+        path_cost,
+        destination_ieee_address: None,
+    });
+    command_frame(header, &command, true)
+}
+
+/// Header of a data frame of 0x796f to `destination`.
+pub(super) fn data_header(destination: u16, radius: u8) -> NwkHeader {
+    let mut frame_control = FrameControl::new(FrameTypeIdentifier::Data);
+    frame_control.set_discover_route(DiscoverRoute::Enable);
+    NwkHeader {
+        frame_control,
+        destination: ShortAddress(destination),
+        source: ShortAddress(0x796f),
+        radius,
+        sequence_number: 1,
+        destination_ieee: None,
+        source_ieee: None,
+        multicast_control: None,
+        source_route_subframe: None,
+    }
+}
+
+/// MAC destination and NWK frame of the `index`th frame sent by `nlme`.
+pub(super) fn sent_frame(nlme: &Nlme<MockRadio>, index: usize) -> (Option<Address>, NwkFrame) {
+    let Ok(MacFrame::Data(frame)) = MacFrame::unpack_from_slice(&nlme.mac.radio.transmitted[index])
+    else {
+        unreachable!("no data frame sent");
+    };
+    let Some((nwk_frame, _)) = open_sent(nlme, &frame.payload) else {
+        unreachable!("no NWK frame sent");
+    };
+    (frame.header.destination, nwk_frame)
+}
+
+/// Router neighbor `address` on the PAN 0x1a62.
+pub(super) fn router(address: u16, relationship: Relationship) -> Neighbor {
+    Neighbor {
+        extended_address: Some(IeeeAddress(u64::from(address))),
+        network_address: ShortAddress(address),
+        device_type: LogicalType::Router,
+        rx_on_when_idle: true,
+        relationship,
+        lqi: 200,
+        pan_id: 0x1a62,
+        extended_pan_id: EXTENDED_PAN_ID,
+        logical_channel: 15,
+        depth: 1,
+        permit_joining: false,
+        router_capacity: false,
+        end_device_capacity: false,
+        potential_parent: false,
+        update_id: 0,
+        outgoing_cost: 0,
+        age: 0,
+        device_timeout: None,
+        timeout_expiry: None,
+        keepalive: None,
+        incoming_frame_counter: None,
+    }
+}
+
+/// Header of a command of `source` to `destination`.
+pub(super) fn nwk_command_header(destination: ShortAddress, source: u16, radius: u8) -> NwkHeader {
+    let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+    frame_control.set_source_ieee_flag(true);
+    NwkHeader {
+        frame_control,
+        destination,
+        source: ShortAddress(source),
+        radius,
+        sequence_number: 1,
+        destination_ieee: None,
+        source_ieee: Some(IeeeAddress(u64::from(source))),
+        multicast_control: None,
+        source_route_subframe: None,
+    }
+}
+
+/// MAC command of the device `extended_address` associating through the
+/// coordinator of the PAN 0x1a62.
+pub(super) fn associating_frame(extended_address: u64, command: MacCommand) -> RxFrame {
+    let source_pan_id = match command {
+        MacCommand::AssociationRequest(_) => BROADCAST,
+        _ => 0x1a62,
+    };
+    let frame = MacFrame::Command(MacCommandFrame {
+        header: MacHeader::new(
+            FrameType::MacCommand,
+            1,
+            Some((0x1a62, Address::Short(ShortAddress(COORDINATOR_ADDRESS)))),
+            Some((
+                source_pan_id,
+                Address::Extended(IeeeAddress(extended_address)),
+            )),
+        ),
+        command,
+    });
+    RxFrame {
+        frame: frame.pack_to_vec().unwrap(),
+        lqi: 230,
+        rssi: -40,
+        timestamp: Duration::ZERO,
+    }
+}
+
+/// Lets the device `extended_address` associate through `nlme` and poll
+/// for the response.
+pub(super) fn associate(nlme: &mut Nlme<MockRadio>, extended_address: u64, router: bool) {
+    let mut flags = FnvIndexSet::new();
+    let _ = flags.insert(MacCapabilityFlag::AllocateAddress);
+    if router {
+        let _ = flags.insert(MacCapabilityFlag::DeviceType);
+        let _ = flags.insert(MacCapabilityFlag::ReceiverOnWhenIdle);
+    }
+    let request = MacCommand::AssociationRequest(MacCapabilities::new(flags));
+    let received = &mut nlme.mac.radio.received;
+    received.push_back(associating_frame(extended_address, request));
+    received.push_back(associating_frame(extended_address, MacCommand::DataRequest));
+}
+
+/// The association response sent by `nlme` to the device
+/// `extended_address`.
+pub(super) fn sent_association_response(
+    nlme: &Nlme<MockRadio>,
+    extended_address: u64,
+) -> AssociationResponse {
+    let Some(frame) = nlme.mac.radio.transmitted.last() else {
+        unreachable!("no frame sent");
+    };
+    let Ok(MacFrame::Command(MacCommandFrame {
+        header,
+        command: MacCommand::AssociationResponse(response),
+    })) = MacFrame::unpack_from_slice(frame)
+    else {
+        unreachable!("no association response sent");
+    };
+    assert_eq!(
+        header.destination,
+        Some(Address::Extended(IeeeAddress(extended_address)))
+    );
+    response
+}
+
+/// End device child 0x796f of the coordinator.
+pub(super) fn end_device_child() -> Neighbor {
+    Neighbor {
+        device_type: LogicalType::EndDevice,
+        rx_on_when_idle: false,
+        device_timeout: EndDeviceTimeout::new(0),
+        timeout_expiry: Some(Duration::from_secs(10)),
+        ..router(0x796f, Relationship::Child)
+    }
+}
+
+/// Data request of the device `source` polling the coordinator.
+pub(super) fn data_poll(source: u16) -> RxFrame {
+    let frame = MacFrame::Command(MacCommandFrame {
+        header: MacHeader::new(
+            FrameType::MacCommand,
+            1,
+            Some((0x1a62, Address::Short(ShortAddress(COORDINATOR_ADDRESS)))),
+            Some((0x1a62, Address::Short(ShortAddress(source)))),
+        ),
+        command: MacCommand::DataRequest,
+    });
+    RxFrame {
+        frame: frame.pack_to_vec().unwrap(),
+        lqi: 230,
+        rssi: -40,
+        timestamp: Duration::ZERO,
+    }
+}
+
+/// The end device 0x796f of the router 0x0002.
+pub(super) fn end_device() -> Nlme<MockRadio> {
+    let mut nlme = parent();
+    nlme.device_type = Some(LogicalType::EndDevice);
+    nlme.router_started = false;
+    nlme.mac.short_address = ShortAddress(0x796f);
+    nlme.mac.extended_address = IeeeAddress(0x0001);
+    let _ = nlme
+        .nib
+        .neighbor_table
+        .insert(router(0x0002, Relationship::Parent));
+    nlme
+}
+
+/// Unicast data request of `nsdu` to the device `destination`.
+pub(super) fn unicast_request(destination: u16, nsdu: &[u8]) -> NldeDataRequest {
+    NldeDataRequest {
+        destination_address_mode: NldeAddressMode::Network,
+        destination: ShortAddress(destination),
+        nsdu: Vec::from_slice(nsdu).unwrap(),
+        radius: 0,
+        non_member_radius: 0,
+        discover_route: true,
+        security_enable: true,
+    }
+}
+
+pub(super) fn join_request(extended_pan_id: u64) -> NlmeJoinRequest {
+    NlmeJoinRequest {
+        extended_pan_id,
+        rejoin_network: RejoinNetwork::Association,
+        scan_channels_list_structure: ChannelMask(0),
+        scan_duration: 3,
+        capability_information: MacCapabilities::new(FnvIndexSet::new()),
+        security_enabled: false,
+    }
+}
@@ -0,0 +1,397 @@
+//! Routing Tables
+//!
+//! The routing table holds the next hop towards destinations reached over
+//! several hops, the route discovery table tracks the route requests a
+//! router took part in. Concentrators keep the relays towards other devices
+//! in the source route table.
+//!
+//! See Section 3.6.3.2.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use super::commands::ManyToOne;
+use super::frame::RELAY_LIST_SIZE;
+use crate::common::types::ShortAddress;
+
+/// Maximum number of routing table entries.
+pub const MAX_ROUTES: usize = 16;
+
+/// Maximum number of route discovery table entries.
+pub const MAX_ROUTE_DISCOVERIES: usize = 8;
+
+/// Maximum number of source route table entries.
+pub const MAX_SOURCE_ROUTES: usize = 16;
+
+/// Status of a route.
+///
+/// See Table 3-67.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteStatus {
+    /// The route is used to forward frames.
+    Active = 0x00,
+    /// A route request for the destination is in progress.
+    DiscoveryUnderway = 0x01,
+    /// No route reply was received for the destination.
+    DiscoveryFailed = 0x02,
+    /// The route is no longer used.
+    Inactive = 0x03,
+    /// The route is being validated.
+    ValidationUnderway = 0x04,
+}
+
+/// Routing table entry
+///
+/// See Table 3-66.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Route {
+    /// 16-bit network address of the destination
+    pub destination: ShortAddress,
+    /// Status of the route
+    pub status: RouteStatus,
+    /// Set if the destination is a concentrator which does not store the
+    /// route records
+    pub no_route_cache: bool,
+    /// Set if the destination is a concentrator which sent a many-to-one
+    /// route request
+    pub many_to_one: bool,
+    /// Set if a route record is sent before the next data frame to the
+    /// destination
+    pub route_record_required: bool,
+    /// 16-bit network address of the next hop towards the destination
+    pub next_hop: ShortAddress,
+}
+
+/// 3.6.3.2 Routing Table
+#[derive(Debug, Default)]
+pub struct RoutingTable {
+    entries: Vec<Route, MAX_ROUTES>,
+}
+
+impl RoutingTable {
+    /// Creates an empty routing table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = &Route> {
+        self.entries.iter()
+    }
+
+    /// Returns the entry of `destination`.
+    pub fn get(&self, destination: ShortAddress) -> Option<&Route> {
+        self.entries
+            .iter()
+            .find(|route| route.destination == destination)
+    }
+
+    pub(crate) fn get_mut(&mut self, destination: ShortAddress) -> Option<&mut Route> {
+        self.entries
+            .iter_mut()
+            .find(|route| route.destination == destination)
+    }
+
+    /// Returns the next hop of the active route to `destination`.
+    pub fn next_hop(&self, destination: ShortAddress) -> Option<ShortAddress> {
+        self.get(destination)
+            .filter(|route| route.status == RouteStatus::Active)
+            .map(|route| route.next_hop)
+    }
+
+    /// Adds a route or replaces the entry of the same destination.
+    ///
+    /// A full table replaces a failed or inactive route. Returns the route if
+    /// all entries are in use.
+    pub(crate) fn insert(&mut self, route: Route) -> Result<(), Route> {
+        if let Some(entry) = self.get_mut(route.destination) {
+            *entry = route;
+            return Ok(());
+        }
+        if self.entries.is_full() {
+            let unused = self.entries.iter().position(|entry| {
+                matches!(
+                    entry.status,
+                    RouteStatus::DiscoveryFailed | RouteStatus::Inactive
+                )
+            });
+            let Some(index) = unused else {
+                return Err(route);
+            };
+            self.entries.swap_remove(index);
+        }
+        self.entries.push(route)
+    }
+
+    /// Removes the routes to and through the device `network_address`.
+    pub(crate) fn remove_device(&mut self, network_address: ShortAddress) {
+        self.entries.retain(|route| {
+            route.destination != network_address && route.next_hop != network_address
+        });
+    }
+}
+
+/// Route discovery table entry
+///
+/// See Table 3-69.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RouteDiscovery {
+    /// Sequence number of the route request
+    pub route_request_id: u8,
+    /// 16-bit network address of the originator of the route request
+    pub source: ShortAddress,
+    /// 16-bit network address of the device the best route request was
+    /// received from, the next hop of the route reply
+    pub sender: ShortAddress,
+    /// Path cost from the originator to the current device
+    pub forward_cost: u8,
+    /// Path cost from the current device to the destination
+    pub residual_cost: u8,
+    /// Time at which the entry expires
+    pub expiration: Duration,
+    /// 16-bit network address of the destination of the route request
+    pub destination: ShortAddress,
+}
+
+/// 3.6.3.2 Route Discovery Table
+#[derive(Debug, Default)]
+pub struct RouteDiscoveryTable {
+    entries: Vec<RouteDiscovery, MAX_ROUTE_DISCOVERIES>,
+}
+
+impl RouteDiscoveryTable {
+    /// Creates an empty route discovery table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = &RouteDiscovery> {
+        self.entries.iter()
+    }
+
+    /// Returns the entry of the route request `route_request_id` of `source`.
+    pub fn get(&self, source: ShortAddress, route_request_id: u8) -> Option<&RouteDiscovery> {
+        self.entries.iter().find(|discovery| {
+            discovery.source == source && discovery.route_request_id == route_request_id
+        })
+    }
+
+    pub(crate) fn get_mut(
+        &mut self,
+        source: ShortAddress,
+        route_request_id: u8,
+    ) -> Option<&mut RouteDiscovery> {
+        self.entries.iter_mut().find(|discovery| {
+            discovery.source == source && discovery.route_request_id == route_request_id
+        })
+    }
+
+    /// Adds a route discovery.
+    ///
+    /// Returns the discovery if the table is full.
+    pub(crate) fn insert(&mut self, discovery: RouteDiscovery) -> Result<(), RouteDiscovery> {
+        self.entries.push(discovery)
+    }
+
+    /// Removes and returns the entries which expired at `now`.
+    pub(crate) fn expire(&mut self, now: Duration) -> Vec<RouteDiscovery, MAX_ROUTE_DISCOVERIES> {
+        let expired = self
+            .entries
+            .iter()
+            .filter(|discovery| discovery.expiration <= now)
+            .copied()
+            .collect();
+        self.entries.retain(|discovery| discovery.expiration > now);
+        expired
+    }
+}
+
+/// Memory model of a concentrator.
+///
+/// See Section 3.6.3.5.1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConcentratorMode {
+    /// The concentrator stores the source routes of all devices, which send
+    /// a route record after each many-to-one route request.
+    #[default]
+    HighRam,
+    /// The concentrator stores a few source routes only, devices send a route
+    /// record before every data frame to it.
+    LowRam,
+}
+
+impl ConcentratorMode {
+    /// The many-to-one sub-field of the route requests of the concentrator.
+    pub fn many_to_one(self) -> ManyToOne {
+        match self {
+            Self::HighRam => ManyToOne::RouteRecordTable,
+            Self::LowRam => ManyToOne::NoRouteRecordTable,
+        }
+    }
+}
+
+/// Source route table entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceRoute {
+    /// 16-bit network address of the destination
+    pub destination: ShortAddress,
+    /// Relays towards the destination, starting with the relay closest to the
+    /// destination
+    pub relay_list: Vec<ShortAddress, RELAY_LIST_SIZE>,
+}
+
+/// Source route table of a concentrator, filled by route records.
+///
+/// See Section 3.6.3.2.
+#[derive(Debug, Default)]
+pub struct SourceRouteTable {
+    entries: Vec<SourceRoute, MAX_SOURCE_ROUTES>,
+}
+
+impl SourceRouteTable {
+    /// Creates an empty source route table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Iterates over all entries.
+    pub fn iter(&self) -> impl Iterator<Item = &SourceRoute> {
+        self.entries.iter()
+    }
+
+    /// Returns the source route to `destination`.
+    pub fn get(&self, destination: ShortAddress) -> Option<&SourceRoute> {
+        self.entries
+            .iter()
+            .find(|route| route.destination == destination)
+    }
+
+    /// Adds a source route or replaces the entry of the same destination.
+    ///
+    /// A full table replaces its oldest entry.
+    pub(crate) fn insert(&mut self, route: SourceRoute) {
+        if let Some(index) = self
+            .entries
+            .iter()
+            .position(|entry| entry.destination == route.destination)
+        {
+            self.entries.remove(index);
+        } else if self.entries.is_full() {
+            self.entries.remove(0);
+        }
+        let _ = self.entries.push(route);
+    }
+
+    /// Removes the source routes to and through the device
+    /// `network_address`.
+    pub(crate) fn remove_device(&mut self, network_address: ShortAddress) {
+        self.entries.retain(|route| {
+            route.destination != network_address && !route.relay_list.contains(&network_address)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: u16, status: RouteStatus) -> Route {
+        Route {
+            destination: ShortAddress(destination),
+            status,
+            no_route_cache: false,
+            many_to_one: false,
+            route_record_required: false,
+            next_hop: ShortAddress(0x0001),
+        }
+    }
+
+    #[test]
+    fn next_hop_requires_active_route() {
+        let mut table = RoutingTable::new();
+        table
+            .insert(route(0x796f, RouteStatus::DiscoveryUnderway))
+            .unwrap();
+        assert_eq!(table.next_hop(ShortAddress(0x796f)), None);
+
+        table.insert(route(0x796f, RouteStatus::Active)).unwrap();
+
+        assert_eq!(table.iter().count(), 1);
+        assert_eq!(
+            table.next_hop(ShortAddress(0x796f)),
+            Some(ShortAddress(0x0001))
+        );
+    }
+
+    #[test]
+    fn full_routing_table_replaces_failed_route() {
+        let mut table = RoutingTable::new();
+        for destination in (0x0100u16..).take(MAX_ROUTES) {
+            table
+                .insert(route(destination, RouteStatus::Active))
+                .unwrap();
+        }
+        assert!(table.insert(route(0x796f, RouteStatus::Active)).is_err());
+
+        table.get_mut(ShortAddress(0x0105)).unwrap().status = RouteStatus::DiscoveryFailed;
+        table.insert(route(0x796f, RouteStatus::Active)).unwrap();
+
+        assert!(table.get(ShortAddress(0x0105)).is_none());
+        assert!(table.get(ShortAddress(0x796f)).is_some());
+    }
+
+    #[test]
+    fn expired_route_discoveries_are_removed() {
+        let mut table = RouteDiscoveryTable::new();
+        for (route_request_id, seconds) in [(1, 10), (2, 20)] {
+            table
+                .insert(RouteDiscovery {
+                    route_request_id,
+                    source: ShortAddress(0x0000),
+                    sender: ShortAddress(0x0000),
+                    forward_cost: 0,
+                    residual_cost: 0xff,
+                    expiration: Duration::from_secs(seconds),
+                    destination: ShortAddress(0x796f),
+                })
+                .unwrap();
+        }
+
+        let expired = table.expire(Duration::from_secs(10));
+
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].route_request_id, 1);
+        assert!(table.get(ShortAddress(0x0000), 1).is_none());
+        assert!(table.get(ShortAddress(0x0000), 2).is_some());
+    }
+
+    #[test]
+    fn full_source_route_table_replaces_oldest_route() {
+        let mut table = SourceRouteTable::new();
+        for destination in (0x0100u16..).take(MAX_SOURCE_ROUTES) {
+            table.insert(SourceRoute {
+                destination: ShortAddress(destination),
+                relay_list: Vec::new(),
+            });
+        }
+        table.insert(SourceRoute {
+            destination: ShortAddress(0x0100),
+            relay_list: Vec::from_slice(&[ShortAddress(0x0001)]).unwrap(),
+        });
+
+        table.insert(SourceRoute {
+            destination: ShortAddress(0x796f),
+            relay_list: Vec::new(),
+        });
+
+        assert_eq!(table.iter().count(), MAX_SOURCE_ROUTES);
+        assert!(table.get(ShortAddress(0x0101)).is_none());
+        assert!(table.get(ShortAddress(0x796f)).is_some());
+        assert_eq!(
+            table.get(ShortAddress(0x0100)).unwrap().relay_list,
+            [ShortAddress(0x0001)]
+        );
+    }
+}
@@ -0,0 +1,258 @@
+//! NWK Layer Security
+//!
+//! Frames are secured with the active network key at nwkSecurityLevel
+//! ENC-MIC-32. The security level is transmitted as 0 and restored by the
+//! receiver before the frame is authenticated.
+//!
+//! See Section 4.3.1.
+
+use heapless::Vec;
+
+use super::frame::NwkFrame;
+use super::frame::NwkHeader;
+use crate::common::parse::remaining;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::types::IeeeAddress;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::security::ccm;
+use crate::security::ccm::NONCE_SIZE;
+use crate::security::frame::AuxFrameHeader;
+use crate::security::frame::KeyIdentifier;
+use crate::security::frame::SecurityControl;
+use crate::security::frame::SecurityLevel;
+
+/// nwkSecurityLevel
+const SECURITY_LEVEL: SecurityLevel = SecurityLevel::EncMic32;
+
+/// Length of the MIC at [`SECURITY_LEVEL`].
+const MIC_LENGTH: usize = 4;
+
+/// A network key and its key sequence number.
+///
+/// See Section 4.3.1.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkKey {
+    pub(crate) key: [u8; 16],
+    pub(crate) sequence_number: u8,
+}
+
+/// Reason a secured frame is dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SecurityError {
+    /// The frame or its auxiliary header is malformed or unsupported.
+    InvalidFrame,
+    /// The frame is not secured with the active network key.
+    UnknownKey,
+    /// The MIC does not match.
+    Authentication,
+}
+
+/// Secures a frame consisting of `header`, which has the security sub-field
+/// set, and `payload`.
+///
+/// The auxiliary header carries the extended `source` address and the
+/// `frame_counter`.
+///
+/// See Section 4.3.1.1.
+pub(super) fn secure(
+    header: &NwkHeader,
+    payload: &[u8],
+    key: &NetworkKey,
+    frame_counter: u32,
+    source: IeeeAddress,
+) -> Result<Vec<u8, MAX_PHY_PACKET_SIZE>, PackError> {
+    let mut aux_header = AuxFrameHeader {
+        security_control: SecurityControl::new(SECURITY_LEVEL, KeyIdentifier::Network, true),
+        frame_counter,
+        source_address: Some(source),
+        key_sequence_numner: Some(key.sequence_number),
+    };
+    let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+    let header_length = header.pack_into(&mut buf)?;
+    let aad_length = header_length + aux_header.pack_into(remaining(&mut buf, header_length)?)?;
+    let length = aad_length + payload.len() + MIC_LENGTH;
+    if length > buf.len() {
+        return Err(PackError::BufferTooSmall);
+    }
+
+    let (aad, rest) = buf.split_at_mut(aad_length);
+    let (encrypted, mic) = rest.split_at_mut(payload.len());
+    encrypted.copy_from_slice(payload);
+    let nonce = nonce(source, frame_counter, aux_header.security_control);
+    ccm::encrypt(&key.key, &nonce, aad, encrypted, &mut mic[..MIC_LENGTH]);
+
+    aux_header
+        .security_control
+        .set_security_level(SecurityLevel::None);
+    buf[header_length] = aux_header.security_control.0;
+    Vec::from_slice(&buf[..length]).map_err(|()| PackError::BufferTooSmall)
+}
+
+/// Authenticates and decrypts a secured `frame`.
+///
+/// Returns the frame without security and its auxiliary header.
+///
+/// See Section 4.3.1.2.
+pub(super) fn unsecure(
+    frame: &[u8],
+    key: &NetworkKey,
+) -> Result<(NwkFrame, AuxFrameHeader), SecurityError> {
+    let mut header =
+        NwkHeader::unpack_from_slice(frame).map_err(|_| SecurityError::InvalidFrame)?;
+    let mut buf = [0u8; MAX_PHY_PACKET_SIZE];
+    let header_length = header
+        .pack_into(&mut buf)
+        .map_err(|_| SecurityError::InvalidFrame)?;
+    let mut aux_header = frame
+        .get(header_length..)
+        .and_then(|aux_header| AuxFrameHeader::unpack_from_slice(aux_header).ok())
+        .ok_or(SecurityError::InvalidFrame)?;
+    if aux_header.security_control.key_identifier() != KeyIdentifier::Network
+        || aux_header.key_sequence_numner != Some(key.sequence_number)
+    {
+        return Err(SecurityError::UnknownKey);
+    }
+    // the source is only known from the auxiliary header
+    let source = aux_header
+        .source_address
+        .ok_or(SecurityError::InvalidFrame)?;
+    aux_header
+        .security_control
+        .set_security_level(SECURITY_LEVEL);
+    let aad_length = header_length
+        + aux_header
+            .pack_into(remaining(&mut buf, header_length).map_err(|_| SecurityError::InvalidFrame)?)
+            .map_err(|_| SecurityError::InvalidFrame)?;
+    let mic_start = frame
+        .len()
+        .checked_sub(MIC_LENGTH)
+        .filter(|mic_start| *mic_start >= aad_length)
+        .ok_or(SecurityError::InvalidFrame)?;
+
+    let (aad, rest) = buf.split_at_mut(aad_length);
+    let payload = &mut rest[..mic_start - aad_length];
+    payload.copy_from_slice(&frame[aad_length..mic_start]);
+    let nonce = nonce(
+        source,
+        aux_header.frame_counter,
+        aux_header.security_control,
+    );
+    ccm::decrypt(&key.key, &nonce, aad, payload, &frame[mic_start..])
+        .map_err(|_| SecurityError::Authentication)?;
+
+    header.frame_control.set_security_flag(false);
+    let mut unsecured = [0u8; MAX_PHY_PACKET_SIZE];
+    let length = header
+        .pack_into(&mut unsecured)
+        .map_err(|_| SecurityError::InvalidFrame)?;
+    unsecured[length..length + payload.len()].copy_from_slice(payload);
+    let frame = NwkFrame::unpack_from_slice(&unsecured[..length + payload.len()])
+        .map_err(|_| SecurityError::InvalidFrame)?;
+    Ok((frame, aux_header))
+}
+
+/// CCM nonce of the frame sent by `source`.
+///
+/// See Section 4.5.2.2.
+fn nonce(
+    source: IeeeAddress,
+    frame_counter: u32,
+    security_control: SecurityControl,
+) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..8].copy_from_slice(&source.0.to_le_bytes());
+    nonce[8..12].copy_from_slice(&frame_counter.to_le_bytes());
+    nonce[12] = security_control.0;
+    nonce
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::types::ShortAddress;
+    use crate::mac::command::AssociationStatus;
+    use crate::nwk::commands::NwkCommand;
+    use crate::nwk::commands::RejoinResponse;
+    use crate::nwk::frame::FrameControl;
+    use crate::nwk::frame::FrameTypeIdentifier;
+
+    const KEY: NetworkKey = NetworkKey {
+        key: [
+            0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a,
+            0x0c, 0x0d,
+        ],
+        sequence_number: 0,
+    };
+    const SOURCE: IeeeAddress = IeeeAddress(0x0015_8d00_01ab_cd12);
+
+    fn header() -> NwkHeader {
+        let mut frame_control = FrameControl::new(FrameTypeIdentifier::NwkCommand);
+        frame_control.set_security_flag(true);
+        NwkHeader {
+            frame_control,
+            destination: ShortAddress(0x796f),
+            source: ShortAddress(0x0000),
+            radius: 1,
+            sequence_number: 0x42,
+            destination_ieee: None,
+            source_ieee: None,
+            multicast_control: None,
+            source_route_subframe: None,
+        }
+    }
+
+    // command identifier followed by a Rejoin Response
+    const PAYLOAD: [u8; 4] = [0x07, 0x6f, 0x79, 0x00];
+
+    #[test]
+    fn secured_frame_round_trip() {
+        let secured = secure(&header(), &PAYLOAD, &KEY, 7, SOURCE).unwrap();
+
+        // header, auxiliary header with level 0, payload and MIC
+        assert_eq!(secured.len(), 8 + 14 + PAYLOAD.len() + MIC_LENGTH);
+        assert_eq!(secured[8], 0x28);
+        assert_ne!(secured[22..26], PAYLOAD);
+
+        let (frame, aux_header) = unsecure(&secured, &KEY).unwrap();
+        assert_eq!(aux_header.frame_counter, 7);
+        assert_eq!(aux_header.source_address, Some(SOURCE));
+        let NwkFrame::NwkCommand(frame) = frame else {
+            unreachable!("expected a command frame");
+        };
+        assert!(!frame.header.frame_control.security_flag());
+        assert_eq!(
+            frame.command(),
+            Ok(NwkCommand::RejoinResponse(RejoinResponse {
+                network_address: ShortAddress(0x796f),
+                rejoin_status: AssociationStatus::Success,
+            }))
+        );
+    }
+
+    #[test]
+    fn tampered_frame_is_rejected() {
+        let mut secured = secure(&header(), &PAYLOAD, &KEY, 7, SOURCE).unwrap();
+        // the frame counter is authenticated
+        secured[9] ^= 0x01;
+
+        assert_eq!(
+            unsecure(&secured, &KEY).err(),
+            Some(SecurityError::Authentication)
+        );
+    }
+
+    #[test]
+    fn frame_secured_with_other_key_is_rejected() {
+        let secured = secure(&header(), &PAYLOAD, &KEY, 7, SOURCE).unwrap();
+        let other = NetworkKey {
+            sequence_number: 1,
+            ..KEY
+        };
+
+        assert_eq!(
+            unsecure(&secured, &other).err(),
+            Some(SecurityError::UnknownKey)
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Radio mock driving unit tests on the host.
+
+use core::convert::Infallible;
+use core::future::Future;
+use core::pin::pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::RawWaker;
+use core::task::RawWakerVTable;
+use core::task::Waker;
+use core::time::Duration;
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+use super::Radio;
+use super::RxFrame;
+use super::TxStatus;
+use super::MIN_CHANNEL;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+
+/// Radio without a medium, recording transmitted frames and replaying
+/// queued frames.
+#[derive(Debug, Default)]
+pub struct MockRadio {
+    pub(crate) channel: u8,
+    pub(crate) pan_id: u16,
+    pub(crate) short_address: Option<ShortAddress>,
+    pub(crate) extended_address: Option<IeeeAddress>,
+    /// Frames passed to [`Radio::transmit`].
+    pub(crate) transmitted: Vec<Vec<u8>>,
+    /// Frames returned by [`Radio::receive`].
+    pub(crate) received: VecDeque<RxFrame>,
+    /// Energy per channel returned by [`Radio::energy_detect`].
+    pub(crate) energy: [u8; 16],
+    /// Result of every transmission.
+    pub(crate) tx_status: Option<TxStatus>,
+    pub(crate) now: Duration,
+    /// Value returned by [`Radio::random`].
+    pub(crate) random: u32,
+}
+
+impl MockRadio {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Radio for MockRadio {
+    type Error = Infallible;
+
+    async fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error> {
+        self.channel = channel;
+        Ok(())
+    }
+
+    async fn set_pan_id(&mut self, pan_id: u16) -> Result<(), Self::Error> {
+        self.pan_id = pan_id;
+        Ok(())
+    }
+
+    async fn set_short_address(&mut self, address: ShortAddress) -> Result<(), Self::Error> {
+        self.short_address = Some(address);
+        Ok(())
+    }
+
+    async fn set_extended_address(&mut self, address: IeeeAddress) -> Result<(), Self::Error> {
+        self.extended_address = Some(address);
+        Ok(())
+    }
+
+    async fn transmit(&mut self, frame: &[u8], _cca: bool) -> Result<TxStatus, Self::Error> {
+        self.transmitted.push(frame.to_vec());
+        Ok(self.tx_status.unwrap_or(TxStatus::Sent))
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<RxFrame>, Self::Error> {
+        let frame = self.received.pop_front();
+        if frame.is_none() {
+            self.now += timeout;
+        }
+        Ok(frame)
+    }
+
+    async fn energy_detect(&mut self, duration: Duration) -> Result<u8, Self::Error> {
+        self.now += duration;
+        Ok(self
+            .energy
+            .get(usize::from(self.channel.saturating_sub(MIN_CHANNEL)))
+            .copied()
+            .unwrap_or_default())
+    }
+
+    async fn sleep(&mut self, duration: Duration) -> Result<(), Self::Error> {
+        self.now += duration;
+        Ok(())
+    }
+
+    fn now(&self) -> Duration {
+        self.now
+    }
+
+    fn random(&mut self) -> u32 {
+        self.random
+    }
+}
+
+/// Waker of [`block_on`], which polls until the future is ready anyway.
+const NOOP_WAKER: RawWakerVTable = RawWakerVTable::new(
+    |data| RawWaker::new(data, &NOOP_WAKER),
+    |_| {},
+    |_| {},
+    |_| {},
+);
+
+/// Runs a future which never waits on anything but the [`MockRadio`].
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    // SAFETY: the vtable ignores the data pointer
+    let waker = unsafe { Waker::from_raw(RawWaker::new(core::ptr::null(), &NOOP_WAKER)) };
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn receive_without_frames_advances_time() {
+        let mut radio = MockRadio::new();
+
+        let got = block_on(radio.receive(Duration::from_millis(5)));
+
+        assert_eq!(got, Ok(None));
+        assert_eq!(radio.now(), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn energy_detect_reports_energy_of_current_channel() {
+        let mut radio = MockRadio::new();
+        radio.energy[4] = 0x80;
+
+        block_on(radio.set_channel(15)).unwrap();
+        let got = block_on(radio.energy_detect(Duration::from_millis(1)));
+
+        assert_eq!(got, Ok(0x80));
+    }
+}
@@ -0,0 +1,137 @@
+//! Radio Driver Abstraction
+//!
+//! The stack is independent of the underlying IEEE 802.15.4 transceiver. A
+//! platform provides an implementation of [`Radio`](crate::radio::Radio)
+//! which is handed to [`crate::init`].
+//!
+//! Frames exchanged with the radio are MAC frames without the frame check
+//! sequence, which is generated and verified by the radio.
+use core::time::Duration;
+
+use heapless::Vec;
+
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+
+#[cfg(test)]
+pub mod mock;
+#[cfg(feature = "std")]
+pub mod sim;
+
+/// Lowest channel of the 2.4 GHz band.
+pub const MIN_CHANNEL: u8 = 11;
+
+/// Highest channel of the 2.4 GHz band.
+pub const MAX_CHANNEL: u8 = 26;
+
+/// Set of channels of channel page 0.
+///
+/// Bit `n` selects channel `n` like the `ScanChannels` parameter of the NLME
+/// primitives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChannelMask(pub u32);
+
+impl ChannelMask {
+    /// All channels of the 2.4 GHz band.
+    pub const ALL_2_4_GHZ: Self = Self(0x07ff_f800);
+
+    /// Mask selecting a single channel.
+    pub fn single(channel: u8) -> Self {
+        Self(1u32.checked_shl(u32::from(channel)).unwrap_or(0))
+    }
+
+    /// Returns if `channel` is selected.
+    pub fn contains(self, channel: u8) -> bool {
+        self.0.checked_shr(u32::from(channel)).unwrap_or(0) & 0b1 != 0
+    }
+
+    /// The selected channels of the 2.4 GHz band in ascending order.
+    pub fn channels(self) -> impl Iterator<Item = u8> {
+        (MIN_CHANNEL..=MAX_CHANNEL).filter(move |channel| self.contains(*channel))
+    }
+
+    /// Returns if no channel of the 2.4 GHz band is selected.
+    pub fn is_empty(self) -> bool {
+        self.0 & Self::ALL_2_4_GHZ.0 == 0
+    }
+}
+
+/// IEEE 802.15.4 transceiver.
+///
+/// Implementations filter received frames by the configured PAN ID and
+/// addresses and acknowledge them automatically if requested.
+#[allow(async_fn_in_trait)]
+pub trait Radio {
+    /// Error reported by the driver.
+    type Error: core::fmt::Debug;
+
+    /// Switches to a channel in the range of [`MIN_CHANNEL`] to
+    /// [`MAX_CHANNEL`].
+    async fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error>;
+
+    /// Sets the PAN ID used for frame filtering.
+    async fn set_pan_id(&mut self, pan_id: u16) -> Result<(), Self::Error>;
+
+    /// Sets the short address used for frame filtering.
+    async fn set_short_address(&mut self, address: ShortAddress) -> Result<(), Self::Error>;
+
+    /// Sets the extended address used for frame filtering.
+    async fn set_extended_address(&mut self, address: IeeeAddress) -> Result<(), Self::Error>;
+
+    /// Transmits a frame.
+    ///
+    /// If `cca` is set the channel is accessed using CSMA-CA. If the frame
+    /// requests an acknowledgment, the radio waits for it.
+    async fn transmit(&mut self, frame: &[u8], cca: bool) -> Result<TxStatus, Self::Error>;
+
+    /// Waits up to `timeout` for the next frame.
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<RxFrame>, Self::Error>;
+
+    /// Measures the peak energy on the current channel for `duration`.
+    ///
+    /// Returns the energy level in the range of 0 to 255.
+    async fn energy_detect(&mut self, duration: Duration) -> Result<u8, Self::Error>;
+
+    /// Turns the receiver off for `duration`.
+    async fn sleep(&mut self, duration: Duration) -> Result<(), Self::Error>;
+
+    /// Time elapsed since an arbitrary, fixed point in time.
+    fn now(&self) -> Duration;
+
+    /// Random number, e.g. drawn from the noise of the receiver.
+    fn random(&mut self) -> u32;
+}
+
+/// Result of a transmission.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TxStatus {
+    /// The frame was sent without requesting an acknowledgment.
+    Sent,
+    /// The frame was acknowledged.
+    Acked {
+        /// The frame pending bit of the acknowledgment.
+        frame_pending: bool,
+    },
+    /// No acknowledgment was received.
+    NoAck,
+    /// The channel was busy.
+    ChannelAccessFailure,
+    /// The frame exceeds the maximum PSDU size.
+    FrameTooLong,
+    /// The frame was not secured, the outgoing frame counter is exhausted.
+    CounterError,
+}
+
+/// A frame received by the radio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RxFrame {
+    /// The MAC frame without its frame check sequence.
+    pub frame: Vec<u8, MAX_PHY_PACKET_SIZE>,
+    /// Link quality indication.
+    pub lqi: u8,
+    /// Received signal strength in dBm.
+    pub rssi: i8,
+    /// Time of reception, see [`Radio::now`].
+    pub timestamp: Duration,
+}
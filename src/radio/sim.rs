@@ -0,0 +1,665 @@
+//! Simulated Radio Medium
+//!
+//! A [`Medium`] connects any number of virtual nodes, each driven by a
+//! [`SimRadio`]. Time is virtual and advances only while all nodes are
+//! waiting, so a simulation with the same seed always produces the same
+//! result.
+//!
+//! ```
+//! use core::time::Duration;
+//!
+//! use zigbee::radio::sim::Link;
+//! use zigbee::radio::sim::Medium;
+//! use zigbee::radio::Radio;
+//!
+//! let medium = Medium::new(42);
+//! let mut a = medium.add_node();
+//! let mut b = medium.add_node();
+//! medium.connect(a.id(), b.id(), Link::default());
+//!
+//! let mut executor = medium.executor();
+//! executor.spawn(async move {
+//!     // broadcast data frame
+//!     let frame = [0x01, 0x08, 0x00, 0xff, 0xff, 0xff, 0xff, 0x2a];
+//!     let _ = a.transmit(&frame, false).await;
+//! });
+//! let received = executor.block_on(Duration::from_millis(10), async {
+//!     b.receive(Duration::from_millis(5)).await
+//! });
+//! assert!(matches!(received, Some(Ok(Some(_)))));
+//! ```
+//!
+//! Frames are filtered by PAN ID and destination address like a real
+//! transceiver does, unicast frames requesting an acknowledgment are
+//! acknowledged by the receiver. Like most transceivers do by default, the
+//! acknowledgment of a data request has the frame pending bit set. Collisions
+//! are not modelled, with CCA a transmission is deferred until the channel is
+//! idle.
+use core::cell::RefCell;
+use core::convert::TryFrom;
+use core::future::Future;
+use core::pin::pin;
+use core::pin::Pin;
+use core::task::Context;
+use core::task::Poll;
+use core::task::Waker;
+use core::time::Duration;
+use std::boxed::Box;
+use std::collections::BTreeMap;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Wake;
+use std::vec::Vec;
+
+use thiserror::Error;
+
+use super::Radio;
+use super::RxFrame;
+use super::TxStatus;
+use super::MAX_CHANNEL;
+use super::MIN_CHANNEL;
+use crate::common::parse::PackBytes;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::command::MacCommand;
+use crate::mac::frame::Address;
+use crate::mac::frame::FrameType;
+use crate::mac::frame::MacCommandFrame;
+use crate::mac::frame::MacFrame;
+use crate::mac::frame::MacHeader;
+use crate::mac::frame::BROADCAST;
+use crate::mac::frame::FCS_SIZE;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+
+/// Duration of a single byte at 250 kbit/s.
+const BYTE_DURATION: Duration = Duration::from_micros(32);
+
+/// Preamble, start of frame delimiter and PHY header in bytes.
+const PHY_OVERHEAD: usize = 6;
+
+/// Size of an acknowledgment frame including its frame check sequence.
+const ACK_SIZE: usize = 5;
+
+/// aTurnaroundTime - 12 symbols of 16 µs.
+const TURNAROUND_TIME: Duration = Duration::from_micros(192);
+
+/// Identifier of a node attached to a [`Medium`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NodeId(pub usize);
+
+/// Properties of the link from one node to another.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Link {
+    /// Probability in the range of 0.0 to 1.0 that a frame is lost.
+    pub loss: f64,
+    /// Signal strength reported to the receiver in dBm.
+    pub rssi: i8,
+    /// Link quality reported to the receiver.
+    pub lqi: u8,
+    /// Propagation delay added to the airtime of a frame.
+    pub delay: Duration,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self {
+            loss: 0.0,
+            rssi: -50,
+            lqi: 255,
+            delay: Duration::ZERO,
+        }
+    }
+}
+
+/// Error reported by a [`SimRadio`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum SimError {
+    /// The channel is not in the 2.4 GHz band.
+    #[error("invalid channel {0}")]
+    InvalidChannel(u8),
+}
+
+#[derive(Debug)]
+struct Node {
+    channel: u8,
+    pan_id: u16,
+    short_address: ShortAddress,
+    extended_address: Option<IeeeAddress>,
+    sleeping_until: Duration,
+    rx_queue: VecDeque<RxFrame>,
+    rx_waker: Option<Waker>,
+}
+
+impl Node {
+    fn new() -> Self {
+        Self {
+            channel: MIN_CHANNEL,
+            pan_id: BROADCAST,
+            short_address: ShortAddress(BROADCAST),
+            extended_address: None,
+            sleeping_until: Duration::ZERO,
+            rx_queue: VecDeque::new(),
+            rx_waker: None,
+        }
+    }
+
+    /// Frame filtering, see IEEE 802.15.4-2015 Section 6.7.2.
+    fn accepts(&self, header: &MacHeader) -> bool {
+        if header.frame_control.frame_type() == FrameType::Beacon {
+            return self.pan_id == BROADCAST || header.source_pan_id() == Some(self.pan_id);
+        }
+        let pan_id_matches = |pan_id| pan_id == BROADCAST || pan_id == self.pan_id;
+        header.destination.map_or_else(
+            || header.source_pan_id() == Some(self.pan_id),
+            |destination| {
+                header.destination_pan_id.map_or(true, pan_id_matches)
+                    && match destination {
+                        Address::Short(address) => {
+                            address.0 == BROADCAST || address == self.short_address
+                        }
+                        Address::Extended(address) => Some(address) == self.extended_address,
+                    }
+            },
+        )
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    now: Duration,
+    rng: u64,
+    nodes: Vec<Node>,
+    links: BTreeMap<(NodeId, NodeId), Option<Link>>,
+    default_link: Option<Link>,
+    channel_busy_until: [Duration; 16],
+    channel_energy: [u8; 16],
+    timers: Vec<(Duration, Waker)>,
+}
+
+impl State {
+    /// Next value of the splitmix64 generator.
+    fn next_u64(&mut self) -> u64 {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sample_loss(&mut self, link: &Link) -> bool {
+        let sample = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        sample < link.loss
+    }
+
+    fn link(&self, from: NodeId, to: NodeId) -> Option<Link> {
+        self.links
+            .get(&(from, to))
+            .copied()
+            .unwrap_or(self.default_link)
+    }
+
+    fn add_timer(&mut self, at: Duration, waker: &Waker) {
+        self.timers.push((at, waker.clone()));
+    }
+
+    /// Sends a frame from `from` to all nodes in range accepting it.
+    ///
+    /// Returns if the frame was acknowledged or `None` if no acknowledgment
+    /// was requested.
+    fn deliver(&mut self, from: NodeId, frame: &[u8], end: Duration) -> Option<bool> {
+        let header = MacHeader::unpack_from_slice(frame).ok()?;
+        let ack_request = header.frame_control.ack_request()
+            && header.destination.is_some()
+            && header.destination != Some(Address::BROADCAST);
+        let channel = self.nodes[from.0].channel;
+        let mut acked = false;
+        for to in (0..self.nodes.len()).map(NodeId) {
+            if to == from || self.nodes[to.0].channel != channel {
+                continue;
+            }
+            let Some(link) = self.link(from, to) else {
+                continue;
+            };
+            let arrival = end + link.delay;
+            if self.nodes[to.0].sleeping_until > arrival.saturating_sub(airtime(frame.len())) {
+                continue;
+            }
+            if self.sample_loss(&link) {
+                continue;
+            }
+            if !self.nodes[to.0].accepts(&header) {
+                continue;
+            }
+            if ack_request {
+                acked |= self
+                    .link(to, from)
+                    .is_some_and(|link| !self.sample_loss(&link));
+            }
+            let node = &mut self.nodes[to.0];
+            node.rx_queue.push_back(RxFrame {
+                frame: heapless::Vec::from_slice(frame).unwrap_or_default(),
+                lqi: link.lqi,
+                rssi: link.rssi,
+                timestamp: arrival,
+            });
+            if let Some(waker) = node.rx_waker.clone() {
+                self.add_timer(arrival, &waker);
+            }
+        }
+        ack_request.then_some(acked)
+    }
+}
+
+/// Radio medium shared by simulated nodes.
+#[derive(Debug, Clone)]
+pub struct Medium {
+    state: Rc<RefCell<State>>,
+}
+
+impl Medium {
+    /// Creates a medium whose randomness is derived from `seed`.
+    ///
+    /// All nodes are connected by a [`Link::default`] unless configured
+    /// otherwise.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(State {
+                now: Duration::ZERO,
+                rng: seed,
+                nodes: Vec::new(),
+                links: BTreeMap::new(),
+                default_link: Some(Link::default()),
+                channel_busy_until: [Duration::ZERO; 16],
+                channel_energy: [0; 16],
+                timers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Attaches a new node to the medium.
+    pub fn add_node(&self) -> SimRadio {
+        let mut state = self.state.borrow_mut();
+        state.nodes.push(Node::new());
+        SimRadio {
+            id: NodeId(state.nodes.len() - 1),
+            state: Rc::clone(&self.state),
+        }
+    }
+
+    /// Sets the link used between nodes which are not configured explicitly,
+    /// `None` leaves them out of range.
+    pub fn set_default_link(&self, link: Option<Link>) {
+        self.state.borrow_mut().default_link = link;
+    }
+
+    /// Sets the link from one node to another.
+    pub fn set_link(&self, from: NodeId, to: NodeId, link: Option<Link>) {
+        self.state.borrow_mut().links.insert((from, to), link);
+    }
+
+    /// Connects two nodes in both directions.
+    pub fn connect(&self, a: NodeId, b: NodeId, link: Link) {
+        self.set_link(a, b, Some(link));
+        self.set_link(b, a, Some(link));
+    }
+
+    /// Moves two nodes out of range of each other.
+    pub fn disconnect(&self, a: NodeId, b: NodeId) {
+        self.set_link(a, b, None);
+        self.set_link(b, a, None);
+    }
+
+    /// Sets the energy measured on a channel by an energy detection.
+    pub fn set_channel_energy(&self, channel: u8, energy: u8) {
+        if let Some(index) = channel_index(channel) {
+            self.state.borrow_mut().channel_energy[index] = energy;
+        }
+    }
+
+    /// Current virtual time.
+    pub fn now(&self) -> Duration {
+        self.state.borrow().now
+    }
+
+    /// Creates an executor running futures in virtual time.
+    pub fn executor<'a>(&self) -> Executor<'a> {
+        Executor {
+            medium: self.clone(),
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Advances the time to the earliest timer, waking all timers due.
+    fn advance(&self, limit: Duration) -> bool {
+        let mut state = self.state.borrow_mut();
+        let Some(next) = state.timers.iter().map(|(at, _)| *at).min() else {
+            state.now = state.now.max(limit);
+            return false;
+        };
+        if next > limit {
+            state.now = state.now.max(limit);
+            return false;
+        }
+        state.now = state.now.max(next);
+        let now = state.now;
+        let (due, pending) = state.timers.drain(..).partition(|(at, _)| *at <= now);
+        state.timers = pending;
+        drop(state);
+        for (_, waker) in due {
+            waker.wake();
+        }
+        true
+    }
+}
+
+/// Duration of a frame of `len` bytes on air.
+fn airtime(len: usize) -> Duration {
+    let bytes = u32::try_from(PHY_OVERHEAD + len + FCS_SIZE).unwrap_or(u32::MAX);
+    BYTE_DURATION * bytes
+}
+
+fn channel_index(channel: u8) -> Option<usize> {
+    (MIN_CHANNEL..=MAX_CHANNEL)
+        .contains(&channel)
+        .then(|| usize::from(channel - MIN_CHANNEL))
+}
+
+/// Radio of a simulated node.
+#[derive(Debug)]
+pub struct SimRadio {
+    id: NodeId,
+    state: Rc<RefCell<State>>,
+}
+
+impl SimRadio {
+    /// Identifier of the node within its [`Medium`].
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Waits until the virtual time reaches `at`.
+    async fn wait_until(&self, at: Duration) {
+        core::future::poll_fn(|cx| {
+            let mut state = self.state.borrow_mut();
+            if state.now >= at {
+                Poll::Ready(())
+            } else {
+                state.add_timer(at, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+
+    fn with_node<T>(&self, f: impl FnOnce(&mut Node) -> T) -> T {
+        f(&mut self.state.borrow_mut().nodes[self.id.0])
+    }
+}
+
+impl Radio for SimRadio {
+    type Error = SimError;
+
+    async fn set_channel(&mut self, channel: u8) -> Result<(), Self::Error> {
+        channel_index(channel).ok_or(SimError::InvalidChannel(channel))?;
+        self.with_node(|node| node.channel = channel);
+        Ok(())
+    }
+
+    async fn set_pan_id(&mut self, pan_id: u16) -> Result<(), Self::Error> {
+        self.with_node(|node| node.pan_id = pan_id);
+        Ok(())
+    }
+
+    async fn set_short_address(&mut self, address: ShortAddress) -> Result<(), Self::Error> {
+        self.with_node(|node| node.short_address = address);
+        Ok(())
+    }
+
+    async fn set_extended_address(&mut self, address: IeeeAddress) -> Result<(), Self::Error> {
+        self.with_node(|node| node.extended_address = Some(address));
+        Ok(())
+    }
+
+    async fn transmit(&mut self, frame: &[u8], cca: bool) -> Result<TxStatus, Self::Error> {
+        if frame.len() + FCS_SIZE > MAX_PHY_PACKET_SIZE {
+            return Ok(TxStatus::FrameTooLong);
+        }
+        let channel = self.with_node(|node| node.channel);
+        let index = usize::from(channel - MIN_CHANNEL);
+        if cca {
+            let busy_until = self.state.borrow().channel_busy_until[index];
+            self.wait_until(busy_until).await;
+        }
+
+        let (end, acked) = {
+            let mut state = self.state.borrow_mut();
+            let end = state.now + airtime(frame.len());
+            state.channel_busy_until[index] = end;
+            (end, state.deliver(self.id, frame, end))
+        };
+        self.wait_until(end).await;
+
+        match acked {
+            None => Ok(TxStatus::Sent),
+            Some(acked) => {
+                let ack_wait = TURNAROUND_TIME + airtime(ACK_SIZE - FCS_SIZE);
+                self.wait_until(end + ack_wait).await;
+                let data_request = matches!(
+                    MacFrame::unpack_from_slice(frame),
+                    Ok(MacFrame::Command(MacCommandFrame {
+                        command: MacCommand::DataRequest,
+                        ..
+                    }))
+                );
+                Ok(if acked {
+                    TxStatus::Acked {
+                        frame_pending: data_request,
+                    }
+                } else {
+                    TxStatus::NoAck
+                })
+            }
+        }
+    }
+
+    async fn receive(&mut self, timeout: Duration) -> Result<Option<RxFrame>, Self::Error> {
+        let deadline = self.now() + timeout;
+        let frame = core::future::poll_fn(|cx| {
+            let mut state = self.state.borrow_mut();
+            let now = state.now;
+            let node = &mut state.nodes[self.id.0];
+            if let Some(index) = node.rx_queue.iter().position(|rx| rx.timestamp <= now) {
+                node.rx_waker = None;
+                return Poll::Ready(node.rx_queue.remove(index));
+            }
+            if now >= deadline {
+                node.rx_waker = None;
+                return Poll::Ready(None);
+            }
+            let next = node
+                .rx_queue
+                .iter()
+                .map(|rx| rx.timestamp)
+                .min()
+                .map_or(deadline, |timestamp| timestamp.min(deadline));
+            node.rx_waker = Some(cx.waker().clone());
+            state.add_timer(next, cx.waker());
+            Poll::Pending
+        })
+        .await;
+        Ok(frame)
+    }
+
+    async fn energy_detect(&mut self, duration: Duration) -> Result<u8, Self::Error> {
+        let end = self.now() + duration;
+        self.wait_until(end).await;
+        let state = self.state.borrow();
+        let channel = state.nodes[self.id.0].channel;
+        Ok(state.channel_energy[usize::from(channel - MIN_CHANNEL)])
+    }
+
+    async fn sleep(&mut self, duration: Duration) -> Result<(), Self::Error> {
+        let end = self.now() + duration;
+        self.with_node(|node| {
+            node.sleeping_until = end;
+            node.rx_queue.clear();
+        });
+        self.wait_until(end).await;
+        Ok(())
+    }
+
+    fn now(&self) -> Duration {
+        self.state.borrow().now
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn random(&mut self) -> u32 {
+        (self.state.borrow_mut().next_u64() >> 32) as u32
+    }
+}
+
+/// Wake flag of a task.
+#[derive(Debug, Default)]
+struct Flag(AtomicBool);
+
+impl Flag {
+    fn take(&self) -> bool {
+        self.0.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Wake for Flag {
+    fn wake(self: Arc<Self>) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+struct Task<'a> {
+    future: Pin<Box<dyn Future<Output = ()> + 'a>>,
+    flag: Arc<Flag>,
+}
+
+/// Single-threaded executor running the nodes of a [`Medium`] in virtual
+/// time.
+///
+/// Spawned tasks keep running across calls to [`Executor::block_on`] and
+/// [`Executor::run_for`] until they complete or the executor is dropped.
+pub struct Executor<'a> {
+    medium: Medium,
+    tasks: Vec<Task<'a>>,
+}
+
+impl core::fmt::Debug for Executor<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Executor")
+            .field("medium", &self.medium)
+            .field("tasks", &self.tasks.len())
+            .finish()
+    }
+}
+
+impl<'a> Executor<'a> {
+    /// Runs `future` in the background, e.g. the main loop of a node.
+    pub fn spawn(&mut self, future: impl Future<Output = ()> + 'a) {
+        let flag = Arc::new(Flag::default());
+        flag.0.store(true, Ordering::Relaxed);
+        self.tasks.push(Task {
+            future: Box::pin(future),
+            flag,
+        });
+    }
+
+    /// Runs `future` together with the spawned tasks until it completes.
+    ///
+    /// Returns `None` if it did not complete within `timeout` of virtual
+    /// time.
+    pub fn block_on<T>(&mut self, timeout: Duration, future: impl Future<Output = T>) -> Option<T> {
+        let deadline = self.medium.now() + timeout;
+        let mut future = pin!(future);
+        let flag = Arc::new(Flag(AtomicBool::new(true)));
+        let waker = Waker::from(Arc::clone(&flag));
+        loop {
+            loop {
+                let mut progress = self.poll_tasks();
+                if flag.take() {
+                    progress = true;
+                    if let Poll::Ready(output) =
+                        future.as_mut().poll(&mut Context::from_waker(&waker))
+                    {
+                        return Some(output);
+                    }
+                }
+                if !progress {
+                    break;
+                }
+            }
+            if !self.medium.advance(deadline) {
+                return None;
+            }
+        }
+    }
+
+    /// Runs the spawned tasks for `duration` of virtual time.
+    pub fn run_for(&mut self, duration: Duration) {
+        let _ = self.block_on(duration, core::future::pending::<()>());
+    }
+
+    /// Polls all woken tasks once and returns if any was polled.
+    fn poll_tasks(&mut self) -> bool {
+        let mut progress = false;
+        self.tasks.retain_mut(|task| {
+            if !task.flag.take() {
+                return true;
+            }
+            progress = true;
+            let waker = Waker::from(Arc::clone(&task.flag));
+            task.future
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_pending()
+        });
+        progress
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn airtime_of_maximum_frame() {
+        let got = airtime(MAX_PHY_PACKET_SIZE - FCS_SIZE);
+
+        assert_eq!(got, Duration::from_micros(4256));
+    }
+
+    #[test]
+    fn rng_is_deterministic() {
+        let a = Medium::new(7);
+        let b = Medium::new(7);
+
+        let got_a: Vec<u64> = (0..4).map(|_| a.state.borrow_mut().next_u64()).collect();
+        let got_b: Vec<u64> = (0..4).map(|_| b.state.borrow_mut().next_u64()).collect();
+
+        assert_eq!(got_a, got_b);
+        assert_ne!(got_a[0], got_a[1]);
+    }
+
+    #[test]
+    fn run_for_advances_time() {
+        let medium = Medium::new(0);
+        let mut radio = medium.add_node();
+        let mut executor = medium.executor();
+        executor.spawn(async move {
+            let _ = radio.sleep(Duration::from_millis(3)).await;
+        });
+
+        executor.run_for(Duration::from_millis(10));
+
+        assert_eq!(medium.now(), Duration::from_millis(10));
+    }
+}
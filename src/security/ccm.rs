@@ -0,0 +1,201 @@
+//! CCM* Mode of Operation
+//!
+//! CCM* with a length field of 2 octets as used by the NWK and APS layer.
+//! A MIC length of 0 only encrypts the payload.
+//!
+//! See Annex A and Section 4.3.1.1.
+
+use super::aes::Aes128;
+use super::aes::BLOCK_SIZE;
+
+/// Length of the nonce in bytes.
+pub const NONCE_SIZE: usize = 13;
+
+/// Largest supported message integrity code in bytes.
+pub(super) const MAX_MIC_SIZE: usize = 16;
+
+/// Size of the length field, 15 - [`NONCE_SIZE`].
+const LENGTH_SIZE: u8 = 2;
+
+/// Error returned if a frame can not be authenticated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticationError;
+
+/// Encrypts `payload` in place and writes the MIC authenticating `aad` and
+/// `payload` to `mic`.
+///
+/// The length of `mic` is one of 0, 4, 8 or 16 bytes.
+pub fn encrypt(
+    key: &[u8; BLOCK_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    payload: &mut [u8],
+    mic: &mut [u8],
+) {
+    let cipher = Aes128::new(key);
+    let tag = authenticate(&cipher, nonce, aad, payload, mic.len());
+    transform(&cipher, nonce, payload, mic, &tag);
+}
+
+/// Decrypts `payload` in place and verifies its `mic`.
+///
+/// The payload is left decrypted even if the verification fails.
+pub fn decrypt(
+    key: &[u8; BLOCK_SIZE],
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    payload: &mut [u8],
+    mic: &[u8],
+) -> Result<(), AuthenticationError> {
+    let cipher = Aes128::new(key);
+    let mut encrypted = [0u8; BLOCK_SIZE];
+    encrypted[..mic.len()].copy_from_slice(mic);
+    let mut received = [0u8; MAX_MIC_SIZE];
+    let received = &mut received[..mic.len()];
+    transform(&cipher, nonce, payload, received, &encrypted);
+    let tag = authenticate(&cipher, nonce, aad, payload, mic.len());
+    // compare all bytes to not leak the position of a mismatch
+    let difference = received
+        .iter()
+        .zip(&tag)
+        .fold(0u8, |difference, (a, b)| difference | (a ^ b));
+    if difference == 0 {
+        Ok(())
+    } else {
+        Err(AuthenticationError)
+    }
+}
+
+/// Computes the unencrypted authentication tag T.
+///
+/// See Section A.2.2.
+fn authenticate(
+    cipher: &Aes128,
+    nonce: &[u8; NONCE_SIZE],
+    aad: &[u8],
+    payload: &[u8],
+    mic_length: usize,
+) -> [u8; BLOCK_SIZE] {
+    let mut tag = [0u8; BLOCK_SIZE];
+    if mic_length == 0 {
+        return tag;
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let encoded_mic_length = ((mic_length - 2) / 2) as u8;
+    let mut flags = (encoded_mic_length << 3) | (LENGTH_SIZE - 1);
+    if !aad.is_empty() {
+        flags |= 1 << 6;
+    }
+    tag[0] = flags;
+    tag[1..=NONCE_SIZE].copy_from_slice(nonce);
+    #[allow(clippy::cast_possible_truncation)]
+    tag[NONCE_SIZE + 1..].copy_from_slice(&(payload.len() as u16).to_be_bytes());
+    cipher.encrypt_block(&mut tag);
+
+    if !aad.is_empty() {
+        // the length of frames is far below 0xff00
+        #[allow(clippy::cast_possible_truncation)]
+        let length = (aad.len() as u16).to_be_bytes();
+        cbc_mac(cipher, &mut tag, length.iter().chain(aad).copied());
+    }
+    cbc_mac(cipher, &mut tag, payload.iter().copied());
+    tag
+}
+
+/// Chains the bytes of `data`, zero padded to the block size, into `tag`.
+fn cbc_mac(cipher: &Aes128, tag: &mut [u8; BLOCK_SIZE], data: impl Iterator<Item = u8>) {
+    let mut position = 0;
+    for byte in data {
+        tag[position] ^= byte;
+        position += 1;
+        if position == BLOCK_SIZE {
+            cipher.encrypt_block(tag);
+            position = 0;
+        }
+    }
+    if position != 0 {
+        cipher.encrypt_block(tag);
+    }
+}
+
+/// Applies the key stream to `payload` and `tag` to `mic`.
+///
+/// See Section A.2.3.
+fn transform(
+    cipher: &Aes128,
+    nonce: &[u8; NONCE_SIZE],
+    payload: &mut [u8],
+    mic: &mut [u8],
+    tag: &[u8; BLOCK_SIZE],
+) {
+    let key_stream = |counter: u16| {
+        let mut block = [0u8; BLOCK_SIZE];
+        block[0] = LENGTH_SIZE - 1;
+        block[1..=NONCE_SIZE].copy_from_slice(nonce);
+        block[NONCE_SIZE + 1..].copy_from_slice(&counter.to_be_bytes());
+        cipher.encrypt_block(&mut block);
+        block
+    };
+    for (block, counter) in payload.chunks_mut(BLOCK_SIZE).zip(1u16..) {
+        for (byte, key) in block.iter_mut().zip(key_stream(counter)) {
+            *byte ^= key;
+        }
+    }
+    for ((byte, key), tag) in mic.iter_mut().zip(key_stream(0)).zip(tag) {
+        *byte = tag ^ key;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 3610, Packet Vector #1
+    const KEY: [u8; BLOCK_SIZE] = [
+        0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xcb, 0xcc, 0xcd, 0xce,
+        0xcf,
+    ];
+    const NONCE: [u8; NONCE_SIZE] = [
+        0x00, 0x00, 0x00, 0x03, 0x02, 0x01, 0x00, 0xa0, 0xa1, 0xa2, 0xa3, 0xa4, 0xa5,
+    ];
+    const AAD: [u8; 8] = [0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+    const CIPHERTEXT: [u8; 23] = [
+        0x58, 0x8c, 0x97, 0x9a, 0x61, 0xc6, 0x63, 0xd2, 0xf0, 0x66, 0xd0, 0xc2, 0xc0, 0xf9, 0x89,
+        0x80, 0x6d, 0x5f, 0x6b, 0x61, 0xda, 0xc3, 0x84,
+    ];
+    const MIC: [u8; 8] = [0x17, 0xe8, 0xd1, 0x2c, 0xfd, 0xf9, 0x26, 0xe0];
+
+    fn plaintext() -> [u8; 23] {
+        let mut plaintext = [0u8; 23];
+        for (byte, value) in plaintext.iter_mut().zip(0x08..) {
+            *byte = value;
+        }
+        plaintext
+    }
+
+    #[test]
+    fn encrypt_rfc_3610_packet_vector() {
+        let mut payload = plaintext();
+        let mut mic = [0u8; 8];
+
+        encrypt(&KEY, &NONCE, &AAD, &mut payload, &mut mic);
+
+        assert_eq!(payload, CIPHERTEXT);
+        assert_eq!(mic, MIC);
+    }
+
+    #[test]
+    fn decrypt_verifies_the_mic() {
+        let mut payload = CIPHERTEXT;
+        assert_eq!(decrypt(&KEY, &NONCE, &AAD, &mut payload, &MIC), Ok(()));
+        assert_eq!(payload, plaintext());
+
+        let mut payload = CIPHERTEXT;
+        let mut aad = AAD;
+        aad[0] ^= 0x01;
+        assert_eq!(
+            decrypt(&KEY, &NONCE, &aad, &mut payload, &MIC),
+            Err(AuthenticationError)
+        );
+    }
+}
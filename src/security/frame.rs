@@ -1,10 +1,16 @@
 //! Security Frame Formats
+use crate::common::parse::remaining;
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
 use crate::impl_pack_bytes;
 use crate::types::IeeeAddress;
 
 /// Auxiliary Frame Header Format
 ///
 /// See Section 4.5.1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AuxFrameHeader {
     /// Security control
     pub security_control: SecurityControl,
@@ -16,10 +22,178 @@ pub struct AuxFrameHeader {
     pub key_sequence_numner: Option<u8>,
 }
 
+impl PackBytes for AuxFrameHeader {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        let mut src = Input::new(src);
+        let security_control: SecurityControl = src.unpack()?;
+        let frame_counter = src.unpack()?;
+        let source_address = if security_control.extended_nonce() {
+            Some(src.unpack()?)
+        } else {
+            None
+        };
+        let key_sequence_numner = if security_control.key_identifier() == KeyIdentifier::Network {
+            Some(src.byte()?)
+        } else {
+            None
+        };
+        Ok(Self {
+            security_control,
+            frame_counter,
+            source_address,
+            key_sequence_numner,
+        })
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        let mut offset = self.security_control.pack_into(buf)?;
+        offset += self.frame_counter.pack_into(remaining(buf, offset)?)?;
+        if let Some(source_address) = &self.source_address {
+            offset += source_address.pack_into(remaining(buf, offset)?)?;
+        }
+        if let Some(key_sequence_numner) = self.key_sequence_numner {
+            offset += key_sequence_numner.pack_into(remaining(buf, offset)?)?;
+        }
+        Ok(offset)
+    }
+}
+
 impl_pack_bytes! {
     /// Security Control
     ///
     /// See Section 4.5.1.1.
-    #[derive(Clone, Copy)]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct SecurityControl(pub u8);
 }
+
+impl SecurityControl {
+    /// Creates a security control field.
+    pub fn new(
+        security_level: SecurityLevel,
+        key_identifier: KeyIdentifier,
+        extended_nonce: bool,
+    ) -> Self {
+        Self(security_level as u8 | (key_identifier as u8) << 3 | u8::from(extended_nonce) << 5)
+    }
+
+    /// See Section 4.5.1.1.1.
+    pub fn security_level(&self) -> SecurityLevel {
+        SecurityLevel::from_u8(self.0 & 0b111)
+    }
+
+    /// Replaces the security level, which is transmitted as 0 and restored
+    /// by the receiver.
+    pub fn set_security_level(&mut self, security_level: SecurityLevel) {
+        self.0 = (self.0 & !0b111) | security_level as u8;
+    }
+
+    /// See Section 4.5.1.1.2.
+    pub fn key_identifier(&self) -> KeyIdentifier {
+        KeyIdentifier::from_u8((self.0 >> 3) & 0b11)
+    }
+
+    /// Set if the auxiliary header carries the source address.
+    ///
+    /// See Section 4.5.1.1.3.
+    pub fn extended_nonce(&self) -> bool {
+        (self.0 >> 5) & 0b1 != 0
+    }
+}
+
+/// Security Levels
+///
+/// See Table 4-38.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum SecurityLevel {
+    None = 0x00,
+    Mic32 = 0x01,
+    Mic64 = 0x02,
+    Mic128 = 0x03,
+    Enc = 0x04,
+    EncMic32 = 0x05,
+    EncMic64 = 0x06,
+    EncMic128 = 0x07,
+}
+
+impl SecurityLevel {
+    fn from_u8(b: u8) -> Self {
+        match b {
+            0x01 => Self::Mic32,
+            0x02 => Self::Mic64,
+            0x03 => Self::Mic128,
+            0x04 => Self::Enc,
+            0x05 => Self::EncMic32,
+            0x06 => Self::EncMic64,
+            0x07 => Self::EncMic128,
+            _ => Self::None,
+        }
+    }
+
+    /// Length of the message integrity code in bytes.
+    pub fn mic_length(self) -> usize {
+        match self {
+            Self::None | Self::Enc => 0,
+            Self::Mic32 | Self::EncMic32 => 4,
+            Self::Mic64 | Self::EncMic64 => 8,
+            Self::Mic128 | Self::EncMic128 => 16,
+        }
+    }
+
+    /// Returns if the payload is encrypted.
+    pub fn encrypted(self) -> bool {
+        self as u8 & 0b100 != 0
+    }
+}
+
+/// Key Identifier
+///
+/// See Table 4-39.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+pub enum KeyIdentifier {
+    Data = 0x00,
+    Network = 0x01,
+    KeyTransport = 0x02,
+    KeyLoad = 0x03,
+}
+
+impl KeyIdentifier {
+    fn from_u8(b: u8) -> Self {
+        match b {
+            0x00 => Self::Data,
+            0x01 => Self::Network,
+            0x02 => Self::KeyTransport,
+            _ => Self::KeyLoad,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_network_aux_frame_header() {
+        let header = AuxFrameHeader {
+            security_control: SecurityControl::new(
+                SecurityLevel::None,
+                KeyIdentifier::Network,
+                true,
+            ),
+            frame_counter: 0x0403_0201,
+            source_address: Some(IeeeAddress(0x0011_2233_4455_6677)),
+            key_sequence_numner: Some(0x05),
+        };
+
+        let packed: heapless::Vec<u8, 14> = header.pack_to_vec().unwrap();
+
+        assert_eq!(
+            packed,
+            [0x28, 0x01, 0x02, 0x03, 0x04, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0x00, 0x05]
+        );
+        assert_eq!(AuxFrameHeader::unpack_from_slice(&packed), Ok(header));
+    }
+}
@@ -1 +1,3 @@
+pub(crate) mod aes;
+pub mod ccm;
 pub mod frame;
@@ -1,12 +1,34 @@
 /// zigbee configuration
 #[derive(Default)]
 pub struct Config {
-    /// The radio channel to operate on
+    /// The radio channel to operate on, 0 scans all channels
     pub radio_channel: u8,
     /// Discovery type
     pub device_discovery_type: DiscoveryType,
     /// This indicates the device class
     pub device_type: crate::apl::descriptors::node_descriptor::LogicalType,
+    /// The IEEE address of the device
+    pub extended_address: crate::types::IeeeAddress,
+    /// The pre-configured network key with key sequence number 0
+    pub network_key: Option<[u8; 16]>,
+    /// Operate as concentrator of many-to-one routes, coordinator and routers
+    /// only
+    pub concentrator: Option<crate::nwk::routing::ConcentratorMode>,
+    /// The time between the many-to-one route requests of a concentrator,
+    /// zero sends a single request
+    pub concentrator_discovery_time: core::time::Duration,
+    /// The time after which the parent of an end device forgets about it
+    /// without keep-alive, nwkEndDeviceTimeoutDefault if not set
+    pub end_device_timeout: Option<crate::nwk::commands::EndDeviceTimeout>,
+    /// Turn the receiver off while idle and poll the parent at these rates,
+    /// end devices only
+    pub poll_rates: Option<crate::nwk::nlme::PollRates>,
+    /// The application endpoint data is sent from and to
+    pub endpoint: u8,
+    /// The profile of the data sent to the coordinator
+    pub profile_id: u16,
+    /// The cluster of the data sent to the coordinator
+    pub cluster_id: u16,
 }
 
 /// Discovery Type
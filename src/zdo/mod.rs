@@ -1,57 +1,469 @@
 use config::Config;
 
 pub mod config;
+use core::time::Duration;
+
+use heapless::FnvIndexSet;
+use heapless::Vec;
+
+use crate::apl::descriptors::node_descriptor::LogicalType;
+use crate::apl::descriptors::node_descriptor::MacCapabilities;
+use crate::apl::descriptors::node_descriptor::MacCapabilityFlag;
 use crate::aps::apsme::Apsme;
+use crate::aps::frame::ApsDataFrame;
+use crate::aps::frame::ApsFrameControl;
+use crate::aps::frame::ApsFrameType;
+use crate::common::parse::PackBytes;
+use crate::common::types::IeeeAddress;
+use crate::common::types::ShortAddress;
+use crate::mac::frame::MAX_PHY_PACKET_SIZE;
+use crate::nwk::commands::EndDeviceTimeout;
+use crate::nwk::nib::MIN_BROADCAST_ADDRESS;
+use crate::nwk::nlde::NldeDataIndication;
+use crate::nwk::nlde::NldeDataStatus;
+use crate::nwk::nlme::management::NetworkDescriptor;
+use crate::nwk::nlme::management::NlmeIndication;
+use crate::nwk::nlme::management::NlmeJoinStatus;
+use crate::nwk::nlme::management::NlmeLeaveStatus;
+use crate::nwk::nlme::management::NlmeNetworkDiscoveryStatus;
+use crate::nwk::nlme::management::NlmeNetworkFormationStatus;
+use crate::nwk::nlme::management::NlmePermitJoiningStatus;
+use crate::nwk::nlme::management::NlmeStartRouterStatus;
+use crate::nwk::nlme::management::MAX_NETWORK_DESCRIPTORS;
+use crate::nwk::nlme::COORDINATOR_ADDRESS;
+use crate::nwk::nlme::ROUTERS_ADDRESS;
+use crate::nwk::security::NetworkKey;
+use crate::radio::ChannelMask;
+use crate::radio::Radio;
+use crate::zdp::client_services::management::MgmtPermitJoiningReq;
+use crate::zdp::client_services::management::MgmtPermitJoiningRsp;
+use crate::zdp::client_services::management::MGMT_PERMIT_JOINING_REQ;
+use crate::zdp::client_services::management::MGMT_PERMIT_JOINING_RSP;
+use crate::zdp::ZdpStatus;
+use crate::zdp::ZDO_ENDPOINT;
+use crate::zdp::ZDP_PROFILE_ID;
 
 /// provides an interface between the appication object, the device profile and the APS
-pub struct ZigbeeDevice {
+pub struct ZigbeeDevice<R> {
     config: Config,
-    apsme: Apsme,
+    apsme: Apsme<R>,
+    /// The transaction sequence number of the next ZDP request.
+    transaction_sequence_number: u8,
 }
 
 /// zigbee network
-pub struct ZigBeeNetwork {}
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZigBeeNetwork {
+    /// 16-bit PAN identifier
+    pub pan_id: u16,
+    /// 64-bit PAN identifier
+    pub extended_pan_id: u64,
+    /// logical channel the network operates on
+    pub logical_channel: u8,
+}
 
-impl ZigbeeDevice {
-    /// creates a new instance
-    pub fn new() -> Self {
+impl<R: Radio> ZigbeeDevice<R> {
+    /// creates a new instance operating on the given radio
+    pub fn new(radio: R) -> Self {
         Self {
             config: Config::default(),
-            apsme: Apsme::new(),
+            apsme: Apsme::new(radio),
+            transaction_sequence_number: 0,
         }
     }
 
     /// configures the device
-    pub fn configure(&self, _config: Config) {}
+    pub fn configure(&mut self, config: Config) {
+        self.apsme.nwk.mac.extended_address = config.extended_address;
+        self.apsme.nwk.nib.network_key = config.network_key.map(|key| NetworkKey {
+            key,
+            sequence_number: 0,
+        });
+        let nib = &mut self.apsme.nwk.nib;
+        nib.is_concentrator = config.concentrator.is_some();
+        nib.concentrator_mode = config.concentrator.unwrap_or_default();
+        nib.concentrator_discovery_time = config.concentrator_discovery_time;
+        self.apsme.nwk.set_poll_rates(
+            config
+                .poll_rates
+                .filter(|_| config.device_type == LogicalType::EndDevice),
+        );
+        self.config = config;
+    }
 
     /// Indicates if the device is connected to a zigbee network
     pub fn is_connected(&self) -> bool {
-        false // TODO: check connection state
+        self.apsme.is_joined()
+    }
+
+    /// forms a new network as coordinator on the quietest of the configured
+    /// channels
+    ///
+    /// Requires [`LogicalType::Coordinator`] as device type.
+    pub async fn form_network(&mut self) -> Result<ZigBeeNetwork, NlmeNetworkFormationStatus> {
+        if self.config.device_type != LogicalType::Coordinator {
+            return Err(NlmeNetworkFormationStatus::InvalidRequest);
+        }
+        let confirm = self.apsme.form_network(self.channels()).await;
+        match confirm.status {
+            NlmeNetworkFormationStatus::Success => Ok(ZigBeeNetwork {
+                pan_id: confirm.pan_id,
+                extended_pan_id: confirm.extended_pan_id,
+                logical_channel: confirm.logical_channel,
+            }),
+            status => Err(status),
+        }
+    }
+
+    /// Handles incoming frames, like beacon requests, for `duration`.
+    ///
+    /// Returns early with the first indication of the network layer, like a
+    /// device which rejoined through this device or this device leaving the
+    /// network on request of its parent. ZDP requests served by this device
+    /// are not indicated.
+    pub async fn process(
+        &mut self,
+        duration: Duration,
+    ) -> Result<Option<NlmeIndication>, R::Error> {
+        let deadline = self.apsme.nwk.mac.radio.now() + duration;
+        loop {
+            let timeout = deadline.saturating_sub(self.apsme.nwk.mac.radio.now());
+            let indication = self.apsme.process(timeout).await?;
+            let Some(NlmeIndication::Data(data)) = &indication else {
+                return Ok(indication);
+            };
+            if !self.serve_device_profile(data).await? {
+                return Ok(indication);
+            }
+            if self.apsme.nwk.mac.radio.now() >= deadline {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Serves a ZDP request received in `indication`.
+    ///
+    /// Returns `false` if the frame is not a request served by this device.
+    async fn serve_device_profile(
+        &mut self,
+        indication: &NldeDataIndication,
+    ) -> Result<bool, R::Error> {
+        let Ok(frame) = ApsDataFrame::unpack_from_slice(&indication.nsdu) else {
+            return Ok(false);
+        };
+        if frame.destination_endpoint != Some(ZDO_ENDPOINT)
+            || frame.profile_id != ZDP_PROFILE_ID
+            || frame.cluster_id != MGMT_PERMIT_JOINING_REQ
+        {
+            return Ok(false);
+        }
+        // network management requests require the network key
+        if !indication.security_use && self.apsme.nwk.nib.network_key.is_some() {
+            return Ok(true);
+        }
+        if let Ok(request) = MgmtPermitJoiningReq::unpack_from_slice(&frame.payload) {
+            self.receive_permit_joining(indication, request).await?;
+        }
+        Ok(true)
+    }
+
+    /// Permits joining as asked by a `Mgmt_Permit_Joining_req`, answering it
+    /// unless it was broadcast.
+    ///
+    /// See Section 2.4.4.3.7.
+    async fn receive_permit_joining(
+        &mut self,
+        indication: &NldeDataIndication,
+        request: MgmtPermitJoiningReq,
+    ) -> Result<(), R::Error> {
+        let confirm = self.apsme.permit_joining(request.permit_duration).await;
+        if indication.destination.0 >= MIN_BROADCAST_ADDRESS {
+            return Ok(());
+        }
+        let response = MgmtPermitJoiningRsp {
+            transaction_sequence_number: request.transaction_sequence_number,
+            status: match confirm.status {
+                NlmePermitJoiningStatus::Success => ZdpStatus::Success,
+                _ => ZdpStatus::InvalidRequest,
+            },
+        };
+        let Ok(payload) = response.pack_to_vec() else {
+            return Ok(());
+        };
+        let frame = device_profile_frame(MGMT_PERMIT_JOINING_RSP, payload);
+        self.apsme.send_frame(indication.source, frame).await?;
+        Ok(())
+    }
+
+    /// permits devices to join the network for `permit_duration` seconds
+    ///
+    /// A duration of 0 disables joining again, 0xff permits it until it is
+    /// disabled. The request is broadcast to all routers and applied by this
+    /// device as well unless it is an end device.
+    pub async fn permit_joining(
+        &mut self,
+        permit_duration: u8,
+    ) -> Result<(), NlmePermitJoiningStatus> {
+        if !self.is_connected() {
+            return Err(NlmePermitJoiningStatus::InvalidRequest);
+        }
+        let request = MgmtPermitJoiningReq {
+            transaction_sequence_number: self.transaction_sequence_number,
+            permit_duration,
+            tc_significance: 0x01,
+        };
+        self.transaction_sequence_number = self.transaction_sequence_number.wrapping_add(1);
+        let Ok(payload) = request.pack_to_vec() else {
+            return Err(NlmePermitJoiningStatus::InvalidRequest);
+        };
+        let frame = device_profile_frame(MGMT_PERMIT_JOINING_REQ, payload);
+        // broadcasts are not acknowledged, routers out of reach stay closed
+        self.apsme
+            .send_frame(ROUTERS_ADDRESS, frame)
+            .await
+            .map_err(|_| NlmePermitJoiningStatus::RadioError)?;
+        if self.config.device_type == LogicalType::EndDevice {
+            return Ok(());
+        }
+        let confirm = self.apsme.permit_joining(permit_duration).await;
+        match confirm.status {
+            NlmePermitJoiningStatus::Success => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    /// The configured radio channel or all channels if none is configured.
+    fn channels(&self) -> ChannelMask {
+        match self.config.radio_channel {
+            0 => ChannelMask::ALL_2_4_GHZ,
+            channel => ChannelMask::single(channel),
+        }
     }
 
     /// scans for nearby reachable networks by sending a beacon request
-    pub fn scan_for_available_networks(&self) {
-        self.apsme.start_network_discovery();
-        // TODO: send beacon requests to actively scan for networks
-        // TODO: Beacon response (signal strenght - RSSI, network PAN ID, permit
-        // to join)
+    ///
+    /// Scans the configured radio channel or all channels if none is
+    /// configured.
+    pub async fn scan_for_available_networks(
+        &mut self,
+    ) -> Result<Vec<NetworkDescriptor, MAX_NETWORK_DESCRIPTORS>, NlmeNetworkDiscoveryStatus> {
+        let confirm = self.apsme.start_network_discovery(self.channels()).await;
+        match confirm.status {
+            NlmeNetworkDiscoveryStatus::Successful => Ok(confirm.network_descriptors),
+            status => Err(status),
+        }
     }
 
-    /// tries to connect to the first reachable network by sending a join request
-    pub fn try_to_connect(&self) {
-        self.apsme.join_network();
-        // TODO: send Association request to choosen network coordinator or
-        // router TODO coordinator/router responds with an association
-        // confirmation
+    /// tries to connect to the reachable network with the best link quality
+    /// which permits joining
+    pub async fn try_to_connect(&mut self) -> Result<ShortAddress, NlmeJoinStatus> {
+        let networks = self
+            .scan_for_available_networks()
+            .await
+            .map_err(|status| match status {
+                NlmeNetworkDiscoveryStatus::RadioError => NlmeJoinStatus::RadioError,
+                _ => NlmeJoinStatus::NoNetworks,
+            })?;
+        let network = networks
+            .iter()
+            .filter(|network| network.permit_joining)
+            .max_by_key(|network| network.lqi)
+            .ok_or(NlmeJoinStatus::NotPermitted)?;
+        self.join_network(network.extended_pan_id).await
+    }
+
+    /// joins the network `extended_pan_id` found by
+    /// [`scan_for_available_networks`](Self::scan_for_available_networks)
+    /// through the parent with the best link
+    ///
+    /// Returns the network address assigned by the parent.
+    pub async fn join_network(
+        &mut self,
+        extended_pan_id: u64,
+    ) -> Result<ShortAddress, NlmeJoinStatus> {
+        if self.config.device_type == LogicalType::Coordinator {
+            return Err(NlmeJoinStatus::InvalidRequest);
+        }
+        let capability_information = self.capability_information();
+        let confirm = self
+            .apsme
+            .join_network(extended_pan_id, capability_information)
+            .await;
+        match confirm.status {
+            NlmeJoinStatus::Success => self.joined(confirm.network_address, false).await,
+            status => Err(status),
+        }
+    }
+
+    /// rejoins the network the device was connected to after losing its
+    /// parent, on the current channel or on all channels
+    ///
+    /// The rejoin is secured with the network key if one is configured and
+    /// falls back to a trust center rejoin. Returns the network address which
+    /// is kept if possible.
+    pub async fn rejoin(&mut self, all_channels: bool) -> Result<ShortAddress, NlmeJoinStatus> {
+        let channels = if all_channels {
+            ChannelMask::ALL_2_4_GHZ
+        } else {
+            ChannelMask::single(self.apsme.nwk.mac.channel)
+        };
+        let extended_pan_id = self.apsme.nwk.nib.extended_pan_id;
+        let capability_information = self.capability_information();
+        let confirm = self
+            .apsme
+            .rejoin_network(extended_pan_id, channels, capability_information)
+            .await;
+        match confirm.status {
+            NlmeJoinStatus::Success => self.joined(confirm.network_address, true).await,
+            status => Err(status),
+        }
+    }
+
+    /// starts operating as the configured device type after joining the
+    /// network with `network_address`
+    ///
+    /// A device which fails to do so leaves the network again, keeping its
+    /// address and bindings if it will `rejoin`.
+    async fn joined(
+        &mut self,
+        network_address: u16,
+        rejoin: bool,
+    ) -> Result<ShortAddress, NlmeJoinStatus> {
+        let started = match self.start_router().await {
+            Ok(()) => self.request_timeout().await,
+            failed => failed,
+        };
+        if let Err(status) = started {
+            let _ = self.apsme.leave_network(rejoin, false).await;
+            return Err(status);
+        }
+        Ok(ShortAddress(network_address))
+    }
+
+    /// starts operating as router after joining the network, if configured
+    /// as router
+    ///
+    /// The router answers beacon requests and accepts children while joining
+    /// is permitted.
+    async fn start_router(&mut self) -> Result<(), NlmeJoinStatus> {
+        if self.config.device_type != LogicalType::Router {
+            return Ok(());
+        }
+        match self.apsme.start_router().await.status {
+            NlmeStartRouterStatus::Success => Ok(()),
+            NlmeStartRouterStatus::RadioError => Err(NlmeJoinStatus::RadioError),
+            NlmeStartRouterStatus::InvalidRequest | NlmeStartRouterStatus::InvalidParameter => {
+                Err(NlmeJoinStatus::InvalidRequest)
+            }
+        }
+    }
+
+    /// asks the parent to forget about this device after the configured end
+    /// device timeout without keep-alive, if configured as end device
+    ///
+    /// Keep-alives are sent while [`process`](Self::process) handles incoming
+    /// frames.
+    async fn request_timeout(&mut self) -> Result<(), NlmeJoinStatus> {
+        if self.config.device_type != LogicalType::EndDevice {
+            return Ok(());
+        }
+        let nwk = &mut self.apsme.nwk;
+        let Some(timeout) = self
+            .config
+            .end_device_timeout
+            .or_else(|| EndDeviceTimeout::new(nwk.nib.end_device_timeout_default))
+        else {
+            return Ok(());
+        };
+        nwk.request_timeout(timeout)
+            .await
+            .map_err(|_| NlmeJoinStatus::RadioError)
+    }
+
+    /// leaves the network, asking the children to leave as well if
+    /// `remove_children` is set
+    ///
+    /// The network address is kept if the device will `rejoin`, otherwise
+    /// its bindings are removed as well.
+    pub async fn leave_network(
+        &mut self,
+        rejoin: bool,
+        remove_children: bool,
+    ) -> Result<(), NlmeLeaveStatus> {
+        let confirm = self.apsme.leave_network(rejoin, remove_children).await;
+        match confirm.status {
+            NlmeLeaveStatus::Success => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    /// asks the child `extended_address` to leave the network, and to
+    /// `rejoin` it afterwards if set
+    ///
+    /// The child is removed even if it did not acknowledge the request.
+    pub async fn remove_child(
+        &mut self,
+        extended_address: IeeeAddress,
+        rejoin: bool,
+    ) -> Result<(), NlmeLeaveStatus> {
+        let confirm = self.apsme.remove_child(extended_address.0, rejoin).await;
+        match confirm.status {
+            NlmeLeaveStatus::Success => Ok(()),
+            status => Err(status),
+        }
+    }
+
+    /// MAC capability flags of the configured device type
+    fn capability_information(&self) -> MacCapabilities {
+        let mut flags = FnvIndexSet::new();
+        if self.apsme.nwk.mac.rx_on_when_idle {
+            let _ = flags.insert(MacCapabilityFlag::ReceiverOnWhenIdle);
+        }
+        let _ = flags.insert(MacCapabilityFlag::AllocateAddress);
+        if self.config.device_type == LogicalType::Router {
+            let _ = flags.insert(MacCapabilityFlag::DeviceType);
+            let _ = flags.insert(MacCapabilityFlag::PowerSource);
+        }
+        MacCapabilities::new(flags)
     }
 
     pub fn setup_security(&self) {
         // TODO: exchange security keys (pre-configured trust center link keys)
     }
 
-    pub fn send_keep_alive(&self) {}
+    /// sends a keep-alive to the parent, which forgets about an end device
+    /// whose timeout expired
+    ///
+    /// Keep-alives are also sent while [`process`](Self::process) handles
+    /// incoming frames. Returns if the parent acknowledged the keep-alive,
+    /// `false` if the device has no parent to keep alive.
+    pub async fn send_keep_alive(&mut self) -> Result<bool, R::Error> {
+        self.apsme.nwk.send_keepalive().await
+    }
 
-    pub fn send_data(&self, _input: &[u8]) {}
+    /// sends `input` to the configured endpoint, profile and cluster of the
+    /// coordinator
+    ///
+    /// An end device which turns its receiver off while idle polls its
+    /// parent at the fast poll rate afterwards, for a response.
+    pub async fn send_data(&mut self, input: &[u8]) -> Result<NldeDataStatus, R::Error> {
+        let Ok(payload) = Vec::from_slice(input) else {
+            return Ok(NldeDataStatus::FrameTooLong);
+        };
+        let frame = ApsDataFrame {
+            frame_control: ApsFrameControl::new(ApsFrameType::Data),
+            destination_endpoint: Some(self.config.endpoint),
+            group_address: None,
+            cluster_id: self.config.cluster_id,
+            profile_id: self.config.profile_id,
+            source_endpoint: self.config.endpoint,
+            counter: 0,
+            payload,
+        };
+        self.apsme
+            .send_frame(ShortAddress(COORDINATOR_ADDRESS), frame)
+            .await
+    }
 
     /// 2.1.3.1 - Device Discovery
     /// is the process whereby a ZigBee device can discover other ZigBee
@@ -78,8 +490,17 @@ impl ZigbeeDevice {
     pub fn start_service_discovery(&self) {}
 }
 
-impl Default for ZigbeeDevice {
-    fn default() -> Self {
-        Self::new()
+/// A frame of the `ZigBee` Device Profile carrying `payload` from and to the
+/// ZDO endpoint.
+fn device_profile_frame(cluster_id: u16, payload: Vec<u8, MAX_PHY_PACKET_SIZE>) -> ApsDataFrame {
+    ApsDataFrame {
+        frame_control: ApsFrameControl::new(ApsFrameType::Data),
+        destination_endpoint: Some(ZDO_ENDPOINT),
+        group_address: None,
+        cluster_id,
+        profile_id: ZDP_PROFILE_ID,
+        source_endpoint: ZDO_ENDPOINT,
+        counter: 0,
+        payload,
     }
 }
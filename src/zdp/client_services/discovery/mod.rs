@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 
-use crate::common::types::{IeeeAddress, NwkAddress};
 use crate::apl::descriptors::node_descriptor::{MacCapabilities, NodeDescriptor, ServerMask};
+use crate::common::types::{IeeeAddress, NwkAddress};
 
 use heapless::Vec;
 
@@ -0,0 +1,77 @@
+//! 2.4.3.3 Network Management Client Services
+//!
+//! Requests to manage the network, which are answered by the network
+//! management server services of the remote device.
+use crate::impl_pack_bytes;
+use crate::zdp::ZdpStatus;
+
+/// Cluster identifier of the `Mgmt_Permit_Joining_req`.
+pub const MGMT_PERMIT_JOINING_REQ: u16 = 0x0036;
+/// Cluster identifier of the `Mgmt_Permit_Joining_rsp`.
+pub const MGMT_PERMIT_JOINING_RSP: u16 = 0x8036;
+
+impl_pack_bytes! {
+    /// 2.4.3.3.7 `Mgmt_Permit_Joining_req`
+    ///
+    /// Asks a router or the coordinator, or all of them if broadcast, to
+    /// permit joining for some time.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MgmtPermitJoiningReq {
+        /// identifies the response to this request
+        #[transparent(u8)]
+        pub transaction_sequence_number: u8,
+        /// seconds joining is permitted, 0x00 disables joining and 0xff
+        /// permits it indefinitely
+        #[transparent(u8)]
+        pub permit_duration: u8,
+        /// 0x01 if the trust center applies the request as well, always set
+        /// by current devices
+        #[transparent(u8)]
+        pub tc_significance: u8,
+    }
+}
+
+impl_pack_bytes! {
+    /// 2.4.4.3.7 `Mgmt_Permit_Joining_rsp`
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct MgmtPermitJoiningRsp {
+        /// the transaction sequence number of the request
+        #[transparent(u8)]
+        pub transaction_sequence_number: u8,
+        /// the result of the request
+        #[transparent(ZdpStatus)]
+        pub status: ZdpStatus,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::parse::PackBytes;
+
+    #[test]
+    fn unpack_mgmt_permit_joining_req() {
+        let got = MgmtPermitJoiningReq::unpack_from_slice(&[0x2a, 0xb4, 0x01]);
+
+        assert_eq!(
+            got,
+            Ok(MgmtPermitJoiningReq {
+                transaction_sequence_number: 0x2a,
+                permit_duration: 180,
+                tc_significance: 0x01,
+            })
+        );
+    }
+
+    #[test]
+    fn pack_mgmt_permit_joining_rsp() {
+        let response = MgmtPermitJoiningRsp {
+            transaction_sequence_number: 0x2a,
+            status: ZdpStatus::InvalidRequest,
+        };
+        let mut buf = [0u8; 2];
+
+        assert_eq!(response.pack_into(&mut buf), Ok(2));
+        assert_eq!(buf, [0x2a, 0xc2]);
+    }
+}
@@ -5,3 +5,4 @@
 //! Services support receipt of responses to these requests from the server.
 
 pub mod discovery;
+pub mod management;
@@ -2,13 +2,52 @@
 //!
 //! The ZigBee Device Profile operates like any ZigBee profile by defining clusters.
 //! Unlike application specific profiles, the clusters within the ZigBee Device Profile define capabilities supported in all ZigBee devices.
-//! 
+//!
 //! The Device Profile supports four key inter-device communication functions within the ZigBee protocol.
-//! These functions are explained in the following sections: 
-//! * Device and Service Discovery Overview 
-//! * End Device Bind Overview 
-//! * Bind and Unbind Overview 
-//! * Binding Table Management Overview 
+//! These functions are explained in the following sections:
+//! * Device and Service Discovery Overview
+//! * End Device Bind Overview
+//! * Bind and Unbind Overview
+//! * Binding Table Management Overview
 //! * Network Management Overview
 
 pub mod client_services;
+
+use crate::common::parse::Input;
+use crate::common::parse::PackBytes;
+use crate::common::parse::PackError;
+use crate::common::parse::ParseError;
+
+/// Endpoint of the `ZigBee` Device Object.
+pub const ZDO_ENDPOINT: u8 = 0x00;
+/// Profile identifier of the `ZigBee` Device Profile.
+pub const ZDP_PROFILE_ID: u16 = 0x0000;
+
+/// Status of a ZDP response
+///
+/// See Section 2.4.5.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZdpStatus {
+    /// the request was performed
+    Success = 0x00,
+    /// the network layer refused the request
+    InvalidRequest = 0xc2,
+}
+
+impl PackBytes for ZdpStatus {
+    fn unpack_from_iter(src: impl IntoIterator<Item = u8>) -> Result<Self, ParseError> {
+        match Input::new(src).byte()? {
+            0x00 => Ok(Self::Success),
+            0xc2 => Ok(Self::InvalidRequest),
+            _ => Err(ParseError::InvalidValue {
+                field: "zdp_status",
+                offset: 0,
+            }),
+        }
+    }
+
+    fn pack_into(&self, buf: &mut [u8]) -> Result<usize, PackError> {
+        (*self as u8).pack_into(buf)
+    }
+}
@@ -0,0 +1,912 @@
+use core::time::Duration;
+
+use heapless::Vec;
+use zigbee::apl::descriptors::node_descriptor::LogicalType;
+use zigbee::apl::descriptors::node_descriptor::MacCapabilityFlag;
+use zigbee::aps::frame::ApsDataFrame;
+use zigbee::mac::beacon::Beacon;
+use zigbee::mac::beacon::SuperframeSpecification;
+use zigbee::mac::command::AssociationResponse;
+use zigbee::mac::command::AssociationStatus;
+use zigbee::mac::command::MacCommand;
+use zigbee::mac::frame::Address;
+use zigbee::mac::frame::FrameType;
+use zigbee::mac::frame::MacBeaconFrame;
+use zigbee::mac::frame::MacCommandFrame;
+use zigbee::mac::frame::MacDataFrame;
+use zigbee::mac::frame::MacFrame;
+use zigbee::mac::frame::MacHeader;
+use zigbee::nwk::beacon::BeaconPayload;
+use zigbee::nwk::beacon::ZIGBEE_PROTOCOL_ID;
+use zigbee::nwk::commands::EndDeviceTimeout;
+use zigbee::nwk::commands::NetworkStatusCode;
+use zigbee::nwk::frame::FrameControl;
+use zigbee::nwk::frame::FrameTypeIdentifier;
+use zigbee::nwk::frame::NwkDataFrame;
+use zigbee::nwk::frame::NwkFrame;
+use zigbee::nwk::frame::NwkHeader;
+use zigbee::nwk::nlde::NldeDataStatus;
+use zigbee::nwk::nlme::management::NlmeIndication;
+use zigbee::nwk::nlme::management::NlmeJoinStatus;
+use zigbee::nwk::nlme::management::NlmeLeaveStatus;
+use zigbee::nwk::nlme::management::NlmeNetworkDiscoveryStatus;
+use zigbee::nwk::nlme::management::RejoinNetwork;
+use zigbee::nwk::nlme::PollRates;
+use zigbee::radio::sim::Link;
+use zigbee::radio::sim::Medium;
+use zigbee::radio::sim::NodeId;
+use zigbee::radio::sim::SimRadio;
+use zigbee::radio::Radio;
+use zigbee::radio::TxStatus;
+use zigbee::types::IeeeAddress;
+use zigbee::types::ShortAddress;
+use zigbee::PackBytes;
+use zigbee::ZigBeeNetwork;
+use zigbee::ZigbeeDevice;
+
+const PAN_ID: u16 = 0x1a62;
+const TIMEOUT: Duration = Duration::from_millis(100);
+
+fn data_frame(sequence_number: u8, destination: u16, ack_request: bool) -> Vec<u8, 127> {
+    let mut header = MacHeader::new(
+        FrameType::Data,
+        sequence_number,
+        Some((PAN_ID, Address::Short(ShortAddress(destination)))),
+        Some((PAN_ID, Address::Short(ShortAddress(0x0000)))),
+    );
+    header.frame_control.set_ack_request(ack_request);
+    MacFrame::Data(MacDataFrame {
+        header,
+        payload: Vec::from_slice(&[0x2a]).unwrap(),
+    })
+    .pack_to_vec()
+    .unwrap()
+}
+
+fn node(medium: &Medium, short_address: u16) -> SimRadio {
+    let mut radio = medium.add_node();
+    let mut executor = medium.executor();
+    executor
+        .block_on(TIMEOUT, async {
+            radio.set_pan_id(PAN_ID).await.unwrap();
+            radio
+                .set_short_address(ShortAddress(short_address))
+                .await
+                .unwrap();
+        })
+        .unwrap();
+    radio
+}
+
+/// Transmits `frames` from `tx` while `rx` receives, returns the transmit
+/// results and the number of received frames.
+fn exchange(
+    medium: &Medium,
+    tx: &mut SimRadio,
+    rx: &mut SimRadio,
+    frames: &[Vec<u8, 127>],
+) -> (std::vec::Vec<TxStatus>, usize) {
+    let mut executor = medium.executor();
+    let mut received = 0;
+    executor.spawn(async {
+        while let Ok(Some(_)) = rx.receive(TIMEOUT).await {
+            received += 1;
+        }
+    });
+    let status = executor
+        .block_on(TIMEOUT * 10, async {
+            let mut status = std::vec::Vec::new();
+            for frame in frames {
+                status.push(tx.transmit(frame, true).await.unwrap());
+            }
+            status
+        })
+        .unwrap();
+    executor.run_for(TIMEOUT * 2);
+    drop(executor);
+    (status, received)
+}
+
+#[test]
+fn unicast_frame_is_acknowledged_and_received() {
+    let medium = Medium::new(1);
+    let mut a = node(&medium, 0x0000);
+    let mut b = node(&medium, 0x796f);
+    let link = Link {
+        rssi: -70,
+        lqi: 180,
+        delay: Duration::from_micros(100),
+        ..Link::default()
+    };
+    medium.connect(a.id(), b.id(), link);
+    let frame = data_frame(1, 0x796f, true);
+
+    let start = medium.now();
+    let mut got = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        got = b.receive(TIMEOUT).await.unwrap();
+    });
+    let status = executor
+        .block_on(TIMEOUT, a.transmit(&frame, true))
+        .unwrap()
+        .unwrap();
+    drop(executor);
+
+    assert_eq!(
+        status,
+        TxStatus::Acked {
+            frame_pending: false
+        }
+    );
+    let got = got.unwrap();
+    assert_eq!(&got.frame, &frame);
+    assert_eq!(got.rssi, -70);
+    assert_eq!(got.lqi, 180);
+    // 6 bytes PHY overhead, frame and FCS at 32 µs per byte plus the delay
+    let airtime = Duration::from_micros(32 * (6 + frame.len() as u64 + 2));
+    assert_eq!(got.timestamp, start + airtime + link.delay);
+}
+
+#[test]
+fn disconnected_node_does_not_receive_or_acknowledge() {
+    let medium = Medium::new(1);
+    let mut a = node(&medium, 0x0000);
+    let mut b = node(&medium, 0x796f);
+    medium.disconnect(a.id(), b.id());
+
+    let (status, received) = exchange(&medium, &mut a, &mut b, &[data_frame(1, 0x796f, true)]);
+
+    assert_eq!(status, [TxStatus::NoAck]);
+    assert_eq!(received, 0);
+}
+
+#[test]
+fn frames_are_filtered_by_channel_and_address() {
+    let medium = Medium::new(1);
+    let mut a = node(&medium, 0x0000);
+    let mut b = node(&medium, 0x796f);
+    let frames = [
+        data_frame(1, 0x1234, true),
+        data_frame(2, 0xffff, false),
+        data_frame(3, 0x796f, false),
+    ];
+
+    let (status, received) = exchange(&medium, &mut a, &mut b, &frames);
+    assert_eq!(status, [TxStatus::NoAck, TxStatus::Sent, TxStatus::Sent]);
+    assert_eq!(received, 2);
+
+    let mut executor = medium.executor();
+    executor
+        .block_on(TIMEOUT, b.set_channel(15))
+        .unwrap()
+        .unwrap();
+    drop(executor);
+    let (_, received) = exchange(&medium, &mut a, &mut b, &frames);
+    assert_eq!(received, 0);
+}
+
+#[test]
+fn sleeping_node_misses_frames() {
+    let medium = Medium::new(1);
+    let mut a = node(&medium, 0x0000);
+    let mut b = node(&medium, 0x796f);
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        let _ = b.sleep(Duration::from_millis(10)).await;
+    });
+    let status = executor
+        .block_on(TIMEOUT, a.transmit(&data_frame(1, 0x796f, true), true))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(status, TxStatus::NoAck);
+}
+
+#[test]
+fn lossy_link_is_deterministic_for_a_seed() {
+    let run = |seed| {
+        let medium = Medium::new(seed);
+        let mut a = node(&medium, 0x0000);
+        let mut b = node(&medium, 0x796f);
+        medium.set_link(
+            a.id(),
+            b.id(),
+            Some(Link {
+                loss: 0.5,
+                ..Link::default()
+            }),
+        );
+        let frames: std::vec::Vec<_> = (0..64).map(|n| data_frame(n, 0xffff, false)).collect();
+        exchange(&medium, &mut a, &mut b, &frames).1
+    };
+
+    let received = run(7);
+
+    assert_eq!(received, run(7));
+    assert!((16..48).contains(&received), "received {} of 64", received);
+}
+
+#[test]
+fn energy_detect_reports_channel_energy() {
+    let medium = Medium::new(1);
+    let mut a = node(&medium, 0x0000);
+    medium.set_channel_energy(20, 0xc8);
+
+    let mut executor = medium.executor();
+    let got = executor
+        .block_on(TIMEOUT, async {
+            a.set_channel(20).await.unwrap();
+            a.energy_detect(Duration::from_millis(1)).await.unwrap()
+        })
+        .unwrap();
+
+    assert_eq!(got, 0xc8);
+    assert_eq!(medium.now(), Duration::from_millis(1));
+}
+
+#[test]
+fn zigbee_device_runs_on_simulated_radio() {
+    let medium = Medium::new(1);
+    let config = zigbee::Config {
+        radio_channel: 11,
+        ..Default::default()
+    };
+
+    let mut device = zigbee::init(config, medium.add_node());
+    let mut executor = medium.executor();
+    let got = executor
+        .block_on(TIMEOUT * 10, device.try_to_connect())
+        .unwrap();
+
+    assert_eq!(got, Err(NlmeJoinStatus::NoNetworks));
+    assert!(!device.is_connected());
+}
+
+/// Plays a coordinator of `extended_pan_id` on the current channel of
+/// `radio` which answers beacon requests and admits every device with
+/// `assigned_address`, until nothing is received for five seconds.
+async fn run_coordinator(radio: &mut SimRadio, extended_pan_id: u64, assigned_address: u16) {
+    let mut associating = None;
+    while let Ok(Some(rx)) = radio.receive(Duration::from_secs(5)).await {
+        let Ok(MacFrame::Command(request)) = MacFrame::unpack_from_slice(&rx.frame) else {
+            continue;
+        };
+        let frame = match request.command {
+            MacCommand::BeaconRequest => beacon(extended_pan_id),
+            MacCommand::AssociationRequest(_) => {
+                associating = request.header.source;
+                continue;
+            }
+            MacCommand::DataRequest if request.header.source == associating => {
+                let mut header = MacHeader::new(
+                    FrameType::MacCommand,
+                    0,
+                    Some((PAN_ID, associating.take().unwrap())),
+                    Some((PAN_ID, Address::Extended(IeeeAddress(0x00ff)))),
+                );
+                header.frame_control.set_ack_request(true);
+                MacFrame::Command(MacCommandFrame {
+                    header,
+                    command: MacCommand::AssociationResponse(AssociationResponse {
+                        short_address: ShortAddress(assigned_address),
+                        status: AssociationStatus::Success,
+                    }),
+                })
+            }
+            _ => continue,
+        };
+        let frame: Vec<u8, 127> = frame.pack_to_vec().unwrap();
+        radio.transmit(&frame, true).await.unwrap();
+    }
+}
+
+/// Zigbee beacon of a coordinator permitting association.
+fn beacon(extended_pan_id: u64) -> MacFrame {
+    let mut superframe = SuperframeSpecification::NON_BEACON;
+    superframe.set_pan_coordinator(true);
+    superframe.set_association_permit(true);
+    let payload = BeaconPayload {
+        protocol_id: ZIGBEE_PROTOCOL_ID,
+        stack_profile: 2,
+        protocol_version: 2,
+        router_capacity: true,
+        device_depth: 0,
+        end_device_capacity: true,
+        extended_pan_id,
+        tx_offset: 0x00ff_ffff,
+        update_id: 0,
+    };
+    MacFrame::Beacon(MacBeaconFrame {
+        header: MacHeader::new(
+            FrameType::Beacon,
+            0,
+            None,
+            Some((PAN_ID, Address::Short(ShortAddress(0x0000)))),
+        ),
+        beacon: Beacon::new(superframe),
+        payload: payload.pack_to_vec().unwrap(),
+    })
+}
+
+#[test]
+fn network_discovery_finds_coordinator_on_any_channel() {
+    let medium = Medium::new(1);
+    let mut coordinator = node(&medium, 0x0000);
+    let mut device = zigbee::init(zigbee::Config::default(), medium.add_node());
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.set_channel(20).await.unwrap();
+        run_coordinator(&mut coordinator, 0x0015_8d00_01ab_cd12, 0x796f).await;
+    });
+    let networks = executor
+        .block_on(TIMEOUT * 50, device.scan_for_available_networks())
+        .unwrap()
+        .unwrap();
+    drop(executor);
+
+    assert_eq!(networks.len(), 1);
+    assert_eq!(networks[0].extended_pan_id, 0x0015_8d00_01ab_cd12);
+    assert_eq!(networks[0].pan_id, PAN_ID);
+    assert_eq!(networks[0].logical_channel, 20);
+    assert!(networks[0].permit_joining);
+    assert_eq!(networks[0].lqi, 255);
+}
+
+#[test]
+fn network_discovery_without_networks_reports_no_beacon() {
+    let medium = Medium::new(1);
+    let config = zigbee::Config {
+        radio_channel: 15,
+        ..Default::default()
+    };
+    let mut device = zigbee::init(config, medium.add_node());
+
+    let mut executor = medium.executor();
+    let got = executor
+        .block_on(TIMEOUT * 10, device.scan_for_available_networks())
+        .unwrap();
+
+    assert_eq!(got, Err(NlmeNetworkDiscoveryStatus::NoBeacon));
+}
+
+#[test]
+fn coordinator_forms_network_that_is_discovered() {
+    let medium = Medium::new(1);
+    medium.set_channel_energy(11, 0xf0);
+    let config = zigbee::Config {
+        device_type: LogicalType::Coordinator,
+        extended_address: IeeeAddress(0x0015_8d00_01ab_cd12),
+        ..Default::default()
+    };
+    let mut coordinator = zigbee::init(config, medium.add_node());
+    let mut device = zigbee::init(zigbee::Config::default(), medium.add_node());
+
+    let mut executor = medium.executor();
+    let network = executor
+        .block_on(TIMEOUT * 50, coordinator.form_network())
+        .unwrap()
+        .unwrap();
+    executor.spawn(async {
+        coordinator.process(Duration::from_secs(5)).await.unwrap();
+    });
+    let networks = executor
+        .block_on(TIMEOUT * 50, device.scan_for_available_networks())
+        .unwrap()
+        .unwrap();
+    drop(executor);
+
+    assert_eq!(network.extended_pan_id, 0x0015_8d00_01ab_cd12);
+    assert_eq!(network.logical_channel, 12);
+    assert!(coordinator.is_connected());
+    assert_eq!(networks.len(), 1);
+    assert_eq!(networks[0].pan_id, network.pan_id);
+    assert_eq!(networks[0].extended_pan_id, network.extended_pan_id);
+    assert_eq!(networks[0].logical_channel, 12);
+    assert!(!networks[0].permit_joining);
+}
+
+#[test]
+fn device_joins_network_through_association() {
+    let medium = Medium::new(1);
+    let mut coordinator = node(&medium, 0x0000);
+    let config = zigbee::Config {
+        radio_channel: 15,
+        device_type: LogicalType::Router,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0001),
+        ..Default::default()
+    };
+    let mut device = zigbee::init(config, medium.add_node());
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.set_channel(15).await.unwrap();
+        run_coordinator(&mut coordinator, 0x0015_8d00_01ab_cd12, 0x796f).await;
+    });
+    let got = executor
+        .block_on(TIMEOUT * 20, device.try_to_connect())
+        .unwrap();
+    drop(executor);
+
+    assert_eq!(got, Ok(ShortAddress(0x796f)));
+    assert!(device.is_connected());
+}
+
+const NETWORK_KEY: [u8; 16] = [
+    0x01, 0x03, 0x05, 0x07, 0x09, 0x0b, 0x0d, 0x0f, 0x00, 0x02, 0x04, 0x06, 0x08, 0x0a, 0x0c, 0x0d,
+];
+
+/// A coordinator which formed a network and a router which rejoined it.
+struct Rejoined {
+    coordinator: ZigbeeDevice<SimRadio>,
+    /// the node of the coordinator on the medium
+    coordinator_node: NodeId,
+    router: ZigbeeDevice<SimRadio>,
+    /// the node of the router on the medium
+    router_node: NodeId,
+    network: ZigBeeNetwork,
+    /// the result of the rejoin
+    rejoined: Result<ShortAddress, NlmeJoinStatus>,
+    /// the indication of the coordinator
+    indication: Option<NlmeIndication>,
+}
+
+/// Joins a router holding `network_key` through a coordinator which
+/// disappears afterwards and rejoins it through a coordinator formed on
+/// another channel.
+fn rejoin_after_losing_parent(medium: &Medium, network_key: Option<[u8; 16]>) -> Rejoined {
+    medium.set_channel_energy(11, 0xf0);
+    let mut parent = node(medium, 0x0000);
+    let config = zigbee::Config {
+        device_type: LogicalType::Coordinator,
+        extended_address: IeeeAddress(0x0015_8d00_01ab_cd12),
+        network_key: Some(NETWORK_KEY),
+        ..Default::default()
+    };
+    let radio = medium.add_node();
+    let coordinator_node = radio.id();
+    let mut coordinator = zigbee::init(config, radio);
+    let config = zigbee::Config {
+        radio_channel: 15,
+        device_type: LogicalType::Router,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0001),
+        network_key,
+        ..Default::default()
+    };
+    let radio = medium.add_node();
+    let router_node = radio.id();
+    let mut router = zigbee::init(config, radio);
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        parent.set_channel(15).await.unwrap();
+        run_coordinator(&mut parent, 0x0015_8d00_01ab_cd12, 0x796f).await;
+    });
+    let joined = executor
+        .block_on(TIMEOUT * 20, router.try_to_connect())
+        .unwrap();
+    assert_eq!(joined, Ok(ShortAddress(0x796f)));
+    drop(executor);
+
+    let mut indication = None;
+    let mut executor = medium.executor();
+    let network = executor
+        .block_on(TIMEOUT * 50, coordinator.form_network())
+        .unwrap()
+        .unwrap();
+    executor.spawn(async {
+        indication = coordinator.process(Duration::from_secs(5)).await.unwrap();
+    });
+    let rejoined = executor
+        .block_on(TIMEOUT * 50, router.rejoin(true))
+        .unwrap();
+    // let the coordinator finish its transmission of the response
+    let mut observer = medium.add_node();
+    executor
+        .block_on(TIMEOUT, observer.sleep(TIMEOUT))
+        .unwrap()
+        .unwrap();
+    drop(executor);
+    Rejoined {
+        coordinator,
+        coordinator_node,
+        router,
+        router_node,
+        network,
+        rejoined,
+        indication,
+    }
+}
+
+#[test]
+fn device_rejoins_network_secured_with_network_key() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        rejoined,
+        indication,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    let Some(NlmeIndication::Join(indication)) = indication else {
+        unreachable!("no join indication");
+    };
+    assert_eq!(indication.network_address, 0x796f);
+    assert_eq!(indication.extended_address, 0x0015_8d00_0000_0001);
+    assert_eq!(indication.rejoin_network, RejoinNetwork::Rejoin);
+    assert!(indication.secure_rejoin);
+}
+
+#[test]
+fn device_with_outdated_key_falls_back_to_trust_center_rejoin() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        rejoined,
+        indication,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some([0xff; 16]));
+
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    let Some(NlmeIndication::Join(indication)) = indication else {
+        unreachable!("no join indication");
+    };
+    assert!(!indication.secure_rejoin);
+}
+
+#[test]
+fn router_gives_up_address_reported_in_conflict() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        mut coordinator,
+        mut router,
+        router_node,
+        network,
+        rejoined,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    // another device out of range of the router uses its address
+    let mut intruder = medium.add_node();
+    medium.disconnect(intruder.id(), router_node);
+    let mut frame_control = FrameControl::new(FrameTypeIdentifier::Data);
+    frame_control.set_source_ieee_flag(true);
+    let frame = NwkFrame::Data(NwkDataFrame {
+        header: NwkHeader {
+            frame_control,
+            destination: ShortAddress(0xffff),
+            source: ShortAddress(0x796f),
+            radius: 1,
+            sequence_number: 1,
+            destination_ieee: None,
+            source_ieee: Some(IeeeAddress(0x0015_8d00_0000_0002)),
+            multicast_control: None,
+            source_route_subframe: None,
+        },
+        payload: Vec::from_slice(&[0x2a]).unwrap(),
+    });
+    let frame: Vec<u8, 127> = MacFrame::Data(MacDataFrame {
+        header: MacHeader::new(
+            FrameType::Data,
+            1,
+            Some((network.pan_id, Address::BROADCAST)),
+            Some((network.pan_id, Address::Short(ShortAddress(0x796f)))),
+        ),
+        payload: frame.pack_to_vec().unwrap(),
+    })
+    .pack_to_vec()
+    .unwrap();
+
+    let mut indication = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.process(Duration::from_secs(1)).await.unwrap();
+    });
+    executor.spawn(async {
+        indication = router.process(Duration::from_secs(1)).await.unwrap();
+    });
+    executor
+        .block_on(TIMEOUT * 20, async {
+            intruder.set_channel(network.logical_channel).await.unwrap();
+            intruder.set_pan_id(network.pan_id).await.unwrap();
+            intruder.transmit(&frame, true).await.unwrap();
+            intruder.sleep(Duration::from_secs(1)).await.unwrap();
+        })
+        .unwrap();
+    drop(executor);
+
+    let Some(NlmeIndication::NetworkStatus(indication)) = indication else {
+        unreachable!("no network status indication");
+    };
+    assert_eq!(indication.status, NetworkStatusCode::NetworkAddressUpdate);
+    assert_ne!(indication.network_address, 0x796f);
+    assert_ne!(indication.network_address, 0x0000);
+}
+
+#[test]
+fn coordinator_removes_router_from_network() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        mut coordinator,
+        mut router,
+        rejoined,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    assert!(router.is_connected());
+
+    let mut indication = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        indication = router.process(Duration::from_secs(1)).await.unwrap();
+    });
+    let removed = executor
+        .block_on(
+            TIMEOUT * 20,
+            coordinator.remove_child(IeeeAddress(0x0015_8d00_0000_0001), false),
+        )
+        .unwrap();
+    executor.run_for(Duration::from_secs(1));
+    drop(executor);
+
+    assert_eq!(removed, Ok(()));
+    let Some(NlmeIndication::Leave(indication)) = indication else {
+        unreachable!("no leave indication");
+    };
+    assert_eq!(indication.device_address, None);
+    assert!(!indication.rejoin);
+    assert!(!router.is_connected());
+}
+
+#[test]
+fn coordinator_permits_joining_for_a_limited_time() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        mut coordinator,
+        mut router,
+        rejoined,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    let config = zigbee::Config {
+        device_type: LogicalType::Router,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0002),
+        ..Default::default()
+    };
+    let mut device = zigbee::init(config, medium.add_node());
+    let mut observer = zigbee::init(zigbee::Config::default(), medium.add_node());
+
+    let mut permitted = None;
+    let mut indication = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        permitted = Some(coordinator.permit_joining(30).await);
+        // serve the joining device and let the window close
+        while coordinator
+            .process(Duration::from_secs(35))
+            .await
+            .unwrap()
+            .is_some()
+        {}
+    });
+    executor.spawn(async {
+        indication = router.process(Duration::from_secs(1)).await.unwrap();
+    });
+    let joined = executor
+        .block_on(TIMEOUT * 50, device.try_to_connect())
+        .unwrap();
+    executor.run_for(Duration::from_secs(35));
+    drop(executor);
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.process(Duration::from_secs(5)).await.unwrap();
+    });
+    let networks = executor
+        .block_on(TIMEOUT * 50, observer.scan_for_available_networks())
+        .unwrap()
+        .unwrap();
+    drop(executor);
+
+    assert_eq!(permitted, Some(Ok(())));
+    // the router served the broadcast Mgmt_Permit_Joining_req by itself
+    assert_eq!(indication, None);
+    assert!(joined.is_ok());
+    assert!(device.is_connected());
+    assert_eq!(networks.len(), 1);
+    assert!(!networks[0].permit_joining);
+}
+
+#[test]
+fn device_joins_through_started_router() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        mut coordinator,
+        coordinator_node,
+        mut router,
+        rejoined,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    let config = zigbee::Config {
+        device_type: LogicalType::EndDevice,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0002),
+        ..Default::default()
+    };
+    let radio = medium.add_node();
+    // the device is only in range of the router
+    medium.disconnect(radio.id(), coordinator_node);
+    let mut device = zigbee::init(config, radio);
+
+    let mut indication = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.permit_joining(60).await.unwrap();
+        coordinator.process(Duration::from_secs(10)).await.unwrap();
+    });
+    executor.spawn(async {
+        indication = router.process(Duration::from_secs(10)).await.unwrap();
+    });
+    let joined = executor
+        .block_on(TIMEOUT * 50, device.try_to_connect())
+        .unwrap();
+    executor.run_for(Duration::from_secs(1));
+    drop(executor);
+
+    let Ok(address) = joined else {
+        unreachable!("device did not join");
+    };
+    let Some(NlmeIndication::Join(indication)) = indication else {
+        unreachable!("no join indication");
+    };
+    assert_eq!(indication.network_address, address.0);
+    assert_eq!(indication.extended_address, 0x0015_8d00_0000_0002);
+    assert!(device.is_connected());
+}
+
+#[test]
+fn parent_ages_out_end_device_which_stops_keeping_alive() {
+    let medium = Medium::new(1);
+    let Rejoined {
+        mut coordinator,
+        coordinator_node,
+        router_node,
+        rejoined,
+        ..
+    } = rejoin_after_losing_parent(&medium, Some(NETWORK_KEY));
+    assert_eq!(rejoined, Ok(ShortAddress(0x796f)));
+    let config = zigbee::Config {
+        device_type: LogicalType::EndDevice,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0002),
+        network_key: Some(NETWORK_KEY),
+        end_device_timeout: EndDeviceTimeout::new(0),
+        ..Default::default()
+    };
+    let radio = medium.add_node();
+    let device_node = radio.id();
+    // the device is only in range of the coordinator
+    medium.disconnect(device_node, router_node);
+    let mut device = zigbee::init(config, radio);
+
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.permit_joining(60).await.unwrap();
+        coordinator.process(Duration::from_secs(10)).await.unwrap();
+    });
+    let joined = executor
+        .block_on(TIMEOUT * 50, device.try_to_connect())
+        .unwrap();
+    let kept_alive = executor
+        .block_on(TIMEOUT * 10, device.send_keep_alive())
+        .unwrap()
+        .unwrap();
+    executor.run_for(Duration::from_secs(1));
+    drop(executor);
+
+    medium.disconnect(device_node, coordinator_node);
+    let mut removed = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        coordinator.process(Duration::from_secs(15)).await.unwrap();
+        let child = IeeeAddress(0x0015_8d00_0000_0002);
+        removed = Some(coordinator.remove_child(child, false).await);
+    });
+    let lost = executor
+        .block_on(TIMEOUT * 10, device.send_keep_alive())
+        .unwrap()
+        .unwrap();
+    executor.run_for(Duration::from_secs(20));
+    drop(executor);
+
+    assert!(joined.is_ok());
+    assert!(kept_alive);
+    assert!(!lost);
+    assert_eq!(removed, Some(Err(NlmeLeaveStatus::UnknownDevice)));
+}
+
+#[test]
+fn sleeping_end_device_sends_data_and_polls_for_frames() {
+    let medium = Medium::new(1);
+    medium.set_channel_energy(11, 0xf0);
+    let config = zigbee::Config {
+        device_type: LogicalType::Coordinator,
+        extended_address: IeeeAddress(0x0015_8d00_01ab_cd12),
+        network_key: Some(NETWORK_KEY),
+        ..Default::default()
+    };
+    let mut coordinator = zigbee::init(config, medium.add_node());
+    let config = zigbee::Config {
+        device_type: LogicalType::EndDevice,
+        extended_address: IeeeAddress(0x0015_8d00_0000_0002),
+        network_key: Some(NETWORK_KEY),
+        poll_rates: Some(PollRates {
+            fast: Duration::from_millis(100),
+            slow: Duration::from_secs(1),
+            fast_poll_timeout: Duration::from_secs(1),
+        }),
+        endpoint: 0x01,
+        profile_id: 0x0104,
+        cluster_id: 0x0402,
+        ..Default::default()
+    };
+    let mut device = zigbee::init(config, medium.add_node());
+
+    let (mut joined_indication, mut data_indication) = (None, None);
+    let mut executor = medium.executor();
+    executor
+        .block_on(TIMEOUT * 50, coordinator.form_network())
+        .unwrap()
+        .unwrap();
+    executor.spawn(async {
+        coordinator.permit_joining(60).await.unwrap();
+        joined_indication = coordinator.process(Duration::from_secs(10)).await.unwrap();
+        data_indication = coordinator.process(Duration::from_secs(10)).await.unwrap();
+    });
+    let joined = executor
+        .block_on(TIMEOUT * 50, device.try_to_connect())
+        .unwrap();
+    let sent = executor
+        .block_on(TIMEOUT * 10, device.send_data(&[0x7a, 0x69]))
+        .unwrap()
+        .unwrap();
+    executor.run_for(Duration::from_secs(1));
+    drop(executor);
+
+    // frames for the sleeping device are kept until it polls for them
+    let mut removed = None;
+    let mut executor = medium.executor();
+    executor.spawn(async {
+        let child = IeeeAddress(0x0015_8d00_0000_0002);
+        removed = Some(coordinator.remove_child(child, false).await);
+        coordinator.process(Duration::from_secs(5)).await.unwrap();
+    });
+    let left = executor
+        .block_on(TIMEOUT * 50, device.process(Duration::from_secs(5)))
+        .unwrap()
+        .unwrap();
+    drop(executor);
+
+    assert!(joined.is_ok());
+    let Some(NlmeIndication::Join(joined_indication)) = joined_indication else {
+        unreachable!("no join indication");
+    };
+    assert!(!joined_indication
+        .capability_information
+        .is_set(MacCapabilityFlag::ReceiverOnWhenIdle));
+    assert_eq!(sent, NldeDataStatus::Success);
+    let Some(NlmeIndication::Data(data_indication)) = data_indication else {
+        unreachable!("no data indication");
+    };
+    let frame = ApsDataFrame::unpack_from_slice(&data_indication.nsdu).unwrap();
+    assert_eq!(frame.destination_endpoint, Some(0x01));
+    assert_eq!(frame.profile_id, 0x0104);
+    assert_eq!(frame.cluster_id, 0x0402);
+    assert_eq!(frame.payload.as_slice(), [0x7a, 0x69]);
+    assert_eq!(removed, Some(Ok(())));
+    let Some(NlmeIndication::Leave(left)) = left else {
+        unreachable!("no leave indication");
+    };
+    assert_eq!(left.device_address, None);
+    assert!(!device.is_connected());
+}